@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+type Large = [u64; 256];
+
+fn setup(chunk_size: Option<usize>) -> (ecs_tiny::ECS, ecs_tiny::EntityKey) {
+    let mut ecs = ecs_tiny::ECS::new();
+    match chunk_size {
+        Some(chunk_size) => ecs.register_chunked::<Large>(chunk_size).unwrap(),
+        None => ecs.register::<Large>().map(|_| ()).unwrap(),
+    }
+
+    let entity_key = ecs.insert_entity();
+    (ecs, entity_key)
+}
+
+// The default column copies all rows each time it grows, whereas the chunked column only allocates a new chunk.
+fn chunked_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_comp 10000 comps of 2KB");
+
+    for (name, chunk_size) in [("default", None), ("chunked", Some(256))] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || setup(chunk_size),
+                |(mut ecs, entity_key)| {
+                    for i in 0..10000 {
+                        ecs.insert_comp(entity_key, [i; 256]).unwrap();
+                    }
+                    ecs
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, chunked_insert);
+criterion_main!(benches);
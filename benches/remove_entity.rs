@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+fn setup(entity_count: usize, comp_count: usize) -> (ecs_tiny::ECS, Vec<ecs_tiny::EntityKey>) {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<f32>().unwrap();
+
+    let mut entity_keys = vec![];
+    for _ in 0..entity_count {
+        let entity_key = ecs.insert_entity();
+        for i in 0..comp_count / 3 {
+            ecs.insert_comp(entity_key, i as i32).unwrap();
+            ecs.insert_comp(entity_key, i as u32).unwrap();
+            ecs.insert_comp(entity_key, i as f32).unwrap();
+        }
+        entity_keys.push(entity_key);
+    }
+
+    (ecs, entity_keys)
+}
+
+// The world is dropped inside the routine so that storage left behind by the cascade is measured too.
+fn remove_entity(c: &mut Criterion) {
+    c.bench_function("remove_entity with 30 comps", |b| {
+        b.iter_batched(
+            || setup(1000, 30),
+            |(mut ecs, entity_keys)| {
+                for entity_key in entity_keys {
+                    ecs.remove_entity(entity_key).unwrap();
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, remove_entity);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+fn setup() -> ecs_tiny::ECS {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<f32>().unwrap();
+    ecs
+}
+
+fn spawn_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spawn 10000 entities with 3 comps");
+
+    group.bench_function("loop", |b| {
+        b.iter_batched(
+            setup,
+            |mut ecs| {
+                for i in 0..10000 {
+                    let entity_key = ecs.insert_entity();
+                    ecs.insert_comp(entity_key, i).unwrap();
+                    ecs.insert_comp(entity_key, i as u32).unwrap();
+                    ecs.insert_comp(entity_key, i as f32).unwrap();
+                }
+                ecs
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("spawn_batch", |b| {
+        b.iter_batched(
+            setup,
+            |mut ecs| {
+                ecs.spawn_batch((0..10000).map(|i| (i, i as u32, i as f32)))
+                    .unwrap();
+                ecs
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, spawn_batch);
+criterion_main!(benches);
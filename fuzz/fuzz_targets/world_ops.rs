@@ -0,0 +1,9 @@
+#![no_main]
+
+libfuzzer_sys::fuzz_target!(|ops: Vec<ecs_tiny::WorldOp>| {
+    let mut ecs = ecs_tiny::ECS::new();
+    for op in &ops {
+        ecs.apply_op(op);
+    }
+    assert_eq!(ecs.validate(), Ok(()));
+});
@@ -0,0 +1,211 @@
+use crate::{change, lifecycle, CompKey, CompRow, EntityKey, Error, Rows, WorldHasher, ECS};
+
+impl<S: WorldHasher> ECS<S> {
+    /// Insert the components into the entity corresponding to the entity key, in order,
+    /// and return the corresponding component keys in the same order.
+    /// The entity and the component type are looked up once, and the capacity of the column and the reference tables
+    /// is reserved up front by the lower bound of the size hint of the iterator.
+    /// Components of a type with a component limit, an insert validator or an insert hook, or registered as world-unique,
+    /// are inserted one by one as by `insert_comp`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If a component cannot be inserted, as in `insert_comp`,
+    /// the components inserted so far are removed and return the `Err(Error)` of the insertion.
+    /// Otherwise, return an `Ok(Vec<CompKey>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    ///
+    /// let comp_keys = ecs.insert_comps(entity_key, 0..3).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp::<i32>(comp_keys[2]), Ok(&2));
+    /// assert_eq!(ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().count(), 3);
+    /// ```
+    pub fn insert_comps<T>(
+        &mut self,
+        entity_key: EntityKey,
+        comps: impl IntoIterator<Item = T>,
+    ) -> Result<Vec<CompKey>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.release_dropped_taken();
+        self.check_entity(entity_key)?;
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+
+        let mut comps = comps.into_iter().peekable();
+        let (additional, _) = comps.size_hint();
+
+        // each component is checked, or hands the world to the hook, between insertions
+        let checked = comp_col.comp_limit.is_some()
+            || comp_col.comp_limit_total.is_some()
+            || comp_col.world_unique
+            || comp_col.insert_validator.is_some()
+            || comp_col.insert_hook.is_some();
+        if checked {
+            let mut comp_keys = Vec::with_capacity(additional);
+            for comp in comps {
+                match self.insert_comp(entity_key, comp) {
+                    Ok(comp_key) => comp_keys.push(comp_key),
+                    Err(error) => {
+                        self.remove_inserted(comp_keys);
+                        return Err(error);
+                    }
+                }
+            }
+            return Ok(comp_keys);
+        }
+
+        // no reference table is left behind by an empty batch
+        if comps.peek().is_none() {
+            return Ok(vec![]);
+        }
+
+        let key_limit = self.key_limit;
+        let tick = self.tick;
+
+        let comp_col = self.comp_cols.get_mut(&type_key).unwrap();
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+        comp_rows.reserve(additional);
+
+        let ref_0_col = self.ref_0_cols.entry(entity_key).or_default();
+        ref_0_col.reserve(additional);
+        let first = ref_0_col.is_empty();
+
+        let ref_1_col = self.ref_1_cols.entry((entity_key, type_key)).or_default();
+        ref_1_col.reserve(additional);
+
+        let mut row_keys = Vec::with_capacity(additional);
+        for comp in comps {
+            let row_key = crate::checked_key_within(key_limit, comp_rows.vacant_key());
+            let ref_0_row_key = crate::checked_key_within(key_limit, ref_0_col.vacant_key());
+
+            ref_0_col.insert((type_key, row_key));
+            let ref_1_row_key = ref_1_col.push(row_key);
+
+            let stable_id = comp_col
+                .stable_ids
+                .as_mut()
+                .map(|stable_ids| stable_ids.assign(row_key));
+
+            let comp_row = CompRow {
+                comp,
+                entity_key,
+                ref_0_row_key,
+                ref_1_row_key,
+                stable_id,
+                ticks: change::Ticks::new(tick),
+            };
+            comp_rows.insert_at(row_key as usize, comp_row);
+
+            row_keys.push(row_key);
+        }
+
+        for _ in &row_keys {
+            comp_col.count_access(true);
+        }
+
+        if first {
+            if let Some(events) = &mut self.lifecycle_events {
+                events.push(lifecycle::EntityLifecycle::FirstComponentAdded(entity_key));
+            }
+        }
+
+        let comp_keys = row_keys
+            .into_iter()
+            .map(|row_key| self.comp_key(type_key, row_key))
+            .collect::<Vec<_>>();
+        for comp_key in &comp_keys {
+            self.note_comp_inserted(entity_key, *comp_key, None);
+        }
+
+        Ok(comp_keys)
+    }
+
+    /// Insert each component into the entity paired with it, and return the corresponding component keys
+    /// in the order of the pairs.
+    /// The pairs are grouped by entity, in the order each entity first appears,
+    /// and the components of each entity are inserted together as by `insert_comps`, keeping their order.
+    /// If a component cannot be inserted, as in `insert_comps`,
+    /// the components inserted so far are removed and return the `Err(Error)` of the insertion.
+    /// Otherwise, return an `Ok(Vec<CompKey>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    ///
+    /// let comp_keys = ecs
+    ///     .insert_comp_batch([(entity_key0, 0), (entity_key1, 1), (entity_key0, 2)])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(ecs.get_entity_by_comp(comp_keys[1]), Ok(entity_key1));
+    /// let comps = ecs.iter_comp_by_entity::<i32>(entity_key0).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![&0, &2]);
+    /// ```
+    pub fn insert_comp_batch<T>(
+        &mut self,
+        comps: impl IntoIterator<Item = (EntityKey, T)>,
+    ) -> Result<Vec<CompKey>, Error>
+    where
+        T: std::any::Any,
+    {
+        let mut groups = Vec::<(EntityKey, Vec<usize>, Vec<T>)>::new();
+        let mut group_indices = crate::hash::HashMap::<EntityKey, usize>::default();
+
+        let mut len = 0;
+        for (entity_key, comp) in comps {
+            let group_index = *group_indices.entry(entity_key).or_insert_with(|| {
+                groups.push((entity_key, vec![], vec![]));
+                groups.len() - 1
+            });
+            let (_, indices, group) = &mut groups[group_index];
+            indices.push(len);
+            group.push(comp);
+            len += 1;
+        }
+
+        let mut comp_keys = vec![None; len];
+        let mut inserted = Vec::with_capacity(len);
+        for (entity_key, indices, group) in groups {
+            match self.insert_comps(entity_key, group) {
+                Ok(group_keys) => {
+                    for (index, comp_key) in indices.into_iter().zip(group_keys) {
+                        comp_keys[index] = Some(comp_key);
+                        inserted.push(comp_key);
+                    }
+                }
+                Err(error) => {
+                    self.remove_inserted(inserted);
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(comp_keys.into_iter().map(Option::unwrap).collect())
+    }
+
+    /// Remove the components inserted so far by a batch that failed, last first.
+    /// A component already removed in the meantime, as by an insert hook, is skipped.
+    fn remove_inserted(&mut self, comp_keys: Vec<CompKey>) {
+        for comp_key in comp_keys.into_iter().rev() {
+            self.remove_comp_row(comp_key);
+        }
+    }
+}
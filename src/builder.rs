@@ -0,0 +1,90 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, ECS};
+
+/// A builder of an entity, inserting each component as it is given, returned by [`ECS::build_entity`].
+///
+/// The first failure is kept and reported by `finish`, and the components given after it are dropped.
+/// Dropping the builder without calling `finish` leaves the entity and its components in place.
+pub struct EntityBuilder<'a, S = crate::DefaultHashBuilder> {
+    ecs: &'a mut ECS<S>,
+    entity_key: EntityKey,
+    comp_keys: Vec<CompKey>,
+    error: Option<Error>,
+}
+
+impl<S: WorldHasher> EntityBuilder<'_, S> {
+    /// Insert the component into the entity, as in `insert_comp`, unless an earlier component failed.
+    pub fn with<T>(mut self, comp: T) -> Self
+    where
+        T: std::any::Any,
+    {
+        if self.error.is_none() {
+            match self.ecs.insert_comp(self.entity_key, comp) {
+                Ok(comp_key) => self.comp_keys.push(comp_key),
+                Err(error) => self.error = Some(error),
+            }
+        }
+
+        self
+    }
+
+    /// Return the entity key of the entity being built.
+    pub fn entity_key(&self) -> EntityKey {
+        self.entity_key
+    }
+
+    /// Return the component keys of the components inserted so far, in the order they were given.
+    pub fn comp_keys(&self) -> &[CompKey] {
+        &self.comp_keys
+    }
+
+    /// Return the first failure so far.
+    /// If no component failed, return an `None`.
+    /// Otherwise, return an `Some(&Error)`.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Finish building the entity.
+    /// If any component failed, the entity is removed along with the components inserted so far,
+    /// and return the first failure.
+    /// Otherwise, return an `Ok(EntityKey)`.
+    pub fn finish(self) -> Result<EntityKey, Error> {
+        if let Some(error) = self.error {
+            self.ecs.remove_entity(self.entity_key).unwrap();
+            return Err(error);
+        }
+
+        Ok(self.entity_key)
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Insert a new entity, and return a builder inserting its components one after another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    ///
+    /// let builder = ecs.build_entity().with(42).with(1u32);
+    /// let comp_keys = builder.comp_keys().to_vec();
+    /// let entity_key = builder.finish().unwrap();
+    /// assert_eq!(ecs.get_comp::<u32>(comp_keys[1]), Ok(&1));
+    ///
+    /// let result = ecs.build_entity().with(63).with(2.0f32).finish();
+    /// assert_eq!(result, Err(ecs_tiny::Error::TypeNotRegistered));
+    /// assert_eq!(ecs.iter_entity().collect::<Vec<_>>(), vec![entity_key]);
+    /// ```
+    pub fn build_entity(&mut self) -> EntityBuilder<'_, S> {
+        let entity_key = self.insert_entity();
+
+        EntityBuilder {
+            ecs: self,
+            entity_key,
+            comp_keys: vec![],
+            error: None,
+        }
+    }
+}
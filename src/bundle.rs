@@ -0,0 +1,159 @@
+use crate::{CompKey, EntityKey, Error, TypedCompKey, WorldHasher, ECS};
+
+/// A typed group of components, implemented for tuples of up to eight component types.
+///
+/// Each component type can appear only once in a bundle.
+pub trait Bundle: Sized {
+    /// The components found for each type, returned by [`ECS::remove_bundle`].
+    type Removed;
+
+    /// The keys of the components inserted for each type, returned by [`ECS::insert_bundle`].
+    type Keys;
+
+    /// Remove the first component of each type from the entity, skipping missing types.
+    #[doc(hidden)]
+    fn remove<S: WorldHasher>(ecs: &mut ECS<S>, entity_key: EntityKey) -> Self::Removed;
+
+    /// Remove the first component of each type from the entity only if every type is present.
+    #[doc(hidden)]
+    fn remove_strict<S: WorldHasher>(
+        ecs: &mut ECS<S>,
+        entity_key: EntityKey,
+    ) -> Result<Self, Error>;
+
+    /// Reserve capacity for the number of additional components of each type.
+    #[doc(hidden)]
+    fn reserve<S: WorldHasher>(ecs: &mut ECS<S>, additional: usize);
+
+    /// Insert each component into the entity, stopping at the first failure.
+    #[doc(hidden)]
+    fn insert<S: WorldHasher>(self, ecs: &mut ECS<S>, entity_key: EntityKey) -> Result<(), Error>;
+
+    /// Insert each component into the entity only if every component can be inserted, and return their keys.
+    #[doc(hidden)]
+    fn insert_all<S: WorldHasher>(
+        self,
+        ecs: &mut ECS<S>,
+        entity_key: EntityKey,
+    ) -> Result<Self::Keys, Error>;
+}
+
+fn check_distinct(type_keys: &[std::any::TypeId]) {
+    for (i, type_key) in type_keys.iter().enumerate() {
+        for other in &type_keys[i + 1..] {
+            assert!(
+                type_key != other,
+                "each component type can appear only once in a bundle"
+            );
+        }
+    }
+}
+
+fn first_comp_key<T>(ecs: &ECS<impl WorldHasher>, entity_key: EntityKey) -> Result<CompKey, Error>
+where
+    T: std::any::Any,
+{
+    let type_key = std::any::TypeId::of::<T>();
+
+    if !ecs.comp_cols.contains_key(&type_key) {
+        return Err(Error::TypeNotRegistered);
+    }
+
+    let ref_1_col = ecs
+        .ref_1_cols
+        .get(&(entity_key, type_key))
+        .ok_or(Error::CompNotFound)?;
+    // the rows of taken components are skipped
+    let row_key = ref_1_col
+        .iter()
+        .find(|row_key| !ecs.is_taken(type_key, **row_key))
+        .ok_or(Error::TemporarilyTaken)?;
+
+    Ok(ecs.comp_key(type_key, *row_key))
+}
+
+fn reserve_comps<T>(ecs: &mut ECS<impl WorldHasher>, additional: usize)
+where
+    T: std::any::Any,
+{
+    // each entity gets a reference entry per type
+    ecs.ref_1_cols.reserve(additional);
+
+    // an unregistered type fails on insertion anyway
+    ecs.reserve_comp::<T>(additional).ok();
+}
+
+fn remove_first<T>(ecs: &mut ECS<impl WorldHasher>, entity_key: EntityKey) -> Option<T>
+where
+    T: std::any::Any,
+{
+    let comp_key = first_comp_key::<T>(ecs, entity_key).ok()?;
+    Some(ecs.remove_comp::<T>(comp_key).unwrap())
+}
+
+macro_rules! impl_bundle {
+    ($(($comp:ident, $index:tt)),*) => {
+        impl<$($comp),*> Bundle for ($($comp,)*)
+        where
+            $($comp: std::any::Any),*
+        {
+            type Removed = ($(Option<$comp>,)*);
+
+            type Keys = ($(TypedCompKey<$comp>,)*);
+
+            fn remove<S: WorldHasher>(ecs: &mut ECS<S>, entity_key: EntityKey) -> Self::Removed {
+                check_distinct(&[$(std::any::TypeId::of::<$comp>()),*]);
+
+                ($(remove_first::<$comp>(ecs, entity_key),)*)
+            }
+
+            fn remove_strict<S: WorldHasher>(ecs: &mut ECS<S>, entity_key: EntityKey) -> Result<Self, Error> {
+                check_distinct(&[$(std::any::TypeId::of::<$comp>()),*]);
+
+                // resolve every component before removing any, so that a missing type changes nothing
+                let comp_keys = [$(first_comp_key::<$comp>(ecs, entity_key)?),*];
+
+                Ok(($(ecs.remove_comp::<$comp>(comp_keys[$index]).unwrap(),)*))
+            }
+
+            fn reserve<S: WorldHasher>(ecs: &mut ECS<S>, additional: usize) {
+                $(reserve_comps::<$comp>(ecs, additional);)*
+            }
+
+            fn insert<S: WorldHasher>(self, ecs: &mut ECS<S>, entity_key: EntityKey) -> Result<(), Error> {
+                check_distinct(&[$(std::any::TypeId::of::<$comp>()),*]);
+
+                $(ecs.insert_comp(entity_key, self.$index)?;)*
+                Ok(())
+            }
+
+            fn insert_all<S: WorldHasher>(self, ecs: &mut ECS<S>, entity_key: EntityKey) -> Result<Self::Keys, Error> {
+                check_distinct(&[$(std::any::TypeId::of::<$comp>()),*]);
+
+                // check every component before inserting any, so that a failure changes nothing,
+                // and defer the hooks, so that their operations cannot fail a checked component
+                $(ecs.check_insert_comp(entity_key, &self.$index)?;)*
+
+                ecs.defer_hooks(|ecs| Ok(($(ecs.insert_comp_typed(entity_key, self.$index)?,)*)))
+            }
+        }
+    };
+}
+
+impl_bundle!((A, 0));
+impl_bundle!((A, 0), (B, 1));
+impl_bundle!((A, 0), (B, 1), (C, 2));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+impl_bundle!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5), (G, 6));
+impl_bundle!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7)
+);
@@ -0,0 +1,94 @@
+use crate::{CompRow, Error, Registered, Rows, WorldHasher, ECS};
+
+impl<S: WorldHasher> ECS<S> {
+    /// Register component type as in `register`, with capacity for at least the number of components,
+    /// as in `reserve_comp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_with_capacity::<i32>(1000).unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ```
+    pub fn register_with_capacity<T>(&mut self, comps: usize) -> Result<Registered<T>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let registered = self.register::<T>()?;
+        self.reserve_comp::<T>(comps).unwrap();
+
+        Ok(registered)
+    }
+
+    /// Reserve capacity for at least the number of additional components of the type,
+    /// so that inserting them does not grow the column.
+    /// The chunked storage, as in `register_chunked`, allocates each chunk as it is reached, so it reserves nothing.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.reserve_comp::<i32>(1000).unwrap();
+    /// ```
+    pub fn reserve_comp<T>(&mut self, additional: usize) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_col = self
+            .comp_cols
+            .get_mut(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .reserve(additional);
+
+        Ok(())
+    }
+
+    /// Shrink the capacity of the entities, of every column and of the reference tables as much as possible,
+    /// as after removing many entities at once.
+    /// No entity or component is moved, so every key stays valid: the storage of the entities and of each column
+    /// can only drop the vacant slots after the last occupied one, and keeps its capacity up to it.
+    /// The generations of the dropped slots are kept, so the keys of the entities and components removed before
+    /// never resolve to the ones inserted into the same slots later.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_keys = ecs.insert_entities(1000);
+    /// for entity_key in &entity_keys[1..] {
+    ///     ecs.remove_entity(*entity_key).unwrap();
+    /// }
+    ///
+    /// ecs.shrink_to_fit();
+    ///
+    /// assert!(ecs.get_entity(entity_keys[0]).is_ok());
+    /// assert!(ecs.get_entity(entity_keys[999]).is_err());
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.entities.shrink_to_fit();
+        self.generations.shrink_to_fit();
+
+        self.ref_0_cols.shrink_to_fit();
+        for ref_0_col in self.ref_0_cols.values_mut() {
+            ref_0_col.shrink_to_fit();
+        }
+
+        self.ref_1_cols.shrink_to_fit();
+        for ref_1_col in self.ref_1_cols.values_mut() {
+            ref_1_col.shrink_to_fit();
+        }
+
+        for comp_col in self.comp_cols.values_mut() {
+            (comp_col.shrink_fn)(comp_col);
+        }
+    }
+}
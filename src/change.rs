@@ -0,0 +1,183 @@
+use crate::{CompKey, CompRow, Error, Rows, WorldHasher, ECS};
+
+/// The ticks of a row, at which its component was inserted and last changed, as in `ECS::tick`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Ticks {
+    pub(crate) added: u64,
+    pub(crate) changed: u64,
+}
+
+impl Ticks {
+    /// Return the ticks of a component inserted at the tick, which counts as changed at the same tick.
+    pub(crate) fn new(tick: u64) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+}
+
+impl<T> CompRow<T> {
+    /// Return the component mutably, marking it changed at the tick.
+    pub(crate) fn comp_mut(&mut self, tick: u64) -> &mut T {
+        self.ticks.changed = tick;
+        &mut self.comp
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return the current change tick, starting at zero, as advanced by `advance_tick`.
+    ///
+    /// Each component records the tick at which it was inserted, as in `iter_comp_added`,
+    /// and the tick at which it was last changed, as in `iter_comp_changed`.
+    /// A component counts as changed whenever it is handed out mutably by `get_comp_mut`,
+    /// the `iter_comp_mut` family or the other methods of the world returning or taking `&mut T`,
+    /// and when it is swapped, set by field, or put back after being taken, whether or not its value is written.
+    /// The views of the world, as the columns, the entity views, the queries and the locked columns,
+    /// mark the components they hand out mutably at the tick of the world when the view was taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// assert_eq!(ecs.tick(), 0);
+    ///
+    /// assert_eq!(ecs.advance_tick(), 1);
+    /// assert_eq!(ecs.tick(), 1);
+    /// ```
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Advance the change tick, as in `tick`, and return the new one.
+    /// This is meant to be called once per frame, before the changes to be told apart from the previous ones.
+    pub fn advance_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Return an iterator over the components of the corresponding type inserted at the tick or later, as in `tick`,
+    /// together with their component keys, in the same order as `iter_comp`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (CompKey, &T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let tick = ecs.advance_tick();
+    /// let comp_key = ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// let comps = ecs.iter_comp_added::<i32>(tick).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![(comp_key, &63)]);
+    /// ```
+    pub fn iter_comp_added<T>(
+        &self,
+        since_tick: u64,
+    ) -> Result<impl Iterator<Item = (CompKey, &T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.iter_comp_since::<T>(move |ticks| ticks.added >= since_tick)
+    }
+
+    /// Return an iterator over the components of the corresponding type changed at the tick or later, as in `tick`,
+    /// together with their component keys, in the same order as `iter_comp`.
+    /// A component inserted at the tick or later counts as changed.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (CompKey, &T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// let tick = ecs.advance_tick();
+    /// *ecs.get_comp_mut::<i32>(comp_key0).unwrap() += 1;
+    ///
+    /// let comps = ecs.iter_comp_changed::<i32>(tick).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![(comp_key0, &43)]);
+    /// ```
+    pub fn iter_comp_changed<T>(
+        &self,
+        since_tick: u64,
+    ) -> Result<impl Iterator<Item = (CompKey, &T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.iter_comp_since::<T>(move |ticks| ticks.changed >= since_tick)
+    }
+
+    fn iter_comp_since<T>(
+        &self,
+        f: impl Fn(Ticks) -> bool,
+    ) -> Result<impl Iterator<Item = (CompKey, &T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let iter = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .filter(move |(_, comp_row)| f(comp_row.ticks))
+            .map(move |(row_key, comp_row)| {
+                (self.comp_key(type_key, row_key as u32), &comp_row.comp)
+            });
+
+        Ok(iter)
+    }
+
+    /// Mark the component with the corresponding component key changed at the current tick, as in `tick`,
+    /// as for a component written through a view of the world, which does not mark it on its own.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`, since it cannot have been written.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let tick = ecs.advance_tick();
+    /// ecs.mark_changed(comp_key).unwrap();
+    ///
+    /// assert_eq!(ecs.iter_comp_changed::<i32>(tick).unwrap().count(), 1);
+    /// ```
+    pub fn mark_changed(&mut self, comp_key: CompKey) -> Result<(), Error> {
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        (comp_col.get_comp_mut_fn)(comp_col, row_key, self.tick)
+            .ok_or_else(|| crate::take::missing_comp(&self.taken, type_key, row_key))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{CompKey, CompRow, EntityKey, Error, Rows, WorldHasher, ECS};
+
+/// The iterator returned by `ECS::drain_comp`, removing each component as it is yielded and the rest when dropped.
+struct DrainComp<'a, T, S: WorldHasher> {
+    ecs: &'a mut ECS<S>,
+    comp_keys: std::vec::IntoIter<CompKey>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T, S: WorldHasher> Iterator for DrainComp<'_, T, S>
+where
+    T: std::any::Any,
+{
+    type Item = (EntityKey, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let comp_key = self.comp_keys.next()?;
+        let entity_key = self.ecs.get_entity_by_comp(comp_key).unwrap();
+        let comp = self.ecs.remove_comp::<T>(comp_key).unwrap();
+        Some((entity_key, comp))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.comp_keys.size_hint()
+    }
+}
+
+impl<T, S: WorldHasher> Drop for DrainComp<'_, T, S> {
+    fn drop(&mut self) {
+        for comp_key in self.comp_keys.by_ref() {
+            self.ecs.remove_comp_row(comp_key).unwrap();
+        }
+    }
+}
+
+/// The progress of the incremental clear of a world, returned by [`ECS::clear_incremental`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearProgress {
+    /// Entities and components were removed within the budget, and some of the entities to clear remain.
+    Pending {
+        /// The number of entities and components removed by the step.
+        removed: usize,
+    },
+    /// All entities to clear are removed.
+    Done {
+        /// The number of entities and components removed by the step.
+        removed: usize,
+    },
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Remove at most the number of entities and components given, counting each entity and each component as one,
+    /// so that repeated steps clear the world without a hitch, as `clear` does at once.
+    /// The first step takes the alive entities at the time, and the following steps remove those entities alone,
+    /// together with all their components, including components inserted in the meantime.
+    /// The world stays consistent between steps, and can be used as usual:
+    /// entities inserted or flushed from reservation in the meantime are kept,
+    /// and entities removed in the meantime are skipped.
+    /// Unlike `clear`, the component types stay registered, and the removals are recorded as by `remove_entity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::ClearProgress;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.clear_incremental(2), ClearProgress::Pending { removed: 2 });
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+    /// assert_eq!(ecs.clear_incremental(2), ClearProgress::Done { removed: 1 });
+    /// assert!(ecs.get_entity(entity_key).is_err());
+    /// ```
+    pub fn clear_incremental(&mut self, budget: usize) -> ClearProgress {
+        let mut clearing = self.clearing.take().unwrap_or_else(|| {
+            // popped from the back, so that the entities are cleared in the order of their keys
+            let mut entity_keys = self
+                .iter_entity_filtered(crate::EntityFilter::ALIVE)
+                .collect::<Vec<_>>();
+            entity_keys.reverse();
+            entity_keys
+        });
+
+        let mut removed = 0;
+        while let Some(entity_key) = clearing.last().copied() {
+            if self.get_entity(entity_key).is_err() {
+                clearing.pop();
+                continue;
+            }
+
+            // an entity removed with its taken components may have gone over the budget
+            let remaining = budget.saturating_sub(removed);
+            if remaining == 0 {
+                break;
+            }
+
+            let comp_count = self.ref_0_cols.get(&entity_key).map_or(0, |c| c.len());
+            let comp_keys = if comp_count < remaining {
+                vec![]
+            } else {
+                self.removable_comp_keys(entity_key, remaining)
+            };
+
+            // the taken components cannot be removed alone, so they go with the entity,
+            // over the budget if they do not fit into a whole one
+            if comp_keys.is_empty() {
+                if comp_count >= remaining && removed > 0 {
+                    break;
+                }
+                self.remove_entity(entity_key).unwrap();
+                removed += comp_count + 1;
+                clearing.pop();
+                continue;
+            }
+
+            for comp_key in &comp_keys {
+                self.remove_comp_row(*comp_key).unwrap();
+            }
+            removed += comp_keys.len();
+        }
+
+        if clearing.is_empty() {
+            ClearProgress::Done { removed }
+        } else {
+            self.clearing = Some(clearing);
+            ClearProgress::Pending { removed }
+        }
+    }
+
+    /// Remove all components of the corresponding type, keeping the type registered and the entities alive,
+    /// and return the number of components removed.
+    /// The removals are recorded as by `remove_comp`, one component after another.
+    /// Taken components, as in `take_comp`, are left in place.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.clear_comp::<i32>(), Ok(2));
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ```
+    pub fn clear_comp<T>(&mut self) -> Result<usize, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let row_keys = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(|(row_key, _)| row_key as u32)
+            .collect::<Vec<_>>();
+
+        Ok(self.remove_rows::<T>(&row_keys))
+    }
+
+    /// Remove all components of the corresponding type, as in `clear_comp`,
+    /// and return an iterator yielding each of them by value with the entity key of its owner, in the order of `iter_comp`.
+    /// Each component is removed as it is yielded, as by `remove_comp`,
+    /// and the components left when the iterator is dropped are removed then, as in `Vec::drain`.
+    /// Taken components, as in `take_comp`, are left in place.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (EntityKey, T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// let mut drain = ecs.drain_comp::<i32>().unwrap();
+    /// assert_eq!(drain.next(), Some((entity_key, 42)));
+    /// drop(drain);
+    ///
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+    /// ```
+    pub fn drain_comp<T>(&mut self) -> Result<impl Iterator<Item = (EntityKey, T)> + '_, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_keys = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(|(row_key, _)| self.comp_key(type_key, row_key as u32))
+            .collect::<Vec<_>>();
+
+        Ok(DrainComp {
+            ecs: self,
+            comp_keys: comp_keys.into_iter(),
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Remove the components of the corresponding type for which the predicate returns false,
+    /// walking the column once in the order of `iter_comp`, and return the number of components removed.
+    /// The remaining components keep their order, as among the components of each entity.
+    /// The removals are recorded as by `remove_comp`, one component after another, once the predicate has seen all components.
+    /// Taken components, as in `take_comp`, are left in place.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, -1).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.retain_comp::<i32>(|_, comp| *comp > 0), Ok(1));
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(), vec![&42, &63]);
+    /// ```
+    pub fn retain_comp<T>(
+        &mut self,
+        mut f: impl FnMut(EntityKey, &T) -> bool,
+    ) -> Result<usize, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let row_keys = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .filter(|(_, comp_row)| !f(comp_row.entity_key, &comp_row.comp))
+            .map(|(row_key, _)| row_key as u32)
+            .collect::<Vec<_>>();
+
+        Ok(self.remove_rows::<T>(&row_keys))
+    }
+
+    /// Remove the components of the corresponding type for which the function returns false, as in `retain_comp`,
+    /// handing each component mutably to the function, as to update it and decide whether to keep it in one pass.
+    /// The components are marked changed, as in `iter_comp_mut`, and their updates are not recorded by the journal.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 1u32).unwrap();
+    /// ecs.insert_comp(entity_key, 3u32).unwrap();
+    ///
+    /// let tick = |_, timer: &mut u32| {
+    ///     *timer -= 1;
+    ///     *timer > 0
+    /// };
+    /// assert_eq!(ecs.retain_comp_mut::<u32>(tick), Ok(1));
+    /// assert_eq!(ecs.iter_comp::<u32>().unwrap().collect::<Vec<_>>(), vec![&2]);
+    /// ```
+    pub fn retain_comp_mut<T>(
+        &mut self,
+        mut f: impl FnMut(EntityKey, &mut T) -> bool,
+    ) -> Result<usize, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let row_keys = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter_mut()
+            .filter_map(|(row_key, comp_row)| {
+                let entity_key = comp_row.entity_key;
+                (!f(entity_key, comp_row.comp_mut(self.tick))).then_some(row_key as u32)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(self.remove_rows::<T>(&row_keys))
+    }
+
+    /// Remove the components of the rows of the type, as in `remove_comp`, and return the number of components removed.
+    fn remove_rows<T>(&mut self, row_keys: &[u32]) -> usize
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+        for row_key in row_keys {
+            self.remove_comp::<T>(self.comp_key(type_key, *row_key))
+                .unwrap();
+        }
+
+        row_keys.len()
+    }
+
+    /// Return the component keys of at most the number of components of the entity given, which are not taken.
+    fn removable_comp_keys(&self, entity_key: EntityKey, max: usize) -> Vec<crate::CompKey> {
+        self.ref_0_cols
+            .get(&entity_key)
+            .into_iter()
+            .flatten()
+            .map(|(_, (type_key, row_key))| (*type_key, *row_key))
+            .filter(|(type_key, row_key)| !self.is_taken(*type_key, *row_key))
+            .take(max)
+            .map(|(type_key, row_key)| self.comp_key(type_key, row_key))
+            .collect()
+    }
+}
@@ -0,0 +1,462 @@
+use crate::{generations, CompKey, CompRow, EntityKey, Error, Rows, WorldHasher, WorldId, ECS};
+
+/// A read-only handle of the column of a component type, returned by [`ECS::column`](crate::ECS::column).
+///
+/// This is a low-level API for building external query engines, indexes and executors,
+/// and may change between minor releases. The handle upholds the following invariants:
+///
+/// - A row key identifies a component of the type, and `comp_key(row_key)` is its component key.
+/// - A row key is stable while the component is alive, and may be reused after the component is removed.
+/// - Every row belongs to exactly one alive entity.
+///
+/// # Examples
+///
+/// A join of two component types, driven by the first column:
+///
+/// ```
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let entity_key0 = ecs.insert_entity();
+/// let entity_key1 = ecs.insert_entity();
+/// ecs.register::<i32>().unwrap();
+/// ecs.register::<u32>().unwrap();
+/// ecs.insert_comp(entity_key0, 42).unwrap();
+/// ecs.insert_comp(entity_key1, 63).unwrap();
+/// ecs.insert_comp(entity_key1, 1u32).unwrap();
+///
+/// let ints = ecs.column::<i32>().unwrap();
+/// let uints = ecs.column::<u32>().unwrap();
+///
+/// let join = ints
+///     .iter_rows()
+///     .filter_map(|(_, entity_key, int)| {
+///         let row_key = ecs.rows_by_entity::<u32>(entity_key).unwrap().next()?;
+///         Some((entity_key, *int, *uints.get(row_key).unwrap()))
+///     })
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(join, vec![(entity_key1, 63, 1)]);
+/// ```
+pub struct Column<'a, T> {
+    comp_rows: &'a Rows<CompRow<T>>,
+    generations: &'a [u32],
+    world: WorldId,
+}
+
+impl<'a, T> Column<'a, T> {
+    pub(crate) fn new(
+        comp_rows: &'a Rows<CompRow<T>>,
+        generations: &'a [u32],
+        world: WorldId,
+    ) -> Self {
+        Self {
+            comp_rows,
+            generations,
+            world,
+        }
+    }
+
+    /// Return the number of rows.
+    pub fn len(&self) -> usize {
+        self.comp_rows.len()
+    }
+
+    /// Return whether the column has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.comp_rows.is_empty()
+    }
+
+    /// Return a component with the corresponding row key.
+    /// If the row is not found, return an `None`.
+    /// Otherwise, return an `Some(&T)`.
+    pub fn get(&self, row_key: u32) -> Option<&'a T> {
+        let comp_row = self.comp_rows.get(row_key as usize)?;
+        Some(&comp_row.comp)
+    }
+
+    /// Return the component key of the row with the corresponding row key, whether or not the row is found.
+    pub fn comp_key(&self, row_key: u32) -> CompKey
+    where
+        T: std::any::Any,
+    {
+        CompKey {
+            type_key: std::any::TypeId::of::<T>(),
+            row_key,
+            generation: generations::generation(self.generations, row_key),
+            world: self.world,
+        }
+    }
+
+    /// Return the entity key owning the row with the corresponding row key.
+    /// If the row is not found, return an `None`.
+    /// Otherwise, return an `Some(EntityKey)`.
+    pub fn entity_key(&self, row_key: u32) -> Option<EntityKey> {
+        let comp_row = self.comp_rows.get(row_key as usize)?;
+        Some(comp_row.entity_key)
+    }
+
+    /// Return an iterator over all rows, together with the row key and the owning entity key.
+    pub fn iter_rows(&self) -> impl Iterator<Item = (u32, EntityKey, &'a T)> + 'a {
+        self.comp_rows
+            .iter()
+            .map(|(row_key, comp_row)| (row_key as u32, comp_row.entity_key, &comp_row.comp))
+    }
+}
+
+/// A mutable handle of the column of a component type, returned by [`ECS::column_mut`](crate::ECS::column_mut).
+///
+/// The handle gives mutable access to the components, but cannot insert or remove rows,
+/// so the invariants of [`Column`] hold as well.
+///
+/// # Examples
+///
+/// ```
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let entity_key = ecs.insert_entity();
+/// ecs.register::<i32>().unwrap();
+/// ecs.insert_comp(entity_key, 42).unwrap();
+///
+/// let row_keys = ecs.rows_by_entity::<i32>(entity_key).unwrap().collect::<Vec<_>>();
+/// let mut ints = ecs.column_mut::<i32>().unwrap();
+/// for row_key in row_keys {
+///     *ints.get_mut(row_key).unwrap() += 1;
+/// }
+///
+/// assert_eq!(ints.get(0), Some(&43));
+/// ```
+pub struct ColumnMut<'a, T> {
+    comp_rows: &'a mut Rows<CompRow<T>>,
+    generations: &'a [u32],
+    ref_1_cols: &'a (dyn crate::refs::RefTables + Sync),
+    world: WorldId,
+    tick: u64,
+}
+
+impl<'a, T> ColumnMut<'a, T> {
+    pub(crate) fn new(
+        comp_rows: &'a mut Rows<CompRow<T>>,
+        generations: &'a [u32],
+        ref_1_cols: &'a (dyn crate::refs::RefTables + Sync),
+        world: WorldId,
+        tick: u64,
+    ) -> Self {
+        Self {
+            comp_rows,
+            generations,
+            ref_1_cols,
+            world,
+            tick,
+        }
+    }
+
+    /// Return the number of rows.
+    pub fn len(&self) -> usize {
+        self.comp_rows.len()
+    }
+
+    /// Return whether the column has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.comp_rows.is_empty()
+    }
+
+    /// Return a component with the corresponding row key.
+    /// If the row is not found, return an `None`.
+    /// Otherwise, return an `Some(&T)`.
+    pub fn get(&self, row_key: u32) -> Option<&T> {
+        let comp_row = self.comp_rows.get(row_key as usize)?;
+        Some(&comp_row.comp)
+    }
+
+    /// Return a mutable component with the corresponding row key.
+    /// If the row is not found, return an `None`.
+    /// Otherwise, return an `Some(&mut T)`.
+    pub fn get_mut(&mut self, row_key: u32) -> Option<&mut T> {
+        let comp_row = self.comp_rows.get_mut(row_key as usize)?;
+        Some(comp_row.comp_mut(self.tick))
+    }
+
+    /// Return the component key of the row with the corresponding row key, whether or not the row is found.
+    pub fn comp_key(&self, row_key: u32) -> CompKey
+    where
+        T: std::any::Any,
+    {
+        CompKey {
+            type_key: std::any::TypeId::of::<T>(),
+            row_key,
+            generation: generations::generation(self.generations, row_key),
+            world: self.world,
+        }
+    }
+
+    /// Return the entity key owning the row with the corresponding row key.
+    /// If the row is not found, return an `None`.
+    /// Otherwise, return an `Some(EntityKey)`.
+    pub fn entity_key(&self, row_key: u32) -> Option<EntityKey> {
+        let comp_row = self.comp_rows.get(row_key as usize)?;
+        Some(comp_row.entity_key)
+    }
+
+    /// Return an iterator over all rows, together with the row key and the owning entity key.
+    pub fn iter_rows(&self) -> impl Iterator<Item = (u32, EntityKey, &T)> {
+        self.comp_rows
+            .iter()
+            .map(|(row_key, comp_row)| (row_key as u32, comp_row.entity_key, &comp_row.comp))
+    }
+
+    /// Return a mutable iterator over all rows, together with the row key and the owning entity key.
+    pub fn iter_rows_mut(&mut self) -> impl Iterator<Item = (u32, EntityKey, &mut T)> {
+        let tick = self.tick;
+        self.comp_rows.iter_mut().map(move |(row_key, comp_row)| {
+            (row_key as u32, comp_row.entity_key, comp_row.comp_mut(tick))
+        })
+    }
+
+    /// Return an iterator over all components of the column.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.comp_rows.iter().map(|(_, comp_row)| &comp_row.comp)
+    }
+
+    /// Return a mutable iterator over all components of the column.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let tick = self.tick;
+        self.comp_rows
+            .iter_mut()
+            .map(move |(_, comp_row)| comp_row.comp_mut(tick))
+    }
+
+    /// Return a component with the corresponding component key.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the component key is not of the column, return an `Err(Error::TypeMismatch)`.
+    /// If the component is not found or taken, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(&T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42u32).unwrap();
+    ///
+    /// let ints = ecs.column_mut::<i32>().unwrap();
+    ///
+    /// assert_eq!(ints.get_comp(comp_key), Err(ecs_tiny::Error::TypeMismatch));
+    /// ```
+    pub fn get_comp(&self, comp_key: CompKey) -> Result<&T, Error>
+    where
+        T: std::any::Any,
+    {
+        let row_key = self.check_comp_key(comp_key)?;
+        self.get(row_key).ok_or(Error::CompNotFound)
+    }
+
+    /// Return a mutable component with the corresponding component key, as in `get_comp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.remove_comp::<i32>(comp_key).unwrap();
+    ///
+    /// let mut ints = ecs.column_mut::<i32>().unwrap();
+    ///
+    /// assert_eq!(ints.get_comp_mut(comp_key), Err(ecs_tiny::Error::CompNotFound));
+    /// ```
+    pub fn get_comp_mut(&mut self, comp_key: CompKey) -> Result<&mut T, Error>
+    where
+        T: std::any::Any,
+    {
+        let row_key = self.check_comp_key(comp_key)?;
+        self.get_mut(row_key).ok_or(Error::CompNotFound)
+    }
+
+    /// Return the first component of the entity with the corresponding entity key, as in `ECS::iter_comp_by_entity`.
+    /// If the entity has no component of the column, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(&T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    ///
+    /// let ints = ecs.column_mut::<i32>().unwrap();
+    ///
+    /// assert_eq!(ints.get_by_entity(entity_key), Err(ecs_tiny::Error::CompNotFound));
+    /// ```
+    pub fn get_by_entity(&self, entity_key: EntityKey) -> Result<&T, Error>
+    where
+        T: std::any::Any,
+    {
+        let row_key = self.first_row_by_entity(entity_key)?;
+        self.get(row_key).ok_or(Error::CompNotFound)
+    }
+
+    /// Return the first component of the entity with the corresponding entity key mutably, as in `get_by_entity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let mut ints = ecs.column_mut::<i32>().unwrap();
+    /// *ints.get_by_entity_mut(entity_key).unwrap() += 1;
+    ///
+    /// assert_eq!(ints.get_by_entity(entity_key), Ok(&43));
+    /// ```
+    pub fn get_by_entity_mut(&mut self, entity_key: EntityKey) -> Result<&mut T, Error>
+    where
+        T: std::any::Any,
+    {
+        let row_key = self.first_row_by_entity(entity_key)?;
+        self.get_mut(row_key).ok_or(Error::CompNotFound)
+    }
+
+    fn check_comp_key(&self, comp_key: CompKey) -> Result<u32, Error>
+    where
+        T: std::any::Any,
+    {
+        if comp_key.world != self.world {
+            return Err(Error::WrongWorld);
+        }
+        if comp_key.type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+        if comp_key != self.comp_key(comp_key.row_key) {
+            return Err(Error::CompNotFound);
+        }
+
+        Ok(comp_key.row_key)
+    }
+
+    /// The rows of taken components are skipped.
+    fn first_row_by_entity(&self, entity_key: EntityKey) -> Result<u32, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ref_1_cols
+            .get(&(entity_key, std::any::TypeId::of::<T>()))
+            .and_then(|ref_1_col| {
+                ref_1_col
+                    .iter()
+                    .copied()
+                    .find(|row_key| self.comp_rows.get(*row_key as usize).is_some())
+            })
+            .ok_or(Error::CompNotFound)
+    }
+}
+
+/// A tuple of distinct component types, whose columns are borrowed mutably at once by [`ECS::columns_mut`].
+pub trait Columns {
+    /// The tuple of the mutable handles of the columns.
+    type ColumnsMut<'a>;
+
+    /// Borrow the columns of the component types mutably at once.
+    #[doc(hidden)]
+    fn columns_mut<S: WorldHasher>(ecs: &mut ECS<S>) -> Result<Self::ColumnsMut<'_>, Error>;
+}
+
+macro_rules! impl_columns {
+    ($($comp:ident),*) => {
+        impl<$($comp),*> Columns for ($($comp,)*)
+        where
+            $($comp: std::any::Any),*
+        {
+            type ColumnsMut<'a> = ($(ColumnMut<'a, $comp>,)*);
+
+            fn columns_mut<S: WorldHasher>(ecs: &mut ECS<S>) -> Result<Self::ColumnsMut<'_>, Error> {
+                let type_keys = [$(std::any::TypeId::of::<$comp>()),*];
+
+                for (i, type_key) in type_keys.iter().enumerate() {
+                    if type_keys[i + 1..].contains(type_key) {
+                        return Err(Error::DuplicateType);
+                    }
+
+                    let comp_col = ecs
+                        .comp_cols
+                        .get(type_key)
+                        .ok_or(Error::TypeNotRegistered)?;
+                    comp_col.count_access(true);
+
+                    if comp_col.immutable {
+                        return Err(Error::ImmutableType);
+                    }
+                }
+
+                let ECS {
+                    comp_cols,
+                    ref_1_cols,
+                    comp_generations,
+                    world,
+                    tick,
+                    ..
+                } = ecs;
+
+                // the types are distinct, so the borrows are disjoint
+                let mut comp_cols = comp_cols.get_disjoint_mut(type_keys.each_ref()).into_iter();
+
+                Ok(($(
+                    ColumnMut::new(
+                        comp_cols
+                            .next()
+                            .unwrap()
+                            .unwrap()
+                            .comp_rows
+                            .downcast_mut::<Rows<CompRow<$comp>>>()
+                            .unwrap(),
+                        comp_generations.of(std::any::TypeId::of::<$comp>()),
+                        ref_1_cols,
+                        *world,
+                        *tick,
+                    ),
+                )*))
+            }
+        }
+    };
+}
+
+impl_columns!(A, B);
+impl_columns!(A, B, C);
+impl_columns!(A, B, C, D);
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return mutable handles of the columns of distinct component types at once,
+    /// so that components of several types can be read and written at the same time.
+    /// The handles cannot insert or remove components, as in [`ColumnMut`].
+    /// If a component type appears more than once, return an `Err(Error::DuplicateType)`.
+    /// If any component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If any component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(C::ColumnsMut)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 1u32).unwrap();
+    ///
+    /// let (mut ints, uints) = ecs.columns_mut::<(i32, u32)>().unwrap();
+    /// for (_, entity_key, comp) in ints.iter_rows_mut() {
+    ///     *comp += *uints.get_by_entity(entity_key).unwrap() as i32;
+    /// }
+    ///
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(), vec![&43]);
+    /// assert!(ecs.columns_mut::<(i32, i32)>().is_err());
+    /// ```
+    pub fn columns_mut<C>(&mut self) -> Result<C::ColumnsMut<'_>, Error>
+    where
+        C: Columns,
+    {
+        C::columns_mut(self)
+    }
+}
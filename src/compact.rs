@@ -0,0 +1,178 @@
+use crate::{CompKey, CompRow, Error, Rows, WorldHasher, WorldOp, ECS};
+
+/// The progress of the incremental compaction of a column, returned by [`ECS::compact_step`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactProgress {
+    /// Rows were relocated within the budget, and vacant slots remain before the last row.
+    Pending {
+        /// The number of rows relocated by the step.
+        relocated: usize,
+    },
+    /// The column is dense.
+    Done {
+        /// The number of rows relocated by the step.
+        relocated: usize,
+    },
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Relocate at most the number of rows given from the back of the column of the component type
+    /// into the vacant slots toward the front, fixing the reference tables,
+    /// so that repeated steps make the column dense without a hitch.
+    /// Each relocation changes the component key of the component,
+    /// and is recorded into the remaps of the type, as in `take_remaps`.
+    /// Relocating any row discards the undo history.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If a component of the type is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`,
+    /// since its row must stay in place.
+    /// Otherwise, return an `Ok(CompactProgress)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key, 63).unwrap();
+    /// ecs.remove_comp::<i32>(comp_key0).unwrap();
+    ///
+    /// let progress = ecs.compact_step::<i32>(16).unwrap();
+    /// let remaps = ecs.take_remaps::<i32>().unwrap();
+    ///
+    /// assert_eq!(progress, ecs_tiny::CompactProgress::Done { relocated: 1 });
+    /// assert_eq!(ecs.get_comp::<i32>(remaps[&comp_key1]), Ok(&63));
+    /// ```
+    pub fn compact_step<T>(&mut self, budget_rows: usize) -> Result<CompactProgress, Error>
+    where
+        T: std::any::Any,
+    {
+        self.release_dropped_taken();
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        if self
+            .taken
+            .keys()
+            .any(|(taken_type_key, _)| *taken_type_key == type_key)
+        {
+            return Err(Error::TemporarilyTaken);
+        }
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+        let remaps = &mut comp_col.remaps;
+        let unique_row = &mut comp_col.unique_row;
+        let stable_ids = &mut comp_col.stable_ids;
+        let ref_0_cols = &mut self.ref_0_cols;
+        let ref_1_cols = &mut self.ref_1_cols;
+        let comp_generations = &mut self.comp_generations;
+
+        // the slab moves its last rows into its first vacant slots, until the closure declines a move
+        let mut relocated = 0;
+        comp_rows.compact(|comp_row, from, to| {
+            if relocated == budget_rows {
+                return false;
+            }
+
+            let (from, to) = (from as u32, to as u32);
+
+            let ref_0_col = ref_0_cols.get_mut(&comp_row.entity_key).unwrap();
+            ref_0_col[comp_row.ref_0_row_key as usize].1 = to;
+
+            let ref_1_col = ref_1_cols
+                .get_mut(&(comp_row.entity_key, type_key))
+                .unwrap();
+            ref_1_col.set(comp_row.ref_1_row_key, to);
+
+            let original = remaps
+                .remove(&from)
+                .unwrap_or((from, comp_generations.get(type_key, from)));
+            remaps.insert(to, original);
+            // the relocated component takes the generation of its new row, as if removed and inserted again
+            comp_generations.bump(type_key, from);
+
+            if *unique_row == Some(from) {
+                *unique_row = Some(to);
+            }
+
+            if let (Some(stable_ids), Some(stable_id)) = (stable_ids.as_mut(), comp_row.stable_id) {
+                stable_ids.rows.insert(stable_id, to);
+            }
+
+            relocated += 1;
+            true
+        });
+
+        // compaction drops the trailing vacant slots and lists the rest from the front,
+        // so the first vacant slot is past the last row only if the column is dense
+        let dense = comp_rows.vacant_key() == comp_rows.len();
+
+        if relocated > 0 {
+            self.discard_undo();
+            self.record(None, |_| WorldOp::CompactStep {
+                type_name: std::any::type_name::<T>(),
+                budget_rows,
+                compact_fn: |ecs, budget_rows| ecs.compact_step::<T>(budget_rows).map(|_| ()),
+            });
+        }
+
+        match dense {
+            true => Ok(CompactProgress::Done { relocated }),
+            false => Ok(CompactProgress::Pending { relocated }),
+        }
+    }
+
+    /// Take the component keys changed by `compact_step` since the last call, from the original to the current key.
+    /// A component relocated several times maps from its key before the first relocation,
+    /// and a component removed since its relocation is dropped.
+    /// The original keys are rejected as the keys of removed components, even once their rows are reused.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(HashMap<CompKey, CompKey>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    /// ecs.remove_comp::<i32>(comp_key0).unwrap();
+    /// ecs.compact_step::<i32>(16).unwrap();
+    ///
+    /// assert_eq!(ecs.take_remaps::<i32>().unwrap().len(), 1);
+    /// assert!(ecs.take_remaps::<i32>().unwrap().is_empty());
+    /// ```
+    pub fn take_remaps<T>(&mut self) -> Result<std::collections::HashMap<CompKey, CompKey>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        let remaps = std::mem::take(&mut comp_col.remaps);
+
+        let remaps = remaps
+            .into_iter()
+            .map(|(row_key, (original, generation))| {
+                let original = CompKey {
+                    generation,
+                    ..self.comp_key(type_key, original)
+                };
+                (original, self.comp_key(type_key, row_key))
+            })
+            .collect();
+
+        Ok(remaps)
+    }
+}
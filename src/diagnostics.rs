@@ -0,0 +1,307 @@
+use crate::{EntityFilter, EntityKey, WorldHasher, ECS};
+
+/// A histogram of the number of components per entity, returned by [`ECS::comp_histogram`].
+///
+/// The bucket `0` holds the entities without components,
+/// and the bucket `i` holds the entities with `2^(i - 1)` to `2^i - 1` components.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    buckets: Vec<usize>,
+}
+
+impl Histogram {
+    /// Return the number of entities in each bucket, up to the last non-empty bucket.
+    pub fn buckets(&self) -> &[usize] {
+        &self.buckets
+    }
+
+    /// Return the range of the number of components counted by the bucket.
+    pub fn bucket_range(index: usize) -> std::ops::RangeInclusive<usize> {
+        match index {
+            0 => 0..=0,
+            _ => 1 << (index - 1)..=(1 << index) - 1,
+        }
+    }
+
+    /// Return the number of entities counted by the histogram.
+    pub fn entity_count(&self) -> usize {
+        self.buckets.iter().sum()
+    }
+
+    fn insert(&mut self, comp_count: usize) {
+        let index = (usize::BITS - comp_count.leading_zeros()) as usize;
+
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+
+        self.buckets[index] += 1;
+    }
+}
+
+impl std::fmt::Display for Histogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.buckets.is_empty() {
+            return write!(f, "no entities");
+        }
+
+        let labels = (0..self.buckets.len())
+            .map(|index| {
+                let range = Histogram::bucket_range(index);
+                if range.start() == range.end() {
+                    format!("{}", range.start())
+                } else {
+                    format!("{}..={}", range.start(), range.end())
+                }
+            })
+            .collect::<Vec<_>>();
+        let width = labels.iter().map(|label| label.len()).max().unwrap();
+
+        for (index, (label, count)) in labels.iter().zip(&self.buckets).enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:>width$} components: {} entities", label, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return a histogram of the number of components per entity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.comp_histogram().buckets(), &[1, 1]);
+    /// ```
+    pub fn comp_histogram(&self) -> Histogram {
+        let mut histogram = Histogram::default();
+
+        for entity_key in self.iter_entity_filtered(EntityFilter::ALIVE) {
+            histogram.insert(self.comp_count(entity_key));
+        }
+
+        histogram
+    }
+
+    /// Return the entities with the most components and their numbers of components, at most the number given,
+    /// in descending order of the number of components, and then in ascending order of the entity keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key1, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.top_entities_by_comp_count(1), vec![(entity_key1, 1)]);
+    /// ```
+    pub fn top_entities_by_comp_count(&self, n: usize) -> Vec<(EntityKey, usize)> {
+        let mut counts = self
+            .iter_entity_filtered(EntityFilter::ALIVE)
+            .map(|entity_key| (entity_key, self.comp_count(entity_key)))
+            .collect::<Vec<_>>();
+
+        let by_count =
+            |a: &(EntityKey, usize), b: &(EntityKey, usize)| b.1.cmp(&a.1).then(a.0.cmp(&b.0));
+
+        // only the first elements need sorting
+        if n < counts.len() {
+            counts.select_nth_unstable_by(n, by_count);
+            counts.truncate(n);
+        }
+        counts.sort_unstable_by(by_count);
+
+        counts
+    }
+
+    fn comp_count(&self, entity_key: EntityKey) -> usize {
+        self.ref_0_cols
+            .get(&entity_key)
+            .map_or(0, |ref_0_col| ref_0_col.len())
+    }
+}
+
+/// A report of the orphaned entries of the bookkeeping, returned by [`ECS::find_orphans`].
+/// Each list is sorted, so that reports can be compared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrphanReport {
+    /// The component rows owned by an entity that is not alive.
+    pub dead_owners: Vec<OrphanRow>,
+    /// The entries of the reference tables by entity and type pointing at a vacant row.
+    pub vacant_refs: Vec<OrphanRow>,
+    /// The entries of the reference tables by entity referencing a component type that is not registered.
+    pub unregistered_refs: Vec<UnregisteredRef>,
+}
+
+impl OrphanReport {
+    /// Return whether no orphan is found.
+    pub fn is_empty(&self) -> bool {
+        self.dead_owners.is_empty()
+            && self.vacant_refs.is_empty()
+            && self.unregistered_refs.is_empty()
+    }
+}
+
+impl std::fmt::Display for OrphanReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no orphans");
+        }
+
+        let mut lines = vec![];
+        for row in &self.dead_owners {
+            lines.push(format!(
+                "row {} of {} owned by dead entity {:?}",
+                row.row_key, row.type_name, row.entity_key
+            ));
+        }
+        for row in &self.vacant_refs {
+            lines.push(format!(
+                "reference of entity {:?} to vacant row {} of {}",
+                row.entity_key, row.row_key, row.type_name
+            ));
+        }
+        for entry in &self.unregistered_refs {
+            lines.push(format!(
+                "reference of entity {:?} to row {} of unregistered type {:?}",
+                entry.entity_key, entry.row_key, entry.type_key
+            ));
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// The sizes of the reference tables, returned by [`ECS::ref_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefStats {
+    /// The number of entities with a reference table, that is, with at least one component.
+    pub ref_0_entries: usize,
+    /// The number of pairs of an entity and a component type with a reference table,
+    /// that is, with at least one component of the type.
+    pub ref_1_entries: usize,
+}
+
+/// A component row or a reference entry of an orphan, as in [`OrphanReport`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OrphanRow {
+    /// The name of the component type.
+    pub type_name: &'static str,
+    /// The entity key of the owner or of the entry.
+    pub entity_key: EntityKey,
+    /// The row key in the column of the type.
+    pub row_key: u32,
+}
+
+/// A reference entry to a component type that is not registered, as in [`OrphanReport`].
+/// The name of the type is not known, since the column is gone.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnregisteredRef {
+    /// The type key of the component type.
+    pub type_key: std::any::TypeId,
+    /// The entity key of the entry.
+    pub entity_key: EntityKey,
+    /// The row key the entry points at.
+    pub row_key: u32,
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return the orphaned entries of the bookkeeping:
+    /// component rows owned by dead entities, references to vacant rows,
+    /// and references to component types that are not registered.
+    /// This visits every row and every reference once, and is cheaper than `validate`,
+    /// which also checks that every reference points back at its row.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert!(ecs.find_orphans().is_empty());
+    /// ```
+    pub fn find_orphans(&self) -> OrphanReport {
+        let mut report = OrphanReport::default();
+
+        for comp_col in self.comp_cols.values() {
+            for (row_key, entity_key) in (comp_col.iter_owners_fn)(comp_col) {
+                if self.check_entity(entity_key).is_err() {
+                    report.dead_owners.push(OrphanRow {
+                        type_name: comp_col.type_name,
+                        entity_key,
+                        row_key,
+                    });
+                }
+            }
+        }
+
+        for ((entity_key, type_key), ref_1_col) in &self.ref_1_cols {
+            let Some(comp_col) = self.comp_cols.get(type_key) else {
+                continue;
+            };
+
+            for row_key in ref_1_col {
+                if self.get_row_or_taken(*type_key, *row_key).is_none() {
+                    report.vacant_refs.push(OrphanRow {
+                        type_name: comp_col.type_name,
+                        entity_key: *entity_key,
+                        row_key: *row_key,
+                    });
+                }
+            }
+        }
+
+        for (entity_key, ref_0_col) in &self.ref_0_cols {
+            for (_, (type_key, row_key)) in ref_0_col {
+                if !self.comp_cols.contains_key(type_key) {
+                    report.unregistered_refs.push(UnregisteredRef {
+                        type_key: *type_key,
+                        entity_key: *entity_key,
+                        row_key: *row_key,
+                    });
+                }
+            }
+        }
+
+        report.dead_owners.sort_unstable();
+        report.vacant_refs.sort_unstable();
+        report.unregistered_refs.sort_unstable();
+
+        report
+    }
+    /// Return the sizes of the reference tables.
+    /// The tables of an entity, and of an entity and a type, are dropped with their last component,
+    /// so that the sizes are bounded by the components alive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// assert_eq!(ecs.ref_stats().ref_1_entries, 1);
+    ///
+    /// ecs.remove_comp::<i32>(comp_key).unwrap();
+    /// assert_eq!(ecs.ref_stats(), ecs_tiny::RefStats::default());
+    /// ```
+    pub fn ref_stats(&self) -> RefStats {
+        RefStats {
+            ref_0_entries: self.ref_0_cols.len(),
+            ref_1_entries: self.ref_1_cols.len(),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, ECS};
+
+/// The differences between two worlds, returned by [`ECS::diff`].
+///
+/// The keys are compared by slot index and generation, or row key and generation, regardless of the world identifier,
+/// so that two worlds driven by the same operations compare equal.
+/// The keys of the entities and components found only in the other world are the keys of the other world.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorldDiff {
+    /// The entities alive only in the world, by slot index.
+    pub only_in_self: Vec<EntityKey>,
+    /// The entities alive only in the other world, by slot index.
+    pub only_in_other: Vec<EntityKey>,
+    /// The components that differ, by type name and row key.
+    pub comps: Vec<CompDiff>,
+    /// The names of the component types holding components that are not compared,
+    /// because the type is not compared, as in `set_eq`, or not registered in both worlds.
+    pub unchecked_types: Vec<&'static str>,
+}
+
+impl WorldDiff {
+    /// Return whether no difference is found.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty() && self.comps.is_empty()
+    }
+}
+
+/// A component that differs between two worlds, as in [`WorldDiff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompDiff {
+    /// The entity key of the entity of the component.
+    pub entity_key: EntityKey,
+    /// The name of the component type.
+    pub type_name: &'static str,
+    /// The component key of the component.
+    pub comp_key: CompKey,
+    /// How the component differs.
+    pub kind: CompDiffKind,
+}
+
+/// How a component differs between two worlds, as in [`CompDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompDiffKind {
+    /// The component is found only in the world.
+    OnlyInSelf,
+    /// The component is found only in the other world.
+    OnlyInOther,
+    /// The component belongs to another entity in the other world.
+    OwnerDiffers,
+    /// The values of the component differ.
+    ValueDiffers,
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Compare the components of the type between worlds, as in `diff`, by the `PartialEq` implementation of the type.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.set_eq::<i32>().unwrap();
+    /// ```
+    pub fn set_eq<T>(&mut self) -> Result<(), Error>
+    where
+        T: PartialEq + std::any::Any,
+    {
+        let comp_col = self
+            .comp_cols
+            .get_mut(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.eq_fn = Some(|comp0, comp1| {
+            comp0.downcast_ref::<T>().unwrap() == comp1.downcast_ref::<T>().unwrap()
+        });
+
+        Ok(())
+    }
+
+    /// Return the differences between the world and the other world, as for finding where two peers of a
+    /// deterministic simulation diverge: the entities alive in only one of them,
+    /// and the components of the types compared in the world, as in `set_eq`,
+    /// that are found in only one of them, belong to different entities, or hold different values.
+    /// The entities are ordered by slot index, and the components by type name and row key,
+    /// so that the result does not depend on the order of insertion into the internal tables.
+    /// Reserved entities, as in `reserve_entity`, and taken components, as in `take_comp`, are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// ecs.set_eq::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let mut peer = ecs.clone_filtered(|_| true).unwrap();
+    /// assert!(ecs.diff(&peer).is_empty());
+    ///
+    /// *peer.get_comp_mut::<i32>(comp_key).unwrap() += 1;
+    /// let diff = ecs.diff(&peer);
+    /// assert_eq!(diff.comps[0].comp_key, comp_key);
+    /// assert_eq!(diff.comps[0].kind, ecs_tiny::CompDiffKind::ValueDiffers);
+    /// ```
+    pub fn diff(&self, other: &ECS<S>) -> WorldDiff {
+        let mut diff = WorldDiff::default();
+
+        let entities0 = alive_entities(self);
+        let entities1 = alive_entities(other);
+        merge_by_key(&entities0, &entities1, |entity_key0, entity_key1| {
+            match (entity_key0, entity_key1) {
+                (Some(entity_key0), Some(entity_key1))
+                    if entity_key0.generation == entity_key1.generation => {}
+                _ => {
+                    diff.only_in_self.extend(entity_key0);
+                    diff.only_in_other.extend(entity_key1);
+                }
+            }
+        });
+
+        let mut comp_cols = self.comp_cols.iter().collect::<Vec<_>>();
+        comp_cols.sort_by_key(|(_, comp_col)| (comp_col.type_name, comp_col.registration));
+
+        for (type_key, comp_col0) in comp_cols {
+            let comp_col1 = other.comp_cols.get(type_key);
+            let (Some(eq_fn), Some(comp_col1)) = (comp_col0.eq_fn, comp_col1) else {
+                if (comp_col0.len_fn)(comp_col0) > 0 {
+                    diff.unchecked_types.push(comp_col0.type_name);
+                }
+                continue;
+            };
+
+            let rows0 = owned_rows(self, *type_key);
+            let rows1 = owned_rows(other, *type_key);
+            merge_by_key(&rows0, &rows1, |row0, row1| {
+                let comp_diff =
+                    |ecs: &ECS<S>, (row_key, entity_key): (u32, EntityKey), kind| CompDiff {
+                        entity_key,
+                        type_name: comp_col0.type_name,
+                        comp_key: ecs.comp_key(*type_key, row_key),
+                        kind,
+                    };
+
+                match (row0, row1) {
+                    (Some(row0), Some(row1))
+                        if self.comp_generations.get(*type_key, row0.0)
+                            == other.comp_generations.get(*type_key, row1.0) =>
+                    {
+                        if (row0.1.index, row0.1.generation) != (row1.1.index, row1.1.generation) {
+                            diff.comps
+                                .push(comp_diff(self, row0, CompDiffKind::OwnerDiffers));
+                            return;
+                        }
+
+                        let comp0 = (comp_col0.get_comp_fn)(comp_col0, row0.0).unwrap();
+                        let comp1 = (comp_col1.get_comp_fn)(comp_col1, row1.0).unwrap();
+                        if !eq_fn(comp0, comp1) {
+                            diff.comps
+                                .push(comp_diff(self, row0, CompDiffKind::ValueDiffers));
+                        }
+                    }
+                    _ => {
+                        if let Some(row0) = row0 {
+                            diff.comps
+                                .push(comp_diff(self, row0, CompDiffKind::OnlyInSelf));
+                        }
+                        if let Some(row1) = row1 {
+                            diff.comps
+                                .push(comp_diff(other, row1, CompDiffKind::OnlyInOther));
+                        }
+                    }
+                }
+            });
+        }
+
+        diff.unchecked_types.extend(
+            other
+                .comp_cols
+                .iter()
+                .filter(|(type_key, comp_col1)| {
+                    !self.comp_cols.contains_key(type_key) && (comp_col1.len_fn)(comp_col1) > 0
+                })
+                .map(|(_, comp_col1)| comp_col1.type_name),
+        );
+        diff.unchecked_types.sort_unstable();
+
+        diff
+    }
+}
+
+/// Return the keys of the alive entities by slot index, leaving out the reserved ones.
+fn alive_entities<S: WorldHasher>(ecs: &ECS<S>) -> Vec<(u32, EntityKey)> {
+    ecs.iter_entity_slots()
+        .filter(|(_, slot)| !slot.is_reserved())
+        .map(|(entity_key, _)| (entity_key.index, entity_key))
+        .collect()
+}
+
+/// Return the row keys and the owners of the components of the type, by row key.
+fn owned_rows<S: WorldHasher>(
+    ecs: &ECS<S>,
+    type_key: std::any::TypeId,
+) -> Vec<(u32, (u32, EntityKey))> {
+    let comp_col = &ecs.comp_cols[&type_key];
+    let mut rows = (comp_col.iter_owners_fn)(comp_col)
+        .map(|(row_key, entity_key)| (row_key, (row_key, entity_key)))
+        .collect::<Vec<_>>();
+    rows.sort_unstable_by_key(|(row_key, _)| *row_key);
+    rows
+}
+
+/// Walk two lists sorted by key together, calling the function with the values of each key found in either.
+fn merge_by_key<V>(
+    values0: &[(u32, V)],
+    values1: &[(u32, V)],
+    mut f: impl FnMut(Option<V>, Option<V>),
+) where
+    V: Copy,
+{
+    let (mut i0, mut i1) = (0, 0);
+    loop {
+        match (values0.get(i0), values1.get(i1)) {
+            (None, None) => break,
+            (Some((key0, value0)), Some((key1, value1))) if key0 == key1 => {
+                f(Some(*value0), Some(*value1));
+                i0 += 1;
+                i1 += 1;
+            }
+            (Some((key0, value0)), Some((key1, _))) if key0 < key1 => {
+                f(Some(*value0), None);
+                i0 += 1;
+            }
+            (Some((_, value0)), None) => {
+                f(Some(*value0), None);
+                i0 += 1;
+            }
+            (_, Some((_, value1))) => {
+                f(None, Some(*value1));
+                i1 += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use crate::{EntityKey, EntityStatus, Error, PartitionId, WorldHasher, WorldOp, ECS};
+
+/// A set of entity states, selecting the entities yielded by [`ECS::iter_entity_filtered`].
+///
+/// Each entity carries a set of flags: a reserved entity carries `RESERVED` alone,
+/// and an alive entity carries `DISABLED` and `PENDING_DESPAWN` in any combination, or `LIVE` if neither.
+/// An entity is selected if all of its flags are in the filter, so that
+/// `LIVE` selects the entities neither disabled nor pending despawn, as in `iter_entity`,
+/// `LIVE | DISABLED` adds the disabled entities not pending despawn,
+/// `DISABLED | PENDING_DESPAWN` selects every entity carrying any of the two,
+/// `ALIVE` selects every alive entity, and `ALL` adds the reserved entities.
+///
+/// The flags only affect the selection of entities.
+/// Disabled entities and entities pending despawn are alive otherwise, and keep their components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityFilter(u8);
+
+impl EntityFilter {
+    /// The entities neither disabled nor pending despawn.
+    pub const LIVE: Self = Self(1);
+    /// The disabled entities, as in `disable_entity`.
+    pub const DISABLED: Self = Self(1 << 1);
+    /// The entities pending despawn, as in `despawn_deferred`.
+    pub const PENDING_DESPAWN: Self = Self(1 << 2);
+    /// The reserved entities, as in `reserve_entity`.
+    pub const RESERVED: Self = Self(1 << 3);
+    /// All alive entities.
+    pub const ALIVE: Self = Self(Self::LIVE.0 | Self::DISABLED.0 | Self::PENDING_DESPAWN.0);
+    /// All alive and reserved entities.
+    pub const ALL: Self = Self(Self::ALIVE.0 | Self::RESERVED.0);
+
+    const NONE: Self = Self(0);
+
+    /// Return whether all flags of the other set are in the set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl std::ops::BitOr for EntityFilter {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for EntityFilter {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The slot of an entity in the storage of the entities.
+#[derive(Clone, Copy)]
+pub(crate) struct EntitySlot {
+    pub(crate) partition: PartitionId,
+    /// The flags of the entity, without `LIVE`, which stands for none.
+    flags: EntityFilter,
+}
+
+impl EntitySlot {
+    pub(crate) fn new(partition: PartitionId) -> Self {
+        Self {
+            partition,
+            flags: EntityFilter::NONE,
+        }
+    }
+
+    pub(crate) fn is_reserved(&self) -> bool {
+        self.flags == EntityFilter::RESERVED
+    }
+
+    /// Return the flags of the entity, with `LIVE` if it carries no other flag.
+    pub(crate) fn flags(&self) -> EntityFilter {
+        if self.flags == EntityFilter::NONE {
+            EntityFilter::LIVE
+        } else {
+            self.flags
+        }
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return an iterator over the entity keys of all entities selected by the filter, as in `EntityFilter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::EntityFilter;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.disable_entity(entity_key1).unwrap();
+    ///
+    /// let disabled = ecs.iter_entity_filtered(EntityFilter::DISABLED).collect::<Vec<_>>();
+    /// assert_eq!(disabled, vec![entity_key1]);
+    /// assert_eq!(ecs.iter_entity_filtered(EntityFilter::ALIVE).count(), 2);
+    /// ```
+    pub fn iter_entity_filtered(
+        &self,
+        filter: EntityFilter,
+    ) -> impl Iterator<Item = EntityKey> + '_ {
+        self.iter_entity_slots()
+            .filter(move |(_, slot)| filter.contains(slot.flags()))
+            .map(|(entity_key, _)| entity_key)
+    }
+
+    /// Return an iterator over the entity keys and the slots of all alive and reserved entities.
+    pub(crate) fn iter_entity_slots(&self) -> impl Iterator<Item = (EntityKey, &EntitySlot)> + '_ {
+        self.entities.iter().map(|(index, slot)| {
+            let entity_key = EntityKey {
+                index: index as u32,
+                generation: self.generations[index],
+                world: self.world,
+            };
+            (entity_key, slot)
+        })
+    }
+
+    /// Return the flags of the entity with the corresponding entity key, as in `EntityFilter`.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is neither alive nor reserved, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(EntityFilter)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::EntityFilter;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.disable_entity(entity_key).unwrap();
+    /// ecs.despawn_deferred(entity_key).unwrap();
+    ///
+    /// assert_eq!(
+    ///     ecs.entity_flags(entity_key),
+    ///     Ok(EntityFilter::DISABLED | EntityFilter::PENDING_DESPAWN)
+    /// );
+    /// ```
+    pub fn entity_flags(&self, entity_key: EntityKey) -> Result<EntityFilter, Error> {
+        if entity_key.world != self.world {
+            return Err(Error::WrongWorld);
+        }
+
+        match self.entity_status(entity_key) {
+            EntityStatus::Alive | EntityStatus::Reserved => {
+                Ok(self.entities[entity_key.index as usize].flags())
+            }
+            status => Err(Error::EntityNotFound(status)),
+        }
+    }
+
+    /// Disable the entity with the corresponding entity key,
+    /// so that it is skipped by `iter_entity`, while keeping it alive.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.disable_entity(entity_key).unwrap();
+    ///
+    /// assert_eq!(ecs.iter_entity().count(), 0);
+    /// assert!(ecs.get_entity(entity_key).is_ok());
+    /// ```
+    pub fn disable_entity(&mut self, entity_key: EntityKey) -> Result<(), Error> {
+        self.update_entity_flags(entity_key, |flags| flags | EntityFilter::DISABLED)
+    }
+
+    /// Enable the disabled entity with the corresponding entity key, as in `disable_entity`.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.disable_entity(entity_key).unwrap();
+    /// ecs.enable_entity(entity_key).unwrap();
+    ///
+    /// assert_eq!(ecs.iter_entity().count(), 1);
+    /// ```
+    pub fn enable_entity(&mut self, entity_key: EntityKey) -> Result<(), Error> {
+        self.update_entity_flags(entity_key, |flags| flags.without(EntityFilter::DISABLED))
+    }
+
+    /// Mark the entity with the corresponding entity key to be removed by the next `flush_despawns`,
+    /// so that it is skipped by `iter_entity` until then, while keeping it alive.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.despawn_deferred(entity_key).unwrap();
+    ///
+    /// assert!(ecs.get_entity(entity_key).is_ok());
+    /// assert_eq!(ecs.flush_despawns(), 1);
+    /// assert!(ecs.get_entity(entity_key).is_err());
+    /// ```
+    pub fn despawn_deferred(&mut self, entity_key: EntityKey) -> Result<(), Error> {
+        self.update_entity_flags(entity_key, |flags| flags | EntityFilter::PENDING_DESPAWN)
+    }
+
+    /// Remove all entities pending despawn, as in `despawn_deferred`, together with their components,
+    /// and return the number of entities removed.
+    pub fn flush_despawns(&mut self) -> usize {
+        let entity_keys = self
+            .iter_entity_slots()
+            .filter(|(_, slot)| slot.flags.contains(EntityFilter::PENDING_DESPAWN))
+            .map(|(entity_key, _)| entity_key)
+            .collect::<Vec<_>>();
+
+        for entity_key in &entity_keys {
+            self.remove_entity(*entity_key).unwrap();
+        }
+
+        entity_keys.len()
+    }
+
+    /// Reserve a new entity key in the default partition, and return the corresponding entity key.
+    /// The entity is not alive until the next `flush_reserved`, so it cannot be given components until then.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.reserve_entity();
+    ///
+    /// assert_eq!(ecs.entity_status(entity_key), ecs_tiny::EntityStatus::Reserved);
+    /// assert_eq!(ecs.flush_reserved(), 1);
+    /// assert_eq!(ecs.entity_status(entity_key), ecs_tiny::EntityStatus::Alive);
+    /// ```
+    pub fn reserve_entity(&mut self) -> EntityKey {
+        let slot = EntitySlot {
+            partition: PartitionId::DEFAULT,
+            flags: EntityFilter::RESERVED,
+        };
+        let key = self.checked_key(self.entities.vacant_key());
+        let index = self.entities.insert(slot);
+        self.reserved_count += 1;
+
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+
+        EntityKey {
+            index: key,
+            generation: self.generations[index],
+            world: self.world,
+        }
+    }
+
+    /// Insert all reserved entities, as in `reserve_entity`, and return the number of entities inserted.
+    pub fn flush_reserved(&mut self) -> usize {
+        let entity_keys = self
+            .iter_entity_slots()
+            .filter(|(_, slot)| slot.is_reserved())
+            .map(|(entity_key, _)| entity_key)
+            .collect::<Vec<_>>();
+        self.reserved_count = 0;
+
+        for entity_key in &entity_keys {
+            self.entities[entity_key.index as usize].flags = EntityFilter::NONE;
+
+            let inverse = self.capture_undo(|_| {
+                vec![WorldOp::RemoveEntity {
+                    entity_key: *entity_key,
+                }]
+            });
+            self.record(inverse, |_| WorldOp::InsertEntity {
+                entity_key: *entity_key,
+                partition: PartitionId::DEFAULT,
+            });
+        }
+
+        entity_keys.len()
+    }
+
+    /// Set the flags of the alive entity, as in `WorldOp::SetEntityFlags`.
+    /// An alive entity cannot be reserved again, so `RESERVED` is ignored.
+    /// If the entity is not found, return an `None`.
+    /// Otherwise, return an `Some(())`.
+    pub(crate) fn set_entity_flags(
+        &mut self,
+        entity_key: EntityKey,
+        flags: EntityFilter,
+    ) -> Option<()> {
+        self.update_entity_flags(entity_key, |_| {
+            flags.without(EntityFilter::LIVE | EntityFilter::RESERVED)
+        })
+        .ok()
+    }
+
+    fn update_entity_flags(
+        &mut self,
+        entity_key: EntityKey,
+        update: impl FnOnce(EntityFilter) -> EntityFilter,
+    ) -> Result<(), Error> {
+        self.check_entity(entity_key)?;
+
+        let slot = &mut self.entities[entity_key.index as usize];
+        let old_flags = slot.flags();
+        slot.flags = update(slot.flags);
+        let flags = slot.flags();
+
+        let inverse = self.capture_undo(|_| {
+            vec![WorldOp::SetEntityFlags {
+                entity_key,
+                flags: old_flags,
+            }]
+        });
+        self.record(inverse, |_| WorldOp::SetEntityFlags { entity_key, flags });
+
+        Ok(())
+    }
+}
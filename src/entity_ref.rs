@@ -0,0 +1,207 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, ECS};
+
+/// A shared handle of a live entity, returned by [`ECS::entity`].
+///
+/// The handle borrows the world, so the entity stays alive as long as the handle does.
+#[derive(Clone, Copy)]
+pub struct EntityRef<'a, S = crate::DefaultHashBuilder> {
+    ecs: &'a ECS<S>,
+    entity_key: EntityKey,
+}
+
+impl<'a, S: WorldHasher> EntityRef<'a, S> {
+    /// Return the entity key of the entity.
+    pub fn entity_key(&self) -> EntityKey {
+        self.entity_key
+    }
+
+    /// Return the first component of the type, as in `iter_comp_by_entity`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the entity has no component of the type, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(&T)`.
+    pub fn get<T>(&self) -> Result<&'a T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs
+            .iter_comp_by_entity::<T>(self.entity_key)?
+            .next()
+            .ok_or(Error::CompNotFound)
+    }
+
+    /// Return an iterator over all components of the type, as in `iter_comp_by_entity`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &T>)`.
+    pub fn iter<T>(&self) -> Result<impl Iterator<Item = &'a T>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs.iter_comp_by_entity::<T>(self.entity_key)
+    }
+
+    /// Return whether the entity holds at least one component of the type, as in `has_comp`.
+    pub fn has<T>(&self) -> bool
+    where
+        T: std::any::Any,
+    {
+        self.ecs.has_comp::<T>(self.entity_key)
+    }
+}
+
+/// A mutable handle of an entity, returned by [`ECS::entity_mut`].
+///
+/// The entity is alive when the handle is created,
+/// but a hook run by `insert` may remove it, after which every method fails as on a removed entity.
+pub struct EntityMut<'a, S = crate::DefaultHashBuilder> {
+    ecs: &'a mut ECS<S>,
+    entity_key: EntityKey,
+}
+
+impl<S: WorldHasher> EntityMut<'_, S> {
+    /// Return the entity key of the entity.
+    pub fn entity_key(&self) -> EntityKey {
+        self.entity_key
+    }
+
+    /// Return a shared handle of the entity.
+    pub fn as_ref(&self) -> EntityRef<'_, S> {
+        EntityRef {
+            ecs: self.ecs,
+            entity_key: self.entity_key,
+        }
+    }
+
+    /// Insert a component into the entity, and return the error of `insert_comp` if it fails.
+    pub fn insert<T>(&mut self, comp: T) -> Result<CompKey, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs.insert_comp(self.entity_key, comp)
+    }
+
+    /// Remove the first component of the type, as in `remove_bundle`.
+    /// If the entity is not found, as when a hook removed it, return an `Err(Error::EntityNotFound)`.
+    /// If the entity has no component of the type, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(T)`.
+    pub fn remove<T>(&mut self) -> Result<T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs
+            .remove_bundle::<(T,)>(self.entity_key)?
+            .0
+            .ok_or(Error::CompNotFound)
+    }
+
+    /// Return the first component of the type, as in `EntityRef::get`.
+    pub fn get<T>(&self) -> Result<&T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs
+            .iter_comp_by_entity::<T>(self.entity_key)?
+            .next()
+            .ok_or(Error::CompNotFound)
+    }
+
+    /// Return the first component of the type mutably.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If the entity has no component of the type, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(&mut T)`.
+    pub fn get_mut<T>(&mut self) -> Result<&mut T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs
+            .iter_comp_mut_by_entity::<T>(self.entity_key)?
+            .next()
+            .ok_or(Error::CompNotFound)
+    }
+
+    /// Return an iterator over all components of the type, as in `EntityRef::iter`.
+    pub fn iter<T>(&self) -> Result<impl Iterator<Item = &T>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs.iter_comp_by_entity::<T>(self.entity_key)
+    }
+
+    /// Return a mutable iterator over all components of the type, as in `iter_comp_mut_by_entity`.
+    pub fn iter_mut<T>(&mut self) -> Result<impl Iterator<Item = &mut T>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.ecs.iter_comp_mut_by_entity::<T>(self.entity_key)
+    }
+
+    /// Return whether the entity holds at least one component of the type, as in `has_comp`.
+    pub fn has<T>(&self) -> bool
+    where
+        T: std::any::Any,
+    {
+        self.ecs.has_comp::<T>(self.entity_key)
+    }
+
+    /// Remove the entity along with its components, as in `remove_entity`.
+    pub fn despawn(self) {
+        self.ecs.remove_entity(self.entity_key).ok();
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return a shared handle of the entity, for several operations on it without repeating the key.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(EntityRef)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let entity = ecs.entity(entity_key).unwrap();
+    /// assert_eq!(entity.get::<i32>(), Ok(&42));
+    /// assert!(!entity.has::<u32>());
+    /// ```
+    pub fn entity(&self, entity_key: EntityKey) -> Result<EntityRef<'_, S>, Error> {
+        self.check_entity(entity_key)?;
+
+        Ok(EntityRef {
+            ecs: self,
+            entity_key,
+        })
+    }
+
+    /// Return a mutable handle of the entity, for several operations on it without repeating the key.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(EntityMut)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    ///
+    /// let mut entity = ecs.entity_mut(entity_key).unwrap();
+    /// entity.insert(42).unwrap();
+    /// *entity.get_mut::<i32>().unwrap() += 1;
+    /// assert_eq!(entity.remove::<i32>(), Ok(43));
+    ///
+    /// entity.despawn();
+    /// assert!(ecs.entity(entity_key).is_err());
+    /// ```
+    pub fn entity_mut(&mut self, entity_key: EntityKey) -> Result<EntityMut<'_, S>, Error> {
+        self.check_entity(entity_key)?;
+
+        Ok(EntityMut {
+            ecs: self,
+            entity_key,
+        })
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{CompRow, EntityKey, Error, Rows, WorldHasher, ECS};
+
+/// A filter on the entities by the component types they hold, taken by [`ECS::iter_comp_filtered`],
+/// implemented for `With<M>`, `Without<M>` and tuples of up to four filters, which match when all of them match.
+///
+/// Filters only look up the associations of the entities, without fetching the components.
+pub trait CompFilter {
+    /// Check the component types of the filter.
+    /// If any component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    #[doc(hidden)]
+    fn check<S: WorldHasher>(ecs: &ECS<S>) -> Result<(), Error>;
+
+    /// Return whether the entity matches the filter.
+    #[doc(hidden)]
+    fn matches<S: WorldHasher>(ecs: &ECS<S>, entity_key: EntityKey) -> bool;
+}
+
+/// A filter matching the entities holding a component of the type.
+pub struct With<M>(std::marker::PhantomData<fn() -> M>);
+
+/// A filter matching the entities holding no component of the type.
+pub struct Without<M>(std::marker::PhantomData<fn() -> M>);
+
+fn check_type<M>(ecs: &ECS<impl WorldHasher>) -> Result<(), Error>
+where
+    M: std::any::Any,
+{
+    if !ecs.comp_cols.contains_key(&std::any::TypeId::of::<M>()) {
+        return Err(Error::TypeNotRegistered);
+    }
+
+    Ok(())
+}
+
+impl<M> CompFilter for With<M>
+where
+    M: std::any::Any,
+{
+    fn check<S: WorldHasher>(ecs: &ECS<S>) -> Result<(), Error> {
+        check_type::<M>(ecs)
+    }
+
+    fn matches<S: WorldHasher>(ecs: &ECS<S>, entity_key: EntityKey) -> bool {
+        ecs.has_comp::<M>(entity_key)
+    }
+}
+
+impl<M> CompFilter for Without<M>
+where
+    M: std::any::Any,
+{
+    fn check<S: WorldHasher>(ecs: &ECS<S>) -> Result<(), Error> {
+        check_type::<M>(ecs)
+    }
+
+    fn matches<S: WorldHasher>(ecs: &ECS<S>, entity_key: EntityKey) -> bool {
+        !ecs.has_comp::<M>(entity_key)
+    }
+}
+
+macro_rules! impl_comp_filter {
+    ($($filter:ident),*) => {
+        impl<$($filter),*> CompFilter for ($($filter,)*)
+        where
+            $($filter: CompFilter),*
+        {
+            fn check<S: WorldHasher>(ecs: &ECS<S>) -> Result<(), Error> {
+                $($filter::check(ecs)?;)*
+                Ok(())
+            }
+
+            fn matches<S: WorldHasher>(ecs: &ECS<S>, entity_key: EntityKey) -> bool {
+                $($filter::matches(ecs, entity_key))&&*
+            }
+        }
+    };
+}
+
+impl_comp_filter!(A);
+impl_comp_filter!(A, B);
+impl_comp_filter!(A, B, C);
+impl_comp_filter!(A, B, C, D);
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return an iterator over the components of the corresponding type whose entities match the filter,
+    /// in the same order as `iter_comp`.
+    /// A taken component still counts as held by its entity.
+    /// If the component type or any component type of the filter is not registered,
+    /// return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::{With, Without};
+    ///
+    /// struct Visible;
+    /// struct Frozen;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<Visible>().unwrap();
+    /// ecs.register::<Frozen>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key0, Visible).unwrap();
+    /// ecs.insert_comp(entity_key1, 63).unwrap();
+    /// ecs.insert_comp(entity_key1, Visible).unwrap();
+    /// ecs.insert_comp(entity_key1, Frozen).unwrap();
+    ///
+    /// let comps = ecs.iter_comp_filtered::<i32, With<Visible>>().unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![&42, &63]);
+    ///
+    /// let comps = ecs
+    ///     .iter_comp_filtered::<i32, (With<Visible>, Without<Frozen>)>()
+    ///     .unwrap()
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![&42]);
+    /// ```
+    pub fn iter_comp_filtered<T, F>(&self) -> Result<impl Iterator<Item = &T>, Error>
+    where
+        T: std::any::Any,
+        F: CompFilter,
+    {
+        let comp_col = self
+            .comp_cols
+            .get(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+        F::check(self)?;
+        comp_col.count_access(false);
+
+        let iter = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .filter(|(_, comp_row)| F::matches(self, comp_row.entity_key))
+            .map(|(_, comp_row)| &comp_row.comp);
+
+        Ok(iter)
+    }
+
+    /// Return a mutable iterator over the components of the corresponding type whose entities match the filter,
+    /// as in `iter_comp_filtered`.
+    /// If the component type or any component type of the filter is not registered,
+    /// return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &mut T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::Without;
+    ///
+    /// struct Frozen;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<Frozen>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key1, 63).unwrap();
+    /// ecs.insert_comp(entity_key1, Frozen).unwrap();
+    ///
+    /// for comp in ecs.iter_comp_mut_filtered::<i32, Without<Frozen>>().unwrap() {
+    ///     *comp += 1;
+    /// }
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&43));
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&63));
+    /// ```
+    pub fn iter_comp_mut_filtered<T, F>(&mut self) -> Result<impl Iterator<Item = &mut T>, Error>
+    where
+        T: std::any::Any,
+        F: CompFilter,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
+        }
+        F::check(self)?;
+
+        // the filter is resolved first, so that the column can be borrowed mutably
+        let matched = self
+            .comp_cols
+            .get(&type_key)
+            .unwrap()
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(|(_, comp_row)| F::matches(self, comp_row.entity_key))
+            .collect::<Vec<_>>();
+
+        let comp_col = self.comp_cols.get_mut(&type_key).unwrap();
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let tick = self.tick;
+        let iter = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter_mut()
+            .zip(matched)
+            .filter(|(_, matched)| *matched)
+            .map(move |((_, comp_row), _)| comp_row.comp_mut(tick));
+
+        Ok(iter)
+    }
+}
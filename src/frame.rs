@@ -0,0 +1,68 @@
+use crate::{WorldHasher, ECS};
+
+/// A summary of the maintenance done at the end of a frame, returned by [`ECS::end_frame`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameReport {
+    /// The frame ended, as in `frame`.
+    pub frame: u64,
+    /// The number of reserved entities inserted, as in `flush_reserved`.
+    pub reserved: usize,
+    /// The number of entities removed, as in `flush_despawns`.
+    pub despawned: usize,
+    /// The number of taken components removed because their handles were dropped, as in `take_comp`.
+    pub released: usize,
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return the number of frames ended by `end_frame` since the creation of the world.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.end_frame();
+    ///
+    /// assert_eq!(ecs.frame(), 1);
+    /// ```
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// End the current frame, doing the deferred maintenance of the world in its supported order,
+    /// and return a summary of what was done.
+    /// This is meant to be called once per iteration of the main loop.
+    ///
+    /// The reserved entities are inserted first, as in `flush_reserved`,
+    /// then the entities pending despawn are removed, as in `flush_despawns`,
+    /// then the taken components whose handles were dropped are removed, as in `take_comp`,
+    /// and the frame counter is advanced last, as in `frame`.
+    /// Each step can also be done on its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.reserve_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.despawn_deferred(entity_key1).unwrap();
+    ///
+    /// let report = ecs.end_frame();
+    /// assert_eq!((report.frame, report.reserved, report.despawned), (0, 1, 1));
+    /// assert_eq!(ecs.iter_entity().collect::<Vec<_>>(), vec![entity_key0]);
+    /// ```
+    pub fn end_frame(&mut self) -> FrameReport {
+        let reserved = self.flush_reserved();
+        let despawned = self.flush_despawns();
+        let released = self.release_dropped_taken();
+
+        let frame = self.frame;
+        self.frame += 1;
+
+        FrameReport {
+            frame,
+            reserved,
+            despawned,
+            released,
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use arbitrary::Arbitrary;
+
+use crate::{
+    CompKey, CompValue, EntityFilter, EntityKey, Error, PartitionId, WorldId, WorldOp, ECS,
+};
+
+type RegisterFn = fn(&mut ECS) -> Result<(), Error>;
+
+/// The component types of the arbitrary operations, by the registration methods recorded for them.
+const FUZZ_REGISTERS: [(&str, RegisterFn); 5] = [
+    ("i32", ECS::register_cloneable::<i32>),
+    ("u32", ECS::register_cloneable::<u32>),
+    ("i32", ECS::register_ordered::<i32>),
+    ("u32", ECS::register_immutable::<u32>),
+    ("u32", ECS::register_world_unique::<u32>),
+];
+
+/// The world of the keys of the arbitrary operations, rebound by `apply_op` to the world applying them.
+const FUZZ_WORLD: WorldId = WorldId(u32::MAX);
+
+/// Keep the keys and counts of the arbitrary operations small, so that they often hit the existing entities and rows.
+fn small(u: &mut arbitrary::Unstructured<'_>, max: u8) -> arbitrary::Result<u8> {
+    Ok(u8::arbitrary(u)? % (max + 1))
+}
+
+fn arbitrary_entity_key(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<EntityKey> {
+    Ok(EntityKey {
+        index: small(u, 7)? as u32,
+        generation: small(u, 1)? as u32,
+        world: FUZZ_WORLD,
+    })
+}
+
+/// Return whether the arbitrary component type is `i32`, or `u32` otherwise, together with a component key of it.
+fn arbitrary_comp_key(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<(bool, CompKey)> {
+    let signed = bool::arbitrary(u)?;
+    let type_key = if signed {
+        std::any::TypeId::of::<i32>()
+    } else {
+        std::any::TypeId::of::<u32>()
+    };
+
+    let comp_key = CompKey {
+        type_key,
+        row_key: small(u, 15)? as u32,
+        generation: 0,
+        world: FUZZ_WORLD,
+    };
+
+    Ok((signed, comp_key))
+}
+
+fn arbitrary_comp(
+    u: &mut arbitrary::Unstructured<'_>,
+    signed: bool,
+) -> arbitrary::Result<Option<CompValue>> {
+    let comp = if signed {
+        CompValue::new(i32::arbitrary(u)?)
+    } else {
+        CompValue::new(u32::arbitrary(u)?)
+    };
+
+    Ok(Some(comp))
+}
+
+/// An arbitrary operation on the components of `i32` and `u32`, with keys of at most 8 entities and 16 rows per type,
+/// for fuzzing with [`ECS::apply_op`].
+impl<'a> Arbitrary<'a> for WorldOp {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        // weighted towards insertions, so that the world is rarely empty
+        let op = match small(u, 31)? {
+            0..=1 => {
+                let (type_name, register_fn) = FUZZ_REGISTERS[small(u, 4)? as usize];
+                WorldOp::Register {
+                    type_name,
+                    register_fn,
+                }
+            }
+            2 => {
+                if bool::arbitrary(u)? {
+                    WorldOp::Unregister {
+                        type_name: "i32",
+                        unregister_fn: ECS::unregister::<i32>,
+                    }
+                } else {
+                    WorldOp::Unregister {
+                        type_name: "u32",
+                        unregister_fn: ECS::unregister::<u32>,
+                    }
+                }
+            }
+            3 => {
+                let partition = small(u, 3)?;
+                WorldOp::CreatePartition {
+                    partition: PartitionId(partition as u32),
+                    name: format!("partition {}", partition),
+                }
+            }
+            4..=7 => WorldOp::InsertEntity {
+                entity_key: arbitrary_entity_key(u)?,
+                partition: PartitionId(small(u, 3)? as u32),
+            },
+            8..=9 => WorldOp::RemoveEntity {
+                entity_key: arbitrary_entity_key(u)?,
+            },
+            10..=11 => {
+                let entity_key = arbitrary_entity_key(u)?;
+                let bits = u8::arbitrary(u)?;
+                let mut flags = EntityFilter::LIVE;
+                for (bit, flag) in [
+                    EntityFilter::DISABLED,
+                    EntityFilter::PENDING_DESPAWN,
+                    EntityFilter::RESERVED,
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    if bits & (1 << bit) != 0 {
+                        flags |= flag;
+                    }
+                }
+                WorldOp::SetEntityFlags { entity_key, flags }
+            }
+            12..=19 => {
+                let entity_key = arbitrary_entity_key(u)?;
+                let (signed, comp_key) = arbitrary_comp_key(u)?;
+                WorldOp::InsertComp {
+                    entity_key,
+                    comp_key,
+                    comp: arbitrary_comp(u, signed)?,
+                }
+            }
+            20..=21 => WorldOp::ReorderComp {
+                comp_key: arbitrary_comp_key(u)?.1,
+                index: small(u, 3)? as usize,
+            },
+            22..=24 => WorldOp::RemoveComp {
+                comp_key: arbitrary_comp_key(u)?.1,
+            },
+            25..=28 => {
+                let (signed, comp_key) = arbitrary_comp_key(u)?;
+                WorldOp::SetComp {
+                    comp_key,
+                    comp: arbitrary_comp(u, signed)?,
+                }
+            }
+            29..=30 => {
+                let budget_rows = small(u, 7)? as usize;
+                if bool::arbitrary(u)? {
+                    WorldOp::CompactStep {
+                        type_name: "i32",
+                        budget_rows,
+                        compact_fn: |ecs, budget_rows| {
+                            ecs.compact_step::<i32>(budget_rows).map(|_| ())
+                        },
+                    }
+                } else {
+                    WorldOp::CompactStep {
+                        type_name: "u32",
+                        budget_rows,
+                        compact_fn: |ecs, budget_rows| {
+                            ecs.compact_step::<u32>(budget_rows).map(|_| ())
+                        },
+                    }
+                }
+            }
+            _ => WorldOp::Clear,
+        };
+
+        Ok(op)
+    }
+}
+
+impl WorldOp {
+    /// Return the operation with its keys moved to the world,
+    /// and its component keys at the current generations of their rows.
+    fn rebind(&self, ecs: &ECS) -> WorldOp {
+        let world = ecs.world;
+        let rebind_comp_key = |comp_key: &mut CompKey| {
+            comp_key.world = world;
+            comp_key.generation = ecs
+                .comp_generations
+                .get(comp_key.type_key, comp_key.row_key);
+        };
+        let mut op = self.clone();
+
+        match &mut op {
+            WorldOp::InsertEntity { entity_key, .. }
+            | WorldOp::RemoveEntity { entity_key }
+            | WorldOp::SetEntityFlags { entity_key, .. } => entity_key.world = world,
+            WorldOp::InsertComp {
+                entity_key,
+                comp_key,
+                ..
+            } => {
+                entity_key.world = world;
+                rebind_comp_key(comp_key);
+            }
+            WorldOp::ReorderComp { comp_key, .. }
+            | WorldOp::RemoveComp { comp_key }
+            | WorldOp::SetComp { comp_key, .. } => rebind_comp_key(comp_key),
+            WorldOp::Register { .. }
+            | WorldOp::Unregister { .. }
+            | WorldOp::CreatePartition { .. }
+            | WorldOp::CompactStep { .. }
+            | WorldOp::Clear => {}
+        }
+
+        op
+    }
+}
+
+impl ECS {
+    /// Apply the operation, as in `replay`, taking the keys of other worlds as keys of this world,
+    /// since arbitrary operations, as generated for fuzzing, cannot know the world.
+    /// Any operation is accepted, including ones with keys that were never handed out, and none panics.
+    /// If the operation fails or its key is occupied, return an `None`.
+    /// Otherwise, return an `Some(())`.
+    ///
+    /// A fuzz target applies the operations one by one:
+    ///
+    /// ```
+    /// let ops = vec![ecs_tiny::WorldOp::Clear];
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// for op in &ops {
+    ///     ecs.apply_op(op);
+    /// }
+    /// ```
+    pub fn apply_op(&mut self, op: &WorldOp) -> Option<()> {
+        self.apply(&op.rebind(self), 0).ok()
+    }
+}
@@ -0,0 +1,69 @@
+/// The generation of each row of each component type, bumped every time the component in the row is removed,
+/// so that the key of a removed component never resolves to a component inserted later into the same row.
+/// The generations outlive the columns, so that they keep counting across registrations of the type.
+#[derive(Clone, Default)]
+pub(crate) struct CompGenerations<S>(crate::hash::HashMap<std::any::TypeId, Vec<u32>, S>);
+
+impl<S: crate::WorldHasher> CompGenerations<S> {
+    pub(crate) fn with_hasher(hasher: S) -> Self {
+        Self(crate::hash::HashMap::with_hasher(hasher))
+    }
+
+    /// Return the generation of the row, of its component if occupied, or of the next component otherwise.
+    pub(crate) fn get(&self, type_key: std::any::TypeId, row_key: u32) -> u32 {
+        generation(self.of(type_key), row_key)
+    }
+
+    /// Return the generations of the rows of the component type, by row key.
+    pub(crate) fn of(&self, type_key: std::any::TypeId) -> &[u32] {
+        self.0.get(&type_key).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bump the generation of the row, whose component was just removed.
+    pub(crate) fn bump(&mut self, type_key: std::any::TypeId, row_key: u32) {
+        let generation = self.slot(type_key, row_key);
+        *generation = generation.wrapping_add(1);
+    }
+
+    /// Set the generation of the vacant row, so that a component is inserted at a recorded key.
+    pub(crate) fn set(&mut self, type_key: std::any::TypeId, row_key: u32, generation: u32) {
+        *self.slot(type_key, row_key) = generation;
+    }
+
+    fn slot(&mut self, type_key: std::any::TypeId, row_key: u32) -> &mut u32 {
+        let generations = self.0.entry(type_key).or_default();
+        if row_key as usize >= generations.len() {
+            generations.resize(row_key as usize + 1, 0);
+        }
+        &mut generations[row_key as usize]
+    }
+}
+
+/// Return the generation of the row among the generations of a component type.
+pub(crate) fn generation(generations: &[u32], row_key: u32) -> u32 {
+    generations.get(row_key as usize).copied().unwrap_or(0)
+}
+
+impl<S: crate::WorldHasher> crate::ECS<S> {
+    /// Bump the generations of all rows of the column of the component type, including the reserved rows of taken
+    /// components, before the column is dropped, so that no key of its components resolves after a registration again.
+    pub(crate) fn bump_column_generations(&mut self, type_key: std::any::TypeId) {
+        let Some(comp_col) = self.comp_cols.get(&type_key) else {
+            return;
+        };
+
+        let row_keys = (comp_col.iter_owners_fn)(comp_col)
+            .map(|(row_key, _)| row_key)
+            .chain(
+                self.taken
+                    .keys()
+                    .filter(|(taken_type_key, _)| *taken_type_key == type_key)
+                    .map(|(_, row_key)| *row_key),
+            )
+            .collect::<Vec<_>>();
+
+        for row_key in row_keys {
+            self.comp_generations.bump(type_key, row_key);
+        }
+    }
+}
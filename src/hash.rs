@@ -0,0 +1,59 @@
+/// The hash map of the internal tables of the world, built with the hasher of the world, as in `ECS::with_hasher`.
+///
+/// By default, the hasher is ahash seeded at random, as hash flooding protection,
+/// or the hasher of the standard library if the `ahash` feature is disabled.
+/// With the `deterministic` feature, it is seeded with fixed keys, so that, within a build,
+/// the iteration order of every table, and of everything derived from it,
+/// depends only on the operations done on the world, as for deterministic lockstep simulations,
+/// and not on other worlds of the process, since the world identifier is left out of the hash of the keys.
+pub(crate) type HashMap<K, V, S = DefaultHashBuilder> = std::collections::HashMap<K, V, S>;
+
+/// The hasher of the internal tables of a world, as in [`ECS::with_hasher`](crate::ECS::with_hasher).
+///
+/// Implemented for every hasher builder that can be created by default, cloned and shared between threads,
+/// such as `std::collections::hash_map::RandomState` and `std::hash::BuildHasherDefault`.
+pub trait WorldHasher: std::hash::BuildHasher + Default + Clone + Send + Sync + 'static {}
+
+impl<S> WorldHasher for S where S: std::hash::BuildHasher + Default + Clone + Send + Sync + 'static {}
+
+/// The default hasher of the internal tables of a world, as selected by the `ahash` and `deterministic` features.
+#[cfg(all(feature = "ahash", not(feature = "deterministic")))]
+pub type DefaultHashBuilder = ahash::RandomState;
+
+/// The default hasher of the internal tables of a world, as selected by the `ahash` and `deterministic` features.
+#[cfg(all(not(feature = "ahash"), not(feature = "deterministic")))]
+pub type DefaultHashBuilder = std::collections::hash_map::RandomState;
+
+/// The default hasher of the internal tables of a world, as selected by the `ahash` and `deterministic` features.
+/// The hasher of the standard library, whose default keys are fixed.
+#[cfg(all(not(feature = "ahash"), feature = "deterministic"))]
+pub type DefaultHashBuilder =
+    std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+/// The default hasher of the internal tables of a world, as selected by the `ahash` and `deterministic` features.
+/// The hasher of ahash, seeded with fixed keys.
+#[cfg(all(feature = "ahash", feature = "deterministic"))]
+#[derive(Debug, Clone)]
+pub struct DefaultHashBuilder(ahash::RandomState);
+
+#[cfg(all(feature = "ahash", feature = "deterministic"))]
+impl Default for DefaultHashBuilder {
+    fn default() -> Self {
+        // the digits of pi, a choice of seeds with nothing up the sleeve
+        DefaultHashBuilder(ahash::RandomState::with_seeds(
+            0x243f_6a88_85a3_08d3,
+            0x1319_8a2e_0370_7344,
+            0xa409_3822_299f_31d0,
+            0x082e_fa98_ec4e_6c89,
+        ))
+    }
+}
+
+#[cfg(all(feature = "ahash", feature = "deterministic"))]
+impl std::hash::BuildHasher for DefaultHashBuilder {
+    type Hasher = ahash::AHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        self.0.build_hasher()
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, ECS};
+
+/// The most operations applied for the hooks triggered by a single operation, counting the operations queued by nested hooks.
+const HOOK_COMMAND_LIMIT: usize = 1024;
+
+/// An operation queued by a hook, applied after the triggering operation completes.
+pub(crate) type HookCommand<S> = Box<dyn FnOnce(&mut ECS<S>) -> Result<(), Error> + Send + Sync>;
+
+/// The context handed to an insert hook, as in [`ECS::set_insert_hook`].
+///
+/// The context gives a read view of the world, and queues structural operations instead of applying them,
+/// so that a hook cannot alias the column being inserted into.
+/// The queued operations are applied in order once the triggering operation completes,
+/// and the operations they trigger in turn are queued after them.
+/// Each failure of a queued operation is recorded, and returned by `take_hook_errors`.
+pub struct HookContext<'a, S = crate::DefaultHashBuilder> {
+    ecs: &'a ECS<S>,
+    commands: Vec<HookCommand<S>>,
+}
+
+impl<'a, S: WorldHasher> HookContext<'a, S> {
+    /// Return a read view of the world, as of the triggering operation.
+    pub fn ecs(&self) -> &'a ECS<S> {
+        self.ecs
+    }
+
+    /// Queue the insertion of a component, as in `ECS::insert_comp`.
+    pub fn insert_comp<T>(&mut self, entity_key: EntityKey, comp: T)
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.queue(move |ecs| ecs.insert_comp(entity_key, comp).map(drop));
+    }
+
+    /// Queue the removal of a component, as in `ECS::remove_comp`.
+    pub fn remove_comp<T>(&mut self, comp_key: CompKey)
+    where
+        T: std::any::Any,
+    {
+        self.queue(move |ecs| ecs.remove_comp::<T>(comp_key).map(drop));
+    }
+
+    /// Queue the removal of an entity, as in `ECS::remove_entity`.
+    pub fn remove_entity(&mut self, entity_key: EntityKey) {
+        self.queue(move |ecs| ecs.remove_entity(entity_key));
+    }
+
+    /// Queue the registration of a component type, as in `ECS::register`.
+    pub fn register<T>(&mut self)
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.queue(ECS::<S>::register_op::<T>);
+    }
+
+    /// Queue an arbitrary operation on the world.
+    pub fn queue(
+        &mut self,
+        f: impl FnOnce(&mut ECS<S>) -> Result<(), Error> + Send + Sync + 'static,
+    ) {
+        self.commands.push(Box::new(f));
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Set the insert hook of the component type, called after each insertion of a component of the type,
+    /// as by `insert_comp`, `insert_comp_at` and `insert_comp_at_row`,
+    /// with the entity key and the component key of the inserted component.
+    /// Components loaded or put back, as by `deserialize_into`, `restore` and `put_back`, are not inserted anew,
+    /// so the hook does not run for them.
+    /// The hook receives a [`HookContext`], which queues the operations of the hook until the insertion completes.
+    /// Setting a new hook replaces the previous one.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.set_insert_hook::<i32>(|ctx, entity_key, comp_key| {
+    ///     let comp = *ctx.ecs().get_comp::<i32>(comp_key).unwrap();
+    ///     ctx.insert_comp(entity_key, comp as u32);
+    /// }).unwrap();
+    ///
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// assert_eq!(ecs.iter_comp::<u32>().unwrap().collect::<Vec<_>>(), vec![&42]);
+    /// ```
+    pub fn set_insert_hook<T>(
+        &mut self,
+        f: impl Fn(&mut HookContext<'_, S>, EntityKey, CompKey) + Send + Sync + 'static,
+    ) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.insert_hook = Some(std::sync::Arc::new(f));
+
+        Ok(())
+    }
+
+    /// Return the failures of the operations queued by hooks since the last call, in the order of the operations.
+    /// If the hooks triggered by a single operation queue more than 1024 operations in total,
+    /// as when a hook keeps triggering itself, the rest are dropped and an `Error::ReentrantMutation` is recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.set_insert_hook::<i32>(|ctx, entity_key, _| ctx.insert_comp(entity_key, 0u32)).unwrap();
+    ///
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// assert_eq!(ecs.take_hook_errors(), vec![ecs_tiny::Error::TypeNotRegistered]);
+    /// ```
+    pub fn take_hook_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.hook_errors)
+    }
+
+    /// Call the insert hook of the component type, if any, and apply the operations it queues.
+    /// Operations queued while applying are left to the outermost call, so that the applications never nest.
+    pub(crate) fn run_insert_hook(
+        &mut self,
+        type_key: std::any::TypeId,
+        entity_key: EntityKey,
+        comp_key: CompKey,
+    ) {
+        let Some(insert_hook) = self.comp_cols[&type_key].insert_hook.clone() else {
+            return;
+        };
+
+        let mut ctx = HookContext {
+            ecs: self,
+            commands: vec![],
+        };
+        insert_hook(&mut ctx, entity_key, comp_key);
+        let commands = ctx.commands;
+        self.hook_commands.extend(commands);
+
+        if self.applying_hooks {
+            return;
+        }
+
+        self.apply_hook_commands();
+    }
+
+    /// Run the closure with the operations of the hooks it triggers queued, and apply them once it returns,
+    /// as for the insertions of a bundle, so that a hook cannot make a later insertion fail.
+    pub(crate) fn defer_hooks<R>(&mut self, f: impl FnOnce(&mut ECS<S>) -> R) -> R {
+        // the outermost operation applies the queued operations
+        if self.applying_hooks {
+            return f(self);
+        }
+
+        self.applying_hooks = true;
+        let r = f(self);
+        self.applying_hooks = false;
+        self.apply_hook_commands();
+
+        r
+    }
+
+    fn apply_hook_commands(&mut self) {
+        self.applying_hooks = true;
+        let mut applied = 0;
+        while let Some(command) = self.hook_commands.pop_front() {
+            if applied == HOOK_COMMAND_LIMIT {
+                self.hook_commands.clear();
+                self.hook_errors.push(Error::ReentrantMutation);
+                break;
+            }
+            applied += 1;
+
+            if let Err(err) = command(self) {
+                self.hook_errors.push(err);
+            }
+        }
+        self.applying_hooks = false;
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{Bundle, EntityKey, Error, WorldHasher, ECS};
+
+impl EntityKey {
+    /// Return the entity key packed into 64 bits, with the generation in the high half and the slot index in the low half,
+    /// as the entity ids of hecs and bevy are, so that it can stand in for them while migrating.
+    /// The world is not packed, so the bits are resolved by `ECS::entity_from_bits` of the same world.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    ///
+    /// assert_eq!(ecs.entity_from_bits(entity_key.to_bits()), Ok(entity_key));
+    /// ```
+    pub fn to_bits(&self) -> u64 {
+        ((self.generation as u64) << 32) | self.index as u64
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return the entity key of the alive entity packed into the bits, as in `EntityKey::to_bits`.
+    /// If the entity is not alive, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(EntityKey)`.
+    pub fn entity_from_bits(&self, bits: u64) -> Result<EntityKey, Error> {
+        let entity_key = EntityKey {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+            world: self.world,
+        };
+        self.get_entity(entity_key)?;
+
+        Ok(entity_key)
+    }
+}
+
+/// An error returned by [`MigrationImporter::import`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// The external id is already mapped to an alive entity.
+    DuplicateId {
+        /// The index of the pair in the import.
+        index: usize,
+        /// The external id.
+        external_id: u64,
+    },
+    /// A component of the bundle could not be inserted.
+    Bundle {
+        /// The index of the pair in the import.
+        index: usize,
+        /// The error of the insertion.
+        error: Error,
+    },
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::DuplicateId { index, external_id } => {
+                write!(
+                    f,
+                    "pair {} not imported: external id {} already mapped",
+                    index, external_id
+                )
+            }
+            ImportError::Bundle { index, error } => {
+                write!(f, "pair {} not imported: {}", index, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// A bidirectional map between the ids of entities of another ECS and the entity keys of the entities imported for them.
+///
+/// A mapping is only returned while its entity is alive, so the map stays consistent
+/// however the entity is removed, and `prune` drops the mappings of the removed entities.
+/// The map is kept apart from the world, so a map must be used with the world it imported into.
+///
+/// # Examples
+///
+/// ```
+/// let mut ecs = ecs_tiny::ECS::new();
+/// ecs.register::<i32>().unwrap();
+///
+/// let mut importer = ecs_tiny::MigrationImporter::new();
+/// let entity_keys = importer.import(&mut ecs, [(7, (42,)), (9, (63,))]).unwrap();
+///
+/// assert_eq!(importer.entity_key(&ecs, 9), Ok(entity_keys[1]));
+/// assert_eq!(importer.external_id(&ecs, entity_keys[0]), Ok(7));
+///
+/// ecs.remove_entity(entity_keys[0]).unwrap();
+/// assert_eq!(
+///     importer.entity_key(&ecs, 7),
+///     Err(ecs_tiny::Error::EntityNotFound(ecs_tiny::EntityStatus::Removed {
+///         current_generation: 1
+///     }))
+/// );
+/// assert_eq!(importer.prune(&ecs), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MigrationImporter {
+    entity_keys: crate::hash::HashMap<u64, EntityKey>,
+    external_ids: crate::hash::HashMap<EntityKey, u64>,
+}
+
+impl MigrationImporter {
+    /// Create a new empty map.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Insert a new entity for each pair, holding the components of the bundle, and map the external id to it,
+    /// returning the corresponding entity keys in the order of the pairs.
+    /// An external id whose entity was removed is mapped again.
+    /// If an external id is mapped to an alive entity, return an `Err(ImportError::DuplicateId { .. })`.
+    /// If a component of a bundle cannot be inserted, as in `insert_comp`,
+    /// the entity of that pair is removed along with the components inserted so far,
+    /// and return an `Err(ImportError::Bundle { .. })`.
+    /// Either way, the pairs before stay imported.
+    /// Otherwise, return an `Ok(Vec<EntityKey>)`.
+    pub fn import<B, S: WorldHasher>(
+        &mut self,
+        ecs: &mut ECS<S>,
+        pairs: impl IntoIterator<Item = (u64, B)>,
+    ) -> Result<Vec<EntityKey>, ImportError>
+    where
+        B: Bundle,
+    {
+        let mut entity_keys = vec![];
+        for (index, (external_id, bundle)) in pairs.into_iter().enumerate() {
+            if self.entity_key(ecs, external_id).is_ok() {
+                return Err(ImportError::DuplicateId { index, external_id });
+            }
+
+            let entity_key = ecs.insert_entity();
+
+            if let Err(error) = bundle.insert(ecs, entity_key) {
+                ecs.remove_entity(entity_key).unwrap();
+                return Err(ImportError::Bundle { index, error });
+            }
+
+            if let Some(old_key) = self.entity_keys.insert(external_id, entity_key) {
+                self.external_ids.remove(&old_key);
+            }
+            self.external_ids.insert(entity_key, external_id);
+            entity_keys.push(entity_key);
+        }
+
+        Ok(entity_keys)
+    }
+
+    /// Return the entity key mapped to the external id.
+    /// If the external id is not mapped, return an `Err(Error::ExternalIdNotFound)`.
+    /// If its entity is not alive, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(EntityKey)`.
+    pub fn entity_key<S: WorldHasher>(
+        &self,
+        ecs: &ECS<S>,
+        external_id: u64,
+    ) -> Result<EntityKey, Error> {
+        let entity_key = *self
+            .entity_keys
+            .get(&external_id)
+            .ok_or(Error::ExternalIdNotFound)?;
+        ecs.get_entity(entity_key)?;
+
+        Ok(entity_key)
+    }
+
+    /// Return the external id mapped to the entity key.
+    /// If the entity was not imported, return an `Err(Error::ExternalIdNotFound)`.
+    /// If it is not alive, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(u64)`.
+    pub fn external_id<S: WorldHasher>(
+        &self,
+        ecs: &ECS<S>,
+        entity_key: EntityKey,
+    ) -> Result<u64, Error> {
+        let external_id = *self
+            .external_ids
+            .get(&entity_key)
+            .ok_or(Error::ExternalIdNotFound)?;
+        ecs.get_entity(entity_key)?;
+
+        Ok(external_id)
+    }
+
+    /// Return an iterator over the external ids and the entity keys of all mappings whose entities are alive.
+    pub fn iter<'a, S: WorldHasher>(
+        &'a self,
+        ecs: &'a ECS<S>,
+    ) -> impl Iterator<Item = (u64, EntityKey)> + 'a {
+        self.entity_keys
+            .iter()
+            .filter(|(_, entity_key)| ecs.get_entity(**entity_key).is_ok())
+            .map(|(external_id, entity_key)| (*external_id, *entity_key))
+    }
+
+    /// Drop the mappings of the entities that are no longer alive, and return the number of mappings dropped.
+    pub fn prune<S: WorldHasher>(&mut self, ecs: &ECS<S>) -> usize {
+        let len = self.entity_keys.len();
+
+        self.entity_keys
+            .retain(|_, entity_key| ecs.get_entity(*entity_key).is_ok());
+        self.external_ids
+            .retain(|entity_key, _| ecs.get_entity(*entity_key).is_ok());
+
+        len - self.entity_keys.len()
+    }
+}
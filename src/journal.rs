@@ -0,0 +1,495 @@
+use crate::{CompKey, EntityFilter, EntityKey, Error, PartitionId, WorldHasher, ECS};
+
+/// A mutating operation recorded by the journal of [`ECS`], as in [`ECS::enable_journal`].
+///
+/// Only the operations going through the methods of the world are recorded.
+/// Mutations through mutable references, as in `get_comp_mut`, `iter_comp_mut` and `query`,
+/// and the configurations of component types, as in `set_insert_validator`, are not recorded.
+///
+/// With the `fuzzing` feature, arbitrary operations can be generated for fuzzing, and applied by [`ECS::apply_op`].
+#[derive(Debug, Clone)]
+pub enum WorldOp<S = crate::DefaultHashBuilder> {
+    /// Register a component type by the registration method used, as in `register_cloneable`.
+    Register {
+        /// The name of the component type.
+        type_name: &'static str,
+        /// The registration method, monomorphized for the component type.
+        register_fn: fn(&mut ECS<S>) -> Result<(), Error>,
+    },
+    /// Unregister a component type.
+    Unregister {
+        /// The name of the component type.
+        type_name: &'static str,
+        /// The unregistration method, monomorphized for the component type.
+        unregister_fn: fn(&mut ECS<S>) -> Result<(), Error>,
+    },
+    /// Create a partition of entities.
+    CreatePartition {
+        /// The partition id handed out.
+        partition: PartitionId,
+        /// The name of the partition.
+        name: String,
+    },
+    /// Insert an entity.
+    InsertEntity {
+        /// The entity key handed out.
+        entity_key: EntityKey,
+        /// The partition of the entity.
+        partition: PartitionId,
+    },
+    /// Remove an entity together with its components.
+    RemoveEntity {
+        /// The entity key removed.
+        entity_key: EntityKey,
+    },
+    /// Set the flags of an entity, as in `disable_entity` and `despawn_deferred`.
+    SetEntityFlags {
+        /// The entity key of the entity.
+        entity_key: EntityKey,
+        /// The flags of the entity, as in `entity_flags`.
+        flags: EntityFilter,
+    },
+    /// Insert a component into an entity.
+    InsertComp {
+        /// The entity key of the owner.
+        entity_key: EntityKey,
+        /// The component key handed out.
+        comp_key: CompKey,
+        /// The inserted value, or `None` if the type is registered without clone support.
+        comp: Option<CompValue<S>>,
+    },
+    /// Move a component among the components of the same type of its owner, as in `insert_comp_at` and `reorder_comp`.
+    ReorderComp {
+        /// The component key moved.
+        comp_key: CompKey,
+        /// The index the component is placed at.
+        index: usize,
+    },
+    /// Remove a component.
+    RemoveComp {
+        /// The component key removed.
+        comp_key: CompKey,
+    },
+    /// Overwrite the value of a component, as in `update_comp`, `swap_comp` and `set_comp_field`.
+    SetComp {
+        /// The component key overwritten.
+        comp_key: CompKey,
+        /// The new value, or `None` if the type is registered without clone support.
+        comp: Option<CompValue<S>>,
+    },
+    /// Relocate rows of the column of a component type, as in `compact_step`.
+    CompactStep {
+        /// The name of the component type.
+        type_name: &'static str,
+        /// The maximum number of rows relocated.
+        budget_rows: usize,
+        /// The compaction method, monomorphized for the component type.
+        compact_fn: fn(&mut ECS<S>, usize) -> Result<(), Error>,
+    },
+    /// Clear all entities, components and registrations.
+    Clear,
+}
+
+/// A component value recorded by the journal, cloned from a component type registered with clone support.
+///
+/// Shared components are recorded as handles, which keep pointing at the pool of the recorded world.
+pub struct CompValue<S = crate::DefaultHashBuilder>(Box<dyn RecordedComp<S>>);
+
+impl<S: WorldHasher> CompValue<S> {
+    pub(crate) fn new<T>(comp: T) -> Self
+    where
+        T: std::any::Any + Clone + Send + Sync,
+    {
+        Self(Box::new(comp))
+    }
+
+    /// Return the name of the component type.
+    pub fn type_name(&self) -> &'static str {
+        self.0.type_name()
+    }
+
+    /// Return the value as the type.
+    /// If the value is not of the type, return an `None`.
+    /// Otherwise, return an `Some(&T)`.
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: std::any::Any,
+    {
+        self.0.as_any().downcast_ref::<T>()
+    }
+
+    /// Return the value as the type, consuming it.
+    /// If the value is not of the type, return an `None`.
+    /// Otherwise, return an `Some(T)`.
+    pub(crate) fn into_comp<T>(self) -> Option<T>
+    where
+        T: std::any::Any,
+    {
+        self.0.into_any().downcast::<T>().ok().map(|comp| *comp)
+    }
+
+    /// Insert a clone of the value with the corresponding entity key, as in `insert_comp`.
+    pub(crate) fn insert_into(
+        &self,
+        ecs: &mut ECS<S>,
+        entity_key: EntityKey,
+    ) -> Result<CompKey, Error> {
+        self.0.insert_into(ecs, entity_key)
+    }
+}
+
+impl<S> Clone for CompValue<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+
+impl<S> std::fmt::Debug for CompValue<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CompValue")
+            .field(&self.0.type_name())
+            .finish()
+    }
+}
+
+trait RecordedComp<S>: Send + Sync {
+    fn type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn std::any::Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn std::any::Any>;
+
+    fn clone_box(&self) -> Box<dyn RecordedComp<S>>;
+
+    fn insert_at(
+        &self,
+        ecs: &mut ECS<S>,
+        entity_key: EntityKey,
+        comp_key: CompKey,
+    ) -> Option<CompKey>;
+
+    fn assign_to(&self, ecs: &mut ECS<S>, comp_key: CompKey) -> Result<(), Error>;
+
+    fn insert_into(&self, ecs: &mut ECS<S>, entity_key: EntityKey) -> Result<CompKey, Error>;
+}
+
+impl<T, S> RecordedComp<S> for T
+where
+    T: std::any::Any + Clone + Send + Sync,
+    S: WorldHasher,
+{
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn std::any::Any> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn RecordedComp<S>> {
+        Box::new(self.clone())
+    }
+
+    fn insert_at(
+        &self,
+        ecs: &mut ECS<S>,
+        entity_key: EntityKey,
+        comp_key: CompKey,
+    ) -> Option<CompKey> {
+        ecs.insert_comp_row_at(
+            entity_key,
+            comp_key.row_key,
+            Some(comp_key.generation),
+            None,
+            self.clone(),
+        )
+    }
+
+    fn assign_to(&self, ecs: &mut ECS<S>, comp_key: CompKey) -> Result<(), Error> {
+        ecs.update_comp::<T, _>(comp_key, |comp| *comp = self.clone())
+    }
+
+    fn insert_into(&self, ecs: &mut ECS<S>, entity_key: EntityKey) -> Result<CompKey, Error> {
+        ecs.insert_comp(entity_key, self.clone())
+    }
+}
+
+/// An error returned by [`ECS::replay`], with the index of the operation that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The component value was not recorded, because the component type is registered without clone support.
+    MissingValue {
+        /// The index of the operation.
+        index: usize,
+        /// The name of the component type.
+        type_name: &'static str,
+    },
+    /// The operation failed or its key is occupied.
+    Diverged {
+        /// The index of the operation.
+        index: usize,
+    },
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::MissingValue { index, type_name } => write!(
+                f,
+                "operation {} has no recorded value of {}",
+                index, type_name
+            ),
+            ReplayError::Diverged { index } => write!(f, "operation {} diverged", index),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Start recording all mutating operations into the journal.
+    /// A replay starts from an empty world, so the journal should be enabled on a new world.
+    /// If the journal is already enabled, the recorded operations are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_journal();
+    /// ecs.insert_entity();
+    ///
+    /// assert_eq!(ecs.take_journal().len(), 1);
+    /// ```
+    pub fn enable_journal(&mut self) {
+        self.journal.get_or_insert_with(Vec::new);
+    }
+
+    /// Take the operations recorded so far, leaving the journal empty but enabled.
+    /// If the journal is not enabled, return an empty vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_journal();
+    /// let entity_key = ecs.insert_entity();
+    /// let ops = ecs.take_journal();
+    ///
+    /// assert!(matches!(ops[0], ecs_tiny::WorldOp::InsertEntity { entity_key: key, .. } if key == entity_key));
+    /// assert!(ecs.take_journal().is_empty());
+    /// ```
+    pub fn take_journal(&mut self) -> Vec<WorldOp<S>> {
+        self.journal
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Reconstruct a world by replaying the operations on a new ECS instance,
+    /// placing every entity and component at the recorded key.
+    /// The replayed world takes over the world identifier of the recorded keys.
+    /// If a component value was not recorded, return an `Err(ReplayError::MissingValue)`.
+    /// If an operation fails or its key is occupied, return an `Err(ReplayError::Diverged)`.
+    /// Otherwise, return an `Ok(ECS)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_journal();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let replayed = ecs_tiny::ECS::replay(&ecs.take_journal()).unwrap();
+    ///
+    /// assert_eq!(replayed.get_comp::<i32>(comp_key), Ok(&42));
+    /// ```
+    pub fn replay(ops: &[WorldOp<S>]) -> Result<ECS<S>, ReplayError> {
+        let mut ecs = ECS::<S>::default();
+
+        // adopt the world of the recorded keys, so that they resolve in the replayed world
+        if let Some(world) = ops.iter().find_map(|op| match op {
+            WorldOp::InsertEntity { entity_key, .. } => Some(entity_key.world),
+            _ => None,
+        }) {
+            ecs.world = world;
+        }
+
+        for (index, op) in ops.iter().enumerate() {
+            ecs.apply(op, index)?;
+        }
+
+        Ok(ecs)
+    }
+
+    /// Apply the operation, placing entities and components at the recorded keys.
+    pub(crate) fn apply(&mut self, op: &WorldOp<S>, index: usize) -> Result<(), ReplayError> {
+        let diverged = ReplayError::Diverged { index };
+
+        match op {
+            WorldOp::Register { register_fn, .. } => {
+                register_fn(self).map_err(|_| diverged)?;
+            }
+            WorldOp::Unregister { unregister_fn, .. } => {
+                unregister_fn(self).map_err(|_| diverged)?;
+            }
+            WorldOp::CreatePartition { partition, name } => {
+                if self.create_partition(name.clone()) != *partition {
+                    return Err(diverged);
+                }
+            }
+            WorldOp::InsertEntity {
+                entity_key,
+                partition,
+            } => {
+                if self.partition_name(*partition).is_err() {
+                    return Err(diverged);
+                }
+                self.insert_entity_slot(*entity_key, *partition)
+                    .ok_or(diverged)?;
+            }
+            WorldOp::RemoveEntity { entity_key } => {
+                self.remove_entity(*entity_key).map_err(|_| diverged)?;
+            }
+            WorldOp::SetEntityFlags { entity_key, flags } => {
+                self.set_entity_flags(*entity_key, *flags).ok_or(diverged)?;
+            }
+            WorldOp::InsertComp {
+                entity_key,
+                comp_key,
+                comp,
+            } => {
+                let comp = comp
+                    .as_ref()
+                    .ok_or_else(|| self.missing_value(index, *comp_key))?;
+
+                if comp.0.insert_at(self, *entity_key, *comp_key) != Some(*comp_key) {
+                    return Err(diverged);
+                }
+            }
+            WorldOp::ReorderComp { comp_key, index } => {
+                self.move_comp_row(*comp_key, *index).ok_or(diverged)?;
+            }
+            WorldOp::RemoveComp { comp_key } => {
+                self.remove_comp_row(*comp_key).ok_or(diverged)?;
+            }
+            WorldOp::SetComp { comp_key, comp } => {
+                let comp = comp
+                    .as_ref()
+                    .ok_or_else(|| self.missing_value(index, *comp_key))?;
+
+                comp.0.assign_to(self, *comp_key).map_err(|_| diverged)?;
+            }
+            WorldOp::CompactStep {
+                budget_rows,
+                compact_fn,
+                ..
+            } => {
+                compact_fn(self, *budget_rows).map_err(|_| diverged)?;
+            }
+            WorldOp::Clear => self.clear(),
+        }
+
+        Ok(())
+    }
+
+    /// Record the operation into the journal and the undo history, if they are enabled,
+    /// together with the inverse operations captured by `capture_undo` before the change.
+    pub(crate) fn record(
+        &mut self,
+        inverse: Option<Vec<WorldOp<S>>>,
+        op: impl FnOnce(&ECS<S>) -> WorldOp<S>,
+    ) {
+        self.record_all(inverse, |ecs| vec![op(ecs)]);
+    }
+
+    pub(crate) fn record_all(
+        &mut self,
+        inverse: Option<Vec<WorldOp<S>>>,
+        ops: impl FnOnce(&ECS<S>) -> Vec<WorldOp<S>>,
+    ) {
+        if self.journal.is_none() && inverse.is_none() {
+            return;
+        }
+
+        let ops = ops(self);
+
+        if let Some(journal) = &mut self.journal {
+            journal.extend(ops.iter().cloned());
+        }
+
+        if let Some(inverse) = inverse {
+            self.record_undo(ops, inverse);
+        }
+    }
+
+    /// Return a copy of the component for the journal,
+    /// or `None` if the type is registered without clone support.
+    pub(crate) fn recorded_comp(&self, comp_key: CompKey) -> Option<CompValue<S>> {
+        let (type_key, row_key) = self.check_comp_key(comp_key).ok()?;
+
+        let comp_col = self.comp_cols.get(&type_key)?;
+        let record_comp_fn = comp_col.record_comp_fn?;
+        let comp = (comp_col.get_comp_fn)(comp_col, row_key)?;
+
+        Some(record_comp_fn(comp))
+    }
+
+    fn missing_value(&self, index: usize, comp_key: CompKey) -> ReplayError {
+        match self.comp_cols.get(&comp_key.type_key) {
+            Some(comp_col) => ReplayError::MissingValue {
+                index,
+                type_name: comp_col.type_name,
+            },
+            None => ReplayError::Diverged { index },
+        }
+    }
+
+    pub(crate) fn remove_comp_row(&mut self, comp_key: CompKey) -> Option<()> {
+        let (type_key, row_key) = self.check_comp_key(comp_key).ok()?;
+
+        let inverse = self.capture_undo(|ecs| ecs.removed_comp_inverse(comp_key));
+
+        let comp_col = self.comp_cols.get_mut(&type_key)?;
+        comp_col.count_access(true);
+        comp_col.note_removed(comp_key);
+        let comp_row = (comp_col.remove_row_fn)(comp_col, row_key)?;
+        self.comp_generations.bump(type_key, row_key);
+
+        self.remove_ref_0(comp_row.entity_key, comp_row.ref_0_row_key);
+        self.note_comp_removed(comp_row.entity_key);
+
+        self.remove_ref_1(comp_row.entity_key, type_key, comp_row.ref_1_row_key);
+
+        self.record(inverse, |_| WorldOp::RemoveComp { comp_key });
+
+        Some(())
+    }
+
+    /// Return the operations restoring the component at its position, to be captured before it is removed.
+    pub(crate) fn removed_comp_inverse(&self, comp_key: CompKey) -> Vec<WorldOp<S>> {
+        let Ok(entity_key) = self.get_entity_by_comp(comp_key) else {
+            return vec![];
+        };
+        let comp_row = self
+            .get_row_or_taken(comp_key.type_key, comp_key.row_key)
+            .unwrap();
+
+        let index =
+            self.ref_1_cols[&(entity_key, comp_key.type_key)].position(comp_row.ref_1_row_key);
+
+        vec![
+            WorldOp::InsertComp {
+                entity_key,
+                comp_key,
+                comp: self.recorded_comp(comp_key),
+            },
+            WorldOp::ReorderComp { comp_key, index },
+        ]
+    }
+}
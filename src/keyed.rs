@@ -0,0 +1,336 @@
+use crate::{generations, CompKey, CompRow, EntityKey, Error, Rows, WorldHasher, WorldId, ECS};
+
+/// Return the component key of the row, with the generation looked up in the generations of its type,
+/// so that the key can be built while the column is borrowed.
+fn row_comp_key(
+    type_key: std::any::TypeId,
+    row_key: u32,
+    generations: &[u32],
+    world: WorldId,
+) -> CompKey {
+    CompKey {
+        type_key,
+        row_key,
+        generation: generations::generation(generations, row_key),
+        world,
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return an iterator over all components of the corresponding type together with their component keys,
+    /// in the same order as `iter_comp`.
+    /// The keys are the ones returned by `insert_comp`, so they can be passed to `remove_comp` after the iteration.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (CompKey, &T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// let comps = ecs.iter_comp_with_key::<i32>().unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![(comp_key0, &42), (comp_key1, &63)]);
+    /// ```
+    pub fn iter_comp_with_key<T>(&self) -> Result<impl Iterator<Item = (CompKey, &T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let iter = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(move |(row_key, comp_row)| {
+                (self.comp_key(type_key, row_key as u32), &comp_row.comp)
+            });
+
+        Ok(iter)
+    }
+
+    /// Return a mutable iterator over all components of the corresponding type together with their component keys,
+    /// as in `iter_comp_with_key`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (CompKey, &mut T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// for (_, comp) in ecs.iter_comp_mut_with_key::<i32>().unwrap() {
+    ///     *comp += 1;
+    /// }
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+    /// ```
+    pub fn iter_comp_mut_with_key<T>(
+        &mut self,
+    ) -> Result<impl Iterator<Item = (CompKey, &mut T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let generations = self.comp_generations.of(type_key);
+        let world = self.world;
+        let tick = self.tick;
+        let iter = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter_mut()
+            .map(move |(row_key, comp_row)| {
+                let comp_key = row_comp_key(type_key, row_key as u32, generations, world);
+                (comp_key, comp_row.comp_mut(tick))
+            });
+
+        Ok(iter)
+    }
+
+    /// Return an iterator over all components of the corresponding type together with the entity keys of their owners,
+    /// in the same order as `iter_comp`, without looking up each owner.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (EntityKey, &T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key1, 63).unwrap();
+    ///
+    /// let comps = ecs.iter_comp_with_entity::<i32>().unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![(entity_key0, &42), (entity_key1, &63)]);
+    /// ```
+    pub fn iter_comp_with_entity<T>(&self) -> Result<impl Iterator<Item = (EntityKey, &T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let iter = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(|(_, comp_row)| (comp_row.entity_key, &comp_row.comp));
+
+        Ok(iter)
+    }
+
+    /// Return a mutable iterator over all components of the corresponding type together with the entity keys of their owners,
+    /// as in `iter_comp_with_entity`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (EntityKey, &mut T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// for (owner, comp) in ecs.iter_comp_mut_with_entity::<i32>().unwrap() {
+    ///     assert_eq!(owner, entity_key);
+    ///     *comp += 1;
+    /// }
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+    /// ```
+    pub fn iter_comp_mut_with_entity<T>(
+        &mut self,
+    ) -> Result<impl Iterator<Item = (EntityKey, &mut T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let tick = self.tick;
+        let iter = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter_mut()
+            .map(move |(_, comp_row)| (comp_row.entity_key, comp_row.comp_mut(tick)));
+
+        Ok(iter)
+    }
+
+    /// Return an iterator over all components with the corresponding entity key and type together with their component keys,
+    /// in the same order as `iter_comp_by_entity`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (CompKey, &T)>)`, which is empty if the entity has no components of the type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key1, 63).unwrap();
+    ///
+    /// let comps = ecs.iter_comp_with_key_by_entity::<i32>(entity_key1).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![(comp_key, &63)]);
+    /// ```
+    pub fn iter_comp_with_key_by_entity<T>(
+        &self,
+        entity_key: EntityKey,
+    ) -> Result<impl Iterator<Item = (CompKey, &T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        self.check_entity(entity_key)?;
+
+        // the rows of taken components are skipped
+        let iter = self
+            .ref_1_cols
+            .get(&(entity_key, type_key))
+            .into_iter()
+            .flatten()
+            .filter_map(move |row_key| {
+                let comp_row = comp_rows.get(*row_key as usize)?;
+                Some((self.comp_key(type_key, *row_key), &comp_row.comp))
+            });
+
+        Ok(iter)
+    }
+
+    /// Return a mutable iterator over all components with the corresponding entity key and type
+    /// together with their component keys, in the same order as `iter_comp_by_entity`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (CompKey, &mut T)>)`, which is empty if the entity has no components of the type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let mut iter = ecs.iter_comp_mut_with_key_by_entity::<i32>(entity_key).unwrap();
+    /// assert_eq!(iter.next(), Some((comp_key, &mut 42)));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_comp_mut_with_key_by_entity<T>(
+        &mut self,
+        entity_key: EntityKey,
+    ) -> Result<impl Iterator<Item = (CompKey, &mut T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let entity_check = self.check_entity(entity_key);
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        entity_check?;
+
+        // the rows of the entity are picked out of a single pass over the column, so that each is borrowed once,
+        // and then put in the order of the reference table
+        let generations = self.comp_generations.of(type_key);
+        let mut comps = vec![];
+        if let Some(ref_1_col) = self.ref_1_cols.get(&(entity_key, type_key)) {
+            let positions = ref_1_col
+                .iter()
+                .enumerate()
+                .map(|(position, row_key)| (*row_key, position))
+                .collect::<crate::hash::HashMap<_, _>>();
+            comps = comp_rows
+                .iter_mut()
+                .filter(|(_, comp_row)| comp_row.entity_key == entity_key)
+                .filter_map(|(row_key, comp_row)| {
+                    let row_key = row_key as u32;
+                    let comp_key = row_comp_key(type_key, row_key, generations, self.world);
+                    Some((
+                        *positions.get(&row_key)?,
+                        comp_key,
+                        comp_row.comp_mut(self.tick),
+                    ))
+                })
+                .collect::<Vec<_>>();
+            comps.sort_unstable_by_key(|(position, _, _)| *position);
+        }
+
+        Ok(comps
+            .into_iter()
+            .map(|(_, comp_key, comp)| (comp_key, comp)))
+    }
+}
@@ -43,29 +43,602 @@
 //! // Removes specified entity:
 //! 
 //! ecs.remove_entity(entity_key1).unwrap();
+//!
+//! // Ends the frame, once per iteration of the main loop, flushing deferred operations:
+//!
+//! ecs.end_frame();
 //! ```
 
-type EntityKey = u32;
+#![forbid(unsafe_code)]
+
+mod batch;
+mod builder;
+mod bundle;
+mod capacity;
+mod change;
+mod clear;
+mod column;
+mod compact;
+mod diagnostics;
+mod diff;
+mod entity_flags;
+mod entity_ref;
+mod filter;
+mod frame;
+#[cfg(feature = "fuzzing")]
+mod fuzz;
+mod generations;
+mod hash;
+mod hook;
+#[cfg(feature = "interop")]
+mod interop;
+mod journal;
+mod keyed;
+mod lifecycle;
+mod locked;
+mod lookup;
+mod merge;
+mod observer;
+#[cfg(feature = "rayon")]
+mod par;
+mod parts;
+#[cfg(feature = "profile")]
+mod profile;
+mod query;
+mod reflect;
+mod refs;
+mod registered;
+mod removal;
+mod rows;
+#[cfg(feature = "serde")]
+mod serialize;
+mod shard;
+mod shared;
+mod single;
+mod snapshot;
+mod stable;
+mod sync;
+mod take;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod typed_key;
+mod undo;
+mod world;
+
+pub use builder::EntityBuilder;
+pub use bundle::Bundle;
+pub use clear::ClearProgress;
+pub use column::{Column, ColumnMut, Columns};
+pub use compact::CompactProgress;
+pub use diagnostics::{Histogram, OrphanReport, OrphanRow, RefStats, UnregisteredRef};
+pub use diff::{CompDiff, CompDiffKind, WorldDiff};
+pub use entity_flags::EntityFilter;
+pub use entity_ref::{EntityMut, EntityRef};
+pub use filter::{CompFilter, With, Without};
+pub use frame::FrameReport;
+pub use hash::{DefaultHashBuilder, WorldHasher};
+pub use hook::HookContext;
+#[cfg(feature = "interop")]
+pub use interop::{ImportError, MigrationImporter};
+pub use journal::{CompValue, ReplayError, WorldOp};
+pub use lifecycle::EntityLifecycle;
+pub use locked::{ColumnReadGuard, ColumnWriteGuard, LockedECS};
+pub use lookup::EntityLookup;
+pub use merge::{MergeError, MergeReport};
+pub use observer::ObserverHandle;
+pub use parts::EntityParts;
+#[cfg(feature = "profile")]
+pub use profile::AccessCounts;
+pub use query::{Query, QueryElem, QueryElemOwned, QueryOwned, QueryPlan};
+#[doc(hidden)]
+pub use reflect::{__set_field, __unknown_field};
+pub use reflect::{FieldError, FieldType, FieldValue, Reflect};
+pub use registered::Registered;
+pub use removal::RemovedComp;
+#[cfg(feature = "serde")]
+pub use serialize::{SerdeComps, Unserializable};
+pub use shard::ShardRest;
+pub use shared::SharedComp;
+pub use snapshot::{Snapshot, SnapshotRing};
+pub use sync::SharedECS;
+pub use take::TakenComp;
+pub use typed_key::{IntoCompKey, TypedCompKey};
+pub use world::{WorldRead, WorldWrite};
+
+use rows::Rows;
+
+/// An identifier of an ECS instance, embedded in the keys to reject keys of other worlds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct WorldId(u32);
+
+impl Default for WorldId {
+    fn default() -> Self {
+        static NEXT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+        WorldId(NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
+/// The largest slot index of an entity key, and the largest row key of a component key of each type.
+/// Inserting an entity, or a component of a type, beyond it panics instead of wrapping the key around,
+/// which would corrupt the reference tables.
+pub const MAX_KEY: u32 = u32::MAX;
+
+/// A key of an entity, made of the slot index, the generation of the slot and the world identifier.
+/// The generation is bumped every time the entity in the slot is removed,
+/// so the key of a removed entity never resolves to an entity inserted later into the same slot.
+/// Keys of other worlds are rejected, failing with an `Error::WrongWorld` where a `Result` is returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EntityKey {
+    index: u32,
+    generation: u32,
+    world: WorldId,
+}
+
+// the world identifier depends on the number of worlds created before, so it is left out of the hash,
+// keeping the order of the tables keyed by entity independent of it, as for the `deterministic` feature
+impl std::hash::Hash for EntityKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl EntityKey {
+    /// Return the slot index of the entity.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Return the generation of the slot when the entity was inserted.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// An identifier of a partition of entities, returned by [`ECS::create_partition`].
+/// Entities inserted by `insert_entity` belong to the default partition.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PartitionId(u32);
+
+impl PartitionId {
+    /// The default partition, which always exists.
+    pub const DEFAULT: PartitionId = PartitionId(0);
+}
+
+/// The status of an entity key, returned by [`ECS::entity_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityStatus {
+    /// The entity is alive.
+    Alive,
+    /// The entity was removed, and the slot is now at the current generation.
+    Removed {
+        /// The current generation of the slot.
+        current_generation: u32,
+    },
+    /// The key was never handed out by this ECS instance,
+    /// either because the slot was never allocated or because the generation is newer than the slot.
+    NeverExisted,
+    /// The key is reserved, as in `reserve_entity`, and the entity is inserted by the next `flush_reserved`.
+    Reserved,
+}
+
+/// A key of a component, made of the component type, the row key in the column of the type,
+/// the generation of the row and the world identifier.
+/// The generation is bumped every time the component in the row is removed,
+/// so the key of a removed component never resolves to a component inserted later into the same row,
+/// failing with an `Error::CompNotFound` instead.
+/// Keys of other worlds are rejected, failing with an `Error::WrongWorld` where a `Result` is returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CompKey {
+    type_key: std::any::TypeId,
+    row_key: u32,
+    generation: u32,
+    world: WorldId,
+}
+
+// the world identifier is left out of the hash, as for `EntityKey`
+impl std::hash::Hash for CompKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.type_key.hash(state);
+        self.row_key.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl CompKey {
+    /// Return the type id of the component.
+    pub fn type_key(&self) -> std::any::TypeId {
+        self.type_key
+    }
+
+    /// Return the row key of the component in the column of the type.
+    pub fn row_key(&self) -> u32 {
+        self.row_key
+    }
 
-type CompKey = (std::any::TypeId, u32);
+    /// Return the generation of the row when the component was inserted.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
 
 struct CompRow<T> {
     comp: T,
-    entity_key: u32,
+    entity_key: EntityKey,
     ref_0_row_key: u32,
+    /// The key of the entry of the row in the reference table of its entity and type, as in `refs::RefList`.
     ref_1_row_key: u32,
+    stable_id: Option<u64>,
+    ticks: change::Ticks,
 }
 
-const ALLOC_SIZE: usize = std::mem::size_of::<slab::Slab<CompRow<()>>>();
+const ALLOC_SIZE: usize = std::mem::size_of::<Rows<CompRow<()>>>();
 
-struct CompColumn {
+struct CompColumn<S> {
     comp_rows: stack_any::StackAny<ALLOC_SIZE>,
+    type_name: &'static str,
+    len_fn: fn(&Self) -> usize,
     get_row_fn: fn(&Self, u32) -> Option<CompRow<()>>,
+    iter_owners_fn: IterOwnersFn<S>,
     remove_row_fn: fn(&mut Self, u32) -> Option<CompRow<()>>,
+    /// Remove the row as in `remove_row_fn`, returning the component boxed instead of dropping it.
+    remove_boxed_fn: fn(&mut Self, u32) -> Option<Box<dyn std::any::Any>>,
+    insert_boxed_fn: InsertBoxedFn<S>,
+    /// Hand the row over to another owner, with its row keys in the reference tables of that owner.
+    set_owner_fn: fn(&mut Self, u32, EntityKey, u32, u32) -> Option<()>,
+    release_row_fn: fn(&mut Self, u32) -> Option<()>,
+    /// Shrink the storage of the rows, as in `shrink_to_fit`.
+    shrink_fn: fn(&mut Self),
+    get_comp_fn: fn(&Self, u32) -> Option<&dyn std::any::Any>,
+    /// Return the component of the row mutably, marking it changed at the tick, as in `mark_changed`.
+    get_comp_mut_fn: fn(&mut Self, u32, u64) -> Option<&mut dyn std::any::Any>,
+    clone_rows_fn: CloneRowsFn<S>,
+    record_comp_fn: Option<fn(&dyn std::any::Any) -> CompValue<S>>,
+    get_field_fn: Option<GetFieldFn>,
+    set_field_fn: Option<SetFieldFn>,
+    /// Compare two components of the type, as set by `set_eq`.
+    eq_fn: Option<EqFn>,
+    /// The removals of components of the type, as enabled by `enable_removal_tracking`.
+    removal_log: Option<removal::RemovalLog<S>>,
+    /// The observers of insertions and removals, as added by `on_insert` and `on_remove`.
+    observers: observer::Observers,
+    insert_validator: Option<InsertValidator<S>>,
+    insert_hook: Option<InsertHook<S>>,
+    comp_limit: Option<u32>,
+    comp_limit_total: Option<u32>,
+    immutable: bool,
+    ordered: bool,
+    world_unique: bool,
+    /// The row key of the last component inserted into a world unique column,
+    /// which holds the only component of the type if the row is not vacant.
+    unique_row: Option<u32>,
+    stable_ids: Option<stable::StableIds<S>>,
+    #[cfg(feature = "profile")]
+    access: profile::AccessCounters,
+    /// The name of the component type in serialized worlds, as in `register_serde`.
+    #[cfg(feature = "serde")]
+    serde_name: Option<&'static str>,
+    /// The original row keys and generations of the rows relocated by `compact_step`, by the current row keys.
+    remaps: hash::HashMap<u32, (u32, u32), S>,
+    /// The registration number of the type within the world, as in `Registered`.
+    registration: u64,
+}
+
+impl<S> CompColumn<S> {
+    /// Count a read or a write of the components, if the `profile` feature is enabled.
+    #[inline]
+    fn count_access(&self, write: bool) {
+        #[cfg(feature = "profile")]
+        self.access.count(write);
+        #[cfg(not(feature = "profile"))]
+        let _ = write;
+    }
+}
+
+type InsertValidator<S> = std::sync::Arc<
+    dyn Fn(&ECS<S>, EntityKey, &dyn std::any::Any) -> Result<(), String> + Send + Sync,
+>;
+
+/// Iterate over the row keys and the owners of all rows.
+type IterOwnersFn<S> = fn(&CompColumn<S>) -> Box<dyn Iterator<Item = (u32, EntityKey)> + '_>;
+
+type InsertHook<S> =
+    std::sync::Arc<dyn Fn(&mut HookContext<'_, S>, EntityKey, CompKey) + Send + Sync>;
+
+/// Insert a boxed component of the type, as in `insert_comp`.
+type InsertBoxedFn<S> =
+    fn(&mut ECS<S>, EntityKey, Box<dyn std::any::Any>) -> Result<CompKey, Error>;
+
+type GetFieldFn = fn(&dyn std::any::Any, &str) -> Result<FieldValue, FieldError>;
+
+type SetFieldFn = fn(&mut dyn std::any::Any, &str, FieldValue) -> Result<(), FieldError>;
+
+type EqFn = fn(&dyn std::any::Any, &dyn std::any::Any) -> bool;
+
+/// Clone the rows of the entities passing the predicate, keeping the row keys.
+/// For component types registered without clone support, fail if any row passes the predicate.
+type CloneRowsFn<S> =
+    fn(&CompColumn<S>, &dyn Fn(EntityKey) -> bool) -> Option<stack_any::StackAny<ALLOC_SIZE>>;
+
+/// An error returned by fallible [`ECS`] operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The entity corresponding to the entity key is not found, with the reason given by the entity status.
+    EntityNotFound(EntityStatus),
+    /// The component type is not registered.
+    TypeNotRegistered,
+    /// The insert validator of the component type rejected the component.
+    ValidationFailed(String),
+    /// The component limit of the component type is reached.
+    QuotaExceeded,
+    /// The component corresponding to the component key is not found.
+    CompNotFound,
+    /// The component key does not correspond to the requested component type.
+    TypeMismatch,
+    /// The component type is registered as immutable and cannot be mutated.
+    ImmutableType,
+    /// The key belongs to another world.
+    WrongWorld,
+    /// The component type is not registered as ordered, so its components cannot be placed explicitly.
+    TypeNotOrdered,
+    /// The component type is not registered as world unique, as in `ECS::register_world_unique`.
+    TypeNotWorldUnique,
+    /// The slot at the requested key is occupied.
+    SlotOccupied,
+    /// The component type is already registered.
+    TypeAlreadyRegistered,
+    /// Another component type is registered under the same type name,
+    /// as when two copies of a type are compiled separately.
+    DuplicateTypeName(&'static str),
+    /// The component is taken out of the world, as in `take_comp`, and not put back yet.
+    TemporarilyTaken,
+    /// The component type is registered as world unique, and a component of it is already held by the entity.
+    AlreadyExists {
+        /// The entity holding the component.
+        holder: EntityKey,
+    },
+    /// The component type has no stable ids, as in `enable_stable_ids`.
+    StableIdsNotEnabled,
+    /// The column is locked by another guard, as in `LockedECS::try_write_column`.
+    ColumnLocked,
+    /// The hooks triggered by an operation kept queuing operations past the limit, as in `ECS::take_hook_errors`.
+    ReentrantMutation,
+    /// The query has only optional elements, so no column drives its iteration.
+    NoRequiredElement,
+    /// A component type appears more than once where distinct component types are required, as in `ECS::columns_mut`.
+    DuplicateType,
+    /// A component key appears more than once where distinct components are required, as in `ECS::get_many_comp_mut`.
+    DuplicateKey,
+    /// The entity holds several components of the type where at most one is expected, as in `ECS::get_comp_by_entity`.
+    AmbiguousComp,
+    /// The partition is not found, as in `ECS::create_partition`.
+    PartitionNotFound,
+    /// The observer is not found, as when it was already removed or its type was unregistered.
+    ObserverNotFound,
+    /// The undo history is not enabled, as in `ECS::enable_undo`.
+    UndoNotEnabled,
+    /// The undo history has nothing to undo.
+    NothingToUndo,
+    /// The undo history has nothing to redo.
+    NothingToRedo,
+    /// The external id or the entity key is not mapped, as in `MigrationImporter::import`.
+    ExternalIdNotFound,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::EntityNotFound(EntityStatus::Removed { current_generation }) => write!(
+                f,
+                "entity not found: removed (current generation {})",
+                current_generation
+            ),
+            Error::EntityNotFound(EntityStatus::NeverExisted) => {
+                write!(f, "entity not found: never existed")
+            }
+            Error::EntityNotFound(EntityStatus::Reserved) => {
+                write!(f, "entity not found: reserved")
+            }
+            Error::EntityNotFound(EntityStatus::Alive) => write!(f, "entity not found"),
+            Error::TypeNotRegistered => write!(f, "component type not registered"),
+            Error::ValidationFailed(reason) => write!(f, "validation failed: {}", reason),
+            Error::QuotaExceeded => write!(f, "component limit exceeded"),
+            Error::CompNotFound => write!(f, "component not found"),
+            Error::TypeMismatch => write!(f, "component type mismatch"),
+            Error::ImmutableType => write!(f, "component type is immutable"),
+            Error::WrongWorld => write!(f, "key belongs to another world"),
+            Error::TypeNotOrdered => write!(f, "component type is not ordered"),
+            Error::TypeNotWorldUnique => write!(f, "component type is not world unique"),
+            Error::SlotOccupied => write!(f, "slot occupied"),
+            Error::TypeAlreadyRegistered => write!(f, "component type already registered"),
+            Error::DuplicateTypeName(type_name) => write!(
+                f,
+                "component type name registered by another type: {}",
+                type_name
+            ),
+            Error::TemporarilyTaken => write!(f, "component temporarily taken"),
+            Error::AlreadyExists { holder } => {
+                write!(f, "world unique component already held by {:?}", holder)
+            }
+            Error::StableIdsNotEnabled => write!(f, "stable ids not enabled for component type"),
+            Error::ColumnLocked => write!(f, "column locked by another guard"),
+            Error::ReentrantMutation => write!(f, "hook operations exceeded the re-entrancy limit"),
+            Error::NoRequiredElement => write!(f, "query has no required element"),
+            Error::DuplicateType => write!(f, "component type appears more than once"),
+            Error::DuplicateKey => write!(f, "component key appears more than once"),
+            Error::AmbiguousComp => write!(f, "entity holds several components of the type"),
+            Error::PartitionNotFound => write!(f, "partition not found"),
+            Error::ObserverNotFound => write!(f, "observer not found"),
+            Error::UndoNotEnabled => write!(f, "undo not enabled"),
+            Error::NothingToUndo => write!(f, "nothing to undo"),
+            Error::NothingToRedo => write!(f, "nothing to redo"),
+            Error::ExternalIdNotFound => write!(f, "external id not found"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An error returned by [`ECS::try_update_comp`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateError<E> {
+    /// The component cannot be accessed mutably.
+    Access(Error),
+    /// The closure failed, and the component is left untouched.
+    Rejected(E),
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for UpdateError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::Access(e) => write!(f, "{}", e),
+            UpdateError::Rejected(e) => write!(f, "update rejected: {}", e),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for UpdateError<E> {}
+
+/// An error returned by [`ECS::query_single`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SingleError {
+    /// The query is invalid.
+    Query(Error),
+    /// No entity matches the query.
+    NoEntities,
+    /// More than one entity matches the query.
+    MultipleEntities,
+}
+
+impl std::fmt::Display for SingleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SingleError::Query(e) => write!(f, "{}", e),
+            SingleError::NoEntities => write!(f, "no entities match the query"),
+            SingleError::MultipleEntities => write!(f, "multiple entities match the query"),
+        }
+    }
+}
+
+impl std::error::Error for SingleError {}
+
+/// An error returned by [`ECS::clone_filtered`] and [`ECS::clone_entity`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloneError {
+    /// A component to be cloned is of a type registered without clone support.
+    NotCloneable {
+        /// The name of the component type.
+        type_name: &'static str,
+    },
+    /// The entity to be cloned is not found.
+    EntityNotFound,
+    /// A cloned component was rejected on insertion, as by the insert validator.
+    Rejected(Error),
+}
+
+impl std::fmt::Display for CloneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CloneError::NotCloneable { type_name } => {
+                write!(f, "component type not cloneable: {}", type_name)
+            }
+            CloneError::EntityNotFound => write!(f, "entity to be cloned not found"),
+            CloneError::Rejected(error) => write!(f, "cloned component rejected: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for CloneError {}
+
+/// An error returned by [`ECS::spawn_batch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpawnError {
+    /// A component of the bundle could not be inserted.
+    Bundle {
+        /// The index of the bundle in the batch.
+        index: usize,
+        /// The error of the insertion.
+        error: Error,
+    },
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnError::Bundle { index, error } => {
+                write!(f, "bundle {} not spawned: {}", index, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+/// An inconsistency of the internal bookkeeping, returned by [`ECS::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// The reference tables hold entries of an entity that is not alive.
+    DeadEntity {
+        /// The entity key of the entries.
+        entity_key: EntityKey,
+    },
+    /// The reference tables hold entries of a component type that is not registered.
+    UnregisteredType {
+        /// The entity key of the entries.
+        entity_key: EntityKey,
+    },
+    /// A reference entry does not point back at a component row owned by the entity.
+    DanglingRef {
+        /// The name of the component type.
+        type_name: &'static str,
+        /// The entity key of the entry.
+        entity_key: EntityKey,
+        /// The row key the entry points at.
+        row_key: u32,
+    },
+    /// The component rows of the type are not all referenced by the reference tables.
+    UnreferencedRows {
+        /// The name of the component type.
+        type_name: &'static str,
+    },
+}
+
+impl std::fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Inconsistency::DeadEntity { entity_key } => {
+                write!(f, "references of dead entity {:?}", entity_key)
+            }
+            Inconsistency::UnregisteredType { entity_key } => write!(
+                f,
+                "references of unregistered component type on entity {:?}",
+                entity_key
+            ),
+            Inconsistency::DanglingRef {
+                type_name,
+                entity_key,
+                row_key,
+            } => write!(
+                f,
+                "dangling reference of entity {:?} to row {} of {}",
+                entity_key, row_key, type_name
+            ),
+            Inconsistency::UnreferencedRows { type_name } => {
+                write!(f, "unreferenced rows of {}", type_name)
+            }
+        }
+    }
 }
 
+impl std::error::Error for Inconsistency {}
+
 /// A minimal ECS supporting entity and component insertion/removal, association, and single-type iteration.
 ///
+/// The world is `Send` and `Sync`, since every component type, hook and validator must be,
+/// so it can be moved to another thread or shared behind a lock, as in [`SharedECS`].
+///
+/// The internal tables are hashed with the hasher `S`, [`DefaultHashBuilder`] unless set by `with_hasher`.
+///
 /// # Examples
 ///
 /// ```
@@ -82,12 +655,47 @@ struct CompColumn {
 ///     *comp += 1;
 /// }
 /// ```
-#[derive(Default)]
-pub struct ECS {
-    entities: slab::Slab<()>,
-    comp_cols: ahash::AHashMap<std::any::TypeId, CompColumn>,
-    ref_0_cols: ahash::AHashMap<EntityKey, slab::Slab<(std::any::TypeId, u32)>>,
-    ref_1_cols: ahash::AHashMap<(EntityKey, std::any::TypeId), slab::Slab<u32>>,
+pub struct ECS<S = DefaultHashBuilder> {
+    entities: slab::Slab<entity_flags::EntitySlot>,
+    generations: Vec<u32>,
+    partitions: Vec<String>,
+    world: WorldId,
+    comp_cols: hash::HashMap<std::any::TypeId, CompColumn<S>, S>,
+    ref_0_cols: hash::HashMap<EntityKey, slab::Slab<(std::any::TypeId, u32)>, S>,
+    /// The row keys of the components of each entity and type, in their iteration order.
+    ref_1_cols: hash::HashMap<(EntityKey, std::any::TypeId), refs::RefList, S>,
+    shared_pools:
+        hash::HashMap<std::any::TypeId, std::sync::Arc<dyn std::any::Any + Send + Sync>, S>,
+    journal: Option<Vec<WorldOp<S>>>,
+    undo: Option<undo::UndoHistory<S>>,
+    allow_duplicate_names: bool,
+    taken: take::TakenRows<S>,
+    comp_generations: generations::CompGenerations<S>,
+    /// The reusable buffers of the cascades, kept empty between calls so that their allocations are reused.
+    scratch_refs: Vec<(std::any::TypeId, u32)>,
+    scratch_row_keys: Vec<u32>,
+    frame: u64,
+    /// The change tick, as in `tick`.
+    tick: u64,
+    /// The operations queued by hooks and not applied yet, as in `HookContext`.
+    hook_commands: std::collections::VecDeque<hook::HookCommand<S>>,
+    applying_hooks: bool,
+    hook_errors: Vec<Error>,
+    lifecycle_events: Option<Vec<EntityLifecycle>>,
+    /// The number of registrations so far, numbering the next one.
+    registrations: u64,
+    /// The entities left to remove by `clear_incremental`, in reverse order.
+    clearing: Option<Vec<EntityKey>>,
+    /// A limit of the keys below `MAX_KEY`, as in `set_key_limit`, so that running out of keys can be tested.
+    key_limit: Option<u32>,
+    /// The number of reserved entities not flushed yet, as in `reserve_entity`.
+    reserved_count: usize,
+}
+
+impl<S: WorldHasher> Default for ECS<S> {
+    fn default() -> Self {
+        Self::with_hasher(Default::default())
+    }
 }
 
 impl ECS {
@@ -99,221 +707,252 @@ impl ECS {
     /// let mut ecs = ecs_tiny::ECS::new();
     /// ```
     pub fn new() -> Self {
-        Default::default()
+        Self::with_hasher(Default::default())
     }
 
-    /// Insert a new entity and return the corresponding entity key.
+    /// Create a new ECS instance with capacity for at least the number of entities, as in `reserve_entities`.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let mut ecs = ecs_tiny::ECS::with_capacity(1000);
     /// let entity_key = ecs.insert_entity();
     /// ```
-    pub fn insert_entity(&mut self) -> EntityKey {
-        self.entities.insert(()) as u32
+    pub fn with_capacity(entities: usize) -> Self {
+        let mut ecs = Self::new();
+        ecs.reserve_entities(entities);
+        ecs
     }
+}
 
-    /// Remove an entity with the corresponding entity key.
-    /// If the entity corresponding to the entity key is not found, return an `None`.
-    /// Otherwise, return an `Some(())`.
+impl<S: WorldHasher> ECS<S> {
+    /// Create a new ECS instance whose internal tables are hashed with a clone of the hasher,
+    /// as for builds avoiding ahash, or a faster hasher such as FxHash.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let mut ecs = ecs_tiny::ECS::with_hasher(std::collections::hash_map::RandomState::new());
     /// let entity_key = ecs.insert_entity();
-    /// ecs.remove_entity(entity_key).unwrap();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
     /// ```
-    pub fn remove_entity(&mut self, entity_key: EntityKey) -> Option<()> {
-        self.entities.try_remove(entity_key as usize)?;
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            entities: Default::default(),
+            generations: Default::default(),
+            partitions: Default::default(),
+            world: Default::default(),
+            comp_cols: hash::HashMap::with_hasher(hasher.clone()),
+            ref_0_cols: hash::HashMap::with_hasher(hasher.clone()),
+            ref_1_cols: hash::HashMap::with_hasher(hasher.clone()),
+            shared_pools: hash::HashMap::with_hasher(hasher.clone()),
+            journal: Default::default(),
+            undo: Default::default(),
+            allow_duplicate_names: Default::default(),
+            taken: take::TakenRows::with_hasher(hasher.clone()),
+            comp_generations: generations::CompGenerations::with_hasher(hasher),
+            scratch_refs: Default::default(),
+            scratch_row_keys: Default::default(),
+            frame: Default::default(),
+            tick: Default::default(),
+            hook_commands: Default::default(),
+            applying_hooks: Default::default(),
+            hook_errors: Default::default(),
+            lifecycle_events: Default::default(),
+            registrations: Default::default(),
+            clearing: Default::default(),
+            key_limit: Default::default(),
+            reserved_count: Default::default(),
+        }
+    }
 
-        if let Some(ref_0_col) = self.ref_0_cols.remove(&entity_key) {
-            for (_, (type_key, row_key)) in ref_0_col {
-                let comp_col = self.comp_cols.get_mut(&type_key).unwrap();
-                let comp_row = (comp_col.remove_row_fn)(comp_col, row_key).unwrap();
+    /// Return the hasher of the internal tables, as set by `with_hasher`.
+    pub(crate) fn hasher(&self) -> &S {
+        self.comp_cols.hasher()
+    }
 
-                self.ref_1_cols
-                    .get_mut(&(entity_key, type_key))
-                    .unwrap()
-                    .try_remove(comp_row.ref_1_row_key as usize)
-                    .unwrap();
-            }
+    /// Create a new empty ECS instance sharing the world identifier,
+    /// so that the keys handed out by either world resolve in the other.
+    /// The mirror is meant to be driven by the same operations at the same keys,
+    /// as in `insert_entity_at` and `insert_comp_at_row`, so that the keys never collide.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let mirror = ecs.new_mirror();
+    /// let entity_key = ecs.insert_entity();
+    ///
+    /// assert!(mirror.get_entity(entity_key).is_err());
+    /// ```
+    pub fn new_mirror(&self) -> Self {
+        ECS {
+            world: self.world,
+            allow_duplicate_names: self.allow_duplicate_names,
+            ..Self::with_hasher(self.hasher().clone())
         }
-
-        Some(())
     }
 
-    /// Return entity with the corresponding entity key.
-    /// If the entity corresponding to the entity key is not found, return an `None`.
-    /// Otherwise, return an `Some(())`.
+    /// Insert a new entity and return the corresponding entity key.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut ecs = ecs_tiny::ECS::new();
     /// let entity_key = ecs.insert_entity();
-    /// ecs.get_entity(entity_key).unwrap();
     /// ```
-    pub fn get_entity(&self, entity_key: EntityKey) -> Option<()> {
-        self.entities.get(entity_key as usize)?;
-        Some(())
+    pub fn insert_entity(&mut self) -> EntityKey {
+        self.insert_entity_in(PartitionId::DEFAULT).unwrap()
     }
 
-    /// Return an iterator over all entity keys.
+    /// Insert the number of new entities and return the corresponding entity keys in the order of insertion.
+    /// The capacity of the entities is reserved up front, as in `reserve_entities`.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut ecs = ecs_tiny::ECS::new();
-    /// let entity_key0 = ecs.insert_entity();
-    /// let entity_key1 = ecs.insert_entity();
-    /// let entity_key2 = ecs.insert_entity();
-    /// let mut iter = ecs.iter_entity();
+    /// let entity_keys = ecs.insert_entities(3);
     ///
-    /// assert_eq!(iter.next(), Some(entity_key0));
-    /// assert_eq!(iter.next(), Some(entity_key1));
-    /// assert_eq!(iter.next(), Some(entity_key2));
-    /// assert_eq!(iter.next(), None);
+    /// assert_eq!(ecs.iter_entity().collect::<Vec<_>>(), entity_keys);
     /// ```
-    pub fn iter_entity(&self) -> impl Iterator<Item = EntityKey> + '_ {
-        self.entities.iter().map(|(key, _)| key as u32)
+    pub fn insert_entities(&mut self, n: usize) -> Vec<EntityKey> {
+        self.reserve_entities(n);
+
+        (0..n).map(|_| self.insert_entity()).collect()
     }
 
-    /// Register component type.
+    /// Reserve capacity for at least the number of additional entities,
+    /// so that inserting them does not grow the storage of the entities.
+    /// Unlike `reserve_entity`, no entity key is handed out.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.reserve_entities(1000);
     /// let entity_key = ecs.insert_entity();
-    /// ecs.register::<i32>().unwrap();
-    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
     /// ```
-    pub fn register<T>(&mut self) -> Option<()>
-    where
-        T: std::any::Any,
-    {
-        let type_key = std::any::TypeId::of::<T>();
-
-        if self.comp_cols.contains_key(&type_key) {
-            return None;
-        }
-
-        let comp_col = CompColumn {
-            comp_rows: stack_any::StackAny::try_new(slab::Slab::<CompRow<T>>::new()).unwrap(),
-            get_row_fn: |comp_col, row_key| {
-                let comp_row = comp_col
-                    .comp_rows
-                    .downcast_ref::<slab::Slab<CompRow<T>>>()
-                    .unwrap()
-                    .get(row_key as usize)?;
-                Some(CompRow {
-                    comp: (),
-                    entity_key: comp_row.entity_key,
-                    ref_0_row_key: comp_row.ref_0_row_key,
-                    ref_1_row_key: comp_row.ref_1_row_key,
-                })
-            },
-            remove_row_fn: |comp_col, row_key| {
-                let comp_row = comp_col
-                    .comp_rows
-                    .downcast_mut::<slab::Slab<CompRow<T>>>()
-                    .unwrap()
-                    .try_remove(row_key as usize)?;
-                Some(CompRow {
-                    comp: (),
-                    entity_key: comp_row.entity_key,
-                    ref_0_row_key: comp_row.ref_0_row_key,
-                    ref_1_row_key: comp_row.ref_1_row_key,
-                })
-            },
-        };
-        self.comp_cols.insert(type_key, comp_col);
-
-        Some(())
+    pub fn reserve_entities(&mut self, additional: usize) {
+        self.entities.reserve(additional);
+        self.generations.reserve(additional);
     }
 
-    /// Unregister component type.
+    /// Insert a new entity into the partition and return the corresponding entity key.
+    /// If the partition is not found, return an `Err(Error::PartitionNotFound)`.
+    /// Otherwise, return an `Ok(EntityKey)`.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut ecs = ecs_tiny::ECS::new();
-    /// let entity_key = ecs.insert_entity();
-    /// ecs.register::<i32>().unwrap();
-    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
-    /// ecs.unregister::<i32>().unwrap();
+    /// let partition = ecs.create_partition("match_42");
+    /// let entity_key = ecs.insert_entity_in(partition).unwrap();
+    ///
+    /// assert_eq!(ecs.entity_partition(entity_key), Ok(partition));
     /// ```
-    pub fn unregister<T>(&mut self) -> Option<()>
-    where
-        T: std::any::Any,
-    {
-        let type_key = std::any::TypeId::of::<T>();
+    pub fn insert_entity_in(&mut self, partition: PartitionId) -> Result<EntityKey, Error> {
+        self.partition_name(partition)?;
 
-        if !self.comp_cols.contains_key(&type_key) {
-            return None;
-        }
+        let index = self.entities.vacant_key();
 
-        self.comp_cols.remove(&type_key);
+        let entity_key = EntityKey {
+            index: self.checked_key(index),
+            generation: self.generations.get(index).copied().unwrap_or(0),
+            world: self.world,
+        };
+        self.insert_entity_slot(entity_key, partition).unwrap();
 
-        Some(())
+        Ok(entity_key)
     }
 
-    /// Insert a new component with the corresponding entity key and return the corresponding component key.
-    /// If the entity corresponding to the entity key is not found, return an `None`.
-    /// Otherwise, return an `Some(CompKey)`.
+    /// Insert a new entity at the entity key handed out by a world sharing the world identifier, as in `new_mirror`,
+    /// setting the generation of the slot to the one of the key.
+    /// Placing the keys in the order they were handed out keeps the insertion cheap,
+    /// while other slots rebuild the storage of the entities.
+    /// If the entity key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the slot of the entity key is occupied, return an `Err(Error::SlotOccupied)`.
+    /// Otherwise, return an `Ok(())`.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mut ecs = ecs_tiny::ECS::new();
-    /// let entity_key = ecs.insert_entity();
-    /// ecs.register::<i32>().unwrap();
-    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let mut server = ecs_tiny::ECS::new();
+    /// let mut client = server.new_mirror();
+    /// let entity_key = server.insert_entity();
+    /// client.insert_entity_at(entity_key).unwrap();
+    ///
+    /// assert!(client.get_entity(entity_key).is_ok());
     /// ```
-    pub fn insert_comp<T>(&mut self, entity_key: EntityKey, comp: T) -> Option<CompKey>
-    where
-        T: std::any::Any,
-    {
-        self.entities.get(entity_key as usize)?;
+    pub fn insert_entity_at(&mut self, entity_key: EntityKey) -> Result<(), Error> {
+        if entity_key.world != self.world {
+            return Err(Error::WrongWorld);
+        }
 
-        let type_key = std::any::TypeId::of::<T>();
+        self.insert_entity_slot(entity_key, PartitionId::DEFAULT)
+            .ok_or(Error::SlotOccupied)
+    }
 
-        let comp_rows = self
-            .comp_cols
-            .get_mut(&type_key)?
-            .comp_rows
-            .downcast_mut::<slab::Slab<CompRow<T>>>()
-            .unwrap();
+    /// Insert an entity at the entity key, setting the generation of the slot to the one of the key.
+    /// If the slot is occupied, return an `None`.
+    /// Otherwise, return an `Some(())`.
+    pub(crate) fn insert_entity_slot(
+        &mut self,
+        entity_key: EntityKey,
+        partition: PartitionId,
+    ) -> Option<()> {
+        let index = entity_key.index as usize;
 
-        let row_key = comp_rows.vacant_key() as u32;
+        if self.entities.contains(index) {
+            return None;
+        }
 
-        let ref_0_row_key = self
-            .ref_0_cols
-            .entry(entity_key)
-            .or_default()
-            .insert((type_key, row_key)) as u32;
+        insert_at(
+            &mut self.entities,
+            index,
+            entity_flags::EntitySlot::new(partition),
+        );
 
-        let ref_1_row_key = self
-            .ref_1_cols
-            .entry((entity_key, type_key))
-            .or_default()
-            .insert(row_key) as u32;
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+        self.generations[index] = entity_key.generation;
 
-        comp_rows.insert(CompRow {
-            comp,
+        let inverse = self.capture_undo(|_| vec![WorldOp::RemoveEntity { entity_key }]);
+        self.record(inverse, |_| WorldOp::InsertEntity {
             entity_key,
-            ref_0_row_key,
-            ref_1_row_key,
+            partition,
         });
 
-        Some((type_key, row_key))
+        Some(())
+    }
+
+    /// Remove an entity with the corresponding entity key.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.remove_entity(entity_key).unwrap();
+    /// ```
+    pub fn remove_entity(&mut self, entity_key: EntityKey) -> Result<(), Error> {
+        self.remove_entity_collecting(entity_key, None, None)
     }
 
-    /// Remove a component with the corresponding component key and type, and return the component.
-    /// If the component corresponding to the component key and type is not found, return an `None`.
-    /// Otherwise, return an `Some(T)`.
+    /// Remove an entity with the corresponding entity key, as in `remove_entity`,
+    /// and return the component keys of the components removed with it, including the taken ones.
+    /// The keys are in the order the components were attached to the entity,
+    /// except that a component takes the place of an earlier one removed from the entity.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(Vec<CompKey>)`.
     ///
     /// # Examples
     ///
@@ -321,47 +960,3550 @@ impl ECS {
     /// let mut ecs = ecs_tiny::ECS::new();
     /// let entity_key = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
-    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
-    /// let comp = ecs.remove_comp::<i32>(comp_key).unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key, 1u32).unwrap();
     ///
-    /// assert_eq!(comp, 42);
+    /// assert_eq!(ecs.remove_entity_verbose(entity_key), Ok(vec![comp_key0, comp_key1]));
     /// ```
-    pub fn remove_comp<T>(&mut self, comp_key: CompKey) -> Option<T>
-    where
+    pub fn remove_entity_verbose(&mut self, entity_key: EntityKey) -> Result<Vec<CompKey>, Error> {
+        let mut removed = vec![];
+        self.remove_entity_collecting(entity_key, Some(&mut removed), None)?;
+        Ok(removed)
+    }
+
+    /// Remove an entity, as in `remove_entity`, collecting the component keys of the components removed with it,
+    /// and the components themselves, except the taken ones, grouped by type in the order of `iter_comp_by_entity`.
+    pub(crate) fn remove_entity_collecting(
+        &mut self,
+        entity_key: EntityKey,
+        removed: Option<&mut Vec<CompKey>>,
+        mut removed_comps: Option<&mut Vec<(std::any::TypeId, Box<dyn std::any::Any>)>>,
+    ) -> Result<(), Error> {
+        self.check_entity(entity_key)?;
+        self.release_dropped_taken();
+
+        let inverse = self.capture_undo(|ecs| {
+            let slot = &ecs.entities[entity_key.index as usize];
+            let mut inverse = vec![WorldOp::InsertEntity {
+                entity_key,
+                partition: slot.partition,
+            }];
+            if slot.flags() != EntityFilter::LIVE {
+                inverse.push(WorldOp::SetEntityFlags {
+                    entity_key,
+                    flags: slot.flags(),
+                });
+            }
+            // restore the components of each type in their order, so that each is appended in place
+            let mut type_keys = ecs
+                .ref_0_cols
+                .get(&entity_key)
+                .into_iter()
+                .flatten()
+                .map(|(_, (type_key, _))| *type_key)
+                .collect::<Vec<_>>();
+            type_keys.sort_unstable();
+            type_keys.dedup();
+            let mut comp_keys = type_keys
+                .into_iter()
+                .flat_map(|type_key| {
+                    let ref_1_col = &ecs.ref_1_cols[&(entity_key, type_key)];
+                    ref_1_col
+                        .iter()
+                        .enumerate()
+                        .map(move |(index, row_key)| (index, ecs.comp_key(type_key, *row_key)))
+                })
+                .collect::<Vec<_>>();
+            comp_keys.sort_unstable();
+
+            for (index, comp_key) in comp_keys {
+                if ecs.get_entity_by_comp(comp_key).is_ok() {
+                    inverse.push(WorldOp::InsertComp {
+                        entity_key,
+                        comp_key,
+                        comp: ecs.recorded_comp(comp_key),
+                    });
+                    inverse.push(WorldOp::ReorderComp { comp_key, index });
+                }
+            }
+            inverse
+        });
+
+        self.entities.remove(entity_key.index as usize);
+        let generation = &mut self.generations[entity_key.index as usize];
+        *generation = generation.wrapping_add(1);
+
+        if let Some(ref_0_col) = self.ref_0_cols.remove(&entity_key) {
+            if !ref_0_col.is_empty() {
+                self.note_comps_cleared(vec![entity_key]);
+            }
+
+            // group the rows by type so that each column and each reference slab is fetched once
+            let mut refs = std::mem::take(&mut self.scratch_refs);
+            refs.clear();
+            refs.extend(ref_0_col.into_iter().map(|(_, r)| r));
+            if let Some(removed) = removed {
+                removed.extend(
+                    refs.iter()
+                        .map(|(type_key, row_key)| self.comp_key(*type_key, *row_key)),
+                );
+            }
+            refs.sort_unstable_by_key(|(type_key, _)| *type_key);
+
+            for refs in refs.chunk_by(|(type_key0, _), (type_key1, _)| type_key0 == type_key1) {
+                let (type_key, _) = refs[0];
+
+                let ref_1_col = self.ref_1_cols.remove(&(entity_key, type_key)).unwrap();
+                debug_assert_eq!(ref_1_col.len(), refs.len());
+
+                let comp_col = self.comp_cols.get_mut(&type_key).unwrap();
+                comp_col.count_access(true);
+                for row_key in &ref_1_col {
+                    let comp_key = CompKey {
+                        type_key,
+                        row_key: *row_key,
+                        generation: self.comp_generations.get(type_key, *row_key),
+                        world: self.world,
+                    };
+                    comp_col.note_removed(comp_key);
+
+                    let found = match &mut removed_comps {
+                        Some(removed_comps) => (comp_col.remove_boxed_fn)(comp_col, *row_key)
+                            .map(|comp| removed_comps.push((type_key, comp)))
+                            .is_some(),
+                        None => (comp_col.remove_row_fn)(comp_col, *row_key).is_some(),
+                    };
+
+                    // a taken component is removed for good, and its handle can no longer be put back
+                    if !found {
+                        comp_col.note_removed_taken(entity_key, comp_key);
+                        (comp_col.release_row_fn)(comp_col, *row_key).unwrap();
+                        comp_col.remaps.remove(row_key);
+                        let taken_row = self.taken.remove(&(type_key, *row_key)).unwrap();
+                        stable::forget(&mut comp_col.stable_ids, taken_row.stable_id);
+                    }
+                }
+            }
+
+            for (type_key, row_key) in &refs {
+                self.comp_generations.bump(*type_key, *row_key);
+            }
+
+            refs.clear();
+            self.scratch_refs = refs;
+        }
+
+        self.record(inverse, |_| WorldOp::RemoveEntity { entity_key });
+
+        Ok(())
+    }
+
+    /// Return entity with the corresponding entity key.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.get_entity(entity_key).unwrap();
+    /// ```
+    pub fn get_entity(&self, entity_key: EntityKey) -> Result<(), Error> {
+        self.check_entity(entity_key)
+    }
+
+    /// Return whether the entity corresponding to the entity key is found, as in `get_entity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// assert!(ecs.contains_entity(entity_key));
+    ///
+    /// ecs.remove_entity(entity_key).unwrap();
+    /// assert!(!ecs.contains_entity(entity_key));
+    /// ```
+    pub fn contains_entity(&self, entity_key: EntityKey) -> bool {
+        self.check_entity(entity_key).is_ok()
+    }
+
+    /// Return the status of the entity key,
+    /// telling whether the entity is alive, was removed, or never existed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    ///
+    /// assert_eq!(ecs.entity_status(entity_key), ecs_tiny::EntityStatus::Alive);
+    ///
+    /// ecs.remove_entity(entity_key).unwrap();
+    ///
+    /// assert_eq!(
+    ///     ecs.entity_status(entity_key),
+    ///     ecs_tiny::EntityStatus::Removed { current_generation: 1 }
+    /// );
+    /// ```
+    pub fn entity_status(&self, entity_key: EntityKey) -> EntityStatus {
+        if entity_key.world != self.world {
+            return EntityStatus::NeverExisted;
+        }
+
+        let Some(&generation) = self.generations.get(entity_key.index as usize) else {
+            return EntityStatus::NeverExisted;
+        };
+
+        let slot = self
+            .entities
+            .get(entity_key.index as usize)
+            .filter(|_| entity_key.generation == generation);
+
+        if let Some(slot) = slot {
+            if slot.is_reserved() {
+                EntityStatus::Reserved
+            } else {
+                EntityStatus::Alive
+            }
+        } else if entity_key.generation < generation {
+            EntityStatus::Removed {
+                current_generation: generation,
+            }
+        } else {
+            EntityStatus::NeverExisted
+        }
+    }
+
+    fn check_entity(&self, entity_key: EntityKey) -> Result<(), Error> {
+        if entity_key.world != self.world {
+            return Err(Error::WrongWorld);
+        }
+
+        match self.entity_status(entity_key) {
+            EntityStatus::Alive => Ok(()),
+            status => Err(Error::EntityNotFound(status)),
+        }
+    }
+
+    pub(crate) fn comp_key(&self, type_key: std::any::TypeId, row_key: u32) -> CompKey {
+        CompKey {
+            type_key,
+            row_key,
+            generation: self.comp_generations.get(type_key, row_key),
+            world: self.world,
+        }
+    }
+
+    /// Check the world and the generation of the component key, failing with an `Error::CompNotFound`
+    /// if the component of the key was removed.
+    fn check_comp_key(&self, comp_key: CompKey) -> Result<(std::any::TypeId, u32), Error> {
+        if comp_key.world != self.world {
+            return Err(Error::WrongWorld);
+        }
+
+        if comp_key.generation
+            != self
+                .comp_generations
+                .get(comp_key.type_key, comp_key.row_key)
+        {
+            return Err(Error::CompNotFound);
+        }
+
+        Ok((comp_key.type_key, comp_key.row_key))
+    }
+
+    /// Return an iterator over the entity keys of all live entities, neither disabled nor pending despawn,
+    /// as in `iter_entity_filtered` with `EntityFilter::LIVE`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// let entity_key2 = ecs.insert_entity();
+    /// let mut iter = ecs.iter_entity();
+    ///
+    /// assert_eq!(iter.next(), Some(entity_key0));
+    /// assert_eq!(iter.next(), Some(entity_key1));
+    /// assert_eq!(iter.next(), Some(entity_key2));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_entity(&self) -> impl Iterator<Item = EntityKey> + '_ {
+        self.iter_entity_filtered(EntityFilter::LIVE)
+    }
+
+    /// Return the number of alive entities, including the disabled and pending despawn ones,
+    /// as in `iter_entity_filtered` with `EntityFilter::ALIVE`, without iterating them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.insert_entity();
+    /// ecs.reserve_entity();
+    /// assert_eq!(ecs.entity_count(), 2);
+    ///
+    /// ecs.remove_entity(entity_key).unwrap();
+    /// assert_eq!(ecs.entity_count(), 1);
+    /// ```
+    pub fn entity_count(&self) -> usize {
+        self.entities.len() - self.reserved_count
+    }
+
+    /// Create a new partition of entities with the name, and return the corresponding partition id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let partition = ecs.create_partition("match_42");
+    ///
+    /// assert_eq!(ecs.partition_name(partition), Ok("match_42"));
+    /// ```
+    pub fn create_partition(&mut self, name: impl Into<String>) -> PartitionId {
+        let name = name.into();
+        self.partitions.push(name.clone());
+
+        let partition = PartitionId(self.partitions.len() as u32);
+        self.record(None, |_| WorldOp::CreatePartition { partition, name });
+
+        partition
+    }
+
+    /// Return the name of the partition.
+    /// If the partition is not found, return an `Err(Error::PartitionNotFound)`.
+    /// Otherwise, return an `Ok(&str)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let ecs = ecs_tiny::ECS::new();
+    ///
+    /// assert_eq!(ecs.partition_name(ecs_tiny::PartitionId::DEFAULT), Ok("default"));
+    /// ```
+    pub fn partition_name(&self, partition: PartitionId) -> Result<&str, Error> {
+        match partition.0.checked_sub(1) {
+            None => Ok("default"),
+            Some(index) => self
+                .partitions
+                .get(index as usize)
+                .map(|name| name.as_str())
+                .ok_or(Error::PartitionNotFound),
+        }
+    }
+
+    /// Return the partition of the entity with the corresponding entity key.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(PartitionId)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    ///
+    /// assert_eq!(ecs.entity_partition(entity_key), Ok(ecs_tiny::PartitionId::DEFAULT));
+    /// ```
+    pub fn entity_partition(&self, entity_key: EntityKey) -> Result<PartitionId, Error> {
+        self.check_entity(entity_key)?;
+        Ok(self.entities[entity_key.index as usize].partition)
+    }
+
+    /// Return an iterator over the entity keys of all live entities in the partition, as in `iter_entity`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let partition = ecs.create_partition("match_42");
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity_in(partition).unwrap();
+    /// let mut iter = ecs.iter_entity_in(partition);
+    ///
+    /// assert_eq!(iter.next(), Some(entity_key1));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_entity_in(&self, partition: PartitionId) -> impl Iterator<Item = EntityKey> + '_ {
+        self.iter_entity_slots()
+            .filter(move |(_, slot)| {
+                slot.partition == partition && slot.flags() == EntityFilter::LIVE
+            })
+            .map(|(entity_key, _)| entity_key)
+    }
+
+    /// Remove all alive entities in the partition together with their components, including the disabled ones.
+    /// The partition itself is kept, and entities in other partitions are left untouched.
+    /// If the partition is not found, return an `Err(Error::PartitionNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let partition = ecs.create_partition("match_42");
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity_in(partition).unwrap();
+    /// ecs.clear_partition(partition).unwrap();
+    ///
+    /// assert!(ecs.get_entity(entity_key0).is_ok());
+    /// assert!(ecs.get_entity(entity_key1).is_err());
+    /// ```
+    pub fn clear_partition(&mut self, partition: PartitionId) -> Result<(), Error> {
+        self.partition_name(partition)?;
+
+        let entity_keys = self
+            .iter_entity_slots()
+            .filter(|(_, slot)| slot.partition == partition && !slot.is_reserved())
+            .map(|(entity_key, _)| entity_key)
+            .collect::<Vec<_>>();
+        for entity_key in entity_keys {
+            self.remove_entity(entity_key).unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Register component type.
+    /// If the component type is already registered, return an `Err(Error::TypeAlreadyRegistered)`.
+    /// If another component type is registered under the same type name, return an `Err(Error::DuplicateTypeName(..))`,
+    /// unless duplicate names are allowed, as in `allow_duplicate_names`.
+    /// Otherwise, return an `Ok(Registered<T>)`, the token of the registration, as in `Registered`.
+    /// The other registration methods fail in the same way, and return an `Ok(())`.
+    /// The component type must be `Send` and `Sync`, as the world is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ```
+    pub fn register<T>(&mut self) -> Result<Registered<T>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.insert_comp_col::<T>()?;
+        self.record_register::<T>(Self::register_op::<T>);
+
+        Ok(self.registered::<T>().unwrap())
+    }
+
+    /// Register component type under the type name given instead of its own, as in `register`.
+    /// The name of `std::any::type_name` and the `TypeId` may change between builds,
+    /// so a name chosen by the caller keys save files and tooling on the type across them,
+    /// as in `type_name_of` and `type_id_of_name`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// struct Position(i32, i32);
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register_named::<Position>("my_game::Position").unwrap();
+    ///
+    /// let type_key = std::any::TypeId::of::<Position>();
+    /// assert_eq!(ecs.type_name_of(type_key), Ok("my_game::Position"));
+    /// assert_eq!(ecs.type_id_of_name("my_game::Position"), Ok(type_key));
+    /// ```
+    pub fn register_named<T>(&mut self, type_name: &'static str) -> Result<Registered<T>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.insert_comp_col_named::<T>(type_name)?;
+        self.record_register::<T>(Self::register_op::<T>);
+
+        Ok(self.registered::<T>().unwrap())
+    }
+
+    /// Return the type name of the registered component type, as given by `register_named`,
+    /// or the name of `std::any::type_name` otherwise.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(&str)`.
+    pub fn type_name_of(&self, type_key: std::any::TypeId) -> Result<&'static str, Error> {
+        Ok(self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?
+            .type_name)
+    }
+
+    /// Return the type key of the registered component type with the type name, as in `type_name_of`.
+    /// If several types share the name, as allowed by `allow_duplicate_names`, the first registered is returned.
+    /// If no component type is registered under the name, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(TypeId)`.
+    pub fn type_id_of_name(&self, type_name: &str) -> Result<std::any::TypeId, Error> {
+        self.comp_cols
+            .iter()
+            .filter(|(_, comp_col)| comp_col.type_name == type_name)
+            .min_by_key(|(_, comp_col)| comp_col.registration)
+            .map(|(type_key, _)| *type_key)
+            .ok_or(Error::TypeNotRegistered)
+    }
+
+    /// Return an iterator over the registered component types in the order of registration,
+    /// each with its type name, as in `type_name_of`, and its number of components, as in `count_comp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_named::<i32>("hp").unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let types = ecs.iter_registered_types().collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     types,
+    ///     vec![
+    ///         (std::any::TypeId::of::<i32>(), "hp", 1),
+    ///         (std::any::TypeId::of::<u32>(), "u32", 0),
+    ///     ]
+    /// );
+    /// ```
+    pub fn iter_registered_types(
+        &self,
+    ) -> impl Iterator<Item = (std::any::TypeId, &'static str, usize)> + '_ {
+        let mut comp_cols = self.comp_cols.iter().collect::<Vec<_>>();
+        comp_cols.sort_by_key(|(_, comp_col)| comp_col.registration);
+
+        comp_cols.into_iter().map(|(type_key, comp_col)| {
+            (*type_key, comp_col.type_name, (comp_col.len_fn)(comp_col))
+        })
+    }
+
+    /// Allow or forbid registering different component types under the same type name.
+    /// Forbidden by default, since two types sharing a name are almost always two copies of one type
+    /// compiled into separate crates or versions, whose components would silently end up in separate columns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.allow_duplicate_names(true);
+    /// ```
+    pub fn allow_duplicate_names(&mut self, allow: bool) {
+        self.allow_duplicate_names = allow;
+    }
+
+    fn insert_comp_col<T>(&mut self) -> Result<&mut CompColumn<S>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.insert_comp_col_named::<T>(std::any::type_name::<T>())
+    }
+
+    pub(crate) fn insert_comp_col_named<T>(
+        &mut self,
+        type_name: &'static str,
+    ) -> Result<&mut CompColumn<S>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        if self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeAlreadyRegistered);
+        }
+
+        if !self.allow_duplicate_names
+            && self
+                .comp_cols
+                .values()
+                .any(|comp_col| comp_col.type_name == type_name)
+        {
+            return Err(Error::DuplicateTypeName(type_name));
+        }
+
+        let comp_col = CompColumn {
+            comp_rows: stack_any::StackAny::try_new(Rows::<CompRow<T>>::new()).unwrap(),
+            type_name,
+            len_fn: |comp_col| {
+                comp_col
+                    .comp_rows
+                    .downcast_ref::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .len()
+            },
+            get_row_fn: |comp_col, row_key| {
+                let comp_row = comp_col
+                    .comp_rows
+                    .downcast_ref::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .get(row_key as usize)?;
+                Some(CompRow {
+                    comp: (),
+                    entity_key: comp_row.entity_key,
+                    ref_0_row_key: comp_row.ref_0_row_key,
+                    ref_1_row_key: comp_row.ref_1_row_key,
+                    stable_id: comp_row.stable_id,
+                    ticks: comp_row.ticks,
+                })
+            },
+            iter_owners_fn: |comp_col| {
+                let comp_rows = comp_col
+                    .comp_rows
+                    .downcast_ref::<Rows<CompRow<T>>>()
+                    .unwrap();
+                let iter = comp_rows
+                    .iter()
+                    .map(|(row_key, comp_row)| (row_key as u32, comp_row.entity_key));
+                Box::new(iter)
+            },
+            remove_row_fn: |comp_col, row_key| {
+                let comp_row = comp_col
+                    .comp_rows
+                    .downcast_mut::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .try_remove(row_key as usize)?;
+                comp_col.remaps.remove(&row_key);
+                stable::forget(&mut comp_col.stable_ids, comp_row.stable_id);
+                Some(CompRow {
+                    comp: (),
+                    entity_key: comp_row.entity_key,
+                    ref_0_row_key: comp_row.ref_0_row_key,
+                    ref_1_row_key: comp_row.ref_1_row_key,
+                    stable_id: comp_row.stable_id,
+                    ticks: comp_row.ticks,
+                })
+            },
+            remove_boxed_fn: |comp_col, row_key| {
+                let comp_row = comp_col
+                    .comp_rows
+                    .downcast_mut::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .try_remove(row_key as usize)?;
+                comp_col.remaps.remove(&row_key);
+                stable::forget(&mut comp_col.stable_ids, comp_row.stable_id);
+                Some(Box::new(comp_row.comp))
+            },
+            insert_boxed_fn: |ecs, entity_key, comp| {
+                ecs.insert_comp::<T>(entity_key, *comp.downcast::<T>().unwrap())
+            },
+            set_owner_fn: |comp_col, row_key, entity_key, ref_0_row_key, ref_1_row_key| {
+                let comp_row = comp_col
+                    .comp_rows
+                    .downcast_mut::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .get_mut(row_key as usize)?;
+                comp_row.entity_key = entity_key;
+                comp_row.ref_0_row_key = ref_0_row_key;
+                comp_row.ref_1_row_key = ref_1_row_key;
+                Some(())
+            },
+            release_row_fn: |comp_col, row_key| {
+                comp_col
+                    .comp_rows
+                    .downcast_mut::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .release(row_key as usize)
+            },
+            shrink_fn: |comp_col| {
+                comp_col
+                    .comp_rows
+                    .downcast_mut::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .shrink_to_fit()
+            },
+            get_comp_fn: |comp_col, row_key| {
+                let comp_row = comp_col
+                    .comp_rows
+                    .downcast_ref::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .get(row_key as usize)?;
+                Some(&comp_row.comp)
+            },
+            get_comp_mut_fn: |comp_col, row_key, tick| {
+                let comp_row = comp_col
+                    .comp_rows
+                    .downcast_mut::<Rows<CompRow<T>>>()
+                    .unwrap()
+                    .get_mut(row_key as usize)?;
+                Some(comp_row.comp_mut(tick))
+            },
+            clone_rows_fn: |comp_col, keep| {
+                let comp_rows = comp_col
+                    .comp_rows
+                    .downcast_ref::<Rows<CompRow<T>>>()
+                    .unwrap();
+
+                if comp_rows
+                    .iter()
+                    .any(|(_, comp_row)| keep(comp_row.entity_key))
+                {
+                    return None;
+                }
+
+                Some(stack_any::StackAny::try_new(comp_rows.empty_like()).unwrap())
+            },
+            record_comp_fn: None,
+            get_field_fn: None,
+            set_field_fn: None,
+            eq_fn: None,
+            removal_log: None,
+            observers: Default::default(),
+            insert_validator: None,
+            insert_hook: None,
+            comp_limit: None,
+            comp_limit_total: None,
+            immutable: false,
+            ordered: false,
+            world_unique: false,
+            unique_row: None,
+            stable_ids: None,
+            #[cfg(feature = "profile")]
+            access: Default::default(),
+            #[cfg(feature = "serde")]
+            serde_name: None,
+            remaps: hash::HashMap::with_hasher(self.hasher().clone()),
+            registration: self.registrations,
+        };
+        self.registrations += 1;
+
+        Ok(self.comp_cols.entry(type_key).or_insert(comp_col))
+    }
+
+    /// Register the component type as in `register`, dropping the token, as recorded by the journal.
+    pub(crate) fn register_op<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.register::<T>().map(|_| ())
+    }
+
+    pub(crate) fn record_register<T>(&mut self, register_fn: fn(&mut ECS<S>) -> Result<(), Error>)
+    where
+        T: std::any::Any,
+    {
+        self.record(None, |_| WorldOp::Register {
+            type_name: std::any::type_name::<T>(),
+            register_fn,
+        });
+    }
+
+    /// Unregister component type.
+    /// The components of the type are dropped together with every reference to them,
+    /// so that their entities can still be removed or given components afterwards.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.unregister::<i32>().unwrap();
+    ///
+    /// ecs.remove_entity(entity_key).unwrap();
+    /// ```
+    pub fn unregister<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
+        }
+
+        self.notify_column_removed(type_key);
+        self.bump_column_generations(type_key);
+        self.comp_cols.remove(&type_key);
+        self.taken
+            .retain(|(taken_type_key, _), _| *taken_type_key != type_key);
+
+        // the components are dropped with the column, so no reference to them may be left behind
+        self.ref_1_cols
+            .retain(|(_, ref_type_key), _| *ref_type_key != type_key);
+        let mut cleared = vec![];
+        for (entity_key, ref_0_col) in self.ref_0_cols.iter_mut() {
+            let len = ref_0_col.len();
+            ref_0_col.retain(|_, (ref_type_key, _)| *ref_type_key != type_key);
+            if len > 0 && ref_0_col.is_empty() {
+                cleared.push(*entity_key);
+            }
+        }
+        self.ref_0_cols.retain(|_, ref_0_col| !ref_0_col.is_empty());
+        self.note_comps_cleared(cleared);
+
+        self.discard_undo();
+        self.record(None, |_| WorldOp::Unregister {
+            type_name: std::any::type_name::<T>(),
+            unregister_fn: Self::unregister::<T>,
+        });
+
+        Ok(())
+    }
+
+    /// Register immutable component type.
+    /// Components of the type can be inserted and removed, but every mutable access fails with an `Error::ImmutableType`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_immutable::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp_mut::<i32>(comp_key), Err(ecs_tiny::Error::ImmutableType));
+    /// ```
+    pub fn register_immutable<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.insert_comp_col::<T>()?.immutable = true;
+        self.record_register::<T>(Self::register_immutable::<T>);
+
+        Ok(())
+    }
+
+    /// Register ordered component type.
+    /// The components of the type of each entity can be placed explicitly, as in `insert_comp_at` and `reorder_comp`,
+    /// and per-entity iteration yields them in that order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_ordered::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    /// ecs.reorder_comp::<i32>(entity_key, comp_key, 1).unwrap();
+    /// let comps = ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().collect::<Vec<_>>();
+    ///
+    /// assert_eq!(comps, vec![&63, &42]);
+    /// ```
+    pub fn register_ordered<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.insert_comp_col::<T>()?.ordered = true;
+        self.record_register::<T>(Self::register_ordered::<T>);
+
+        Ok(())
+    }
+
+    /// Register world unique component type, of which at most one component exists in the whole world.
+    /// Inserting a second component of the type into any entity fails with an `Error::AlreadyExists`,
+    /// and the component is looked up without iteration, as in `world_unique`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register_world_unique::<u64>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42u64).unwrap();
+    ///
+    /// assert_eq!(
+    ///     ecs.insert_comp(entity_key1, 63u64),
+    ///     Err(ecs_tiny::Error::AlreadyExists { holder: entity_key0 })
+    /// );
+    /// ```
+    pub fn register_world_unique<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        self.insert_comp_col::<T>()?.world_unique = true;
+        self.record_register::<T>(Self::register_world_unique::<T>);
+
+        Ok(())
+    }
+
+    /// Return the only component of the world unique component type, together with the holder and the component key.
+    /// If the component type is not registered as world unique, or no component of it exists or it is taken,
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is not registered as world unique, return an `Err(Error::TypeNotWorldUnique)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// If no component of it exists, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok((EntityKey, CompKey, &T))`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_world_unique::<u64>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42u64).unwrap();
+    ///
+    /// assert_eq!(ecs.world_unique::<u64>(), Ok((entity_key, comp_key, &42)));
+    /// ```
+    pub fn world_unique<T>(&self) -> Result<(EntityKey, CompKey, &T), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        if !comp_col.world_unique {
+            return Err(Error::TypeNotWorldUnique);
+        }
+        let row_key = comp_col.unique_row.ok_or(Error::CompNotFound)?;
+        let comp_row = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .get(row_key as usize)
+            .ok_or_else(|| take::missing_comp(&self.taken, type_key, row_key))?;
+
+        Ok((
+            comp_row.entity_key,
+            self.comp_key(type_key, row_key),
+            &comp_row.comp,
+        ))
+    }
+
+    /// Register chunked component type, whose rows are allocated in chunks of the number of rows given.
+    /// The column never moves its rows when it grows, which avoids copying the whole column for large component types,
+    /// at the cost of an indirection on every access.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chunk size is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_chunked::<[u8; 2048]>(64).unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, [0u8; 2048]).unwrap();
+    /// let comp = ecs.get_comp::<[u8; 2048]>(comp_key).unwrap() as *const _;
+    ///
+    /// for _ in 0..1000 {
+    ///     ecs.insert_comp(entity_key, [0u8; 2048]).unwrap();
+    /// }
+    ///
+    /// assert_eq!(ecs.get_comp::<[u8; 2048]>(comp_key).unwrap() as *const _, comp);
+    /// ```
+    pub fn register_chunked<T>(&mut self, chunk_size: usize) -> Result<(), Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let comp_rows = Rows::<CompRow<T>>::chunked(chunk_size);
+        self.insert_comp_col::<T>()?.comp_rows = stack_any::StackAny::try_new(comp_rows).unwrap();
+        // the storage is not observable through the keys, so a replay registers the default storage
+        self.record_register::<T>(Self::register_op::<T>);
+
+        Ok(())
+    }
+
+    /// Register cloneable component type.
+    /// Components of the type can be cloned along with the entity, as in `clone_filtered`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let clone = ecs.clone_filtered(|_| true).unwrap();
+    ///
+    /// assert_eq!(clone.get_comp::<i32>(comp_key), Ok(&42));
+    /// ```
+    pub fn register_cloneable<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any + Clone + Send + Sync,
+    {
+        self.insert_cloneable_comp_col::<T>()?;
+        self.record_register::<T>(Self::register_cloneable::<T>);
+
+        Ok(())
+    }
+
+    fn insert_cloneable_comp_col<T>(&mut self) -> Result<&mut CompColumn<S>, Error>
+    where
+        T: std::any::Any + Clone + Send + Sync,
+    {
+        let comp_col = self.insert_comp_col::<T>()?;
+        comp_col.record_comp_fn =
+            Some(|comp| CompValue::new(comp.downcast_ref::<T>().unwrap().clone()));
+        comp_col.clone_rows_fn = |comp_col, keep| {
+            let comp_rows = comp_col
+                .comp_rows
+                .downcast_ref::<Rows<CompRow<T>>>()
+                .unwrap();
+            let rows = comp_rows
+                .iter()
+                .filter(|(_, comp_row)| keep(comp_row.entity_key))
+                .map(|(row_key, comp_row)| {
+                    let comp_row = CompRow {
+                        comp: comp_row.comp.clone(),
+                        entity_key: comp_row.entity_key,
+                        ref_0_row_key: comp_row.ref_0_row_key,
+                        ref_1_row_key: comp_row.ref_1_row_key,
+                        stable_id: comp_row.stable_id,
+                        ticks: comp_row.ticks,
+                    };
+                    (row_key, comp_row)
+                });
+            let comp_rows = comp_rows.collect_like(rows);
+
+            Some(stack_any::StackAny::try_new(comp_rows).unwrap())
+        };
+
+        Ok(comp_col)
+    }
+
+    /// Register shared component type, which stores `SharedComp<T>` handles interned in a per-type pool.
+    /// Equal values are deduplicated by the `Hash` and `Eq` implementations of the type.
+    /// If the shared component type is already registered, return an `Err(Error::TypeAlreadyRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_shared::<String>().unwrap();
+    /// let handle = ecs.intern_shared("material".to_string()).unwrap();
+    /// let comp_key = ecs.insert_shared_comp(entity_key, handle).unwrap();
+    /// ```
+    pub fn register_shared<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any + std::hash::Hash + Eq + Send + Sync,
+    {
+        self.insert_cloneable_comp_col::<SharedComp<T>>()?;
+
+        let type_key = std::any::TypeId::of::<T>();
+        let pool = std::sync::Arc::new(std::sync::Mutex::new(shared::SharedPool::<T>::new()));
+        self.shared_pools
+            .insert(type_key, std::sync::Arc::new(pool));
+
+        self.record_register::<SharedComp<T>>(Self::register_shared::<T>);
+
+        Ok(())
+    }
+
+    /// Intern a value in the pool of the shared component type and return the corresponding handle.
+    /// If an equal value is already interned, the handle points at the existing value.
+    /// If the shared component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(SharedComp<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register_shared::<String>().unwrap();
+    /// let handle0 = ecs.intern_shared("material".to_string()).unwrap();
+    /// let handle1 = ecs.intern_shared("material".to_string()).unwrap();
+    ///
+    /// assert_eq!(ecs.shared_pool_len::<String>(), Ok(1));
+    /// ```
+    pub fn intern_shared<T>(&mut self, value: T) -> Result<SharedComp<T>, Error>
+    where
+        T: std::any::Any + std::hash::Hash + Eq,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let pool = self
+            .shared_pools
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?
+            .downcast_ref::<std::sync::Arc<std::sync::Mutex<shared::SharedPool<T>>>>()
+            .unwrap();
+
+        Ok(shared::SharedPool::intern(pool, value))
+    }
+
+    /// Return the number of distinct values alive in the pool of the shared component type.
+    /// If the shared component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_shared::<String>().unwrap();
+    /// let handle = ecs.intern_shared("material".to_string()).unwrap();
+    /// let comp_key = ecs.insert_shared_comp(entity_key, handle).unwrap();
+    ///
+    /// assert_eq!(ecs.shared_pool_len::<String>(), Ok(1));
+    ///
+    /// ecs.remove_entity(entity_key).unwrap();
+    ///
+    /// assert_eq!(ecs.shared_pool_len::<String>(), Ok(0));
+    /// ```
+    pub fn shared_pool_len<T>(&self) -> Result<usize, Error>
+    where
+        T: std::any::Any + std::hash::Hash + Eq,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let pool = self
+            .shared_pools
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?
+            .downcast_ref::<std::sync::Arc<std::sync::Mutex<shared::SharedPool<T>>>>()
+            .unwrap();
+
+        let len = pool.lock().unwrap().len();
+        Ok(len)
+    }
+
+    /// Set the insert validator of the component type.
+    /// The validator receives a read view of the world, the entity key and the component to be inserted,
+    /// and the insertion fails with an `Error::ValidationFailed` when the validator returns an `Err(String)`.
+    /// Setting a new validator replaces the previous one.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.set_insert_validator::<i32>(|_, _, comp| {
+    ///     if *comp > 0 {
+    ///         Ok(())
+    ///     } else {
+    ///         Err("must be positive".to_string())
+    ///     }
+    /// }).unwrap();
+    ///
+    /// assert!(ecs.insert_comp(entity_key, 42).is_ok());
+    /// assert!(ecs.insert_comp(entity_key, -1).is_err());
+    /// ```
+    pub fn set_insert_validator<T>(
+        &mut self,
+        f: impl Fn(&ECS<S>, EntityKey, &T) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.insert_validator = Some(std::sync::Arc::new(move |ecs, entity_key, comp| {
+            f(ecs, entity_key, comp.downcast_ref::<T>().unwrap())
+        }));
+
+        Ok(())
+    }
+
+    /// Make the fields of the component type readable and writable by name, as in `get_comp_field` and `set_comp_field`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// struct Health {
+    ///     current: i32,
+    /// }
+    ///
+    /// ecs_tiny::impl_reflect!(Health { current });
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<Health>().unwrap();
+    /// ecs.set_reflect::<Health>().unwrap();
+    /// ```
+    pub fn set_reflect<T>(&mut self) -> Result<(), Error>
+    where
+        T: Reflect,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.get_field_fn =
+            Some(|comp, field| comp.downcast_ref::<T>().unwrap().get_field(field));
+        comp_col.set_field_fn =
+            Some(|comp, field, value| comp.downcast_mut::<T>().unwrap().set_field(field, value));
+
+        Ok(())
+    }
+
+    /// Set the maximum number of components of the component type per entity.
+    /// Further insertions beyond the limit fail with an `Error::QuotaExceeded`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.set_comp_limit::<i32>(1).unwrap();
+    ///
+    /// assert!(ecs.insert_comp(entity_key, 42).is_ok());
+    /// assert!(ecs.insert_comp(entity_key, 63).is_err());
+    /// ```
+    pub fn set_comp_limit<T>(&mut self, max_per_entity: u32) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.comp_limit = Some(max_per_entity);
+
+        Ok(())
+    }
+
+    /// Set the maximum number of components of the component type in the whole world.
+    /// Further insertions beyond the limit fail with an `Error::QuotaExceeded`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.set_comp_limit_total::<i32>(1).unwrap();
+    ///
+    /// assert!(ecs.insert_comp(entity_key0, 42).is_ok());
+    /// assert!(ecs.insert_comp(entity_key1, 63).is_err());
+    /// ```
+    pub fn set_comp_limit_total<T>(&mut self, max: u32) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.comp_limit_total = Some(max);
+
+        Ok(())
+    }
+
+    /// Insert a new component with the corresponding entity key and return the corresponding component key.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component limit of the component type is reached, return an `Err(Error::QuotaExceeded)`.
+    /// If the insert validator of the component type rejects the component, return an `Err(Error::ValidationFailed)`.
+    /// Otherwise, return an `Ok(CompKey)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ```
+    pub fn insert_comp<T>(&mut self, entity_key: EntityKey, comp: T) -> Result<CompKey, Error>
+    where
+        T: std::any::Any,
+    {
+        self.release_dropped_taken();
+        self.check_insert_comp(entity_key, &comp)?;
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        let row_key = self
+            .comp_cols
+            .get(&type_key)
+            .unwrap()
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .vacant_key();
+        let row_key = self.checked_key(row_key);
+
+        let comp_key = self
+            .insert_comp_row_at(entity_key, row_key, None, None, comp)
+            .unwrap();
+
+        self.run_insert_hook(type_key, entity_key, comp_key);
+
+        Ok(comp_key)
+    }
+
+    /// Insert a new component with the corresponding entity key at the index
+    /// among the components of the type of the entity, and return the corresponding component key.
+    /// An index past the end places the component last.
+    /// If the component type is not registered as ordered, return an `Err(Error::TypeNotOrdered)`.
+    /// Otherwise, this fails in the same cases as `insert_comp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_ordered::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp_at(entity_key, 0, 63).unwrap();
+    /// let comps = ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().collect::<Vec<_>>();
+    ///
+    /// assert_eq!(comps, vec![&63, &42]);
+    /// ```
+    pub fn insert_comp_at<T>(
+        &mut self,
+        entity_key: EntityKey,
+        index: usize,
+        comp: T,
+    ) -> Result<CompKey, Error>
+    where
+        T: std::any::Any,
+    {
+        self.release_dropped_taken();
+        self.check_insert_comp(entity_key, &comp)?;
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self.comp_cols.get(&type_key).unwrap();
+
+        if !comp_col.ordered {
+            return Err(Error::TypeNotOrdered);
+        }
+
+        let row_key = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .vacant_key();
+        let row_key = self.checked_key(row_key);
+
+        let comp_key = self
+            .insert_comp_row_at(entity_key, row_key, None, Some(index), comp)
+            .unwrap();
+
+        self.run_insert_hook(type_key, entity_key, comp_key);
+
+        Ok(comp_key)
+    }
+
+    /// Insert a new component with the corresponding entity key at the row key
+    /// handed out by a world sharing the world identifier, as in `new_mirror`, and return the corresponding component key.
+    /// Placing the keys in the order they were handed out keeps the insertion cheap,
+    /// while other rows rebuild the column.
+    /// If the row of the row key is occupied, return an `Err(Error::SlotOccupied)`.
+    /// Otherwise, this fails in the same cases as `insert_comp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut server = ecs_tiny::ECS::new();
+    /// let mut client = server.new_mirror();
+    /// server.register::<i32>().unwrap();
+    /// client.register::<i32>().unwrap();
+    ///
+    /// let entity_key = server.insert_entity();
+    /// let comp_key = server.insert_comp(entity_key, 42).unwrap();
+    /// client.insert_entity_at(entity_key).unwrap();
+    ///
+    /// assert_eq!(client.insert_comp_at_row(entity_key, comp_key.row_key(), 42), Ok(comp_key));
+    /// ```
+    pub fn insert_comp_at_row<T>(
+        &mut self,
+        entity_key: EntityKey,
+        row_key: u32,
+        comp: T,
+    ) -> Result<CompKey, Error>
+    where
+        T: std::any::Any,
+    {
+        self.release_dropped_taken();
+        self.check_insert_comp(entity_key, &comp)?;
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        let occupied = self
+            .comp_cols
+            .get(&type_key)
+            .unwrap()
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .contains(row_key as usize);
+
+        if occupied {
+            return Err(Error::SlotOccupied);
+        }
+
+        let comp_key = self
+            .insert_comp_row_at(entity_key, row_key, None, None, comp)
+            .unwrap();
+
+        self.run_insert_hook(type_key, entity_key, comp_key);
+
+        Ok(comp_key)
+    }
+
+    /// Return the first component with the corresponding entity key and type, as in `iter_comp_by_entity`,
+    /// or insert the component returned by the closure, as in `insert_comp`, if the entity holds none.
+    /// The closure is called only when the component is inserted.
+    /// If the entity corresponding to the entity key is not found, the component type is not registered or immutable,
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If the components of the type held by the entity are all taken, return an `Err(Error::TemporarilyTaken)`.
+    /// If the component is rejected, return the error of `insert_comp`.
+    /// Otherwise, return an `Ok(&mut T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    ///
+    /// *ecs.get_or_insert_comp_with(entity_key, || 42).unwrap() += 1;
+    /// *ecs.get_or_insert_comp_with(entity_key, || 63).unwrap() += 1;
+    ///
+    /// let comps = ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![&44]);
+    /// ```
+    pub fn get_or_insert_comp_with<T>(
+        &mut self,
+        entity_key: EntityKey,
+        f: impl FnOnce() -> T,
+    ) -> Result<&mut T, Error>
+    where
+        T: std::any::Any,
+    {
+        // checked first, so that an immutable type inserts nothing
+        if self
+            .comp_cols
+            .get(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?
+            .immutable
+        {
+            return Err(Error::ImmutableType);
+        }
+
+        let first = self
+            .iter_comp_with_key_by_entity::<T>(entity_key)?
+            .next()
+            .map(|(comp_key, _)| comp_key);
+
+        let comp_key = match first {
+            Some(comp_key) => comp_key,
+            None if self.has_comp::<T>(entity_key) => return Err(Error::TemporarilyTaken),
+            None => self.insert_comp(entity_key, f())?,
+        };
+
+        self.get_comp_mut::<T>(comp_key)
+    }
+
+    /// Return the first component with the corresponding entity key and type,
+    /// or insert the default component if the entity holds none, as in `get_or_insert_comp_with`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<Vec<i32>>().unwrap();
+    ///
+    /// ecs.get_or_insert_comp::<Vec<i32>>(entity_key).unwrap().push(42);
+    /// ecs.get_or_insert_comp::<Vec<i32>>(entity_key).unwrap().push(63);
+    ///
+    /// let comps = ecs.iter_comp_by_entity::<Vec<i32>>(entity_key).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![&vec![42, 63]]);
+    /// ```
+    pub fn get_or_insert_comp<T>(&mut self, entity_key: EntityKey) -> Result<&mut T, Error>
+    where
+        T: std::any::Any + Default,
+    {
+        self.get_or_insert_comp_with(entity_key, T::default)
+    }
+
+    /// Insert a new component at the row key, without the checks of the component type,
+    /// placing it at the index among the components of the type of the entity, or last if not given.
+    /// The row takes the generation if given, as for a component restored at its recorded key.
+    /// If the entity is not found, the component type is not registered or the row is occupied, return an `None`.
+    /// Otherwise, return an `Some(CompKey)`.
+    pub(crate) fn insert_comp_row_at<T>(
+        &mut self,
+        entity_key: EntityKey,
+        row_key: u32,
+        generation: Option<u32>,
+        index: Option<usize>,
+        comp: T,
+    ) -> Option<CompKey>
+    where
+        T: std::any::Any,
+    {
+        self.check_entity(entity_key).ok()?;
+
+        let ref_0_row_key = self
+            .ref_0_cols
+            .get(&entity_key)
+            .map_or(0, |ref_0_col| ref_0_col.vacant_key());
+        let ref_0_row_key = self.checked_key(ref_0_row_key);
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self.comp_cols.get_mut(&type_key)?;
+        comp_col.count_access(true);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        if comp_rows.contains(row_key as usize) {
+            return None;
+        }
+
+        if let Some(generation) = generation {
+            self.comp_generations.set(type_key, row_key, generation);
+        }
+
+        self.ref_0_cols
+            .entry(entity_key)
+            .or_default()
+            .insert((type_key, row_key));
+
+        let ref_1_col = self.ref_1_cols.entry((entity_key, type_key)).or_default();
+        let index = index.map(|index| index.min(ref_1_col.len()));
+        let ref_1_row_key = match index {
+            Some(index) => ref_1_col.insert(index, row_key),
+            None => ref_1_col.push(row_key),
+        };
+
+        let stable_id = comp_col
+            .stable_ids
+            .as_mut()
+            .map(|stable_ids| stable_ids.assign(row_key));
+
+        let comp_row = CompRow {
+            comp,
+            entity_key,
+            ref_0_row_key,
+            ref_1_row_key,
+            stable_id,
+            ticks: change::Ticks::new(self.tick),
+        };
+        comp_rows.insert_at(row_key as usize, comp_row);
+
+        if comp_col.world_unique {
+            comp_col.unique_row = Some(row_key);
+        }
+        self.note_comp_added(entity_key);
+
+        let comp_key = self.comp_key(type_key, row_key);
+        self.note_comp_inserted(entity_key, comp_key, index);
+
+        Some(comp_key)
+    }
+
+    /// Call the insert observers and record the insertion of the component, once it is in place.
+    pub(crate) fn note_comp_inserted(
+        &mut self,
+        entity_key: EntityKey,
+        comp_key: CompKey,
+        index: Option<usize>,
+    ) {
+        self.comp_cols[&comp_key.type_key].notify_inserted(entity_key, comp_key);
+
+        let inverse = self.capture_undo(|_| vec![WorldOp::RemoveComp { comp_key }]);
+        self.record_all(inverse, |ecs| {
+            let mut ops = vec![WorldOp::InsertComp {
+                entity_key,
+                comp_key,
+                comp: ecs.recorded_comp(comp_key),
+            }];
+            ops.extend(index.map(|index| WorldOp::ReorderComp { comp_key, index }));
+            ops
+        });
+    }
+
+    /// Move a component with the corresponding component key to another entity, without removing it,
+    /// so that the component key stays valid, and return the component key.
+    /// The component is placed last among the components of the type of the new owner.
+    /// The component is not inserted anew, so neither the insert validator, the insert hook nor the observers run.
+    /// If the component or the entity corresponding to the entity key is not found,
+    /// If the key of the component or the entity belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the component is not found, return an `Err(Error::CompNotFound)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the component limit of the type is reached for the entity, return an `Err(Error::QuotaExceeded)`.
+    /// Either way, nothing is changed.
+    /// Otherwise, return an `Ok(CompKey)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key0, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.move_comp(comp_key, entity_key1), Ok(comp_key));
+    /// assert_eq!(ecs.get_entity_by_comp(comp_key), Ok(entity_key1));
+    /// assert_eq!(ecs.iter_comp_by_entity::<i32>(entity_key0).unwrap().count(), 0);
+    /// ```
+    pub fn move_comp(
+        &mut self,
+        comp_key: CompKey,
+        entity_key: EntityKey,
+    ) -> Result<CompKey, Error> {
+        self.release_dropped_taken();
+
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+        self.check_entity(entity_key)?;
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        let comp_row = (comp_col.get_row_fn)(comp_col, row_key)
+            .ok_or_else(|| take::missing_comp(&self.taken, type_key, row_key))?;
+
+        if comp_row.entity_key == entity_key {
+            return Ok(comp_key);
+        }
+
+        if let Some(comp_limit) = comp_col.comp_limit {
+            let len = self
+                .ref_1_cols
+                .get(&(entity_key, type_key))
+                .map_or(0, |ref_1_col| ref_1_col.len());
+
+            if len >= comp_limit as usize {
+                return Err(Error::QuotaExceeded);
+            }
+        }
+
+        let ref_0_row_key = self
+            .ref_0_cols
+            .get(&entity_key)
+            .map_or(0, |ref_0_col| ref_0_col.vacant_key());
+        self.checked_key(ref_0_row_key);
+
+        let inverse = self.capture_undo(|ecs| {
+            let mut inverse = vec![WorldOp::RemoveComp { comp_key }];
+            inverse.extend(ecs.removed_comp_inverse(comp_key));
+            inverse
+        });
+
+        self.remove_ref_0(comp_row.entity_key, comp_row.ref_0_row_key);
+        self.remove_ref_1(comp_row.entity_key, type_key, comp_row.ref_1_row_key);
+        self.note_comp_removed(comp_row.entity_key);
+
+        let ref_0_row_key = self
+            .ref_0_cols
+            .entry(entity_key)
+            .or_default()
+            .insert((type_key, row_key)) as u32;
+        let ref_1_row_key = self
+            .ref_1_cols
+            .entry((entity_key, type_key))
+            .or_default()
+            .push(row_key);
+        self.note_comp_added(entity_key);
+
+        let comp_col = self.comp_cols.get_mut(&type_key).unwrap();
+        comp_col.count_access(true);
+        (comp_col.set_owner_fn)(comp_col, row_key, entity_key, ref_0_row_key, ref_1_row_key)
+            .unwrap();
+
+        // recorded as a removal and an insertion under the same key
+        self.record_all(inverse, |ecs| {
+            vec![
+                WorldOp::RemoveComp { comp_key },
+                WorldOp::InsertComp {
+                    entity_key,
+                    comp_key,
+                    comp: ecs.recorded_comp(comp_key),
+                },
+            ]
+        });
+
+        Ok(comp_key)
+    }
+
+    /// Move all components of an entity to another entity, as in `move_comp`, and return the number of components moved.
+    /// The components of each type keep their order, placed after those of the new owner.
+    /// Taken components, as in `take_comp`, are left in place.
+    /// If either entity is not found,
+    /// If either key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If either entity is not found, return an `Err(Error::EntityNotFound)`.
+    /// If a component limit would be exceeded for the new owner, return an `Err(Error::QuotaExceeded)`.
+    /// Either way, nothing is changed.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key0, 1u32).unwrap();
+    ///
+    /// assert_eq!(ecs.move_all_comps(entity_key0, entity_key1), Ok(2));
+    /// assert_eq!(ecs.iter_comp_by_entity::<i32>(entity_key1).unwrap().collect::<Vec<_>>(), vec![&42]);
+    /// assert_eq!(ecs.move_all_comps(entity_key1, entity_key1), Ok(0));
+    /// ```
+    pub fn move_all_comps(&mut self, from: EntityKey, to: EntityKey) -> Result<usize, Error> {
+        self.release_dropped_taken();
+
+        self.check_entity(from)?;
+        self.check_entity(to)?;
+
+        if from == to {
+            return Ok(0);
+        }
+
+        let mut type_keys = vec![];
+        for (_, (type_key, _)) in self.ref_0_cols.get(&from).into_iter().flatten() {
+            if !type_keys.contains(type_key) {
+                type_keys.push(*type_key);
+            }
+        }
+
+        // check every limit first, so that the components move all or none
+        let mut comp_keys = vec![];
+        for type_key in type_keys {
+            let row_keys = self.ref_1_cols[&(from, type_key)]
+                .iter()
+                .filter(|row_key| !self.is_taken(type_key, **row_key))
+                .copied()
+                .collect::<Vec<_>>();
+
+            if let Some(comp_limit) = self.comp_cols[&type_key].comp_limit {
+                let len = self
+                    .ref_1_cols
+                    .get(&(to, type_key))
+                    .map_or(0, |ref_1_col| ref_1_col.len());
+
+                if len + row_keys.len() > comp_limit as usize {
+                    return Err(Error::QuotaExceeded);
+                }
+            }
+
+            comp_keys.extend(
+                row_keys
+                    .into_iter()
+                    .map(|row_key| self.comp_key(type_key, row_key)),
+            );
+        }
+
+        for comp_key in &comp_keys {
+            self.move_comp(*comp_key, to).unwrap();
+        }
+
+        Ok(comp_keys.len())
+    }
+
+    /// Move a component with the corresponding entity key and type to the index
+    /// among the components of the type of the entity, shifting the others.
+    /// An index past the end places the component last.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the component key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is not registered as ordered, return an `Err(Error::TypeNotOrdered)`.
+    /// If the component is not found or is not owned by the entity, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_ordered::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 63).unwrap();
+    /// ecs.reorder_comp::<i32>(entity_key, comp_key, 0).unwrap();
+    /// let comps = ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().collect::<Vec<_>>();
+    ///
+    /// assert_eq!(comps, vec![&63, &42]);
+    /// ```
+    pub fn reorder_comp<T>(
+        &mut self,
+        entity_key: EntityKey,
+        comp_key: CompKey,
+        index: usize,
+    ) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_entity(entity_key)?;
+
+        let (type_key, _) = self.check_comp_key(comp_key)?;
+
+        if type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+
+        if !comp_col.ordered {
+            return Err(Error::TypeNotOrdered);
+        }
+
+        if self.get_entity_by_comp(comp_key)? != entity_key {
+            return Err(Error::CompNotFound);
+        }
+
+        self.move_comp_row(comp_key, index).unwrap();
+
+        Ok(())
+    }
+
+    /// Move a component to the index among the components of the type of its owner, without the checks of the component type.
+    /// If the component is not found, return an `None`.
+    /// Otherwise, return an `Some(())`.
+    pub(crate) fn move_comp_row(&mut self, comp_key: CompKey, index: usize) -> Option<()> {
+        let (type_key, row_key) = self.check_comp_key(comp_key).ok()?;
+        let entity_key = self.get_entity_by_comp(comp_key).ok()?;
+        let comp_row = self.get_row_or_taken(type_key, row_key).unwrap();
+
+        let ref_1_col = self.ref_1_cols.get_mut(&(entity_key, type_key)).unwrap();
+        let position = ref_1_col.position(comp_row.ref_1_row_key);
+        let index = index.min(ref_1_col.len() - 1);
+        ref_1_col.move_to(comp_row.ref_1_row_key, index);
+
+        let inverse = self.capture_undo(|_| {
+            vec![WorldOp::ReorderComp {
+                comp_key,
+                index: position,
+            }]
+        });
+        self.record(inverse, |_| WorldOp::ReorderComp { comp_key, index });
+
+        Some(())
+    }
+
+    /// Convert the index of a vacant slot or row to a key, before anything is inserted at it.
+    /// Panics if the index exceeds `MAX_KEY`, or the limit of `set_key_limit`.
+    pub(crate) fn checked_key(&self, index: usize) -> u32 {
+        checked_key_within(self.key_limit, index)
+    }
+
+    /// Remove the reference from the reference table of the entity,
+    /// dropping the table once empty, so that no entries are left behind by short-lived components.
+    pub(crate) fn remove_ref_0(&mut self, entity_key: EntityKey, ref_0_row_key: u32) {
+        let ref_0_col = self.ref_0_cols.get_mut(&entity_key).unwrap();
+        ref_0_col.try_remove(ref_0_row_key as usize).unwrap();
+
+        if ref_0_col.is_empty() {
+            self.ref_0_cols.remove(&entity_key);
+        }
+    }
+
+    /// Remove the entry from the reference table of the entity and type by its key, as stored in the row,
+    /// keeping the order of the others, and dropping the table once empty.
+    pub(crate) fn remove_ref_1(
+        &mut self,
+        entity_key: EntityKey,
+        type_key: std::any::TypeId,
+        ref_1_row_key: u32,
+    ) {
+        let ref_1_col = self.ref_1_cols.get_mut(&(entity_key, type_key)).unwrap();
+        ref_1_col.remove(ref_1_row_key);
+
+        if ref_1_col.is_empty() {
+            self.ref_1_cols.remove(&(entity_key, type_key));
+        }
+    }
+
+    /// Check whether the component can be inserted with the corresponding entity key without modifying anything.
+    /// Every insertion path must pass this check before touching the component column and the reference tables.
+    fn check_insert_comp<T>(&self, entity_key: EntityKey, comp: &T) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_entity(entity_key)?;
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+
+        if let Some(comp_limit) = comp_col.comp_limit {
+            let len = self
+                .ref_1_cols
+                .get(&(entity_key, type_key))
+                .map_or(0, |ref_1_col| ref_1_col.len());
+
+            if len >= comp_limit as usize {
+                return Err(Error::QuotaExceeded);
+            }
+        }
+
+        // every insertion updates the cached row, so no other row can be held
+        if let Some(row_key) = comp_col.unique_row.filter(|_| comp_col.world_unique) {
+            let holder = match self.taken.get(&(type_key, row_key)) {
+                Some(taken_row) => Some(taken_row.entity_key).filter(|_| taken_row.is_live()),
+                None => {
+                    (comp_col.get_row_fn)(comp_col, row_key).map(|comp_row| comp_row.entity_key)
+                }
+            };
+            if let Some(holder) = holder {
+                return Err(Error::AlreadyExists { holder });
+            }
+        }
+
+        if let Some(comp_limit_total) = comp_col.comp_limit_total {
+            let len = comp_col
+                .comp_rows
+                .downcast_ref::<Rows<CompRow<T>>>()
+                .unwrap()
+                .len();
+
+            if len >= comp_limit_total as usize {
+                return Err(Error::QuotaExceeded);
+            }
+        }
+
+        if let Some(insert_validator) = &comp_col.insert_validator {
+            insert_validator(self, entity_key, comp).map_err(Error::ValidationFailed)?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert a new shared component with the corresponding entity key and return the corresponding component key.
+    /// The component is stored as a `SharedComp<T>`, so it can be accessed by `get_comp::<SharedComp<T>>` etc.
+    /// This fails in the same cases as `insert_comp`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_shared::<String>().unwrap();
+    /// let handle = ecs.intern_shared("material".to_string()).unwrap();
+    /// let comp_key = ecs.insert_shared_comp(entity_key, handle).unwrap();
+    /// let comp = ecs.get_comp::<ecs_tiny::SharedComp<String>>(comp_key).unwrap();
+    ///
+    /// assert_eq!(**comp, "material");
+    /// ```
+    pub fn insert_shared_comp<T>(
+        &mut self,
+        entity_key: EntityKey,
+        handle: SharedComp<T>,
+    ) -> Result<CompKey, Error>
+    where
+        T: std::any::Any + std::hash::Hash + Eq,
+    {
+        self.insert_comp(entity_key, handle)
+    }
+
+    /// Remove a component with the corresponding component key and type, and return the component.
+    /// The component key is either a `CompKey` or a `TypedCompKey<T>`, from which the type is inferred.
+    /// If the component key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp = ecs.remove_comp::<i32>(comp_key).unwrap();
+    ///
+    /// assert_eq!(comp, 42);
+    /// ```
+    pub fn remove_comp<T>(&mut self, comp_key: impl IntoCompKey<T>) -> Result<T, Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_key = comp_key.into_comp_key();
+        self.release_dropped_taken();
+
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+
+        if type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let inverse = self.capture_undo(|ecs| ecs.removed_comp_inverse(comp_key));
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+        comp_col.note_removed(comp_key);
+        let comp_row = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .try_remove(row_key as usize)
+            .ok_or_else(|| take::missing_comp(&self.taken, type_key, row_key))?;
+        comp_col.remaps.remove(&row_key);
+        stable::forget(&mut comp_col.stable_ids, comp_row.stable_id);
+        self.comp_generations.bump(type_key, row_key);
+
+        self.remove_ref_0(comp_row.entity_key, comp_row.ref_0_row_key);
+        self.note_comp_removed(comp_row.entity_key);
+
+        self.remove_ref_1(comp_row.entity_key, type_key, comp_row.ref_1_row_key);
+
+        self.record(inverse, |_| WorldOp::RemoveComp { comp_key });
+
+        Ok(comp_row.comp)
+    }
+
+    /// Remove all components with the corresponding entity key and type, and return the components,
+    /// in the same order as `iter_comp_by_entity`.
+    /// Taken components, as in `take_comp`, are left in place.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(Vec<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.remove_comps_by_entity::<i32>(entity_key), Ok(vec![42, 63]));
+    /// ```
+    pub fn remove_comps_by_entity<T>(&mut self, entity_key: EntityKey) -> Result<Vec<T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let mut comps = vec![];
+        self.remove_comps_by_entity_into(entity_key, &mut comps)?;
+        Ok(comps)
+    }
+
+    /// Remove all components with the corresponding entity key and type into the buffer, as in `remove_comps_by_entity`.
+    /// The buffer is cleared first, keeping its capacity, so that it can be reused across calls.
+    /// This fails in the same cases as `remove_comps_by_entity`, leaving the buffer empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let mut comps = Vec::with_capacity(16);
+    /// ecs.remove_comps_by_entity_into::<i32>(entity_key, &mut comps).unwrap();
+    /// assert_eq!(comps, vec![42]);
+    /// ```
+    pub fn remove_comps_by_entity_into<T>(
+        &mut self,
+        entity_key: EntityKey,
+        out: &mut Vec<T>,
+    ) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        out.clear();
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
+        }
+
+        self.check_entity(entity_key)?;
+
+        let mut row_keys = std::mem::take(&mut self.scratch_row_keys);
+        row_keys.clear();
+        if let Some(ref_1_col) = self.ref_1_cols.get(&(entity_key, type_key)) {
+            row_keys.extend(ref_1_col.iter().copied());
+        }
+
+        for row_key in &row_keys {
+            // taken components stay reserved until they are put back or dropped
+            if let Ok(comp) = self.remove_comp::<T>(self.comp_key(type_key, *row_key)) {
+                out.push(comp);
+            }
+        }
+
+        row_keys.clear();
+        self.scratch_row_keys = row_keys;
+
+        Ok(())
+    }
+
+    /// Insert the components of the bundle into the entity, as in `insert_comp`, and return their typed keys.
+    /// Every component is checked before any is inserted, so that a failure inserts none of them,
+    /// and the insert hooks, as in `set_insert_hook`, run once all of them are inserted.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If any component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If any component is rejected, return the error of `insert_comp`.
+    /// Otherwise, return an `Ok(B::Keys)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component type appears more than once in the bundle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    ///
+    /// let (comp_key0, comp_key1) = ecs.insert_bundle(entity_key, (42, 1u32)).unwrap();
+    /// assert_eq!(ecs.get_comp(comp_key0), Ok(&42));
+    /// assert_eq!(ecs.get_comp(comp_key1), Ok(&1));
+    ///
+    /// assert_eq!(ecs.insert_bundle(entity_key, (63, 2.0f32)), Err(ecs_tiny::Error::TypeNotRegistered));
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 1);
+    /// ```
+    pub fn insert_bundle<B>(&mut self, entity_key: EntityKey, bundle: B) -> Result<B::Keys, Error>
+    where
+        B: Bundle,
+    {
+        bundle.insert_all(self, entity_key)
+    }
+
+    /// Remove the first component of each type in the bundle from the entity, and return the components found.
+    /// Types the entity has no component of are skipped and yield a `None`.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(B::Removed)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.remove_bundle::<(i32, u32)>(entity_key), Ok((Some(42), None)));
+    /// ```
+    pub fn remove_bundle<B>(&mut self, entity_key: EntityKey) -> Result<B::Removed, Error>
+    where
+        B: Bundle,
+    {
+        self.check_entity(entity_key)?;
+        Ok(B::remove(self, entity_key))
+    }
+
+    /// Remove the first component of each type in the bundle from the entity, and return the components.
+    /// If the entity corresponding to the entity key is not found,
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If a component type in the bundle is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the entity lacks a component of some type in the bundle, return an `Err(Error::CompNotFound)`.
+    /// If the components of some type held by the entity are all taken, return an `Err(Error::TemporarilyTaken)`.
+    /// Either way, nothing is removed.
+    /// Otherwise, return an `Ok(B)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.remove_bundle_strict::<(i32, u32)>(entity_key), Err(ecs_tiny::Error::CompNotFound));
+    ///
+    /// ecs.insert_comp(entity_key, 63u32).unwrap();
+    ///
+    /// assert_eq!(ecs.remove_bundle_strict::<(i32, u32)>(entity_key), Ok((42, 63)));
+    /// ```
+    pub fn remove_bundle_strict<B>(&mut self, entity_key: EntityKey) -> Result<B, Error>
+    where
+        B: Bundle,
+    {
+        self.check_entity(entity_key)?;
+        B::remove_strict(self, entity_key)
+    }
+
+    /// Insert a new entity holding the components of the bundle, as in `insert_bundle`,
+    /// and return the corresponding entity key.
+    /// If any component cannot be inserted, the new entity is removed, and return the error of `insert_bundle`.
+    /// Otherwise, return an `Ok(EntityKey)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component type appears more than once in the bundle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    ///
+    /// let entity_key = ecs.spawn((42, 1u32)).unwrap();
+    /// assert_eq!(ecs.query_single::<(&i32, &u32)>(), Ok((entity_key, (&42, &1))));
+    ///
+    /// assert_eq!(ecs.spawn((63, 2.0f32)), Err(ecs_tiny::Error::TypeNotRegistered));
+    /// assert_eq!(ecs.entity_count(), 1);
+    /// ```
+    pub fn spawn<B>(&mut self, bundle: B) -> Result<EntityKey, Error>
+    where
+        B: Bundle,
+    {
+        let entity_key = self.insert_entity();
+
+        if let Err(error) = bundle.insert_all(self, entity_key) {
+            self.remove_entity(entity_key).unwrap();
+            return Err(error);
+        }
+
+        Ok(entity_key)
+    }
+
+    /// Insert a new entity for each bundle, holding the components of the bundle,
+    /// and return the corresponding entity keys in the order of the bundles.
+    /// The capacity of the entities, the reference tables and the columns is reserved up front
+    /// by the lower bound of the size hint of the iterator.
+    /// If a component of a bundle cannot be inserted, as in `insert_comp`,
+    /// the entity of that bundle is removed along with the components inserted so far,
+    /// the entities of the previous bundles stay in place, and return an `Err(SpawnError::Bundle { .. })`.
+    /// Otherwise, return an `Ok(Vec<EntityKey>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    ///
+    /// let entity_keys = ecs.spawn_batch((0..3).map(|i| (i, i as u32))).unwrap();
+    ///
+    /// assert_eq!(entity_keys.len(), 3);
+    /// assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 3);
+    /// ```
+    pub fn spawn_batch<B>(
+        &mut self,
+        bundles: impl IntoIterator<Item = B>,
+    ) -> Result<Vec<EntityKey>, SpawnError>
+    where
+        B: Bundle,
+    {
+        let bundles = bundles.into_iter();
+        let (additional, _) = bundles.size_hint();
+
+        self.reserve_entities(additional);
+        self.ref_0_cols.reserve(additional);
+        B::reserve(self, additional);
+
+        let mut entity_keys = Vec::with_capacity(additional);
+        for (index, bundle) in bundles.enumerate() {
+            let entity_key = self.insert_entity();
+
+            if let Err(error) = bundle.insert(self, entity_key) {
+                self.remove_entity(entity_key).unwrap();
+                return Err(SpawnError::Bundle { index, error });
+            }
+
+            entity_keys.push(entity_key);
+        }
+
+        Ok(entity_keys)
+    }
+
+    /// Return whether the component corresponding to the component key is found, without fetching it.
+    /// A taken component, as in `take_comp`, is still found, since its key remains.
+    /// Keys of unregistered types are not found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// assert!(ecs.contains_comp(comp_key));
+    ///
+    /// ecs.remove_comp::<i32>(comp_key).unwrap();
+    /// assert!(!ecs.contains_comp(comp_key));
+    /// ```
+    pub fn contains_comp(&self, comp_key: CompKey) -> bool {
+        let Ok((type_key, row_key)) = self.check_comp_key(comp_key) else {
+            return false;
+        };
+
+        self.get_row_or_taken(type_key, row_key).is_some()
+    }
+
+    /// Return a component with the corresponding component key and type.
+    /// The component key is either a `CompKey` or a `TypedCompKey<T>`, from which the type is inferred.
+    /// If the component key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(&T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp = ecs.get_comp::<i32>(comp_key).unwrap();
+    ///
+    /// assert_eq!(comp, &42);
+    /// ```
+    pub fn get_comp<T>(&self, comp_key: impl IntoCompKey<T>) -> Result<&T, Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_key = comp_key.into_comp_key();
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+
+        if type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+        let comp_row = comp_rows
+            .get(row_key as usize)
+            .ok_or_else(|| take::missing_comp(&self.taken, type_key, row_key))?;
+
+        Ok(&comp_row.comp)
+    }
+
+    /// Return a mutable component with the corresponding component key and type.
+    /// The component key is either a `CompKey` or a `TypedCompKey<T>`, from which the type is inferred.
+    /// If the component key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(&mut T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp = ecs.get_comp_mut::<i32>(comp_key).unwrap();
+    ///
+    /// assert_eq!(comp, &mut 42);
+    /// ```
+    pub fn get_comp_mut<T>(&mut self, comp_key: impl IntoCompKey<T>) -> Result<&mut T, Error>
+    where
+        T: std::any::Any,
+    {
+        let tick = self.tick;
+        let comp_row = self.get_comp_row_mut::<T>(comp_key.into_comp_key())?;
+
+        Ok(comp_row.comp_mut(tick))
+    }
+
+    /// Return the row of a component mutably, failing as `get_comp_mut` does, without marking it changed.
+    fn get_comp_row_mut<T>(&mut self, comp_key: CompKey) -> Result<&mut CompRow<T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+
+        if type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+        comp_rows
+            .get_mut(row_key as usize)
+            .ok_or_else(|| take::missing_comp(&self.taken, type_key, row_key))
+    }
+
+    /// Return mutable components with the corresponding distinct component keys and type at once,
+    /// in the order of the component keys.
+    /// If any component key repeats, return an `Err(Error::DuplicateKey)`.
+    /// If any component cannot be accessed, return the error of `get_comp_mut`.
+    /// Otherwise, return an `Ok([&mut T; N])`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// let [comp0, comp1] = ecs.get_many_comp_mut::<i32, 2>([comp_key0, comp_key1]).unwrap();
+    /// std::mem::swap(comp0, comp1);
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&63));
+    ///
+    /// assert!(ecs.get_many_comp_mut::<i32, 2>([comp_key0, comp_key0]).is_err());
+    /// ```
+    pub fn get_many_comp_mut<T, const N: usize>(
+        &mut self,
+        comp_keys: [CompKey; N],
+    ) -> Result<[&mut T; N], Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let mut row_keys = [0; N];
+        for (row_key, comp_key) in row_keys.iter_mut().zip(comp_keys) {
+            let (comp_type_key, comp_row_key) = self.check_comp_key(comp_key)?;
+            if comp_type_key != type_key {
+                return Err(Error::TypeMismatch);
+            }
+            *row_key = comp_row_key as usize;
+        }
+        for (i, row_key) in row_keys.iter().enumerate() {
+            if row_keys[i + 1..].contains(row_key) {
+                return Err(Error::DuplicateKey);
+            }
+        }
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+        if let Some(row_key) = row_keys
+            .iter()
+            .find(|row_key| comp_rows.get(**row_key).is_none())
+        {
+            return Err(take::missing_comp(&self.taken, type_key, *row_key as u32));
+        }
+        let comps = comp_rows.get_disjoint_mut(row_keys).unwrap();
+
+        Ok(comps.map(|comp_row| comp_row.comp_mut(self.tick)))
+    }
+
+    /// Return mutable components with the corresponding component keys of two types at once.
+    /// If both types are the same, this behaves as `get_many_comp_mut`, and the component keys must differ.
+    /// If both types are the same and the component keys are equal, return an `Err(Error::DuplicateKey)`.
+    /// If either component cannot be accessed, return the error of `get_comp_mut`.
+    /// Otherwise, return an `Ok((&mut A, &mut B))`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key, 63u32).unwrap();
+    ///
+    /// let (comp0, comp1) = ecs.get_comp_mut_pair::<i32, u32>(comp_key0, comp_key1).unwrap();
+    /// *comp0 += *comp1 as i32;
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&105));
+    /// ```
+    pub fn get_comp_mut_pair<A, B>(
+        &mut self,
+        comp_key_a: CompKey,
+        comp_key_b: CompKey,
+    ) -> Result<(&mut A, &mut B), Error>
+    where
+        A: std::any::Any,
+        B: std::any::Any,
+    {
+        let type_key_a = std::any::TypeId::of::<A>();
+        let type_key_b = std::any::TypeId::of::<B>();
+
+        let (comp_type_key_a, row_key_a) = self.check_comp_key(comp_key_a)?;
+        let (comp_type_key_b, row_key_b) = self.check_comp_key(comp_key_b)?;
+        if comp_type_key_a != type_key_a || comp_type_key_b != type_key_b {
+            return Err(Error::TypeMismatch);
+        }
+
+        if type_key_a == type_key_b {
+            let [comp_a, comp_b] = self.get_many_comp_mut::<A, 2>([comp_key_a, comp_key_b])?;
+            let comp_b = (comp_b as &mut dyn std::any::Any)
+                .downcast_mut::<B>()
+                .unwrap();
+            return Ok((comp_a, comp_b));
+        }
+
+        let [comp_col_a, comp_col_b] = self.comp_cols.get_disjoint_mut([&type_key_a, &type_key_b]);
+        let comp_col_a = comp_col_a.ok_or(Error::TypeNotRegistered)?;
+        let comp_col_b = comp_col_b.ok_or(Error::TypeNotRegistered)?;
+        comp_col_a.count_access(true);
+        comp_col_b.count_access(true);
+
+        if comp_col_a.immutable || comp_col_b.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let taken = &self.taken;
+        let comp_a = comp_col_a
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<A>>>()
+            .unwrap()
+            .get_mut(row_key_a as usize)
+            .ok_or_else(|| take::missing_comp(taken, type_key_a, row_key_a))?;
+        let comp_b = comp_col_b
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<B>>>()
+            .unwrap()
+            .get_mut(row_key_b as usize)
+            .ok_or_else(|| take::missing_comp(taken, type_key_b, row_key_b))?;
+
+        Ok((comp_a.comp_mut(self.tick), comp_b.comp_mut(self.tick)))
+    }
+
+    /// Update a component with the corresponding component key and type by the closure, and return the closure result.
+    /// This fails in the same cases as `get_comp_mut`.
+    /// Otherwise, return an `Ok(R)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.update_comp::<i32, _>(comp_key, |comp| *comp += 1).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+    /// ```
+    pub fn update_comp<T, R>(
+        &mut self,
+        comp_key: CompKey,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Result<R, Error>
+    where
+        T: std::any::Any,
+    {
+        let inverse = self.capture_set_comp(comp_key);
+
+        let comp = self.get_comp_mut::<T>(comp_key)?;
+        let r = f(comp);
+
+        self.record_set_comp(inverse, comp_key);
+
+        Ok(r)
+    }
+
+    /// Replace the value of a component with the corresponding component key and type in place,
+    /// keeping the component key and its place among the components of the entity, and return the previous value.
+    /// If the component cannot be accessed, return the error of `get_comp_mut`.
+    /// Otherwise, return an `Ok(T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.replace_comp::<i32>(comp_key, 63), Ok(42));
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&63));
+    /// ```
+    pub fn replace_comp<T>(&mut self, comp_key: CompKey, comp: T) -> Result<T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.update_comp::<T, _>(comp_key, |old| std::mem::replace(old, comp))
+    }
+
+    /// Update a component with the corresponding component key and type by the fallible closure.
+    /// The closure runs on a copy of the component, which is written back only when the closure succeeds,
+    /// so the component is left untouched when the closure fails, and is not marked changed, as in `tick`.
+    /// If the component cannot be accessed as in `get_comp_mut`, return an `Err(UpdateError::Access(Error))`.
+    /// If the closure fails, return an `Err(UpdateError::Rejected(E))`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let result = ecs.try_update_comp::<i32, _>(comp_key, |comp| {
+    ///     *comp = -1;
+    ///     Err("negative")
+    /// });
+    ///
+    /// assert_eq!(result, Err(ecs_tiny::UpdateError::Rejected("negative")));
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+    /// ```
+    pub fn try_update_comp<T, E>(
+        &mut self,
+        comp_key: CompKey,
+        f: impl FnOnce(&mut T) -> Result<(), E>,
+    ) -> Result<(), UpdateError<E>>
+    where
+        T: std::any::Any + Clone,
+    {
+        let inverse = self.capture_set_comp(comp_key);
+
+        // the row is marked changed only once the closure succeeds
+        let tick = self.tick;
+        let comp_row = self
+            .get_comp_row_mut::<T>(comp_key)
+            .map_err(UpdateError::Access)?;
+
+        let mut new_comp = comp_row.comp.clone();
+        f(&mut new_comp).map_err(UpdateError::Rejected)?;
+        *comp_row.comp_mut(tick) = new_comp;
+
+        self.record_set_comp(inverse, comp_key);
+
+        Ok(())
+    }
+
+    /// Swap the values of two components with the corresponding component keys and type.
+    /// Entity associations are left untouched, only the component values are exchanged.
+    /// If either component key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If either component corresponding to the component keys is not found, return an `Err(Error::CompNotFound)`.
+    /// If both component keys are identical, nothing is swapped and return an `Ok(())`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key, 63).unwrap();
+    /// ecs.swap_comp::<i32>(comp_key0, comp_key1).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&63));
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&42));
+    /// ```
+    pub fn swap_comp<T>(&mut self, comp_key0: CompKey, comp_key1: CompKey) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let (type_key0, row_key0) = self.check_comp_key(comp_key0)?;
+        let (type_key1, row_key1) = self.check_comp_key(comp_key1)?;
+
+        if type_key0 != std::any::TypeId::of::<T>() || type_key1 != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let inverse = self.capture_undo(|ecs| {
+            let mut inverse = ecs.capture_set_comp(comp_key0).unwrap();
+            inverse.extend(ecs.capture_set_comp(comp_key1).unwrap());
+            inverse
+        });
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key0)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        let missing_comp = |row_key| take::missing_comp(&self.taken, type_key0, row_key);
+
+        if row_key0 == row_key1 {
+            comp_rows
+                .get(row_key0 as usize)
+                .ok_or_else(|| missing_comp(row_key0))?;
+            return Ok(());
+        }
+
+        for row_key in [row_key0, row_key1] {
+            comp_rows
+                .get(row_key as usize)
+                .ok_or_else(|| missing_comp(row_key))?;
+        }
+
+        let (comp_row0, comp_row1) = comp_rows
+            .get2_mut(row_key0 as usize, row_key1 as usize)
+            .unwrap();
+        std::mem::swap(comp_row0.comp_mut(self.tick), comp_row1.comp_mut(self.tick));
+
+        self.record_all(inverse, |ecs| {
+            vec![
+                WorldOp::SetComp {
+                    comp_key: comp_key0,
+                    comp: ecs.recorded_comp(comp_key0),
+                },
+                WorldOp::SetComp {
+                    comp_key: comp_key1,
+                    comp: ecs.recorded_comp(comp_key1),
+                },
+            ]
+        });
+
+        Ok(())
+    }
+
+    /// Return the value of the field of a component with the corresponding component key.
+    /// If the component type is not registered, return an `Err(FieldError::Access(Error::TypeNotRegistered))`.
+    /// If the component type is not reflected, return an `Err(FieldError::NotReflected)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(FieldError::Access(Error::TemporarilyTaken))`.
+    /// If the component corresponding to the component key is not found, return an `Err(FieldError::Access(Error::CompNotFound))`.
+    /// If the component type has no field of the name, return an `Err(FieldError::UnknownField)`.
+    /// Otherwise, return an `Ok(FieldValue)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// struct Health {
+    ///     current: i32,
+    /// }
+    ///
+    /// ecs_tiny::impl_reflect!(Health { current });
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<Health>().unwrap();
+    /// ecs.set_reflect::<Health>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, Health { current: 10 }).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp_field(comp_key, "current"), Ok(ecs_tiny::FieldValue::I32(10)));
+    /// ```
+    pub fn get_comp_field(&self, comp_key: CompKey, field: &str) -> Result<FieldValue, FieldError> {
+        let (type_key, row_key) = self.check_comp_key(comp_key).map_err(FieldError::Access)?;
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(FieldError::Access(Error::TypeNotRegistered))?;
+        comp_col.count_access(false);
+
+        let get_field_fn = comp_col.get_field_fn.ok_or(FieldError::NotReflected {
+            type_name: comp_col.type_name,
+        })?;
+
+        let comp = (comp_col.get_comp_fn)(comp_col, row_key).ok_or_else(|| {
+            FieldError::Access(take::missing_comp(&self.taken, type_key, row_key))
+        })?;
+
+        get_field_fn(comp, field)
+    }
+
+    /// Write the value into the field of a component with the corresponding component key.
+    /// This fails in the same cases as `get_comp_field`.
+    /// If the component type is immutable, return an `Err(FieldError::Access(Error::ImmutableType))`.
+    /// If the value does not match the type of the field, return an `Err(FieldError::TypeMismatch)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// struct Health {
+    ///     current: i32,
+    /// }
+    ///
+    /// ecs_tiny::impl_reflect!(Health { current });
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<Health>().unwrap();
+    /// ecs.set_reflect::<Health>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, Health { current: 10 }).unwrap();
+    /// ecs.set_comp_field(comp_key, "current", ecs_tiny::FieldValue::I32(50)).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp::<Health>(comp_key).unwrap().current, 50);
+    /// ```
+    pub fn set_comp_field(
+        &mut self,
+        comp_key: CompKey,
+        field: &str,
+        value: FieldValue,
+    ) -> Result<(), FieldError> {
+        let (type_key, row_key) = self.check_comp_key(comp_key).map_err(FieldError::Access)?;
+
+        let inverse = self.capture_set_comp(comp_key);
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(FieldError::Access(Error::TypeNotRegistered))?;
+        comp_col.count_access(true);
+
+        let set_field_fn = comp_col.set_field_fn.ok_or(FieldError::NotReflected {
+            type_name: comp_col.type_name,
+        })?;
+
+        if comp_col.immutable {
+            return Err(FieldError::Access(Error::ImmutableType));
+        }
+
+        let comp = (comp_col.get_comp_mut_fn)(comp_col, row_key, self.tick).ok_or_else(|| {
+            FieldError::Access(take::missing_comp(&self.taken, type_key, row_key))
+        })?;
+
+        set_field_fn(comp, field, value)?;
+
+        self.record_set_comp(inverse, comp_key);
+
+        Ok(())
+    }
+
+    fn capture_set_comp(&self, comp_key: CompKey) -> Option<Vec<WorldOp<S>>> {
+        self.capture_undo(|ecs| {
+            vec![WorldOp::SetComp {
+                comp_key,
+                comp: ecs.recorded_comp(comp_key),
+            }]
+        })
+    }
+
+    fn record_set_comp(&mut self, inverse: Option<Vec<WorldOp<S>>>, comp_key: CompKey) {
+        self.record(inverse, |ecs| WorldOp::SetComp {
+            comp_key,
+            comp: ecs.recorded_comp(comp_key),
+        });
+    }
+
+    /// Return an iterator over all components of the corresponding type.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key0, 63).unwrap();
+    /// ecs.insert_comp(entity_key1, 42).unwrap();
+    /// let mut iter = ecs.iter_comp::<i32>().unwrap();
+    ///
+    /// assert_eq!(iter.next(), Some(&42));
+    /// assert_eq!(iter.next(), Some(&63));
+    /// assert_eq!(iter.next(), Some(&42));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_comp<T>(&self) -> Result<impl Iterator<Item = &T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+        let iter = comp_rows.iter().map(|(_, comp_row)| &comp_row.comp);
+
+        Ok(iter)
+    }
+
+    /// Return a mutable iterator over all components of the corresponding type.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &mut T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key0, 63).unwrap();
+    /// ecs.insert_comp(entity_key1, 42).unwrap();
+    /// let mut iter = ecs.iter_comp_mut::<i32>().unwrap();
+    ///
+    /// assert_eq!(iter.next(), Some(&mut 42));
+    /// assert_eq!(iter.next(), Some(&mut 63));
+    /// assert_eq!(iter.next(), Some(&mut 42));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_comp_mut<T>(&mut self) -> Result<impl Iterator<Item = &mut T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let tick = self.tick;
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+        let iter = comp_rows
+            .iter_mut()
+            .map(move |(_, comp_row)| comp_row.comp_mut(tick));
+
+        Ok(iter)
+    }
+
+    /// Return the number of components of the corresponding type, as yielded by `iter_comp`, without iterating them.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.count_comp::<i32>(), Ok(2));
+    /// assert_eq!(ecs.count_comp::<u32>(), Err(ecs_tiny::Error::TypeNotRegistered));
+    /// ```
+    pub fn count_comp<T>(&self) -> Result<usize, Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_col = self
+            .comp_cols
+            .get(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+        Ok((comp_col.len_fn)(comp_col))
+    }
+
+    /// Return a mutable iterator over all components of the first type,
+    /// together with an iterator over all components of the second type, as in `iter_comp_mut` and `iter_comp`.
+    /// The iterators borrow distinct columns, so both can be used at the same time.
+    /// If either component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the first component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok((impl Iterator<Item = &mut A>, impl Iterator<Item = &B>))`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both component types are the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<f32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 1.0f32).unwrap();
+    /// ecs.insert_comp(entity_key, 2u32).unwrap();
+    ///
+    /// let (positions, mut scales) = ecs.iter_comp_mut_and::<f32, u32>().unwrap();
+    /// let scale = *scales.next().unwrap() as f32;
+    /// positions.for_each(|position| *position *= scale);
+    /// drop(scales);
+    ///
+    /// assert_eq!(ecs.iter_comp::<f32>().unwrap().collect::<Vec<_>>(), vec![&2.0]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn iter_comp_mut_and<A, B>(
+        &mut self,
+    ) -> Result<(impl Iterator<Item = &mut A>, impl Iterator<Item = &B>), Error>
+    where
+        A: std::any::Any,
+        B: std::any::Any,
+    {
+        let type_key0 = std::any::TypeId::of::<A>();
+        let type_key1 = std::any::TypeId::of::<B>();
+
+        assert!(
+            type_key0 != type_key1,
+            "the mutable and the shared component types must differ"
+        );
+
+        // the keys are distinct, so the columns are borrowed disjointly
+        let [comp_col0, comp_col1] = self.comp_cols.get_disjoint_mut([&type_key0, &type_key1]);
+        let comp_col0 = comp_col0.ok_or(Error::TypeNotRegistered)?;
+        let comp_col1 = &*comp_col1.ok_or(Error::TypeNotRegistered)?;
+
+        if comp_col0.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        comp_col0.count_access(true);
+        comp_col1.count_access(false);
+
+        let tick = self.tick;
+        let iter0 = comp_col0
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<A>>>()
+            .unwrap()
+            .iter_mut()
+            .map(move |(_, comp_row)| comp_row.comp_mut(tick));
+        let iter1 = comp_col1
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<B>>>()
+            .unwrap()
+            .iter()
+            .map(|(_, comp_row)| &comp_row.comp);
+
+        Ok((iter0, iter1))
+    }
+
+    /// Return an iterator over the values of all shared components of the corresponding type.
+    /// If the shared component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register_shared::<String>().unwrap();
+    /// let handle = ecs.intern_shared("material".to_string()).unwrap();
+    /// ecs.insert_shared_comp(entity_key0, handle.clone()).unwrap();
+    /// ecs.insert_shared_comp(entity_key1, handle).unwrap();
+    /// let mut iter = ecs.iter_shared_comp::<String>().unwrap();
+    ///
+    /// assert_eq!(iter.next().map(String::as_str), Some("material"));
+    /// assert_eq!(iter.next().map(String::as_str), Some("material"));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_shared_comp<T>(&self) -> Result<impl Iterator<Item = &T>, Error>
+    where
+        T: std::any::Any + std::hash::Hash + Eq,
+    {
+        let iter = self.iter_comp::<SharedComp<T>>()?.map(|handle| &**handle);
+        Ok(iter)
+    }
+
+    /// Return an entity key with the corresponding component key.
+    /// The component key is either a `CompKey` or a `TypedCompKey<T>`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// A taken component, as in `take_comp`, keeps its association with the entity.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(EntityKey)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
+    /// let comp_key1 = ecs.insert_comp(entity_key0, 63).unwrap();
+    /// let comp_key2 = ecs.insert_comp(entity_key1, 42).unwrap();
+    /// let entity_key = ecs.get_entity_by_comp(comp_key0).unwrap();
+    ///
+    /// assert_eq!(entity_key, entity_key0);
+    /// ```
+    pub fn get_entity_by_comp(&self, comp_key: impl Into<CompKey>) -> Result<EntityKey, Error> {
+        let comp_key = comp_key.into();
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+
+        // a taken component keeps its association with the entity
+        if let Some(taken_row) = self.taken.get(&(type_key, row_key)) {
+            if !taken_row.is_live() {
+                return Err(Error::CompNotFound);
+            }
+            return Ok(taken_row.entity_key);
+        }
+
+        let comp_row = (comp_col.get_row_fn)(comp_col, row_key).ok_or(Error::CompNotFound)?;
+
+        Ok(comp_row.entity_key)
+    }
+
+    /// Return an iterator over all components with the corresponding entity key and type,
+    /// in the order of the component insertion, or in the explicit order if the component type is ordered.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &T>)`, which is empty if the entity has no components of the type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key0, 63).unwrap();
+    /// ecs.insert_comp(entity_key1, 42).unwrap();
+    /// let mut iter = ecs.iter_comp_by_entity::<i32>(entity_key0).unwrap();
+    ///
+    /// assert_eq!(iter.next(), Some(&42));
+    /// assert_eq!(iter.next(), Some(&63));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_comp_by_entity<T>(
+        &self,
+        entity_key: EntityKey,
+    ) -> Result<impl Iterator<Item = &T>, Error>
+    where
         T: std::any::Any,
     {
-        let (type_key, row_key) = comp_key;
+        let type_key = std::any::TypeId::of::<T>();
 
-        if type_key != std::any::TypeId::of::<T>() {
-            return None;
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        self.check_entity(entity_key)?;
+
+        let ref_1_col = self.ref_1_cols.get(&(entity_key, type_key));
+
+        // the rows of taken components are skipped
+        let iter = ref_1_col
+            .into_iter()
+            .flatten()
+            .filter_map(|row_key| Some(&comp_rows.get(*row_key as usize)?.comp));
+
+        Ok(iter)
+    }
+
+    /// Return a mutable iterator over all components with the corresponding entity key and type,
+    /// in the same order as `iter_comp_by_entity`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &mut T>)`, which is empty if the entity has no components of the type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key0, 63).unwrap();
+    /// ecs.insert_comp(entity_key1, 42).unwrap();
+    /// let mut iter = ecs.iter_comp_mut_by_entity::<i32>(entity_key0).unwrap();
+    ///
+    /// assert_eq!(iter.next(), Some(&mut 42));
+    /// assert_eq!(iter.next(), Some(&mut 63));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn iter_comp_mut_by_entity<T>(
+        &mut self,
+        entity_key: EntityKey,
+    ) -> Result<impl Iterator<Item = &mut T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let iter = self.iter_comp_mut_with_key_by_entity::<T>(entity_key)?;
+
+        Ok(iter.map(|(_, comp)| comp))
+    }
+
+    /// Return whether the entity holds at least one component of the corresponding type, without fetching it.
+    /// A taken component, as in `take_comp`, still counts as held by its entity.
+    /// If the entity is not found or the component type is not registered, return false.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert!(ecs.has_comp::<i32>(entity_key));
+    /// assert!(!ecs.has_comp::<u32>(entity_key));
+    /// ```
+    pub fn has_comp<T>(&self, entity_key: EntityKey) -> bool
+    where
+        T: std::any::Any,
+    {
+        self.ref_1_cols
+            .get(&(entity_key, std::any::TypeId::of::<T>()))
+            .is_some_and(|ref_1_col| !ref_1_col.is_empty())
+    }
+
+    /// Return the number of components of the corresponding type held by the entity,
+    /// as yielded by `iter_comp_by_entity`, without iterating them.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key0, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key0), Ok(2));
+    /// assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key1), Ok(0));
+    /// assert_eq!(ecs.count_comp_by_entity::<u32>(entity_key0), Err(ecs_tiny::Error::TypeNotRegistered));
+    /// ```
+    pub fn count_comp_by_entity<T>(&self, entity_key: EntityKey) -> Result<usize, Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_entity(entity_key)?;
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
+        }
+
+        let Some(ref_1_col) = self.ref_1_cols.get(&(entity_key, type_key)) else {
+            return Ok(0);
+        };
+
+        // the taken components are not yielded
+        if self.taken.is_empty() {
+            return Ok(ref_1_col.len());
         }
+        let count = ref_1_col
+            .iter()
+            .filter(|row_key| !self.is_taken(type_key, **row_key))
+            .count();
+
+        Ok(count)
+    }
+
+    /// Call the closure with every component of the type of each listed entity, fetching the column once,
+    /// and return the entities skipped because they are not alive or have no components of the type.
+    /// A duplicated entity key visits the components of the entity again, one call after another.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(Vec<EntityKey>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key0, 42).unwrap();
+    ///
+    /// let skipped = ecs
+    ///     .for_each_comp_mut_in::<i32>(&[entity_key0, entity_key1], |_, comp| *comp += 1)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(skipped, vec![entity_key1]);
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+    /// ```
+    pub fn for_each_comp_mut_in<T>(
+        &mut self,
+        entities: &[EntityKey],
+        mut f: impl FnMut(EntityKey, &mut T),
+    ) -> Result<Vec<EntityKey>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
 
-        let comp_rows = self
+        let comp_col = self
             .comp_cols
-            .get_mut(&type_key)?
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let comp_rows = comp_col
             .comp_rows
-            .downcast_mut::<slab::Slab<CompRow<T>>>()
+            .downcast_mut::<Rows<CompRow<T>>>()
             .unwrap();
-        let comp_row = comp_rows.try_remove(row_key as usize)?;
 
-        self.ref_0_cols
-            .get_mut(&comp_row.entity_key)
+        // the reference tables hold no entries of dead entities, so a lookup also checks the entity
+        let mut skipped = vec![];
+        for entity_key in entities {
+            let ref_1_col = self
+                .ref_1_cols
+                .get(&(*entity_key, type_key))
+                .filter(|ref_1_col| !ref_1_col.is_empty());
+
+            let Some(ref_1_col) = ref_1_col else {
+                skipped.push(*entity_key);
+                continue;
+            };
+
+            // the rows of taken components are skipped
+            for row_key in ref_1_col {
+                if let Some(comp_row) = comp_rows.get_mut(*row_key as usize) {
+                    f(*entity_key, comp_row.comp_mut(self.tick));
+                }
+            }
+        }
+
+        Ok(skipped)
+    }
+
+    /// Call the closure with every component of the type, together with its owning entity key,
+    /// from scoped threads, each of which visits a contiguous range of whole chunks of the column,
+    /// so that the update loop is spread over the available parallelism without further dependencies.
+    /// A column of at most one chunk is visited serially on the calling thread.
+    /// Taken components, as in `take_comp`, are skipped.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chunk size is zero, or if the closure panics on any thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// for i in 0..1000 {
+    ///     let entity_key = ecs.insert_entity();
+    ///     ecs.insert_comp(entity_key, i).unwrap();
+    /// }
+    ///
+    /// ecs.for_each_comp_mut_chunked::<i32>(100, |_, comp| *comp *= 2).unwrap();
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().sum::<i32>(), 999_000);
+    /// ```
+    pub fn for_each_comp_mut_chunked<T>(
+        &mut self,
+        chunk_size: usize,
+        f: impl Fn(EntityKey, &mut T) + Sync,
+    ) -> Result<(), Error>
+    where
+        T: std::any::Any + Send,
+    {
+        assert!(chunk_size > 0, "chunk size must be positive");
+
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        // the occupied rows are gathered first, so that each thread gets an equal share of them
+        let mut comps = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
             .unwrap()
-            .try_remove(comp_row.ref_0_row_key as usize)
+            .iter_mut()
+            .map(|(_, comp_row)| (comp_row.entity_key, comp_row.comp_mut(self.tick)))
+            .collect::<Vec<_>>();
+
+        if comps.len() <= chunk_size {
+            for (entity_key, comp) in comps {
+                f(entity_key, comp);
+            }
+            return Ok(());
+        }
+
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let chunks_per_thread = comps.len().div_ceil(chunk_size).div_ceil(threads);
+
+        let f = &f;
+        std::thread::scope(|scope| {
+            for range in comps.chunks_mut(chunk_size * chunks_per_thread) {
+                scope.spawn(move || {
+                    for (entity_key, comp) in range {
+                        f(*entity_key, comp);
+                    }
+                });
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Return a low-level read-only handle of the column of the component type.
+    /// See [`Column`] for the invariants of the handle.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(Column<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// let column = ecs.column::<i32>().unwrap();
+    ///
+    /// assert_eq!(column.iter_rows().collect::<Vec<_>>(), vec![(0, entity_key, &42)]);
+    /// ```
+    pub fn column<T>(&self) -> Result<Column<'_, T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
             .unwrap();
 
-        self.ref_1_cols
-            .get_mut(&(comp_row.entity_key, type_key))
-            .unwrap()
-            .try_remove(comp_row.ref_1_row_key as usize)
+        Ok(Column::new(
+            comp_rows,
+            self.comp_generations.of(type_key),
+            self.world,
+        ))
+    }
+
+    /// Return a low-level mutable handle of the column of the component type.
+    /// See [`ColumnMut`] for the invariants of the handle.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(ColumnMut<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// for (_, _, comp) in ecs.column_mut::<i32>().unwrap().iter_rows_mut() {
+    ///     *comp += 1;
+    /// }
+    ///
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+    /// ```
+    pub fn column_mut<T>(&mut self) -> Result<ColumnMut<'_, T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
             .unwrap();
 
-        Some(comp_row.comp)
+        Ok(ColumnMut::new(
+            comp_rows,
+            self.comp_generations.of(type_key),
+            &self.ref_1_cols,
+            self.world,
+            self.tick,
+        ))
     }
 
-    /// Return a component with the corresponding component key and type.
-    /// If the component corresponding to the component key and type is not found, return an `None`.
-    /// Otherwise, return an `Some(&T)`.
+    /// Split the world into mutable handles of the columns of two component types,
+    /// and read access to the other columns, so that the values of both types can be mutated at the same time.
+    /// The handles cannot insert or remove components.
+    /// The handles borrow distinct columns, each of which owns a separate allocation,
+    /// so each handle can be sent to another thread when the component type is `Send`.
+    /// If either component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If either component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok((ColumnMut<A>, ColumnMut<B>, ShardRest))`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both component types are the same.
     ///
     /// # Examples
     ///
@@ -369,35 +4511,92 @@ impl ECS {
     /// let mut ecs = ecs_tiny::ECS::new();
     /// let entity_key = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
-    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
-    /// let comp = ecs.get_comp::<i32>(comp_key).unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 1u32).unwrap();
     ///
-    /// assert_eq!(comp, &42);
+    /// let (mut ints, mut uints, _) = ecs.shard::<i32, u32>().unwrap();
+    /// std::thread::scope(|scope| {
+    ///     scope.spawn(|| ints.iter_rows_mut().for_each(|(_, _, comp)| *comp += 1));
+    ///     scope.spawn(|| uints.iter_rows_mut().for_each(|(_, _, comp)| *comp += 1));
+    /// });
+    ///
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(), vec![&43]);
+    /// assert_eq!(ecs.iter_comp::<u32>().unwrap().collect::<Vec<_>>(), vec![&2]);
     /// ```
-    pub fn get_comp<T>(&self, comp_key: CompKey) -> Option<&T>
+    #[allow(clippy::type_complexity)]
+    pub fn shard<A, B>(
+        &mut self,
+    ) -> Result<(ColumnMut<'_, A>, ColumnMut<'_, B>, ShardRest<'_, S>), Error>
     where
-        T: std::any::Any,
+        A: std::any::Any,
+        B: std::any::Any,
     {
-        let (type_key, row_key) = comp_key;
+        let type_key0 = std::any::TypeId::of::<A>();
+        let type_key1 = std::any::TypeId::of::<B>();
 
-        if type_key != std::any::TypeId::of::<T>() {
-            return None;
+        assert!(
+            type_key0 != type_key1,
+            "each component type can appear only once in a shard"
+        );
+
+        for type_key in [type_key0, type_key1] {
+            let comp_col = self
+                .comp_cols
+                .get(&type_key)
+                .ok_or(Error::TypeNotRegistered)?;
+
+            if comp_col.immutable {
+                return Err(Error::ImmutableType);
+            }
         }
 
-        let comp_rows = self
-            .comp_cols
-            .get(&type_key)?
-            .comp_rows
-            .downcast_ref::<slab::Slab<CompRow<T>>>()
-            .unwrap();
-        let comp_row = comp_rows.get(row_key as usize)?;
+        // the mutable iterator hands out every column exactly once, so the borrows are disjoint
+        let mut comp_rows0 = None;
+        let mut comp_rows1 = None;
+        let mut comp_cols =
+            hash::HashMap::with_capacity_and_hasher(self.comp_cols.len(), self.hasher().clone());
+        for (type_key, comp_col) in self.comp_cols.iter_mut() {
+            if *type_key == type_key0 {
+                comp_rows0 = comp_col.comp_rows.downcast_mut::<Rows<CompRow<A>>>();
+            } else if *type_key == type_key1 {
+                comp_rows1 = comp_col.comp_rows.downcast_mut::<Rows<CompRow<B>>>();
+            } else {
+                comp_cols.insert(*type_key, &*comp_col);
+            }
+        }
 
-        Some(&comp_row.comp)
+        Ok((
+            ColumnMut::new(
+                comp_rows0.unwrap(),
+                self.comp_generations.of(type_key0),
+                &self.ref_1_cols,
+                self.world,
+                self.tick,
+            ),
+            ColumnMut::new(
+                comp_rows1.unwrap(),
+                self.comp_generations.of(type_key1),
+                &self.ref_1_cols,
+                self.world,
+                self.tick,
+            ),
+            ShardRest::new(
+                comp_cols,
+                &self.ref_1_cols,
+                &self.taken,
+                &self.comp_generations,
+                self.world,
+            ),
+        ))
     }
 
-    /// Return a mutable component with the corresponding component key and type.
-    /// If the component corresponding to the component key and type is not found, return an `None`.
-    /// Otherwise, return an `Some(&mut T)`.
+    /// Return an iterator over the row keys of all components with the corresponding entity key and type,
+    /// in the same order as `iter_comp_by_entity`. The row keys index the handle returned by `column`.
+    /// The rows of taken components, as in `take_comp`, are included, though the handle yields nothing at them.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = u32>)`, which is empty if the entity has no components of the type.
     ///
     /// # Examples
     ///
@@ -405,35 +4604,55 @@ impl ECS {
     /// let mut ecs = ecs_tiny::ECS::new();
     /// let entity_key = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
-    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
-    /// let comp = ecs.get_comp_mut::<i32>(comp_key).unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// let row_key0 = ecs.insert_comp(entity_key, 42).unwrap().row_key();
+    /// let row_key1 = ecs.insert_comp(entity_key, 63).unwrap().row_key();
     ///
-    /// assert_eq!(comp, &mut 42);
+    /// let row_keys = ecs.rows_by_entity::<i32>(entity_key).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(row_keys, vec![row_key0, row_key1]);
+    /// assert_eq!(ecs.rows_by_entity::<u32>(entity_key).unwrap().count(), 0);
     /// ```
-    pub fn get_comp_mut<T>(&mut self, comp_key: CompKey) -> Option<&mut T>
+    pub fn rows_by_entity<T>(
+        &self,
+        entity_key: EntityKey,
+    ) -> Result<impl Iterator<Item = u32> + '_, Error>
     where
         T: std::any::Any,
     {
-        let (type_key, row_key) = comp_key;
+        let type_key = std::any::TypeId::of::<T>();
 
-        if type_key != std::any::TypeId::of::<T>() {
-            return None;
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
         }
 
-        let comp_rows = self
-            .comp_cols
-            .get_mut(&type_key)?
-            .comp_rows
-            .downcast_mut::<slab::Slab<CompRow<T>>>()
-            .unwrap();
-        let comp = comp_rows.get_mut(row_key as usize)?;
+        self.check_entity(entity_key)?;
 
-        Some(&mut comp.comp)
+        // taken components keep their rows, unless their handles were dropped
+        let iter = self
+            .ref_1_cols
+            .get(&(entity_key, type_key))
+            .into_iter()
+            .flat_map(|ref_1_col| ref_1_col.iter().copied())
+            .filter(move |row_key| {
+                self.taken
+                    .get(&(type_key, *row_key))
+                    .is_none_or(take::TakenRow::is_live)
+            });
+
+        Ok(iter)
     }
 
-    /// Return an iterator over all components of the corresponding type.
-    /// If the component type is not found, return an `None`.
-    /// Otherwise, return an `Some(impl Iterator<Item = &T>)`.
+    /// Return an iterator over all entities having all of the component types in the query,
+    /// together with the corresponding entity key.
+    /// For entities having several components of a type, the first one is yielded.
+    /// If any component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If any component type accessed mutably is immutable, return an `Err(Error::ImmutableType)`.
+    /// If all elements are optional, return an `Err(Error::NoRequiredElement)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (EntityKey, Q::Item)>)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component type appears more than once in the query.
     ///
     /// # Examples
     ///
@@ -442,36 +4661,25 @@ impl ECS {
     /// let entity_key0 = ecs.insert_entity();
     /// let entity_key1 = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
     /// ecs.insert_comp(entity_key0, 42).unwrap();
-    /// ecs.insert_comp(entity_key0, 63).unwrap();
-    /// ecs.insert_comp(entity_key1, 42).unwrap();
-    /// let mut iter = ecs.iter_comp::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 1u32).unwrap();
+    /// ecs.insert_comp(entity_key1, 63).unwrap();
+    /// let mut iter = ecs.query::<(&mut i32, &u32)>().unwrap();
     ///
-    /// assert_eq!(iter.next(), Some(&42));
-    /// assert_eq!(iter.next(), Some(&63));
-    /// assert_eq!(iter.next(), Some(&42));
+    /// assert_eq!(iter.next(), Some((entity_key0, (&mut 42, &1))));
     /// assert_eq!(iter.next(), None);
     /// ```
-    pub fn iter_comp<T>(&self) -> Option<impl Iterator<Item = &T>>
+    pub fn query<Q>(&mut self) -> Result<impl Iterator<Item = (EntityKey, Q::Item<'_>)>, Error>
     where
-        T: std::any::Any,
+        Q: Query,
     {
-        let type_key = std::any::TypeId::of::<T>();
-
-        let comp_rows = self
-            .comp_cols
-            .get(&type_key)?
-            .comp_rows
-            .downcast_ref::<slab::Slab<CompRow<T>>>()
-            .unwrap();
-        let iter = comp_rows.iter().map(|(_, comp_row)| &comp_row.comp);
-
-        Some(iter)
+        let plan = Q::plan(self)?;
+        Ok(Q::fetch(self, plan).into_iter())
     }
 
-    /// Return a mutable iterator over all components of the corresponding type.
-    /// If the component type is not found, return an `None`.
-    /// Otherwise, return an `Some(impl Iterator<Item = &mut T>)`.
+    /// Return the number of entities matching the query.
+    /// If the query is invalid, no entities match the query.
     ///
     /// # Examples
     ///
@@ -480,63 +4688,48 @@ impl ECS {
     /// let entity_key0 = ecs.insert_entity();
     /// let entity_key1 = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
     /// ecs.insert_comp(entity_key0, 42).unwrap();
-    /// ecs.insert_comp(entity_key0, 63).unwrap();
-    /// ecs.insert_comp(entity_key1, 42).unwrap();
-    /// let mut iter = ecs.iter_comp_mut::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 1u32).unwrap();
+    /// ecs.insert_comp(entity_key1, 63).unwrap();
     ///
-    /// assert_eq!(iter.next(), Some(&mut 42));
-    /// assert_eq!(iter.next(), Some(&mut 63));
-    /// assert_eq!(iter.next(), Some(&mut 42));
-    /// assert_eq!(iter.next(), None);
+    /// assert_eq!(ecs.query_count::<(&i32,)>(), 2);
+    /// assert_eq!(ecs.query_count::<(&i32, &u32)>(), 1);
     /// ```
-    pub fn iter_comp_mut<T>(&mut self) -> Option<impl Iterator<Item = &mut T>>
+    pub fn query_count<Q>(&self) -> usize
     where
-        T: std::any::Any,
+        Q: Query,
     {
-        let type_key = std::any::TypeId::of::<T>();
-
-        let comp_rows = self
-            .comp_cols
-            .get_mut(&type_key)?
-            .comp_rows
-            .downcast_mut::<slab::Slab<CompRow<T>>>()
-            .unwrap();
-        let iter = comp_rows.iter_mut().map(|(_, comp_row)| &mut comp_row.comp);
-
-        Some(iter)
+        Q::plan(self).map_or(0, |plan| plan.len())
     }
 
-    /// Return an entity key with the corresponding component key.
-    /// If the component corresponding to the component key is not found, return an `None`.
-    /// Otherwise, return an `Some(EntityKey)`.
+    /// Return whether any entity matches the query.
+    /// If the query is invalid, no entities match the query.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut ecs = ecs_tiny::ECS::new();
-    /// let entity_key0 = ecs.insert_entity();
-    /// let entity_key1 = ecs.insert_entity();
+    /// let entity_key = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
-    /// let comp_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
-    /// let comp_key1 = ecs.insert_comp(entity_key0, 63).unwrap();
-    /// let comp_key2 = ecs.insert_comp(entity_key1, 42).unwrap();
-    /// let entity_key = ecs.get_entity_by_comp(comp_key0).unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
     ///
-    /// assert_eq!(entity_key, entity_key0);
+    /// assert!(ecs.query_any::<(&i32,)>());
+    /// assert!(!ecs.query_any::<(&i32, &u32)>());
     /// ```
-    pub fn get_entity_by_comp(&self, comp_key: CompKey) -> Option<EntityKey> {
-        let (type_key, row_key) = comp_key;
-
-        let comp_col = self.comp_cols.get(&type_key)?;
-        let comp_row = (comp_col.get_row_fn)(comp_col, row_key)?;
-
-        Some(comp_row.entity_key)
+    pub fn query_any<Q>(&self) -> bool
+    where
+        Q: Query,
+    {
+        self.query_count::<Q>() > 0
     }
 
-    /// Return an iterator over all components with the corresponding entity key and type.
-    /// If the entity corresponding to the entity key and type is not found, return an `None`.
-    /// Otherwise, return an `Some(impl Iterator<Item = &T>)`.
+    /// Return the only entity matching the query, together with the corresponding entity key.
+    /// If the query is invalid as in `query`, return an `Err(SingleError::Query(Error))`.
+    /// If no entities match the query, return an `Err(SingleError::NoEntities)`.
+    /// If more than one entity matches the query, return an `Err(SingleError::MultipleEntities)`.
+    /// Otherwise, return an `Ok((EntityKey, Q::Item))`.
     ///
     /// # Examples
     ///
@@ -545,85 +4738,131 @@ impl ECS {
     /// let entity_key0 = ecs.insert_entity();
     /// let entity_key1 = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
     /// ecs.insert_comp(entity_key0, 42).unwrap();
-    /// ecs.insert_comp(entity_key0, 63).unwrap();
-    /// ecs.insert_comp(entity_key1, 42).unwrap();
-    /// let mut iter = ecs.iter_comp_by_entity::<i32>(entity_key0).unwrap();
+    /// ecs.insert_comp(entity_key0, 1u32).unwrap();
+    /// ecs.insert_comp(entity_key1, 63).unwrap();
     ///
-    /// assert_eq!(iter.next(), Some(&42));
-    /// assert_eq!(iter.next(), Some(&63));
-    /// assert_eq!(iter.next(), None);
+    /// assert_eq!(ecs.query_single::<(&i32, &u32)>(), Ok((entity_key0, (&42, &1))));
     /// ```
-    pub fn iter_comp_by_entity<T>(&self, entity_key: EntityKey) -> Option<impl Iterator<Item = &T>>
+    pub fn query_single<Q>(&mut self) -> Result<(EntityKey, Q::Item<'_>), SingleError>
     where
-        T: std::any::Any,
+        Q: Query,
     {
-        let type_key = std::any::TypeId::of::<T>();
-
-        let comp_rows = self
-            .comp_cols
-            .get(&type_key)?
-            .comp_rows
-            .downcast_ref::<slab::Slab<CompRow<T>>>()
-            .unwrap();
+        let plan = Q::plan(self).map_err(SingleError::Query)?;
 
-        let ref_1_col = self.ref_1_cols.get(&(entity_key, type_key))?;
+        match plan.len() {
+            0 => Err(SingleError::NoEntities),
+            1 => Ok(Q::fetch(self, plan).pop().unwrap()),
+            _ => Err(SingleError::MultipleEntities),
+        }
+    }
 
-        let iter = ref_1_col
-            .iter()
-            .map(|(_, row_key)| &comp_rows.get(*row_key as usize).unwrap().comp);
+    /// Return clones of all entities matching the query, together with the corresponding entity key.
+    /// The collected values are independent of the world and can be sent to other threads.
+    /// This fails in the same cases as `query`.
+    /// Otherwise, return an `Ok(Vec<(EntityKey, Q::Owned)>)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component type appears more than once in the query.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 1u32).unwrap();
+    /// let owned = ecs.query_collect_owned::<(&i32, &u32)>().unwrap();
+    ///
+    /// assert_eq!(owned, vec![(entity_key, (42, 1))]);
+    /// ```
+    pub fn query_collect_owned<Q>(&self) -> Result<Vec<(EntityKey, Q::Owned)>, Error>
+    where
+        Q: QueryOwned,
+    {
+        let plan = Q::plan(self)?;
+        Ok(Q::fetch_owned(self, plan))
+    }
 
-        Some(iter)
+    /// Return clones of all components of the corresponding type,
+    /// together with the corresponding entity key and component key.
+    /// The collected values are independent of the world and can be sent to other threads.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(Vec<(EntityKey, CompKey, T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let cloned = ecs.collect_comp_cloned::<i32>().unwrap();
+    ///
+    /// assert_eq!(cloned, vec![(entity_key, comp_key, 42)]);
+    /// ```
+    pub fn collect_comp_cloned<T>(&self) -> Result<Vec<(EntityKey, CompKey, T)>, Error>
+    where
+        T: std::any::Any + Clone + Send,
+    {
+        let mut cloned = vec![];
+        self.collect_comp_cloned_into(&mut cloned)?;
+        Ok(cloned)
     }
 
-    /// Return a mutable iterator over all components with the corresponding entity key and type.
-    /// If the entity corresponding to the entity key and type is not found, return an `None`.
-    /// Otherwise, return an `None(impl Iterator<Item = &mut T>)`.
+    /// Write clones of all components of the corresponding type into the buffer, as in `collect_comp_cloned`.
+    /// The buffer is cleared first, keeping its capacity, so that it can be reused across calls.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`, leaving the buffer empty.
+    /// Otherwise, return an `Ok(())`.
     ///
     /// # Examples
     ///
     /// ```
     /// let mut ecs = ecs_tiny::ECS::new();
-    /// let entity_key0 = ecs.insert_entity();
-    /// let entity_key1 = ecs.insert_entity();
+    /// let entity_key = ecs.insert_entity();
     /// ecs.register::<i32>().unwrap();
-    /// ecs.insert_comp(entity_key0, 42).unwrap();
-    /// ecs.insert_comp(entity_key0, 63).unwrap();
-    /// ecs.insert_comp(entity_key1, 42).unwrap();
-    /// let mut iter = ecs.iter_comp_mut_by_entity::<i32>(entity_key0).unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
     ///
-    /// assert_eq!(iter.next(), Some(&mut 42));
-    /// assert_eq!(iter.next(), Some(&mut 63));
-    /// assert_eq!(iter.next(), None);
+    /// let mut cloned = vec![];
+    /// ecs.collect_comp_cloned_into::<i32>(&mut cloned).unwrap();
+    /// assert_eq!(cloned, vec![(entity_key, comp_key, 42)]);
     /// ```
-    pub fn iter_comp_mut_by_entity<T>(
-        &mut self,
-        entity_key: EntityKey,
-    ) -> Option<impl Iterator<Item = &mut T>>
+    pub fn collect_comp_cloned_into<T>(
+        &self,
+        out: &mut Vec<(EntityKey, CompKey, T)>,
+    ) -> Result<(), Error>
     where
-        T: std::any::Any,
+        T: std::any::Any + Clone + Send,
     {
+        out.clear();
+
         let type_key = std::any::TypeId::of::<T>();
 
-        let comp_rows = self
+        let comp_col = self
             .comp_cols
-            .get_mut(&type_key)?
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let comp_rows = comp_col
             .comp_rows
-            .downcast_mut::<slab::Slab<CompRow<T>>>()
+            .downcast_ref::<Rows<CompRow<T>>>()
             .unwrap();
+        let cloned = comp_rows.iter().map(|(row_key, comp_row)| {
+            let comp_key = self.comp_key(type_key, row_key as u32);
+            (comp_row.entity_key, comp_key, comp_row.comp.clone())
+        });
+        out.extend(cloned);
 
-        let ref_1_col = self.ref_1_cols.get(&(entity_key, type_key))?;
-
-        // UNSAFE: allow double mutable borrow temporarily
-        let iter = ref_1_col
-            .iter()
-            .map(|(_, row_key)| &mut comp_rows.get_mut(*row_key as usize).unwrap().comp as *mut T)
-            .map(|ptr| unsafe { &mut *ptr });
-
-        Some(iter)
+        Ok(())
     }
 
     /// Clear all entities and components.
+    /// Partitions are kept.
     ///
     /// # Examples
     ///
@@ -635,9 +4874,417 @@ impl ECS {
     /// ecs.clear();
     /// ```
     pub fn clear(&mut self) {
+        for (index, _) in self.entities.iter() {
+            let generation = &mut self.generations[index];
+            *generation = generation.wrapping_add(1);
+        }
+
+        let cleared = self
+            .ref_0_cols
+            .iter()
+            .filter(|(_, ref_0_col)| !ref_0_col.is_empty())
+            .map(|(entity_key, _)| *entity_key)
+            .collect();
+        self.note_comps_cleared(cleared);
+
+        let mut type_keys = self.comp_cols.keys().copied().collect::<Vec<_>>();
+        type_keys.sort_by_key(|type_key| self.comp_cols[type_key].registration);
+        for type_key in type_keys {
+            self.notify_column_removed(type_key);
+            self.bump_column_generations(type_key);
+        }
+
         self.entities.clear();
         self.comp_cols.clear();
         self.ref_0_cols.clear();
         self.ref_1_cols.clear();
+        self.shared_pools.clear();
+        self.taken.clear();
+        self.clearing = None;
+        self.reserved_count = 0;
+
+        self.discard_undo();
+        self.record(None, |_| WorldOp::Clear);
+    }
+
+    /// Clone the entities for which the predicate returns true, together with all their components,
+    /// into a new ECS instance. Entity keys and component keys are preserved,
+    /// and the registrations of all component types carry over.
+    /// Taken components, as in `take_comp`, are not cloned.
+    /// If a kept entity has a component of a type registered without clone support,
+    /// return an `Err(CloneError::NotCloneable)`.
+    /// Otherwise, return an `Ok(ECS)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key1, 63).unwrap();
+    ///
+    /// let clone = ecs.clone_filtered(|entity_key| entity_key == entity_key1).unwrap();
+    ///
+    /// assert!(clone.get_entity(entity_key0).is_err());
+    /// assert_eq!(clone.iter_comp::<i32>().unwrap().collect::<Vec<_>>(), vec![&63]);
+    /// ```
+    pub fn clone_filtered(
+        &self,
+        mut keep: impl FnMut(EntityKey) -> bool,
+    ) -> Result<ECS<S>, CloneError> {
+        let entities = self
+            .iter_entity_slots()
+            .filter(|(entity_key, slot)| !slot.is_reserved() && keep(*entity_key))
+            .map(|(entity_key, slot)| (entity_key.index as usize, *slot))
+            .collect::<slab::Slab<entity_flags::EntitySlot>>();
+        let is_kept = |entity_key: EntityKey| entities.contains(entity_key.index as usize);
+
+        // bump the generations of dropped and reserved entities so that their keys do not resolve in the clone
+        let mut generations = self.generations.clone();
+        for (entity_key, _) in self
+            .iter_entity_slots()
+            .filter(|(entity_key, _)| !is_kept(*entity_key))
+        {
+            let generation = &mut generations[entity_key.index as usize];
+            *generation = generation.wrapping_add(1);
+        }
+
+        let mut comp_cols =
+            hash::HashMap::with_capacity_and_hasher(self.comp_cols.len(), self.hasher().clone());
+        for (type_key, comp_col) in &self.comp_cols {
+            let comp_rows =
+                (comp_col.clone_rows_fn)(comp_col, &is_kept).ok_or(CloneError::NotCloneable {
+                    type_name: comp_col.type_name,
+                })?;
+
+            let mut comp_col = CompColumn {
+                comp_rows,
+                type_name: comp_col.type_name,
+                len_fn: comp_col.len_fn,
+                get_row_fn: comp_col.get_row_fn,
+                iter_owners_fn: comp_col.iter_owners_fn,
+                remove_row_fn: comp_col.remove_row_fn,
+                remove_boxed_fn: comp_col.remove_boxed_fn,
+                insert_boxed_fn: comp_col.insert_boxed_fn,
+                set_owner_fn: comp_col.set_owner_fn,
+                release_row_fn: comp_col.release_row_fn,
+                shrink_fn: comp_col.shrink_fn,
+                get_comp_fn: comp_col.get_comp_fn,
+                get_comp_mut_fn: comp_col.get_comp_mut_fn,
+                clone_rows_fn: comp_col.clone_rows_fn,
+                record_comp_fn: comp_col.record_comp_fn,
+                get_field_fn: comp_col.get_field_fn,
+                set_field_fn: comp_col.set_field_fn,
+                eq_fn: comp_col.eq_fn,
+                removal_log: comp_col
+                    .removal_log
+                    .as_ref()
+                    .map(removal::RemovalLog::empty_like),
+                observers: comp_col.observers.clone(),
+                insert_validator: comp_col.insert_validator.clone(),
+                insert_hook: comp_col.insert_hook.clone(),
+                comp_limit: comp_col.comp_limit,
+                comp_limit_total: comp_col.comp_limit_total,
+                immutable: comp_col.immutable,
+                ordered: comp_col.ordered,
+                world_unique: comp_col.world_unique,
+                unique_row: comp_col.unique_row,
+                stable_ids: comp_col.stable_ids.clone(),
+                #[cfg(feature = "profile")]
+                access: Default::default(),
+                #[cfg(feature = "serde")]
+                serde_name: comp_col.serde_name,
+                remaps: hash::HashMap::with_hasher(self.hasher().clone()),
+                registration: comp_col.registration,
+            };
+
+            // the ids of the dropped and the taken components stay unused in the clone, so only their rows are forgotten
+            if let Some(mut stable_ids) = comp_col.stable_ids.take() {
+                let get_row_fn = comp_col.get_row_fn;
+                stable_ids
+                    .rows
+                    .retain(|_, row_key| get_row_fn(&comp_col, *row_key).is_some());
+                comp_col.stable_ids = Some(stable_ids);
+            }
+
+            comp_cols.insert(*type_key, comp_col);
+        }
+
+        let mut ref_0_cols = self
+            .ref_0_cols
+            .iter()
+            .filter(|(entity_key, _)| is_kept(**entity_key))
+            .map(|(entity_key, ref_0_col)| (*entity_key, ref_0_col.clone()))
+            .collect::<hash::HashMap<_, _, S>>();
+
+        let mut ref_1_cols = self
+            .ref_1_cols
+            .iter()
+            .filter(|((entity_key, _), _)| is_kept(*entity_key))
+            .map(|(key, ref_1_col)| (*key, ref_1_col.clone()))
+            .collect::<hash::HashMap<_, _, S>>();
+
+        // taken components are out of the world, so the clone drops their references
+        for ((type_key, row_key), taken_row) in &self.taken {
+            if !is_kept(taken_row.entity_key) {
+                continue;
+            }
+
+            let ref_0_col = ref_0_cols.get_mut(&taken_row.entity_key).unwrap();
+            ref_0_col.remove(taken_row.ref_0_row_key as usize);
+            let ref_1_col = ref_1_cols
+                .get_mut(&(taken_row.entity_key, *type_key))
+                .unwrap();
+            ref_1_col.retain(|r| r != row_key);
+        }
+        ref_0_cols.retain(|_, ref_0_col| !ref_0_col.is_empty());
+        ref_1_cols.retain(|_, ref_1_col| !ref_1_col.is_empty());
+
+        // the rows left vacant in the clone are bumped, as if their components were removed
+        let mut comp_generations = self.comp_generations.clone();
+        for (type_key, comp_col) in &self.comp_cols {
+            for (row_key, entity_key) in (comp_col.iter_owners_fn)(comp_col) {
+                if !is_kept(entity_key) {
+                    comp_generations.bump(*type_key, row_key);
+                }
+            }
+        }
+        for (type_key, row_key) in self.taken.keys() {
+            comp_generations.bump(*type_key, *row_key);
+        }
+
+        Ok(ECS {
+            entities,
+            generations,
+            partitions: self.partitions.clone(),
+            world: self.world,
+            comp_cols,
+            ref_0_cols,
+            ref_1_cols,
+            shared_pools: self.shared_pools.clone(),
+            journal: None,
+            undo: None,
+            allow_duplicate_names: self.allow_duplicate_names,
+            taken: take::TakenRows::with_hasher(self.hasher().clone()),
+            comp_generations,
+            scratch_refs: vec![],
+            scratch_row_keys: vec![],
+            frame: self.frame,
+            tick: self.tick,
+            hook_commands: Default::default(),
+            applying_hooks: false,
+            hook_errors: vec![],
+            lifecycle_events: None,
+            registrations: self.registrations,
+            clearing: None,
+            key_limit: self.key_limit,
+            reserved_count: 0,
+        })
+    }
+
+    /// Insert a new entity with clones of all components of the entity with the corresponding entity key,
+    /// with fresh component keys, in the partition of the entity, and return the entity key of the new entity.
+    /// The components of each type keep their order, and the source entity is left untouched.
+    /// Its flags are not cloned, and taken components, as in `take_comp`, are skipped.
+    /// If the entity corresponding to the entity key is not found, return an `Err(CloneError::EntityNotFound)`.
+    /// If a component to be cloned is of a type registered without clone support, as in `register_cloneable`,
+    /// return an `Err(CloneError::NotCloneable)`.
+    /// If a clone is rejected, as by the insert validator, the new entity is removed and return an `Err(CloneError::Rejected)`.
+    /// Otherwise, return an `Ok(EntityKey)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let clone_key = ecs.clone_entity(entity_key).unwrap();
+    /// assert_eq!(ecs.iter_comp_by_entity::<i32>(clone_key).unwrap().collect::<Vec<_>>(), vec![&42]);
+    /// ```
+    pub fn clone_entity(&mut self, entity_key: EntityKey) -> Result<EntityKey, CloneError> {
+        self.check_entity(entity_key)
+            .map_err(|_| CloneError::EntityNotFound)?;
+
+        let mut type_keys = vec![];
+        for (_, (type_key, _)) in self.ref_0_cols.get(&entity_key).into_iter().flatten() {
+            if !type_keys.contains(type_key) {
+                type_keys.push(*type_key);
+            }
+        }
+
+        // clone every component first, so that nothing is inserted unless all can be cloned
+        let mut comps = vec![];
+        for type_key in type_keys {
+            let comp_col = &self.comp_cols[&type_key];
+            let Some(record_comp_fn) = comp_col.record_comp_fn else {
+                return Err(CloneError::NotCloneable {
+                    type_name: comp_col.type_name,
+                });
+            };
+
+            for row_key in &self.ref_1_cols[&(entity_key, type_key)] {
+                if let Some(comp) = (comp_col.get_comp_fn)(comp_col, *row_key) {
+                    comps.push(record_comp_fn(comp));
+                }
+            }
+        }
+
+        let partition = self.entities[entity_key.index as usize].partition;
+        let clone_key = self.insert_entity_in(partition).unwrap();
+        for comp in comps {
+            if let Err(error) = comp.insert_into(self, clone_key) {
+                self.remove_entity(clone_key).unwrap();
+                return Err(CloneError::Rejected(error));
+            }
+        }
+
+        Ok(clone_key)
+    }
+
+    /// Check the consistency of the internal bookkeeping between the component rows and the reference tables.
+    /// If any inconsistency is found, return an `Err(Inconsistency)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), Inconsistency> {
+        let mut ref_counts = hash::HashMap::<std::any::TypeId, usize>::default();
+
+        for (entity_key, ref_0_col) in &self.ref_0_cols {
+            let entity_key = *entity_key;
+
+            // empty tables hold no references
+            if ref_0_col.is_empty() {
+                continue;
+            }
+
+            if self.check_entity(entity_key).is_err() {
+                return Err(Inconsistency::DeadEntity { entity_key });
+            }
+
+            for (ref_0_row_key, (type_key, row_key)) in ref_0_col {
+                let Some(comp_col) = self.comp_cols.get(type_key) else {
+                    return Err(Inconsistency::UnregisteredType { entity_key });
+                };
+                let dangling = Inconsistency::DanglingRef {
+                    type_name: comp_col.type_name,
+                    entity_key,
+                    row_key: *row_key,
+                };
+
+                let Some(comp_row) = self.get_row_or_taken(*type_key, *row_key) else {
+                    return Err(dangling);
+                };
+
+                if comp_row.entity_key != entity_key
+                    || comp_row.ref_0_row_key as usize != ref_0_row_key
+                {
+                    return Err(dangling);
+                }
+
+                // the row must also be referenced by the reference table of its type, at its entry
+                let referenced =
+                    self.ref_1_cols
+                        .get(&(entity_key, *type_key))
+                        .is_some_and(|ref_1_col| {
+                            ref_1_col.get(comp_row.ref_1_row_key) == Some(*row_key)
+                        });
+
+                if !referenced {
+                    return Err(dangling);
+                }
+
+                // the reserved rows of taken components are not counted by the column
+                if !self.is_taken(*type_key, *row_key) {
+                    *ref_counts.entry(*type_key).or_default() += 1;
+                }
+            }
+        }
+
+        for ((entity_key, type_key), ref_1_col) in &self.ref_1_cols {
+            let entity_key = *entity_key;
+
+            if ref_1_col.is_empty() {
+                continue;
+            }
+
+            if self.check_entity(entity_key).is_err() {
+                return Err(Inconsistency::DeadEntity { entity_key });
+            }
+
+            let Some(comp_col) = self.comp_cols.get(type_key) else {
+                return Err(Inconsistency::UnregisteredType { entity_key });
+            };
+
+            for (ref_1_row_key, row_key) in ref_1_col.iter_keys() {
+                let comp_row = self.get_row_or_taken(*type_key, *row_key);
+
+                // each row is referenced at most once, by the entry it stores, which the references of the first table then cover
+                let valid = comp_row.is_some_and(|comp_row| {
+                    comp_row.entity_key == entity_key && comp_row.ref_1_row_key == ref_1_row_key
+                });
+
+                if !valid {
+                    return Err(Inconsistency::DanglingRef {
+                        type_name: comp_col.type_name,
+                        entity_key,
+                        row_key: *row_key,
+                    });
+                }
+            }
+        }
+
+        // every reference points at a distinct row, so equal counts mean every row is referenced
+        for (type_key, comp_col) in &self.comp_cols {
+            let ref_count = ref_counts.get(type_key).copied().unwrap_or(0);
+
+            if (comp_col.len_fn)(comp_col) != ref_count {
+                return Err(Inconsistency::UnreferencedRows {
+                    type_name: comp_col.type_name,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Insert the value into the slab at the key, which must be vacant.
+/// The slab is rebuilt only if the key is not the next vacant key.
+fn insert_at<T>(slab: &mut slab::Slab<T>, key: usize, value: T) {
+    if slab.vacant_key() == key {
+        slab.insert(value);
+        return;
+    }
+
+    *slab = std::mem::take(slab)
+        .into_iter()
+        .chain(std::iter::once((key, value)))
+        .collect();
+}
+
+/// Convert the index to a key as in `ECS::checked_key`, given the key limit,
+/// so that it can be called while the tables of the world are borrowed.
+pub(crate) fn checked_key_within(key_limit: Option<u32>, index: usize) -> u32 {
+    let max_key = key_limit.unwrap_or(MAX_KEY);
+
+    match u32::try_from(index) {
+        Ok(key) if key <= max_key => key,
+        _ => panic!(
+            "ecs-tiny: out of keys, {} exceeds the maximum key {}",
+            index, max_key
+        ),
     }
 }
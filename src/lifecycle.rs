@@ -0,0 +1,87 @@
+use crate::{EntityKey, WorldHasher, ECS};
+
+/// A transition of an entity between having no components and having some,
+/// as returned by [`ECS::take_entity_lifecycle_events`].
+///
+/// Taken components, as in `take_comp`, still count as components of their entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntityLifecycle {
+    /// The entity had no components, and the first was inserted.
+    FirstComponentAdded(EntityKey),
+    /// The entity had components, and the last was removed, by removing the component, the entity, or its type.
+    LastComponentRemoved(EntityKey),
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Start recording the lifecycle events of the entities, as in `EntityLifecycle`.
+    /// If the events are already enabled, the recorded events are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::EntityLifecycle;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_lifecycle_events();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    /// ecs.remove_entity(entity_key).unwrap();
+    ///
+    /// assert_eq!(
+    ///     ecs.take_entity_lifecycle_events(),
+    ///     vec![
+    ///         EntityLifecycle::FirstComponentAdded(entity_key),
+    ///         EntityLifecycle::LastComponentRemoved(entity_key),
+    ///     ]
+    /// );
+    /// ```
+    pub fn enable_lifecycle_events(&mut self) {
+        self.lifecycle_events.get_or_insert_with(Vec::new);
+    }
+
+    /// Take the lifecycle events recorded so far, in the order they occurred, leaving the events empty but enabled.
+    /// If the events are not enabled, return an empty vector.
+    pub fn take_entity_lifecycle_events(&mut self) -> Vec<EntityLifecycle> {
+        self.lifecycle_events
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Record a `FirstComponentAdded` if the component just inserted is the only one of the entity.
+    pub(crate) fn note_comp_added(&mut self, entity_key: EntityKey) {
+        if let Some(events) = &mut self.lifecycle_events {
+            if self.ref_0_cols.get(&entity_key).map(|c| c.len()) == Some(1) {
+                events.push(EntityLifecycle::FirstComponentAdded(entity_key));
+            }
+        }
+    }
+
+    /// Record a `LastComponentRemoved` if the component just removed was the last one of the entity.
+    pub(crate) fn note_comp_removed(&mut self, entity_key: EntityKey) {
+        if let Some(events) = &mut self.lifecycle_events {
+            if self
+                .ref_0_cols
+                .get(&entity_key)
+                .is_none_or(|c| c.is_empty())
+            {
+                events.push(EntityLifecycle::LastComponentRemoved(entity_key));
+            }
+        }
+    }
+
+    /// Record a `LastComponentRemoved` for each of the entities, which lost all their components at once,
+    /// in the order of the entity keys.
+    pub(crate) fn note_comps_cleared(&mut self, mut entity_keys: Vec<EntityKey>) {
+        if let Some(events) = &mut self.lifecycle_events {
+            entity_keys.sort_unstable();
+            events.extend(
+                entity_keys
+                    .into_iter()
+                    .map(EntityLifecycle::LastComponentRemoved),
+            );
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use crate::take::{TakenRow, TakenRows};
+use crate::{
+    Column, ColumnMut, CompRow, EntityKey, Error, Rows, WorldHasher, WorldId, ALLOC_SIZE, ECS,
+};
+
+type CompRows = stack_any::StackAny<ALLOC_SIZE>;
+
+/// A view of the world with a lock per column, returned by [`ECS::lock`],
+/// so that the columns can be read and written through shared references, as from several threads at once.
+///
+/// The rows of each column are moved behind a read-write lock while the view is alive, and moved back when it is dropped.
+/// Structural changes, as inserting or removing entities and components or registering types, still need the world itself,
+/// which the view borrows mutably.
+///
+/// # Deadlocks
+///
+/// A thread blocking on a lock while holding another one can deadlock with a thread doing the opposite.
+/// Each column has a lock order, given by `lock_order`, which follows the type names of the component types.
+/// A thread holding locks must acquire any further blocking lock in increasing lock order,
+/// or use `try_read_column` and `try_write_column`, which fail with an `Error::ColumnLocked` instead of blocking.
+/// `write_column_and` acquires its pair of locks in lock order on its own.
+///
+/// # Examples
+///
+/// ```
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let entity_key = ecs.insert_entity();
+/// ecs.register::<i32>().unwrap();
+/// ecs.register::<u32>().unwrap();
+/// ecs.insert_comp(entity_key, 42).unwrap();
+/// ecs.insert_comp(entity_key, 1u32).unwrap();
+///
+/// let locked = ecs.lock();
+/// std::thread::scope(|scope| {
+///     scope.spawn(|| {
+///         let mut ints = locked.write_column::<i32>().unwrap();
+///         ints.column_mut().iter_rows_mut().for_each(|(_, _, comp)| *comp += 1);
+///     });
+///     scope.spawn(|| {
+///         let uints = locked.read_column::<u32>().unwrap();
+///         assert_eq!(uints.column().len(), 1);
+///     });
+/// });
+/// drop(locked);
+///
+/// assert_eq!(ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(), vec![&43]);
+/// ```
+pub struct LockedECS<'a, S = crate::DefaultHashBuilder> {
+    columns: crate::hash::HashMap<std::any::TypeId, LockedColumn<'a>>,
+    ref_1_cols: &'a crate::hash::HashMap<(EntityKey, std::any::TypeId), crate::refs::RefList, S>,
+    taken: &'a TakenRows<S>,
+    world: WorldId,
+    tick: u64,
+}
+
+struct LockedColumn<'a> {
+    rows: std::sync::RwLock<CompRows>,
+    /// The place of the rows in the world, holding a placeholder until the view is dropped.
+    slot: &'a mut CompRows,
+    generations: &'a [u32],
+    order: usize,
+    immutable: bool,
+    #[cfg(feature = "profile")]
+    access: &'a crate::profile::AccessCounters,
+}
+
+impl LockedColumn<'_> {
+    #[inline]
+    fn count_access(&self, write: bool) {
+        #[cfg(feature = "profile")]
+        self.access.count(write);
+        #[cfg(not(feature = "profile"))]
+        let _ = write;
+    }
+}
+
+impl<S> Drop for LockedECS<'_, S> {
+    fn drop(&mut self) {
+        for column in self.columns.values_mut() {
+            let rows = column
+                .rows
+                .get_mut()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            std::mem::swap(column.slot, rows);
+        }
+    }
+}
+
+impl<'a, S: WorldHasher> LockedECS<'a, S> {
+    /// Return the lock order of the column of the component type, as in `LockedECS`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(usize)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// let locked = ecs.lock();
+    ///
+    /// assert_eq!(locked.lock_order::<u32>(), Err(ecs_tiny::Error::TypeNotRegistered));
+    /// ```
+    pub fn lock_order<T>(&self) -> Result<usize, Error>
+    where
+        T: std::any::Any,
+    {
+        let column = self
+            .columns
+            .get(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+        Ok(column.order)
+    }
+
+    /// Lock the column of the component type for reading, blocking until no writer holds it.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(ColumnReadGuard<T>)`.
+    pub fn read_column<T>(&self) -> Result<ColumnReadGuard<'_, T>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let column = self.get_column::<T>()?;
+        column.count_access(false);
+
+        let rows = column
+            .rows
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        Ok(ColumnReadGuard::new(rows, column.generations, self.world))
+    }
+
+    /// Lock the column of the component type for writing, blocking until no other guard holds it.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(ColumnWriteGuard<T>)`.
+    pub fn write_column<T>(&self) -> Result<ColumnWriteGuard<'_, T>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let column = self.get_column_mut::<T>()?;
+        column.count_access(true);
+
+        let rows = column
+            .rows
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        Ok(ColumnWriteGuard::new(
+            rows,
+            column.generations,
+            self.ref_1_cols,
+            self.world,
+            self.tick,
+        ))
+    }
+
+    /// Lock the column of the component type for reading, as in `read_column`, without blocking.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If a writer holds the column, return an `Err(Error::ColumnLocked)`.
+    /// Otherwise, return an `Ok(ColumnReadGuard<T>)`.
+    pub fn try_read_column<T>(&self) -> Result<ColumnReadGuard<'_, T>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let column = self.get_column::<T>()?;
+        column.count_access(false);
+
+        let rows = match column.rows.try_read() {
+            Ok(rows) => rows,
+            Err(std::sync::TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(std::sync::TryLockError::WouldBlock) => return Err(Error::ColumnLocked),
+        };
+
+        Ok(ColumnReadGuard::new(rows, column.generations, self.world))
+    }
+
+    /// Lock the column of the component type for writing, as in `write_column`, without blocking.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If another guard holds the column, return an `Err(Error::ColumnLocked)`.
+    /// Otherwise, return an `Ok(ColumnWriteGuard<T>)`.
+    pub fn try_write_column<T>(&self) -> Result<ColumnWriteGuard<'_, T>, Error>
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let column = self.get_column_mut::<T>()?;
+        column.count_access(true);
+
+        let rows = match column.rows.try_write() {
+            Ok(rows) => rows,
+            Err(std::sync::TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(std::sync::TryLockError::WouldBlock) => return Err(Error::ColumnLocked),
+        };
+
+        Ok(ColumnWriteGuard::new(
+            rows,
+            column.generations,
+            self.ref_1_cols,
+            self.world,
+            self.tick,
+        ))
+    }
+
+    /// Lock the column of the first component type for writing and the column of the second one for reading,
+    /// acquiring both locks in lock order, as in `LockedECS`, and blocking until both are free.
+    /// If either component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the first component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok((ColumnWriteGuard<A>, ColumnReadGuard<B>))`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both component types are the same.
+    pub fn write_column_and<A, B>(
+        &self,
+    ) -> Result<(ColumnWriteGuard<'_, A>, ColumnReadGuard<'_, B>), Error>
+    where
+        A: std::any::Any + Send + Sync,
+        B: std::any::Any + Send + Sync,
+    {
+        assert!(
+            std::any::TypeId::of::<A>() != std::any::TypeId::of::<B>(),
+            "a column cannot be locked for writing and reading at once"
+        );
+
+        let order0 = self.get_column_mut::<A>()?.order;
+        let order1 = self.get_column::<B>()?.order;
+
+        if order0 < order1 {
+            let guard0 = self.write_column::<A>()?;
+            let guard1 = self.read_column::<B>()?;
+            Ok((guard0, guard1))
+        } else {
+            let guard1 = self.read_column::<B>()?;
+            let guard0 = self.write_column::<A>()?;
+            Ok((guard0, guard1))
+        }
+    }
+
+    /// Return an iterator over the row keys of all components with the corresponding entity key and type,
+    /// as in `ECS::rows_by_entity`, without locking the column.
+    /// The iterator is empty if the entity is not found or has no components of the type.
+    pub fn rows_by_entity<T>(&self, entity_key: EntityKey) -> impl Iterator<Item = u32> + 'a
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+        let taken = self.taken;
+
+        self.ref_1_cols
+            .get(&(entity_key, type_key))
+            .into_iter()
+            .flat_map(|ref_1_col| ref_1_col.iter().copied())
+            .filter(move |row_key| {
+                taken
+                    .get(&(type_key, *row_key))
+                    .is_none_or(TakenRow::is_live)
+            })
+    }
+
+    fn get_column<T>(&self) -> Result<&LockedColumn<'a>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.columns
+            .get(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)
+    }
+
+    fn get_column_mut<T>(&self) -> Result<&LockedColumn<'a>, Error>
+    where
+        T: std::any::Any,
+    {
+        let column = self.get_column::<T>()?;
+
+        if column.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        Ok(column)
+    }
+}
+
+/// A read lock of the column of a component type, returned by [`LockedECS::read_column`].
+pub struct ColumnReadGuard<'a, T> {
+    rows: std::sync::RwLockReadGuard<'a, CompRows>,
+    generations: &'a [u32],
+    world: WorldId,
+    phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<'a, T> ColumnReadGuard<'a, T>
+where
+    T: std::any::Any,
+{
+    fn new(
+        rows: std::sync::RwLockReadGuard<'a, CompRows>,
+        generations: &'a [u32],
+        world: WorldId,
+    ) -> Self {
+        Self {
+            rows,
+            generations,
+            world,
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Return a read-only handle of the locked column.
+    pub fn column(&self) -> Column<'_, T> {
+        let comp_rows = self.rows.downcast_ref::<Rows<CompRow<T>>>().unwrap();
+        Column::new(comp_rows, self.generations, self.world)
+    }
+}
+
+/// A write lock of the column of a component type, returned by [`LockedECS::write_column`].
+pub struct ColumnWriteGuard<'a, T> {
+    rows: std::sync::RwLockWriteGuard<'a, CompRows>,
+    generations: &'a [u32],
+    ref_1_cols: &'a (dyn crate::refs::RefTables + Sync),
+    world: WorldId,
+    tick: u64,
+    phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<'a, T> ColumnWriteGuard<'a, T>
+where
+    T: std::any::Any,
+{
+    fn new(
+        rows: std::sync::RwLockWriteGuard<'a, CompRows>,
+        generations: &'a [u32],
+        ref_1_cols: &'a (dyn crate::refs::RefTables + Sync),
+        world: WorldId,
+        tick: u64,
+    ) -> Self {
+        Self {
+            rows,
+            generations,
+            ref_1_cols,
+            world,
+            tick,
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Return a read-only handle of the locked column.
+    pub fn column(&self) -> Column<'_, T> {
+        let comp_rows = self.rows.downcast_ref::<Rows<CompRow<T>>>().unwrap();
+        Column::new(comp_rows, self.generations, self.world)
+    }
+
+    /// Return a mutable handle of the locked column.
+    pub fn column_mut(&mut self) -> ColumnMut<'_, T> {
+        let comp_rows = self.rows.downcast_mut::<Rows<CompRow<T>>>().unwrap();
+        ColumnMut::new(
+            comp_rows,
+            self.generations,
+            self.ref_1_cols,
+            self.world,
+            self.tick,
+        )
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return a view of the world with a lock per column, so that the columns can be read and written
+    /// through shared references, as in [`LockedECS`].
+    /// The rows are moved back into the world when the view is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let locked = ecs.lock();
+    /// let row_key = locked.rows_by_entity::<i32>(entity_key).next().unwrap();
+    /// *locked.write_column::<i32>().unwrap().column_mut().get_mut(row_key).unwrap() += 1;
+    /// drop(locked);
+    ///
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(), vec![&43]);
+    /// ```
+    pub fn lock(&mut self) -> LockedECS<'_, S> {
+        let mut comp_cols = self.comp_cols.iter_mut().collect::<Vec<_>>();
+        comp_cols.sort_unstable_by_key(|(type_key, comp_col)| (comp_col.type_name, **type_key));
+
+        let comp_generations = &self.comp_generations;
+        let columns = comp_cols
+            .into_iter()
+            .enumerate()
+            .map(|(order, (type_key, comp_col))| {
+                let placeholder = stack_any::StackAny::try_new(()).unwrap();
+                let rows = std::mem::replace(&mut comp_col.comp_rows, placeholder);
+                let column = LockedColumn {
+                    rows: std::sync::RwLock::new(rows),
+                    slot: &mut comp_col.comp_rows,
+                    generations: comp_generations.of(*type_key),
+                    order,
+                    immutable: comp_col.immutable,
+                    #[cfg(feature = "profile")]
+                    access: &comp_col.access,
+                };
+                (*type_key, column)
+            })
+            .collect();
+
+        LockedECS {
+            columns,
+            ref_1_cols: &self.ref_1_cols,
+            taken: &self.taken,
+            world: self.world,
+            tick: self.tick,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{CompRow, EntityKey, EntityStatus, Error, Rows, WorldHasher, ECS};
+
+/// The result of looking up the component of an entity, passed to the closure of [`ECS::map_entities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityLookup<R> {
+    /// The first component of the type of the entity.
+    Found(R),
+    /// The entity is alive, but has no component of the type, or only taken ones, as in `take_comp`.
+    NoComp,
+    /// The entity is not found, with the reason given by the entity status.
+    EntityNotFound(EntityStatus),
+}
+
+impl<R> EntityLookup<R> {
+    /// Map the component, if found, leaving a miss as is.
+    pub fn map<S>(self, f: impl FnOnce(R) -> S) -> EntityLookup<S> {
+        match self {
+            EntityLookup::Found(comp) => EntityLookup::Found(f(comp)),
+            EntityLookup::NoComp => EntityLookup::NoComp,
+            EntityLookup::EntityNotFound(status) => EntityLookup::EntityNotFound(status),
+        }
+    }
+
+    /// Return the component, if found.
+    pub fn found(self) -> Option<R> {
+        match self {
+            EntityLookup::Found(comp) => Some(comp),
+            _ => None,
+        }
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Call the closure with each entity key of the slice and the first component of the type of the entity,
+    /// as in `iter_comp_by_entity`, in the order of the slice, and return the results in the same order.
+    /// The column is fetched once for all entities, and an entity without the component is passed
+    /// an `EntityLookup::NoComp` if alive, or an `EntityLookup::EntityNotFound` otherwise.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(Vec<U>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::EntityLookup;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key1, 42).unwrap();
+    ///
+    /// let comps = ecs
+    ///     .map_entities::<i32, _>(&[entity_key1, entity_key0], |_, comp| comp.found().copied())
+    ///     .unwrap();
+    /// assert_eq!(comps, vec![Some(42), None]);
+    /// ```
+    pub fn map_entities<T, U>(
+        &self,
+        entity_keys: &[EntityKey],
+        mut f: impl FnMut(EntityKey, EntityLookup<&T>) -> U,
+    ) -> Result<Vec<U>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        let mapped = entity_keys
+            .iter()
+            .map(|entity_key| {
+                let lookup = match self.first_row(*entity_key, type_key, |row_key| {
+                    comp_rows.contains(row_key as usize)
+                }) {
+                    Ok(Some(row_key)) => EntityLookup::Found(&comp_rows[row_key as usize].comp),
+                    Ok(None) => EntityLookup::NoComp,
+                    Err(status) => EntityLookup::EntityNotFound(status),
+                };
+                f(*entity_key, lookup)
+            })
+            .collect();
+
+        Ok(mapped)
+    }
+
+    /// Call the closure with each entity key of the slice and the first mutable component of the type of the entity,
+    /// as in `map_entities`, and return the results in the order of the slice.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(Vec<U>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// ecs.map_entities_mut::<i32, _>(&[entity_key], |_, comp| {
+    ///     if let ecs_tiny::EntityLookup::Found(comp) = comp {
+    ///         *comp += 1;
+    ///     }
+    /// })
+    /// .unwrap();
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+    /// ```
+    pub fn map_entities_mut<T, U>(
+        &mut self,
+        entity_keys: &[EntityKey],
+        mut f: impl FnMut(EntityKey, EntityLookup<&mut T>) -> U,
+    ) -> Result<Vec<U>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        // the rows are looked up first, since the entities cannot be checked while the column is borrowed mutably
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+        let rows = entity_keys
+            .iter()
+            .map(|entity_key| {
+                self.first_row(*entity_key, type_key, |row_key| {
+                    comp_rows.contains(row_key as usize)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let tick = self.tick;
+        let comp_col = self.comp_cols.get_mut(&type_key).unwrap();
+        comp_col.count_access(true);
+
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        let mapped = entity_keys
+            .iter()
+            .zip(rows)
+            .map(|(entity_key, row)| {
+                let lookup = match row {
+                    Ok(Some(row_key)) => {
+                        EntityLookup::Found(comp_rows[row_key as usize].comp_mut(tick))
+                    }
+                    Ok(None) => EntityLookup::NoComp,
+                    Err(status) => EntityLookup::EntityNotFound(status),
+                };
+                f(*entity_key, lookup)
+            })
+            .collect();
+
+        Ok(mapped)
+    }
+
+    /// Return the row key of the first component of the type of the entity whose row is present.
+    /// If the entity is not alive, return the entity status.
+    fn first_row(
+        &self,
+        entity_key: EntityKey,
+        type_key: std::any::TypeId,
+        present: impl Fn(u32) -> bool,
+    ) -> Result<Option<u32>, EntityStatus> {
+        match self.entity_status(entity_key) {
+            EntityStatus::Alive => {}
+            status => return Err(status),
+        }
+
+        let row_key = self
+            .ref_1_cols
+            .get(&(entity_key, type_key))
+            .into_iter()
+            .flatten()
+            .copied()
+            .find(|row_key| present(*row_key));
+
+        Ok(row_key)
+    }
+}
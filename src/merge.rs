@@ -0,0 +1,157 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, ECS};
+
+/// The keys given to the entities and components moved by [`ECS::merge`], by their keys in the merged world.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// The new entity keys, by the entity keys in the merged world.
+    pub entities: std::collections::HashMap<EntityKey, EntityKey>,
+    /// The new component keys, by the component keys in the merged world.
+    pub comps: std::collections::HashMap<CompKey, CompKey>,
+}
+
+/// An error returned by [`ECS::merge`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// Components of the merged world are of types not registered in the world, so nothing is moved.
+    TypesNotRegistered {
+        /// The type ids and names of the component types, ordered by name.
+        types: Vec<(std::any::TypeId, &'static str)>,
+    },
+    /// A component was rejected on insertion, as by the insert validator, so the merge stopped at its entity.
+    Rejected {
+        /// The entity key of the entity in the merged world.
+        entity_key: EntityKey,
+        /// The error of the insertion.
+        error: Error,
+        /// The keys of the entities and components moved before the entity, which stay in the world.
+        report: Box<MergeReport>,
+    },
+}
+
+impl std::fmt::Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::TypesNotRegistered { types } => {
+                let type_names = types
+                    .iter()
+                    .map(|(_, type_name)| *type_name)
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "component types not registered: {}",
+                    type_names.join(", ")
+                )
+            }
+            MergeError::Rejected { error, .. } => {
+                write!(f, "merged component rejected: {}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Move every entity of the other world into the world, together with all its components,
+    /// in the order of the entities in the other world, and return the new keys given to them.
+    /// The entities are inserted into the default partition as by `insert_entity`, without their flags,
+    /// and their components are inserted as by `insert_comp`, keeping the order of each type, as in `take_entity`.
+    /// Taken components, as in `take_comp`, and reserved entities, as in `reserve_entity`, are dropped.
+    /// If any component of the other world is of a type not registered in the world,
+    /// nothing is moved and return an `Err(MergeError::TypesNotRegistered)` listing the types.
+    /// If a component is rejected, the new entity is removed, the merge stops,
+    /// and return an `Err(MergeError::Rejected)` with the keys of the entities moved so far.
+    /// Otherwise, return an `Ok(MergeReport)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut chunk = ecs_tiny::ECS::new();
+    /// chunk.register::<i32>().unwrap();
+    /// let entity_key = chunk.insert_entity();
+    /// let comp_key = chunk.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// let report = ecs.merge(chunk).unwrap();
+    ///
+    /// let comp_key = report.comps[&comp_key];
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+    /// assert_eq!(ecs.get_entity_by_comp(comp_key), Ok(report.entities[&entity_key]));
+    /// ```
+    pub fn merge(&mut self, mut other: ECS<S>) -> Result<MergeReport, MergeError> {
+        let mut types = other
+            .comp_cols
+            .iter()
+            .filter(|(type_key, comp_col)| {
+                !self.comp_cols.contains_key(type_key) && (comp_col.len_fn)(comp_col) > 0
+            })
+            .map(|(type_key, comp_col)| (*type_key, comp_col.type_name))
+            .collect::<Vec<_>>();
+        if !types.is_empty() {
+            types.sort_by_key(|(_, type_name)| *type_name);
+            return Err(MergeError::TypesNotRegistered { types });
+        }
+
+        let entity_keys = other
+            .iter_entity_slots()
+            .filter(|(_, slot)| !slot.is_reserved())
+            .map(|(entity_key, _)| entity_key)
+            .collect::<Vec<_>>();
+
+        let mut report = MergeReport::default();
+        for old_entity_key in entity_keys {
+            let old_comp_keys = other.part_comp_keys(old_entity_key);
+            let parts = other.take_entity(old_entity_key).unwrap();
+
+            let entity_key = self.insert_entity();
+            let mut comps = Vec::with_capacity(parts.len());
+            for (old_comp_key, (type_key, comp)) in old_comp_keys.into_iter().zip(parts) {
+                // a hook may have unregistered the type in the meantime
+                let inserted = match self.comp_cols.get(&type_key) {
+                    Some(comp_col) => (comp_col.insert_boxed_fn)(self, entity_key, comp),
+                    None => Err(Error::TypeNotRegistered),
+                };
+
+                match inserted {
+                    Ok(comp_key) => comps.push((old_comp_key, comp_key)),
+                    Err(error) => {
+                        self.remove_entity(entity_key).ok();
+                        return Err(MergeError::Rejected {
+                            entity_key: old_entity_key,
+                            error,
+                            report: Box::new(report),
+                        });
+                    }
+                }
+            }
+
+            report.entities.insert(old_entity_key, entity_key);
+            report.comps.extend(comps);
+        }
+
+        Ok(report)
+    }
+
+    /// Return the component keys of the components returned by `take_entity`, in the same order.
+    fn part_comp_keys(&self, entity_key: EntityKey) -> Vec<CompKey> {
+        let mut type_keys = self
+            .ref_0_cols
+            .get(&entity_key)
+            .into_iter()
+            .flat_map(|ref_0_col| ref_0_col.iter().map(|(_, (type_key, _))| *type_key))
+            .collect::<Vec<_>>();
+        type_keys.sort_unstable();
+        type_keys.dedup();
+
+        type_keys
+            .into_iter()
+            .flat_map(|type_key| {
+                self.ref_1_cols[&(entity_key, type_key)]
+                    .iter()
+                    .filter(move |row_key| !self.is_taken(type_key, **row_key))
+                    .map(move |row_key| self.comp_key(type_key, *row_key))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{CompColumn, CompKey, EntityKey, Error, WorldHasher, ECS};
+
+type ObserverFn = std::sync::Arc<dyn Fn(EntityKey, CompKey, &dyn std::any::Any) + Send + Sync>;
+
+/// A handle of an observer set by [`ECS::on_insert`] or [`ECS::on_remove`], to remove it by [`ECS::remove_observer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverHandle {
+    type_key: std::any::TypeId,
+    id: u64,
+}
+
+/// The observers of a column, each in the order it was set.
+#[derive(Clone, Default)]
+pub(crate) struct Observers {
+    insert: Vec<(u64, ObserverFn)>,
+    remove: Vec<(u64, ObserverFn)>,
+}
+
+impl<S> CompColumn<S> {
+    /// Return whether removals of the type are observed, as in `on_remove`.
+    pub(crate) fn has_remove_observers(&self) -> bool {
+        !self.observers.remove.is_empty()
+    }
+
+    /// Call the insert observers with the component of the row, after it is inserted.
+    pub(crate) fn notify_inserted(&self, entity_key: EntityKey, comp_key: CompKey) {
+        if self.observers.insert.is_empty() {
+            return;
+        }
+
+        let comp = (self.get_comp_fn)(self, comp_key.row_key).unwrap();
+        for (_, f) in &self.observers.insert {
+            f(entity_key, comp_key, comp);
+        }
+    }
+
+    /// Call the remove observers with the component, before it is removed.
+    pub(crate) fn notify_removed(
+        &self,
+        entity_key: EntityKey,
+        comp_key: CompKey,
+        comp: &dyn std::any::Any,
+    ) {
+        for (_, f) in &self.observers.remove {
+            f(entity_key, comp_key, comp);
+        }
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Add an observer of the component type, called after each insertion of a component of the type
+    /// with the entity key, the component key and the inserted component,
+    /// as by `insert_comp`, the other insertion methods, `merge`, or a replay or an undo restoring it.
+    /// Several observers can be added to a type, and are called in the order they were added.
+    /// Unlike the insert hook, as in `set_insert_hook`, an observer runs while the world is being modified,
+    /// so it receives the inserted component alone and cannot access the world;
+    /// the changes an observer calls for are to be collected, as into a command buffer, and applied afterwards.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(ObserverHandle)`, to remove the observer by `remove_observer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let inserted = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.on_insert::<i32>({
+    ///     let inserted = inserted.clone();
+    ///     move |_, comp_key, comp| inserted.lock().unwrap().push((comp_key, *comp))
+    /// }).unwrap();
+    ///
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// assert_eq!(*inserted.lock().unwrap(), vec![(comp_key, 42)]);
+    /// ```
+    pub fn on_insert<T>(
+        &mut self,
+        f: impl Fn(EntityKey, CompKey, &T) + Send + Sync + 'static,
+    ) -> Result<ObserverHandle, Error>
+    where
+        T: std::any::Any,
+    {
+        let (handle, f) = self.observer::<T>(f)?;
+        let comp_col = self.comp_cols.get_mut(&handle.type_key).unwrap();
+        comp_col.observers.insert.push((handle.id, f));
+
+        Ok(handle)
+    }
+
+    /// Add an observer of the component type, called before each removal of a component of the type
+    /// with the entity key, the component key and the component about to be removed,
+    /// as by `remove_comp`, the cascade of `remove_entity`, `clear_comp`, `clear`, or unregistering the type.
+    /// Taken components, as in `take_comp`, are removed without calling the observers, since their values are elsewhere,
+    /// and moving a component to another entity, as in `move_comp`, is not a removal.
+    /// The observers are called in the order they were added, and cannot access the world, as in `on_insert`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(ObserverHandle)`, to remove the observer by `remove_observer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let removed = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.on_remove::<i32>({
+    ///     let removed = removed.clone();
+    ///     move |entity_key, _, comp| removed.lock().unwrap().push((entity_key, *comp))
+    /// }).unwrap();
+    ///
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.remove_entity(entity_key).unwrap();
+    /// assert_eq!(*removed.lock().unwrap(), vec![(entity_key, 42)]);
+    /// ```
+    pub fn on_remove<T>(
+        &mut self,
+        f: impl Fn(EntityKey, CompKey, &T) + Send + Sync + 'static,
+    ) -> Result<ObserverHandle, Error>
+    where
+        T: std::any::Any,
+    {
+        let (handle, f) = self.observer::<T>(f)?;
+        let comp_col = self.comp_cols.get_mut(&handle.type_key).unwrap();
+        comp_col.observers.remove.push((handle.id, f));
+
+        Ok(handle)
+    }
+
+    /// Remove the observer of the handle, as returned by `on_insert` or `on_remove`.
+    /// If the observer is not found, as when it was already removed or the type was unregistered, return an `Err(Error::ObserverNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// let handle = ecs.on_insert::<i32>(|_, _, _| unreachable!()).unwrap();
+    ///
+    /// ecs.remove_observer(handle).unwrap();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// assert_eq!(ecs.remove_observer(handle), Err(ecs_tiny::Error::ObserverNotFound));
+    /// ```
+    pub fn remove_observer(&mut self, handle: ObserverHandle) -> Result<(), Error> {
+        let observers = &mut self
+            .comp_cols
+            .get_mut(&handle.type_key)
+            .ok_or(Error::ObserverNotFound)?
+            .observers;
+        for observers in [&mut observers.insert, &mut observers.remove] {
+            if let Some(index) = observers.iter().position(|(id, _)| *id == handle.id) {
+                observers.remove(index);
+                return Ok(());
+            }
+        }
+
+        Err(Error::ObserverNotFound)
+    }
+
+    fn observer<T>(
+        &self,
+        f: impl Fn(EntityKey, CompKey, &T) + Send + Sync + 'static,
+    ) -> Result<(ObserverHandle, ObserverFn), Error>
+    where
+        T: std::any::Any,
+    {
+        static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+        let type_key = std::any::TypeId::of::<T>();
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
+        }
+
+        let handle = ObserverHandle {
+            type_key,
+            id: NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        };
+        let f: ObserverFn = std::sync::Arc::new(move |entity_key, comp_key, comp| {
+            f(entity_key, comp_key, comp.downcast_ref::<T>().unwrap())
+        });
+
+        Ok((handle, f))
+    }
+
+    /// Call the remove observers of the component type with all its components, before the column is dropped.
+    pub(crate) fn notify_column_removed(&self, type_key: std::any::TypeId) {
+        let comp_col = &self.comp_cols[&type_key];
+        if !comp_col.has_remove_observers() {
+            return;
+        }
+
+        for (row_key, entity_key) in (comp_col.iter_owners_fn)(comp_col) {
+            let comp = (comp_col.get_comp_fn)(comp_col, row_key).unwrap();
+            comp_col.notify_removed(entity_key, self.comp_key(type_key, row_key), comp);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use rayon::prelude::*;
+
+use crate::{CompRow, EntityKey, Error, Rows, WorldHasher, ECS};
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return a parallel iterator of rayon over all components of the corresponding type,
+    /// in the same order as `iter_comp` when collected.
+    /// Taken components, as in `take_comp`, are skipped.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl IndexedParallelIterator<Item = &T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.par_iter_comp::<i32>().unwrap().sum::<i32>(), 105);
+    /// ```
+    pub fn par_iter_comp<T>(&self) -> Result<impl IndexedParallelIterator<Item = &T>, Error>
+    where
+        T: std::any::Any + Sync,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        // the rows are gathered first, as in `par_map_comp`
+        let comps = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(|(_, comp_row)| &comp_row.comp)
+            .collect::<Vec<_>>();
+
+        Ok(comps.into_par_iter())
+    }
+
+    /// Return a mutable parallel iterator of rayon over all components of the corresponding type,
+    /// in the same order as `iter_comp_mut` when collected.
+    /// Each component is yielded once, so the mutable references are disjoint.
+    /// Taken components, as in `take_comp`, are skipped.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(impl IndexedParallelIterator<Item = &mut T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rayon::prelude::*;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// ecs.par_iter_comp_mut::<i32>().unwrap().for_each(|comp| *comp += 1);
+    /// assert_eq!(ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(), vec![&43, &64]);
+    /// ```
+    pub fn par_iter_comp_mut<T>(
+        &mut self,
+    ) -> Result<impl IndexedParallelIterator<Item = &mut T>, Error>
+    where
+        T: std::any::Any + Send,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let comps = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter_mut()
+            .map(|(_, comp_row)| comp_row.comp_mut(self.tick))
+            .collect::<Vec<_>>();
+
+        Ok(comps.into_par_iter())
+    }
+
+    /// Map all components of the corresponding type, together with their owning entity keys, on the threads of rayon,
+    /// and return the results in the order of `iter_comp`.
+    /// Taken components, as in `take_comp`, are skipped.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(Vec<U>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 42).unwrap();
+    /// ecs.insert_comp(entity_key1, 63).unwrap();
+    ///
+    /// let mapped = ecs.par_map_comp::<i32, _>(|entity_key, comp| (entity_key, *comp * 2)).unwrap();
+    /// assert_eq!(mapped, vec![(entity_key0, 84), (entity_key1, 126)]);
+    /// ```
+    pub fn par_map_comp<T, U>(
+        &self,
+        f: impl Fn(EntityKey, &T) -> U + Sync + Send,
+    ) -> Result<Vec<U>, Error>
+    where
+        T: std::any::Any + Sync,
+        U: Send,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(false);
+
+        // the rows are gathered first, since the vacant slots keep the column from being split evenly
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(|(_, comp_row)| (comp_row.entity_key, &comp_row.comp))
+            .collect::<Vec<_>>();
+
+        let mapped = comp_rows
+            .into_par_iter()
+            .map(|(entity_key, comp)| f(entity_key, comp))
+            .collect();
+
+        Ok(mapped)
+    }
+}
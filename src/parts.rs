@@ -0,0 +1,83 @@
+use crate::{EntityKey, Error, WorldHasher, ECS};
+
+/// The components of an entity, boxed with their type ids, as returned by [`ECS::take_entity`].
+pub type EntityParts = Vec<(std::any::TypeId, Box<dyn std::any::Any>)>;
+
+impl<S: WorldHasher> ECS<S> {
+    /// Remove an entity with the corresponding entity key, as in `remove_entity`,
+    /// and return its components boxed with their type ids, instead of dropping them.
+    /// The components are grouped by type, and those of each type are in the order of `iter_comp_by_entity`,
+    /// so that `insert_entity_from_parts` restores the order.
+    /// Taken components, as in `take_comp`, are removed for good but not returned.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// Otherwise, return an `Ok(EntityParts)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let parts = ecs.take_entity(entity_key).unwrap();
+    /// assert!(ecs.get_entity(entity_key).is_err());
+    /// assert_eq!(parts[0].1.downcast_ref::<i32>(), Some(&42));
+    /// ```
+    pub fn take_entity(&mut self, entity_key: EntityKey) -> Result<EntityParts, Error> {
+        let mut parts = vec![];
+        self.remove_entity_collecting(entity_key, None, Some(&mut parts))?;
+        Ok(parts)
+    }
+
+    /// Insert a new entity with the components, as returned by `take_entity` from this or another world,
+    /// and return the entity key of the new entity.
+    /// The components are inserted as by `insert_comp`, in the given order.
+    /// If any component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If any component is not of its type id, return an `Err(Error::TypeMismatch)`.
+    /// If a component is rejected, the new entity is removed, and return the error of `insert_comp`.
+    /// Otherwise, return an `Ok(EntityKey)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs0 = ecs_tiny::ECS::new();
+    /// let entity_key = ecs0.insert_entity();
+    /// ecs0.register::<i32>().unwrap();
+    /// ecs0.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let mut ecs1 = ecs_tiny::ECS::new();
+    /// ecs1.register::<i32>().unwrap();
+    /// let parts = ecs0.take_entity(entity_key).unwrap();
+    /// let entity_key = ecs1.insert_entity_from_parts(parts).unwrap();
+    ///
+    /// let comps = ecs1.iter_comp_by_entity::<i32>(entity_key).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![&42]);
+    /// ```
+    pub fn insert_entity_from_parts(&mut self, parts: EntityParts) -> Result<EntityKey, Error> {
+        let mut insert_fns = Vec::with_capacity(parts.len());
+        for (type_key, comp) in &parts {
+            let comp_col = self
+                .comp_cols
+                .get(type_key)
+                .ok_or(Error::TypeNotRegistered)?;
+
+            if comp.as_ref().type_id() != *type_key {
+                return Err(Error::TypeMismatch);
+            }
+
+            insert_fns.push(comp_col.insert_boxed_fn);
+        }
+
+        let entity_key = self.insert_entity();
+        for (insert_fn, (_, comp)) in insert_fns.into_iter().zip(parts) {
+            if let Err(error) = insert_fn(self, entity_key, comp) {
+                self.remove_entity(entity_key).unwrap();
+                return Err(error);
+            }
+        }
+
+        Ok(entity_key)
+    }
+}
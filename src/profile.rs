@@ -0,0 +1,108 @@
+use crate::{WorldHasher, ECS};
+
+/// The numbers of accesses to the components of a type, returned by [`ECS::access_stats`].
+///
+/// Reads count the lookups and iterator constructions by shared reference,
+/// and writes count the lookups and iterator constructions by mutable reference, insertions and removals.
+/// An iterator or a query counts once, however many components it yields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccessCounts {
+    /// The number of reads.
+    pub reads: u64,
+    /// The number of writes.
+    pub writes: u64,
+}
+
+impl std::fmt::Display for AccessCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} reads, {} writes", self.reads, self.writes)
+    }
+}
+
+/// The access counters of a column, bumped through shared references on reads.
+#[derive(Default)]
+pub(crate) struct AccessCounters {
+    reads: std::sync::atomic::AtomicU64,
+    writes: std::sync::atomic::AtomicU64,
+}
+
+impl AccessCounters {
+    pub(crate) fn count(&self, write: bool) {
+        let counter = if write { &self.writes } else { &self.reads };
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn get(&self) -> AccessCounts {
+        AccessCounts {
+            reads: self.reads.load(std::sync::atomic::Ordering::Relaxed),
+            writes: self.writes.load(std::sync::atomic::Ordering::Relaxed),
+        }
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return the numbers of accesses to the components of each registered type since its registration
+    /// or the last `reset_access_stats`, by the name of the type, in the order of the names.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.get_comp::<i32>(comp_key).unwrap();
+    ///
+    /// let counts = ecs_tiny::AccessCounts { reads: 1, writes: 1 };
+    /// assert_eq!(ecs.access_stats(), vec![("i32", counts)]);
+    /// ```
+    pub fn access_stats(&self) -> Vec<(&'static str, AccessCounts)> {
+        let mut stats = self
+            .comp_cols
+            .values()
+            .map(|comp_col| (comp_col.type_name, comp_col.access.get()))
+            .collect::<Vec<_>>();
+        stats.sort_unstable_by_key(|(type_name, _)| *type_name);
+        stats
+    }
+
+    /// Reset the numbers of accesses of all component types to zero, as in `access_stats`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.iter_comp::<i32>().unwrap();
+    /// ecs.reset_access_stats();
+    ///
+    /// assert_eq!(ecs.access_stats(), vec![("i32", Default::default())]);
+    /// ```
+    pub fn reset_access_stats(&mut self) {
+        for comp_col in self.comp_cols.values_mut() {
+            comp_col.access = Default::default();
+        }
+    }
+
+    /// Return a report of the numbers of accesses of all component types, as in `access_stats`,
+    /// one line per type, with the most accessed types first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.iter_comp::<i32>().unwrap();
+    ///
+    /// assert_eq!(ecs.access_report(), "i32: 1 reads, 0 writes\n");
+    /// ```
+    pub fn access_report(&self) -> String {
+        let mut stats = self.access_stats();
+        stats.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.reads + counts.writes));
+
+        stats
+            .into_iter()
+            .map(|(type_name, counts)| format!("{}: {}\n", type_name, counts))
+            .collect()
+    }
+}
@@ -0,0 +1,578 @@
+use crate::{CompRow, EntityKey, Error, Rows, WorldHasher, ECS};
+
+/// An element of a query, which is either `&T` or `&mut T`,
+/// or `Option<&T>` or `Option<&mut T>` for a component the entities may lack.
+pub trait QueryElem {
+    /// The component type accessed by the element.
+    type Comp: std::any::Any;
+
+    /// The item yielded for each entity.
+    type Item<'a>;
+
+    /// Whether the element accesses the component mutably.
+    #[doc(hidden)]
+    const MUTABLE: bool;
+
+    /// Whether the element yields `None` for entities lacking the component, instead of skipping them.
+    #[doc(hidden)]
+    const OPTIONAL: bool;
+
+    /// Resolve the rows of the column in the given order, marking the mutable ones changed at the tick.
+    #[doc(hidden)]
+    fn fetch<'a>(
+        comp_rows: &'a mut dyn std::any::Any,
+        row_keys: &[Option<u32>],
+        tick: u64,
+    ) -> Vec<Self::Item<'a>>;
+}
+
+fn fetch_ref<'a, T>(
+    comp_rows: &'a dyn std::any::Any,
+    row_keys: &[Option<u32>],
+) -> Vec<Option<&'a T>>
+where
+    T: std::any::Any,
+{
+    let comp_rows = comp_rows.downcast_ref::<Rows<CompRow<T>>>().unwrap();
+
+    row_keys
+        .iter()
+        .map(|row_key| Some(&comp_rows.get((*row_key)? as usize).unwrap().comp))
+        .collect()
+}
+
+fn fetch_mut<'a, T>(
+    comp_rows: &'a mut dyn std::any::Any,
+    row_keys: &[Option<u32>],
+    tick: u64,
+) -> Vec<Option<&'a mut T>>
+where
+    T: std::any::Any,
+{
+    let comp_rows = comp_rows.downcast_mut::<Rows<CompRow<T>>>().unwrap();
+
+    // The row keys are distinct because each row belongs to exactly one entity,
+    // so a single pass over the column hands out disjoint mutable references.
+    let mut positions =
+        crate::hash::HashMap::<_, _>::with_capacity_and_hasher(row_keys.len(), Default::default());
+    for (position, row_key) in row_keys.iter().enumerate() {
+        if let Some(row_key) = row_key {
+            positions.insert(*row_key, position);
+        }
+    }
+
+    let mut items = (0..row_keys.len()).map(|_| None).collect::<Vec<_>>();
+    for (row_key, comp_row) in comp_rows.iter_mut() {
+        if let Some(position) = positions.get(&(row_key as u32)) {
+            items[*position] = Some(comp_row.comp_mut(tick));
+        }
+    }
+
+    items
+}
+
+impl<T> QueryElem for &T
+where
+    T: std::any::Any,
+{
+    type Comp = T;
+
+    type Item<'a> = &'a T;
+
+    const MUTABLE: bool = false;
+
+    const OPTIONAL: bool = false;
+
+    fn fetch<'a>(
+        comp_rows: &'a mut dyn std::any::Any,
+        row_keys: &[Option<u32>],
+        _tick: u64,
+    ) -> Vec<Self::Item<'a>> {
+        fetch_ref(comp_rows, row_keys)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()
+    }
+}
+
+impl<T> QueryElem for &mut T
+where
+    T: std::any::Any,
+{
+    type Comp = T;
+
+    type Item<'a> = &'a mut T;
+
+    const MUTABLE: bool = true;
+
+    const OPTIONAL: bool = false;
+
+    fn fetch<'a>(
+        comp_rows: &'a mut dyn std::any::Any,
+        row_keys: &[Option<u32>],
+        tick: u64,
+    ) -> Vec<Self::Item<'a>> {
+        fetch_mut(comp_rows, row_keys, tick)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()
+    }
+}
+
+impl<T> QueryElem for Option<&T>
+where
+    T: std::any::Any,
+{
+    type Comp = T;
+
+    type Item<'a> = Option<&'a T>;
+
+    const MUTABLE: bool = false;
+
+    const OPTIONAL: bool = true;
+
+    fn fetch<'a>(
+        comp_rows: &'a mut dyn std::any::Any,
+        row_keys: &[Option<u32>],
+        _tick: u64,
+    ) -> Vec<Self::Item<'a>> {
+        fetch_ref(comp_rows, row_keys)
+    }
+}
+
+impl<T> QueryElem for Option<&mut T>
+where
+    T: std::any::Any,
+{
+    type Comp = T;
+
+    type Item<'a> = Option<&'a mut T>;
+
+    const MUTABLE: bool = true;
+
+    const OPTIONAL: bool = true;
+
+    fn fetch<'a>(
+        comp_rows: &'a mut dyn std::any::Any,
+        row_keys: &[Option<u32>],
+        tick: u64,
+    ) -> Vec<Self::Item<'a>> {
+        fetch_mut(comp_rows, row_keys, tick)
+    }
+}
+
+/// An element of a query whose component can be cloned into an owned value.
+pub trait QueryElemOwned: QueryElem {
+    /// The owned value collected for each entity.
+    type Owned: Send;
+
+    /// Clone the rows of the column in the given order.
+    #[doc(hidden)]
+    fn fetch_owned(comp_rows: &dyn std::any::Any, row_keys: &[Option<u32>]) -> Vec<Self::Owned>;
+}
+
+fn fetch_cloned<T>(comp_rows: &dyn std::any::Any, row_keys: &[Option<u32>]) -> Vec<Option<T>>
+where
+    T: std::any::Any + Clone,
+{
+    fetch_ref::<T>(comp_rows, row_keys)
+        .into_iter()
+        .map(|comp| comp.cloned())
+        .collect()
+}
+
+impl<T> QueryElemOwned for &T
+where
+    T: std::any::Any + Clone + Send,
+{
+    type Owned = T;
+
+    fn fetch_owned(comp_rows: &dyn std::any::Any, row_keys: &[Option<u32>]) -> Vec<Self::Owned> {
+        fetch_cloned(comp_rows, row_keys)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()
+    }
+}
+
+impl<T> QueryElemOwned for &mut T
+where
+    T: std::any::Any + Clone + Send,
+{
+    type Owned = T;
+
+    fn fetch_owned(comp_rows: &dyn std::any::Any, row_keys: &[Option<u32>]) -> Vec<Self::Owned> {
+        fetch_cloned(comp_rows, row_keys)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()
+    }
+}
+
+impl<T> QueryElemOwned for Option<&T>
+where
+    T: std::any::Any + Clone + Send,
+{
+    type Owned = Option<T>;
+
+    fn fetch_owned(comp_rows: &dyn std::any::Any, row_keys: &[Option<u32>]) -> Vec<Self::Owned> {
+        fetch_cloned(comp_rows, row_keys)
+    }
+}
+
+impl<T> QueryElemOwned for Option<&mut T>
+where
+    T: std::any::Any + Clone + Send,
+{
+    type Owned = Option<T>;
+
+    fn fetch_owned(comp_rows: &dyn std::any::Any, row_keys: &[Option<u32>]) -> Vec<Self::Owned> {
+        fetch_cloned(comp_rows, row_keys)
+    }
+}
+
+/// A query over entities having all of the listed component types,
+/// implemented for tuples of up to four [`QueryElem`].
+///
+/// Optional elements, as `Option<&T>`, yield `None` for entities lacking the component instead of skipping them,
+/// and a query needs at least one element which is not optional.
+/// Each component type can appear only once in a query.
+/// For entities having several components of a type, the first one is yielded.
+pub trait Query {
+    /// The item yielded for each entity.
+    type Item<'a>;
+
+    /// Find the matching entities and the row keys of each element.
+    /// If any component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If any component type accessed mutably is immutable, return an `Err(Error::ImmutableType)`.
+    /// If all elements are optional, return an `Err(Error::NoRequiredElement)`.
+    #[doc(hidden)]
+    fn plan<S: WorldHasher>(ecs: &ECS<S>) -> Result<QueryPlan, Error>;
+
+    /// Resolve the items of the planned entities.
+    #[doc(hidden)]
+    fn fetch<S: WorldHasher>(ecs: &mut ECS<S>, plan: QueryPlan)
+        -> Vec<(EntityKey, Self::Item<'_>)>;
+}
+
+/// A query whose elements can all be cloned into owned values.
+pub trait QueryOwned: Query {
+    /// The owned values collected for each entity.
+    type Owned: Send;
+
+    /// Clone the items of the planned entities.
+    #[doc(hidden)]
+    fn fetch_owned<S: WorldHasher>(ecs: &ECS<S>, plan: QueryPlan) -> Vec<(EntityKey, Self::Owned)>;
+}
+
+/// The matching entities and the row keys of each element, in column-major order.
+#[doc(hidden)]
+pub struct QueryPlan {
+    entity_keys: Vec<EntityKey>,
+    row_keys: Vec<Vec<Option<u32>>>,
+}
+
+impl QueryPlan {
+    /// Return the number of matching entities.
+    pub(crate) fn len(&self) -> usize {
+        self.entity_keys.len()
+    }
+}
+
+struct ElemInfo<S> {
+    type_key: std::any::TypeId,
+    optional: bool,
+    len: usize,
+    iter_row_fn: fn(&ECS<S>) -> Vec<(u32, EntityKey)>,
+}
+
+fn elem_info<T, S: WorldHasher>(
+    ecs: &ECS<S>,
+    mutable: bool,
+    optional: bool,
+) -> Result<ElemInfo<S>, Error>
+where
+    T: std::any::Any,
+{
+    let type_key = std::any::TypeId::of::<T>();
+
+    let comp_col = ecs
+        .comp_cols
+        .get(&type_key)
+        .ok_or(Error::TypeNotRegistered)?;
+
+    if mutable && comp_col.immutable {
+        return Err(Error::ImmutableType);
+    }
+
+    let len = comp_col
+        .comp_rows
+        .downcast_ref::<Rows<CompRow<T>>>()
+        .unwrap()
+        .len();
+
+    Ok(ElemInfo {
+        type_key,
+        optional,
+        len,
+        iter_row_fn: |ecs| {
+            let type_key = std::any::TypeId::of::<T>();
+
+            ecs.comp_cols
+                .get(&type_key)
+                .unwrap()
+                .comp_rows
+                .downcast_ref::<Rows<CompRow<T>>>()
+                .unwrap()
+                .iter()
+                .map(|(row_key, comp_row)| (row_key as u32, comp_row.entity_key))
+                .collect()
+        },
+    })
+}
+
+fn first_row_key<S: WorldHasher>(
+    ecs: &ECS<S>,
+    entity_key: EntityKey,
+    type_key: std::any::TypeId,
+) -> Option<u32> {
+    let ref_1_col = ecs.ref_1_cols.get(&(entity_key, type_key))?;
+    // the rows of taken components are skipped
+    let row_key = ref_1_col
+        .iter()
+        .find(|row_key| !ecs.is_taken(type_key, **row_key))?;
+    Some(*row_key)
+}
+
+fn plan<S: WorldHasher>(ecs: &ECS<S>, elem_infos: &[ElemInfo<S>]) -> Result<QueryPlan, Error> {
+    for (i, elem_info) in elem_infos.iter().enumerate() {
+        for other in &elem_infos[i + 1..] {
+            assert!(
+                elem_info.type_key != other.type_key,
+                "each component type can appear only once in a query"
+            );
+        }
+    }
+
+    // drive the iteration from the smallest column of the required elements,
+    // and look up the optional elements by the entities
+    let driver = elem_infos
+        .iter()
+        .filter(|elem_info| !elem_info.optional)
+        .min_by_key(|elem_info| elem_info.len)
+        .ok_or(Error::NoRequiredElement)?;
+
+    let mut entity_keys = vec![];
+    let mut row_keys = elem_infos.iter().map(|_| vec![]).collect::<Vec<_>>();
+
+    for (row_key, entity_key) in (driver.iter_row_fn)(ecs) {
+        if first_row_key(ecs, entity_key, driver.type_key) != Some(row_key) {
+            continue;
+        }
+
+        let entity_row_keys = elem_infos
+            .iter()
+            .map(|elem_info| {
+                let row_key = first_row_key(ecs, entity_key, elem_info.type_key);
+                match row_key {
+                    None if !elem_info.optional => None,
+                    row_key => Some(row_key),
+                }
+            })
+            .collect::<Option<Vec<_>>>();
+
+        if let Some(entity_row_keys) = entity_row_keys {
+            entity_keys.push(entity_key);
+            for (row_keys, row_key) in row_keys.iter_mut().zip(entity_row_keys) {
+                row_keys.push(row_key);
+            }
+        }
+    }
+
+    Ok(QueryPlan {
+        entity_keys,
+        row_keys,
+    })
+}
+
+macro_rules! impl_query {
+    ($(($elem:ident, $index:tt)),*) => {
+        impl<$($elem),*> Query for ($($elem,)*)
+        where
+            $($elem: QueryElem),*
+        {
+            type Item<'a> = ($($elem::Item<'a>,)*);
+
+            fn plan<S: WorldHasher>(ecs: &ECS<S>) -> Result<QueryPlan, Error> {
+                let elem_infos = [$(elem_info::<$elem::Comp, S>(ecs, $elem::MUTABLE, $elem::OPTIONAL)?),*];
+                plan(ecs, &elem_infos)
+            }
+
+            fn fetch<S: WorldHasher>(ecs: &mut ECS<S>, plan: QueryPlan) -> Vec<(EntityKey, Self::Item<'_>)> {
+                let tick = ecs.tick;
+                let type_keys = [$(std::any::TypeId::of::<$elem::Comp>()),*];
+                let mut comp_cols = ecs.comp_cols.get_disjoint_mut(type_keys.each_ref());
+
+                let mut items = ($({
+                    let comp_col = comp_cols[$index].take().unwrap();
+                    comp_col.count_access($elem::MUTABLE);
+
+                    $elem::fetch(
+                        comp_col
+                            .comp_rows
+                            .downcast_mut::<Rows<CompRow<$elem::Comp>>>()
+                            .unwrap(),
+                        &plan.row_keys[$index],
+                        tick,
+                    )
+                    .into_iter()
+                },)*);
+
+                plan.entity_keys
+                    .into_iter()
+                    .map(|entity_key| (entity_key, ($(items.$index.next().unwrap(),)*)))
+                    .collect()
+            }
+        }
+
+        impl<$($elem),*> QueryOwned for ($($elem,)*)
+        where
+            $($elem: QueryElemOwned),*
+        {
+            type Owned = ($($elem::Owned,)*);
+
+            fn fetch_owned<S: WorldHasher>(ecs: &ECS<S>, plan: QueryPlan) -> Vec<(EntityKey, Self::Owned)> {
+                let mut owned = ($({
+                    let comp_col = ecs
+                        .comp_cols
+                        .get(&std::any::TypeId::of::<$elem::Comp>())
+                        .unwrap();
+                    comp_col.count_access(false);
+
+                    $elem::fetch_owned(
+                        comp_col
+                            .comp_rows
+                            .downcast_ref::<Rows<CompRow<$elem::Comp>>>()
+                            .unwrap(),
+                        &plan.row_keys[$index],
+                    )
+                    .into_iter()
+                },)*);
+
+                plan.entity_keys
+                    .into_iter()
+                    .map(|entity_key| (entity_key, ($(owned.$index.next().unwrap(),)*)))
+                    .collect()
+            }
+        }
+    };
+}
+
+impl_query!((A, 0));
+impl_query!((A, 0), (B, 1));
+impl_query!((A, 0), (B, 1), (C, 2));
+impl_query!((A, 0), (B, 1), (C, 2), (D, 3));
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return an iterator over all entities having components of both types, as in `query::<(&A, &B)>`,
+    /// together with the corresponding entity key, without borrowing the world mutably.
+    /// The iteration is driven by the smaller column, and entities missing either type are skipped.
+    /// For entities having several components of a type, the first one in the order of `iter_comp_by_entity` is yielded,
+    /// so each entity is yielded once.
+    /// If either component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (EntityKey, (&A, &B))>)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both component types are the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.register::<f32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// ecs.insert_comp(entity_key0, 1.0f32).unwrap();
+    /// ecs.insert_comp(entity_key0, 2u32).unwrap();
+    /// ecs.insert_comp(entity_key0, 3u32).unwrap();
+    /// ecs.insert_comp(entity_key1, 4.0f32).unwrap();
+    ///
+    /// let pairs = ecs.iter_comp_pair::<f32, u32>().unwrap().collect::<Vec<_>>();
+    /// assert_eq!(pairs, vec![(entity_key0, (&1.0, &2))]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn iter_comp_pair<A, B>(&self) -> Result<impl Iterator<Item = (EntityKey, (&A, &B))>, Error>
+    where
+        A: std::any::Any,
+        B: std::any::Any,
+    {
+        let QueryPlan {
+            entity_keys,
+            row_keys,
+        } = <(&A, &B)>::plan(self)?;
+
+        let comp_rows = |type_key| {
+            let comp_col = self.comp_cols.get(&type_key).unwrap();
+            comp_col.count_access(false);
+            &comp_col.comp_rows
+        };
+        let comp_rows_a = comp_rows(std::any::TypeId::of::<A>())
+            .downcast_ref::<Rows<CompRow<A>>>()
+            .unwrap();
+        let comp_rows_b = comp_rows(std::any::TypeId::of::<B>())
+            .downcast_ref::<Rows<CompRow<B>>>()
+            .unwrap();
+
+        let [row_keys_a, row_keys_b]: [Vec<Option<u32>>; 2] = row_keys.try_into().unwrap();
+        let iter = entity_keys
+            .into_iter()
+            .zip(row_keys_a.into_iter().zip(row_keys_b))
+            .map(move |(entity_key, (row_key_a, row_key_b))| {
+                let comp_a = &comp_rows_a.get(row_key_a.unwrap() as usize).unwrap().comp;
+                let comp_b = &comp_rows_b.get(row_key_b.unwrap() as usize).unwrap().comp;
+                (entity_key, (comp_a, comp_b))
+            });
+
+        Ok(iter)
+    }
+
+    /// Return an iterator over all entities having components of both types,
+    /// with the component of the first type borrowed mutably, as in `query::<(&mut A, &B)>`,
+    /// together with the corresponding entity key.
+    /// Entities are matched as in `iter_comp_pair`.
+    /// If either component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the first component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (EntityKey, (&mut A, &B))>)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both component types are the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<f32>().unwrap();
+    /// ecs.register::<u32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 1.0f32).unwrap();
+    /// ecs.insert_comp(entity_key, 2u32).unwrap();
+    ///
+    /// for (_, (position, velocity)) in ecs.iter_comp_pair_mut::<f32, u32>().unwrap() {
+    ///     *position += *velocity as f32;
+    /// }
+    /// assert_eq!(ecs.get_comp::<f32>(comp_key), Ok(&3.0));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn iter_comp_pair_mut<A, B>(
+        &mut self,
+    ) -> Result<impl Iterator<Item = (EntityKey, (&mut A, &B))>, Error>
+    where
+        A: std::any::Any,
+        B: std::any::Any,
+    {
+        self.query::<(&mut A, &B)>()
+    }
+}
@@ -0,0 +1,218 @@
+/// A value of a reflected field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Bool(bool),
+    Char(char),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Isize(isize),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    Usize(usize),
+    F32(f32),
+    F64(f64),
+    String(String),
+}
+
+impl FieldValue {
+    /// Return the name of the type held by the value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FieldValue::Bool(_) => "bool",
+            FieldValue::Char(_) => "char",
+            FieldValue::I8(_) => "i8",
+            FieldValue::I16(_) => "i16",
+            FieldValue::I32(_) => "i32",
+            FieldValue::I64(_) => "i64",
+            FieldValue::Isize(_) => "isize",
+            FieldValue::U8(_) => "u8",
+            FieldValue::U16(_) => "u16",
+            FieldValue::U32(_) => "u32",
+            FieldValue::U64(_) => "u64",
+            FieldValue::Usize(_) => "usize",
+            FieldValue::F32(_) => "f32",
+            FieldValue::F64(_) => "f64",
+            FieldValue::String(_) => "alloc::string::String",
+        }
+    }
+}
+
+/// An error returned by the field access of reflected components.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldError {
+    /// The component cannot be accessed.
+    Access(crate::Error),
+    /// The component type is not reflected.
+    NotReflected {
+        /// The name of the component type.
+        type_name: &'static str,
+    },
+    /// The component type has no field of the name.
+    UnknownField {
+        /// The name of the component type.
+        type_name: &'static str,
+        /// The name of the field.
+        field: String,
+    },
+    /// The value does not match the type of the field.
+    TypeMismatch {
+        /// The name of the field.
+        field: String,
+        /// The name of the field type.
+        expected: &'static str,
+        /// The name of the value type.
+        found: &'static str,
+    },
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldError::Access(e) => write!(f, "{}", e),
+            FieldError::NotReflected { type_name } => {
+                write!(f, "component type not reflected: {}", type_name)
+            }
+            FieldError::UnknownField { type_name, field } => {
+                write!(f, "unknown field `{}` of {}", field, type_name)
+            }
+            FieldError::TypeMismatch {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "field `{}` expects {}, but found {}",
+                field, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// A type of a reflected field, implemented for primitive types and `String`.
+pub trait FieldType: Sized {
+    /// Convert the field into a value.
+    fn to_field_value(&self) -> FieldValue;
+
+    /// Convert a value into the field.
+    /// If the value does not hold the type, return the value back.
+    fn from_field_value(value: FieldValue) -> Result<Self, FieldValue>;
+}
+
+macro_rules! impl_field_type {
+    ($(($type:ty, $variant:ident)),*) => {
+        $(
+            impl FieldType for $type {
+                fn to_field_value(&self) -> FieldValue {
+                    FieldValue::$variant(self.clone())
+                }
+
+                fn from_field_value(value: FieldValue) -> Result<Self, FieldValue> {
+                    match value {
+                        FieldValue::$variant(value) => Ok(value),
+                        value => Err(value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_field_type!(
+    (bool, Bool),
+    (char, Char),
+    (i8, I8),
+    (i16, I16),
+    (i32, I32),
+    (i64, I64),
+    (isize, Isize),
+    (u8, U8),
+    (u16, U16),
+    (u32, U32),
+    (u64, U64),
+    (usize, Usize),
+    (f32, F32),
+    (f64, F64),
+    (String, String)
+);
+
+/// A component type whose fields can be read and written by name,
+/// usually implemented by [`impl_reflect!`](crate::impl_reflect).
+pub trait Reflect: std::any::Any {
+    /// Return the value of the field.
+    fn get_field(&self, field: &str) -> Result<FieldValue, FieldError>;
+
+    /// Write the value into the field.
+    fn set_field(&mut self, field: &str, value: FieldValue) -> Result<(), FieldError>;
+}
+
+/// Implement [`Reflect`] for a struct by listing the fields to expose,
+/// each of which must implement [`FieldType`].
+///
+/// # Examples
+///
+/// ```
+/// use ecs_tiny::Reflect;
+///
+/// struct Health {
+///     current: i32,
+///     max: i32,
+/// }
+///
+/// ecs_tiny::impl_reflect!(Health { current, max });
+///
+/// let mut health = Health { current: 10, max: 100 };
+/// health.set_field("current", ecs_tiny::FieldValue::I32(50)).unwrap();
+///
+/// assert_eq!(health.get_field("current"), Ok(ecs_tiny::FieldValue::I32(50)));
+/// ```
+#[macro_export]
+macro_rules! impl_reflect {
+    ($type:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::Reflect for $type {
+            fn get_field(&self, field: &str) -> Result<$crate::FieldValue, $crate::FieldError> {
+                match field {
+                    $(stringify!($field) => Ok($crate::FieldType::to_field_value(&self.$field)),)*
+                    _ => Err($crate::__unknown_field::<Self>(field)),
+                }
+            }
+
+            fn set_field(
+                &mut self,
+                field: &str,
+                value: $crate::FieldValue,
+            ) -> Result<(), $crate::FieldError> {
+                match field {
+                    $(stringify!($field) => $crate::__set_field(&mut self.$field, field, value),)*
+                    _ => Err($crate::__unknown_field::<Self>(field)),
+                }
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+pub fn __unknown_field<T>(field: &str) -> FieldError {
+    FieldError::UnknownField {
+        type_name: std::any::type_name::<T>(),
+        field: field.to_string(),
+    }
+}
+
+#[doc(hidden)]
+pub fn __set_field<F>(slot: &mut F, field: &str, value: FieldValue) -> Result<(), FieldError>
+where
+    F: FieldType,
+{
+    *slot = F::from_field_value(value).map_err(|value| FieldError::TypeMismatch {
+        field: field.to_string(),
+        expected: std::any::type_name::<F>(),
+        found: value.type_name(),
+    })?;
+    Ok(())
+}
@@ -0,0 +1,258 @@
+/// The reference table of the components of an entity and type, a list of row keys in their iteration order.
+///
+/// The entries are linked in order on top of a slab, so that an entry is removed in constant time by its key,
+/// as stored in the row as `ref_1_row_key`, while the others keep both their keys and their order.
+/// Only placing an entry at an index, and finding the index of an entry, walk the list.
+#[derive(Clone)]
+pub(crate) struct RefList {
+    entries: slab::Slab<RefEntry>,
+    head: usize,
+    tail: usize,
+}
+
+#[derive(Clone)]
+struct RefEntry {
+    row_key: u32,
+    prev: usize,
+    next: usize,
+}
+
+/// The end of the list, as the link of the first and the last entries.
+const NIL: usize = usize::MAX;
+
+impl Default for RefList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RefList {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: slab::Slab::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.entries.reserve(additional);
+    }
+
+    /// Shrink the capacity without moving any entry, as in `slab::Slab::shrink_to_fit`.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.entries.shrink_to_fit();
+    }
+
+    /// Return the row key of the entry, if the key is occupied.
+    pub(crate) fn get(&self, key: u32) -> Option<u32> {
+        self.entries
+            .get(key as usize)
+            .map(|ref_entry| ref_entry.row_key)
+    }
+
+    /// Append the row key and return the key of its entry.
+    pub(crate) fn push(&mut self, row_key: u32) -> u32 {
+        self.insert_before(NIL, row_key)
+    }
+
+    /// Insert the row key at the index, or last if the index is past the end, and return the key of its entry.
+    pub(crate) fn insert(&mut self, index: usize, row_key: u32) -> u32 {
+        let next = self.key_at(index);
+        self.insert_before(next, row_key)
+    }
+
+    /// Remove the entry and return its row key.
+    pub(crate) fn remove(&mut self, key: u32) -> u32 {
+        self.unlink(key as usize);
+        self.entries.remove(key as usize).row_key
+    }
+
+    /// Move the entry to the index among the others, or last if the index is past the end, keeping its key.
+    pub(crate) fn move_to(&mut self, key: u32, index: usize) {
+        self.unlink(key as usize);
+        let next = self.key_at(index);
+        self.link_before(key as usize, next);
+    }
+
+    /// Replace the row key of the entry, as for a row relocated by `compact_step`.
+    pub(crate) fn set(&mut self, key: u32, row_key: u32) {
+        self.entries[key as usize].row_key = row_key;
+    }
+
+    /// Return the index of the entry among the others, walking from both ends at once.
+    pub(crate) fn position(&self, key: u32) -> usize {
+        let key = key as usize;
+        let (mut front, mut back) = (self.head, self.tail);
+        let mut index = 0;
+        loop {
+            if front == key {
+                return index;
+            }
+            if back == key {
+                return self.len() - 1 - index;
+            }
+            front = self.entries[front].next;
+            back = self.entries[back].prev;
+            index += 1;
+        }
+    }
+
+    /// Keep only the entries whose row keys satisfy the predicate, keeping the keys of the others.
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(&u32) -> bool) {
+        let keys = self
+            .iter_keys()
+            .filter(|(_, row_key)| !f(row_key))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        for key in keys {
+            self.remove(key);
+        }
+    }
+
+    /// Return an iterator over the row keys in order.
+    pub(crate) fn iter(&self) -> Iter<'_> {
+        Iter {
+            entries: &self.entries,
+            front: self.head,
+            back: self.tail,
+            len: self.len(),
+        }
+    }
+
+    /// Return an iterator over the keys of the entries and their row keys in order.
+    pub(crate) fn iter_keys(&self) -> impl Iterator<Item = (u32, &u32)> + '_ {
+        let mut key = self.head;
+        std::iter::from_fn(move || {
+            let ref_entry = self.entries.get(key)?;
+            let item = (key as u32, &ref_entry.row_key);
+            key = ref_entry.next;
+            Some(item)
+        })
+    }
+
+    /// Return the key of the entry at the index, or the end if the index is past the end.
+    fn key_at(&self, index: usize) -> usize {
+        if index >= self.len() {
+            return NIL;
+        }
+
+        let mut key = self.head;
+        for _ in 0..index {
+            key = self.entries[key].next;
+        }
+        key
+    }
+
+    fn insert_before(&mut self, next: usize, row_key: u32) -> u32 {
+        let key = self.entries.insert(RefEntry {
+            row_key,
+            prev: NIL,
+            next: NIL,
+        });
+        self.link_before(key, next);
+        key as u32
+    }
+
+    fn link_before(&mut self, key: usize, next: usize) {
+        let prev = match next {
+            NIL => std::mem::replace(&mut self.tail, key),
+            next => std::mem::replace(&mut self.entries[next].prev, key),
+        };
+        match prev {
+            NIL => self.head = key,
+            prev => self.entries[prev].next = key,
+        }
+
+        let ref_entry = &mut self.entries[key];
+        ref_entry.prev = prev;
+        ref_entry.next = next;
+    }
+
+    fn unlink(&mut self, key: usize) {
+        let RefEntry { prev, next, .. } = self.entries[key];
+        match prev {
+            NIL => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a RefList {
+    type Item = &'a u32;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The reference tables of a world by entity and type, looked up without naming the hasher of the world,
+/// as by the column handles.
+pub(crate) trait RefTables {
+    fn get(&self, key: &(crate::EntityKey, std::any::TypeId)) -> Option<&RefList>;
+}
+
+impl<S> RefTables for crate::hash::HashMap<(crate::EntityKey, std::any::TypeId), RefList, S>
+where
+    S: std::hash::BuildHasher,
+{
+    fn get(&self, key: &(crate::EntityKey, std::any::TypeId)) -> Option<&RefList> {
+        std::collections::HashMap::get(self, key)
+    }
+}
+
+/// The iterator returned by `RefList::iter`.
+#[derive(Clone)]
+pub(crate) struct Iter<'a> {
+    entries: &'a slab::Slab<RefEntry>,
+    front: usize,
+    back: usize,
+    len: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let ref_entry = &self.entries[self.front];
+        self.front = ref_entry.next;
+        self.len -= 1;
+        Some(&ref_entry.row_key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let ref_entry = &self.entries[self.back];
+        self.back = ref_entry.prev;
+        self.len -= 1;
+        Some(&ref_entry.row_key)
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
@@ -0,0 +1,234 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, WorldId, ECS};
+
+/// A token of the registration of a component type in a world, returned by [`ECS::register`] and [`ECS::registered`].
+///
+/// The `*_checked` methods take the token in place of the type parameter, so they cannot fail
+/// because the type is not registered, and only the failures of the entities and components remain.
+/// The token is invalidated by unregistering the type, even if the type is registered again,
+/// and belongs to the world that handed it out.
+/// A `*_checked` method panics on an invalid token, which is a bug of the caller rather than a failure of the world.
+///
+/// The type of the token must match the type of the component:
+///
+/// ```compile_fail
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let entity_key = ecs.insert_entity();
+/// let token = ecs.register::<i32>().unwrap();
+/// ecs.insert_comp_checked(token, entity_key, 42u32).unwrap();
+/// ```
+pub struct Registered<T> {
+    world: WorldId,
+    registration: u64,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Registered<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Registered<T> {}
+
+impl<T> PartialEq for Registered<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.world == other.world && self.registration == other.registration
+    }
+}
+
+impl<T> Eq for Registered<T> {}
+
+impl<T> std::fmt::Debug for Registered<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registered")
+            .field("type_name", &std::any::type_name::<T>())
+            .field("world", &self.world)
+            .field("registration", &self.registration)
+            .finish()
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return the token of the registration of the component type, as in `Registered`,
+    /// whichever registration method registered it.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(Registered<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register_immutable::<i32>().unwrap();
+    ///
+    /// assert!(ecs.registered::<i32>().is_ok());
+    /// assert!(ecs.registered::<u32>().is_err());
+    /// ```
+    pub fn registered<T>(&self) -> Result<Registered<T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_col = self
+            .comp_cols
+            .get(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+
+        Ok(Registered {
+            world: self.world,
+            registration: comp_col.registration,
+            marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Insert a new component, as in `insert_comp`, with the token of its type.
+    /// The component type is registered, so only the other failures of `insert_comp` remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token belongs to another world or its type was unregistered, as in `Registered`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// let token = ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp_checked(token, entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp_checked(token, comp_key), Ok(&42));
+    /// ```
+    pub fn insert_comp_checked<T>(
+        &mut self,
+        token: Registered<T>,
+        entity_key: EntityKey,
+        comp: T,
+    ) -> Result<CompKey, Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_token(token);
+        self.insert_comp(entity_key, comp)
+    }
+
+    /// Remove a component, as in `remove_comp`, with the token of its type.
+    /// The component type is registered, so only the other failures of `remove_comp` remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token belongs to another world or its type was unregistered, as in `Registered`.
+    pub fn remove_comp_checked<T>(
+        &mut self,
+        token: Registered<T>,
+        comp_key: CompKey,
+    ) -> Result<T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_token(token);
+        self.remove_comp(comp_key)
+    }
+
+    /// Return a component, as in `get_comp`, with the token of its type.
+    /// The component type is registered, so only the other failures of `get_comp` remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token belongs to another world or its type was unregistered, as in `Registered`.
+    pub fn get_comp_checked<T>(&self, token: Registered<T>, comp_key: CompKey) -> Result<&T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_token(token);
+        self.get_comp(comp_key)
+    }
+
+    /// Return a mutable component, as in `get_comp_mut`, with the token of its type.
+    /// The component type is registered, so only the other failures of `get_comp_mut` remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token belongs to another world or its type was unregistered, as in `Registered`.
+    pub fn get_comp_mut_checked<T>(
+        &mut self,
+        token: Registered<T>,
+        comp_key: CompKey,
+    ) -> Result<&mut T, Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_token(token);
+        self.get_comp_mut(comp_key)
+    }
+
+    /// Return an iterator over all components of the type, as in `iter_comp`, with the token of the type,
+    /// which cannot fail.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token belongs to another world or its type was unregistered, as in `Registered`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// let token = ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp_checked(token, entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.iter_comp_checked(token).sum::<i32>(), 42);
+    /// ```
+    pub fn iter_comp_checked<T>(&self, token: Registered<T>) -> impl Iterator<Item = &T>
+    where
+        T: std::any::Any,
+    {
+        self.check_token(token);
+        self.iter_comp::<T>().unwrap()
+    }
+
+    /// Return a mutable iterator over all components of the type, as in `iter_comp_mut`, with the token of the type.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = &mut T>)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token belongs to another world or its type was unregistered, as in `Registered`.
+    pub fn iter_comp_mut_checked<T>(
+        &mut self,
+        token: Registered<T>,
+    ) -> Result<impl Iterator<Item = &mut T>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_token(token);
+        self.iter_comp_mut::<T>()
+    }
+
+    /// Return an iterator over all components of the entity and type, as in `iter_comp_by_entity`,
+    /// with the token of the type.
+    /// The component type is registered, so only the other failures of `iter_comp_by_entity` remain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token belongs to another world or its type was unregistered, as in `Registered`.
+    pub fn iter_comp_by_entity_checked<T>(
+        &self,
+        token: Registered<T>,
+        entity_key: EntityKey,
+    ) -> Result<impl Iterator<Item = &T>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.check_token(token);
+        self.iter_comp_by_entity::<T>(entity_key)
+    }
+
+    fn check_token<T>(&self, token: Registered<T>)
+    where
+        T: std::any::Any,
+    {
+        assert!(
+            self.registered::<T>() == Ok(token),
+            "invalid registration token of {}",
+            std::any::type_name::<T>()
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{CompColumn, CompKey, CompValue, EntityKey, Error, WorldHasher, ECS};
+
+/// A component removed while the removals of its type are tracked, as returned by [`ECS::drain_removed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedComp<T> {
+    /// The entity key of the entity the component belonged to.
+    pub entity_key: EntityKey,
+    /// The component key of the removed component.
+    pub comp_key: CompKey,
+    /// A clone of the removed component, if the type is cloneable, as in `register_cloneable`,
+    /// and the component was not taken, as in `take_comp`.
+    pub comp: Option<T>,
+}
+
+/// The removals of the components of a column, oldest first, keeping at most the capacity.
+pub(crate) struct RemovalLog<S> {
+    removals: std::collections::VecDeque<(EntityKey, CompKey, Option<CompValue<S>>)>,
+    capacity: usize,
+}
+
+impl<S> RemovalLog<S> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            removals: Default::default(),
+            capacity,
+        }
+    }
+
+    /// Create an empty log of the same capacity.
+    pub(crate) fn empty_like(&self) -> Self {
+        Self::new(self.capacity)
+    }
+
+    fn push(&mut self, removal: (EntityKey, CompKey, Option<CompValue<S>>)) {
+        if self.removals.len() == self.capacity {
+            self.removals.pop_front();
+        }
+        self.removals.push_back(removal);
+    }
+}
+
+impl<S: WorldHasher> CompColumn<S> {
+    /// Record the removal of the component of the row into the removal log, if the removals are tracked,
+    /// and call the remove observers, before the row is removed.
+    /// A row holding no component is left to `note_removed_taken`.
+    pub(crate) fn note_removed(&mut self, comp_key: CompKey) {
+        if self.removal_log.is_none() && !self.has_remove_observers() {
+            return;
+        }
+
+        let Some(comp_row) = (self.get_row_fn)(self, comp_key.row_key) else {
+            return;
+        };
+        let comp = (self.get_comp_fn)(self, comp_key.row_key).unwrap();
+        self.notify_removed(comp_row.entity_key, comp_key, comp);
+
+        if self.removal_log.is_none() {
+            return;
+        }
+        let comp = self
+            .record_comp_fn
+            .map(|record_comp_fn| record_comp_fn(comp));
+
+        let removal_log = self.removal_log.as_mut().unwrap();
+        removal_log.push((comp_row.entity_key, comp_key, comp));
+    }
+
+    /// Record the removal of the taken component of the row into the removal log, if the removals are tracked.
+    pub(crate) fn note_removed_taken(&mut self, entity_key: EntityKey, comp_key: CompKey) {
+        if let Some(removal_log) = &mut self.removal_log {
+            removal_log.push((entity_key, comp_key, None));
+        }
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Track the removals of the components of the type, however they are removed,
+    /// as by `remove_comp`, `remove_entity`, `clear_comp` or the release of a dropped taken component,
+    /// for `drain_removed` to return them.
+    /// At most the capacity of the removals are kept, dropping the oldest ones once full.
+    /// Enabling the tracking again changes the capacity, dropping the oldest removals beyond it.
+    /// Unregistering the component type, as by `unregister` or `clear`, drops the tracked removals
+    /// without tracking its components.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// ecs.enable_removal_tracking::<i32>(64).unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// ecs.remove_entity(entity_key).unwrap();
+    ///
+    /// let removed = ecs.drain_removed::<i32>();
+    /// assert_eq!(removed.len(), 1);
+    /// assert_eq!(removed[0].entity_key, entity_key);
+    /// assert_eq!(removed[0].comp_key, comp_key);
+    /// assert_eq!(removed[0].comp, Some(42));
+    /// assert!(ecs.drain_removed::<i32>().is_empty());
+    /// ```
+    pub fn enable_removal_tracking<T>(&mut self, capacity: usize) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        assert!(capacity > 0, "removal log capacity must be non-zero");
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+        let removal_log = comp_col
+            .removal_log
+            .get_or_insert_with(|| RemovalLog::new(capacity));
+        removal_log.capacity = capacity;
+        let excess = removal_log.removals.len().saturating_sub(capacity);
+        removal_log.removals.drain(..excess);
+
+        Ok(())
+    }
+
+    /// Stop tracking the removals of the components of the type, as in `enable_removal_tracking`,
+    /// dropping the removals not drained yet.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    pub fn disable_removal_tracking<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_col = self
+            .comp_cols
+            .get_mut(&std::any::TypeId::of::<T>())
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.removal_log = None;
+
+        Ok(())
+    }
+
+    /// Take the removals of the components of the type tracked since the last call, oldest first,
+    /// as in `enable_removal_tracking`.
+    /// If the removals of the type are not tracked, an empty list is returned.
+    pub fn drain_removed<T>(&mut self) -> Vec<RemovedComp<T>>
+    where
+        T: std::any::Any,
+    {
+        let Some(removal_log) = self
+            .comp_cols
+            .get_mut(&std::any::TypeId::of::<T>())
+            .and_then(|comp_col| comp_col.removal_log.as_mut())
+        else {
+            return vec![];
+        };
+
+        removal_log
+            .removals
+            .drain(..)
+            .map(|(entity_key, comp_key, comp)| RemovedComp {
+                entity_key,
+                comp_key,
+                comp: comp.map(|comp| comp.into_comp::<T>().unwrap()),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,576 @@
+/// The storage of the rows of a column, with the interface of `slab::Slab` used by the columns.
+///
+/// The default storage is a slab, which moves all rows when it grows.
+/// The chunked storage, as in [`ECS::register_chunked`](crate::ECS::register_chunked),
+/// allocates the rows in fixed-size chunks and never moves a row on growth.
+///
+/// A row can be reserved by taking its value out, as in [`ECS::take_comp`](crate::ECS::take_comp).
+/// A reserved row is neither occupied nor vacant, so its key is not handed out again until it is released.
+pub(crate) enum Rows<T> {
+    /// The reserved rows hold a `None`.
+    Slab {
+        slab: slab::Slab<Option<T>>,
+        reserved: usize,
+    },
+    Chunked(Chunked<T>),
+}
+
+/// A slab whose entries are split into chunks, each allocated once with the capacity of the chunk size.
+/// The row key `k` lives at the slot `k % chunk_size` of the chunk `k / chunk_size`.
+pub(crate) struct Chunked<T> {
+    chunks: Vec<Vec<Entry<T>>>,
+    chunk_size: usize,
+    /// The number of entries, vacant or occupied.
+    slots: usize,
+    /// The number of occupied entries.
+    len: usize,
+    /// The number of reserved entries.
+    reserved: usize,
+    /// The head of the list of vacant entries, or `slots` if none.
+    next: usize,
+}
+
+pub(crate) enum Entry<T> {
+    Vacant(usize),
+    Occupied(T),
+    Reserved,
+}
+
+impl<T> Default for Rows<T> {
+    fn default() -> Self {
+        Rows::Slab {
+            slab: slab::Slab::new(),
+            reserved: 0,
+        }
+    }
+}
+
+impl<T> Rows<T> {
+    pub(crate) fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create an empty chunked storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chunk size is zero.
+    pub(crate) fn chunked(chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+
+        Rows::Chunked(Chunked {
+            chunks: vec![],
+            chunk_size,
+            slots: 0,
+            len: 0,
+            reserved: 0,
+            next: 0,
+        })
+    }
+
+    /// Create an empty storage of the same kind.
+    pub(crate) fn empty_like(&self) -> Self {
+        match self {
+            Rows::Slab { .. } => Rows::new(),
+            Rows::Chunked(chunked) => Rows::chunked(chunked.chunk_size),
+        }
+    }
+
+    /// Create a storage of the same kind holding the rows at their keys.
+    pub(crate) fn collect_like(&self, rows: impl Iterator<Item = (usize, T)>) -> Self {
+        match self {
+            Rows::Slab { .. } => Rows::Slab {
+                slab: rows.map(|(key, value)| (key, Some(value))).collect(),
+                reserved: 0,
+            },
+            Rows::Chunked(chunked) => {
+                let mut collected = Rows::chunked(chunked.chunk_size);
+                for (key, value) in rows {
+                    collected.insert_at(key, value);
+                }
+                collected
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Rows::Slab { slab, reserved } => slab.len() - reserved,
+            Rows::Chunked(chunked) => chunked.len,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reserve capacity for at least the number of additional rows given.
+    /// The chunked storage allocates each chunk as it is reached, so it reserves nothing in advance.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        if let Rows::Slab { slab, .. } = self {
+            slab.reserve(additional);
+        }
+    }
+
+    /// Shrink the capacity as much as possible without moving any row, dropping the vacant rows after the last
+    /// occupied or reserved one, so that every key stays where it is.
+    /// The chunked storage never moves a row, so it keeps its chunks allocated.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        if let Rows::Slab { slab, .. } = self {
+            slab.shrink_to_fit();
+        }
+    }
+
+    pub(crate) fn vacant_key(&self) -> usize {
+        match self {
+            Rows::Slab { slab, .. } => slab.vacant_key(),
+            Rows::Chunked(chunked) => chunked.next,
+        }
+    }
+
+    /// Return whether the row is occupied or reserved, that is, whether the key cannot be inserted at.
+    pub(crate) fn contains(&self, key: usize) -> bool {
+        match self {
+            Rows::Slab { slab, .. } => slab.contains(key),
+            Rows::Chunked(chunked) => {
+                matches!(
+                    chunked.entry(key),
+                    Some(Entry::Occupied(_) | Entry::Reserved)
+                )
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, key: usize) -> Option<&T> {
+        match self {
+            Rows::Slab { slab, .. } => slab.get(key)?.as_ref(),
+            Rows::Chunked(chunked) => match chunked.entry(key)? {
+                Entry::Occupied(value) => Some(value),
+                _ => None,
+            },
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        match self {
+            Rows::Slab { slab, .. } => slab.get_mut(key)?.as_mut(),
+            Rows::Chunked(chunked) => match chunked.entry_mut(key)? {
+                Entry::Occupied(value) => Some(value),
+                _ => None,
+            },
+        }
+    }
+
+    pub(crate) fn get2_mut(&mut self, key0: usize, key1: usize) -> Option<(&mut T, &mut T)> {
+        match self {
+            Rows::Slab { slab, .. } => match slab.get2_mut(key0, key1)? {
+                (Some(value0), Some(value1)) => Some((value0, value1)),
+                _ => None,
+            },
+            Rows::Chunked(chunked) => {
+                let chunk_size = chunked.chunk_size;
+                let (chunk0, slot0) = (key0 / chunk_size, key0 % chunk_size);
+                let (chunk1, slot1) = (key1 / chunk_size, key1 % chunk_size);
+
+                let [entry0, entry1] = if chunk0 == chunk1 {
+                    let chunk = chunked.chunks.get_mut(chunk0)?;
+                    chunk.get_disjoint_mut([slot0, slot1]).ok()?
+                } else {
+                    let [chunk0, chunk1] =
+                        chunked.chunks.get_disjoint_mut([chunk0, chunk1]).ok()?;
+                    [chunk0.get_mut(slot0)?, chunk1.get_mut(slot1)?]
+                };
+
+                match (entry0, entry1) {
+                    (Entry::Occupied(value0), Entry::Occupied(value1)) => Some((value0, value1)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Return the values of the distinct keys, or `None` if a key repeats or any row is not occupied.
+    pub(crate) fn get_disjoint_mut<const N: usize>(
+        &mut self,
+        keys: [usize; N],
+    ) -> Option<[&mut T; N]> {
+        match self {
+            Rows::Slab { slab, .. } => {
+                let values = slab.get_disjoint_mut(keys).ok()?;
+                if values.iter().any(|value| value.is_none()) {
+                    return None;
+                }
+                Some(values.map(|value| value.as_mut().unwrap()))
+            }
+            Rows::Chunked(chunked) => {
+                let chunk_size = chunked.chunk_size;
+
+                // walk the keys in ascending order, splitting each chunk as it is reached
+                let mut order: [usize; N] = std::array::from_fn(|i| i);
+                order.sort_unstable_by_key(|&i| keys[i]);
+
+                let mut values: [Option<&mut T>; N] = std::array::from_fn(|_| None);
+                let mut chunks = chunked.chunks.iter_mut();
+                let mut consumed = 0;
+                let mut rest: &mut [Entry<T>] = &mut [];
+                let mut rest_key = 0;
+                let mut chunk_end = 0;
+
+                for i in order {
+                    let key = keys[i];
+
+                    if key < rest_key {
+                        return None;
+                    }
+
+                    if key >= chunk_end {
+                        let chunk_index = key / chunk_size;
+                        rest = chunks.nth(chunk_index - consumed)?;
+                        consumed = chunk_index + 1;
+                        rest_key = chunk_index * chunk_size;
+                        chunk_end = rest_key + chunk_size;
+                    }
+
+                    let (_, tail) =
+                        std::mem::take(&mut rest).split_at_mut_checked(key - rest_key)?;
+                    let (entry, tail) = tail.split_first_mut()?;
+                    rest = tail;
+                    rest_key = key + 1;
+
+                    match entry {
+                        Entry::Occupied(value) => values[i] = Some(value),
+                        _ => return None,
+                    }
+                }
+
+                Some(values.map(|value| value.unwrap()))
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: T) -> usize {
+        match self {
+            Rows::Slab { slab, .. } => slab.insert(Some(value)),
+            Rows::Chunked(chunked) => {
+                let key = chunked.next;
+
+                if key == chunked.slots {
+                    chunked.push_entry(Entry::Occupied(value));
+                    chunked.next = chunked.slots;
+                } else {
+                    let entry = chunked.entry_mut(key).unwrap();
+                    let Entry::Vacant(next) = std::mem::replace(entry, Entry::Occupied(value))
+                    else {
+                        unreachable!("the vacant list points at an occupied entry");
+                    };
+                    chunked.next = next;
+                }
+
+                chunked.len += 1;
+                key
+            }
+        }
+    }
+
+    /// Insert the value at the key, which must be vacant.
+    /// The storage is rebuilt only if the key is not the next vacant key.
+    pub(crate) fn insert_at(&mut self, key: usize, value: T) {
+        if self.vacant_key() == key {
+            self.insert(value);
+            return;
+        }
+
+        match self {
+            Rows::Slab { slab, .. } => {
+                *slab = std::mem::take(slab)
+                    .into_iter()
+                    .chain(std::iter::once((key, Some(value))))
+                    .collect();
+            }
+            // the rows stay in place, and only the vacant list is rebuilt
+            Rows::Chunked(chunked) => {
+                while chunked.slots <= key {
+                    chunked.push_entry(Entry::Vacant(0));
+                }
+
+                *chunked.entry_mut(key).unwrap() = Entry::Occupied(value);
+                chunked.len += 1;
+                chunked.recreate_vacant_list();
+            }
+        }
+    }
+
+    pub(crate) fn try_remove(&mut self, key: usize) -> Option<T> {
+        match self {
+            Rows::Slab { slab, .. } => {
+                slab.get(key)?.as_ref()?;
+                slab.remove(key)
+            }
+            Rows::Chunked(chunked) => {
+                let next = chunked.next;
+                let entry = chunked.entry_mut(key)?;
+
+                if !matches!(entry, Entry::Occupied(_)) {
+                    return None;
+                }
+
+                let Entry::Occupied(value) = std::mem::replace(entry, Entry::Vacant(next)) else {
+                    unreachable!();
+                };
+                chunked.next = key;
+                chunked.len -= 1;
+
+                Some(value)
+            }
+        }
+    }
+
+    /// Take the value out of the occupied row, reserving the row.
+    pub(crate) fn take(&mut self, key: usize) -> Option<T> {
+        match self {
+            Rows::Slab { slab, reserved } => {
+                let value = slab.get_mut(key)?.take()?;
+                *reserved += 1;
+                Some(value)
+            }
+            Rows::Chunked(chunked) => {
+                let entry = chunked.entry_mut(key)?;
+
+                if !matches!(entry, Entry::Occupied(_)) {
+                    return None;
+                }
+
+                let Entry::Occupied(value) = std::mem::replace(entry, Entry::Reserved) else {
+                    unreachable!();
+                };
+                chunked.len -= 1;
+                chunked.reserved += 1;
+
+                Some(value)
+            }
+        }
+    }
+
+    /// Put the value back into the reserved row.
+    /// If the row is not reserved, return an `Err(T)`.
+    pub(crate) fn restore(&mut self, key: usize, value: T) -> Result<(), T> {
+        match self {
+            Rows::Slab { slab, reserved } => match slab.get_mut(key) {
+                Some(slot @ None) => {
+                    *slot = Some(value);
+                    *reserved -= 1;
+                    Ok(())
+                }
+                _ => Err(value),
+            },
+            Rows::Chunked(chunked) => match chunked.entry_mut(key) {
+                Some(entry @ Entry::Reserved) => {
+                    *entry = Entry::Occupied(value);
+                    chunked.len += 1;
+                    chunked.reserved -= 1;
+                    Ok(())
+                }
+                _ => Err(value),
+            },
+        }
+    }
+
+    /// Make the reserved row vacant, so that its key can be handed out again.
+    /// If the row is not reserved, return an `None`.
+    pub(crate) fn release(&mut self, key: usize) -> Option<()> {
+        match self {
+            Rows::Slab { slab, reserved } => {
+                if slab.get(key)?.is_some() {
+                    return None;
+                }
+
+                slab.remove(key);
+                *reserved -= 1;
+            }
+            Rows::Chunked(chunked) => {
+                let next = chunked.next;
+                let entry = chunked.entry_mut(key)?;
+
+                if !matches!(entry, Entry::Reserved) {
+                    return None;
+                }
+
+                *entry = Entry::Vacant(next);
+                chunked.next = key;
+                chunked.reserved -= 1;
+            }
+        }
+
+        Some(())
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_, T> {
+        match self {
+            Rows::Slab { slab, .. } => Iter::Slab(slab.iter()),
+            Rows::Chunked(chunked) => Iter::Chunked(chunked.chunks.iter().flatten().enumerate()),
+        }
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> IterMut<'_, T> {
+        match self {
+            Rows::Slab { slab, .. } => IterMut::Slab(slab.iter_mut()),
+            Rows::Chunked(chunked) => {
+                IterMut::Chunked(chunked.chunks.iter_mut().flatten().enumerate())
+            }
+        }
+    }
+
+    /// Move the last rows into the first vacant slots until the closure declines a move, as in `slab::Slab::compact`.
+    /// Afterwards, no vacant slot trails the last row, and the vacant list runs from the front.
+    /// A reserved row is never moved, and stops the compaction when it is the last row.
+    pub(crate) fn compact(&mut self, mut rekey: impl FnMut(&mut T, usize, usize) -> bool) {
+        match self {
+            Rows::Slab { slab, .. } => slab.compact(|value, from, to| match value {
+                Some(value) => rekey(value, from, to),
+                None => false,
+            }),
+            Rows::Chunked(chunked) => {
+                let mut occupied_until = 0;
+
+                while chunked.slots > chunked.len + chunked.reserved {
+                    let mut value = match chunked.pop_entry() {
+                        Entry::Occupied(value) => value,
+                        Entry::Reserved => {
+                            chunked.push_entry(Entry::Reserved);
+                            break;
+                        }
+                        Entry::Vacant(_) => continue,
+                    };
+
+                    while let Some(Entry::Occupied(_) | Entry::Reserved) =
+                        chunked.entry(occupied_until)
+                    {
+                        occupied_until += 1;
+                    }
+
+                    if !rekey(&mut value, chunked.slots, occupied_until) {
+                        chunked.push_entry(Entry::Occupied(value));
+                        break;
+                    }
+
+                    *chunked.entry_mut(occupied_until).unwrap() = Entry::Occupied(value);
+                    occupied_until += 1;
+                }
+
+                chunked.recreate_vacant_list();
+            }
+        }
+    }
+}
+
+impl<T> std::ops::Index<usize> for Rows<T> {
+    type Output = T;
+
+    fn index(&self, key: usize) -> &T {
+        self.get(key).expect("invalid key")
+    }
+}
+
+impl<T> std::ops::IndexMut<usize> for Rows<T> {
+    fn index_mut(&mut self, key: usize) -> &mut T {
+        self.get_mut(key).expect("invalid key")
+    }
+}
+
+impl<T> Chunked<T> {
+    fn entry(&self, key: usize) -> Option<&Entry<T>> {
+        self.chunks
+            .get(key / self.chunk_size)?
+            .get(key % self.chunk_size)
+    }
+
+    fn entry_mut(&mut self, key: usize) -> Option<&mut Entry<T>> {
+        self.chunks
+            .get_mut(key / self.chunk_size)?
+            .get_mut(key % self.chunk_size)
+    }
+
+    fn push_entry(&mut self, entry: Entry<T>) {
+        // a chunk is never pushed beyond its capacity, so its rows never move
+        if self
+            .chunks
+            .last()
+            .is_none_or(|chunk| chunk.len() == self.chunk_size)
+        {
+            self.chunks.push(Vec::with_capacity(self.chunk_size));
+        }
+
+        self.chunks.last_mut().unwrap().push(entry);
+        self.slots += 1;
+    }
+
+    fn pop_entry(&mut self) -> Entry<T> {
+        let chunk = self.chunks.last_mut().unwrap();
+        let entry = chunk.pop().unwrap();
+
+        if chunk.is_empty() {
+            self.chunks.pop();
+        }
+
+        self.slots -= 1;
+        entry
+    }
+
+    fn recreate_vacant_list(&mut self) {
+        self.next = self.slots;
+
+        // link the vacant entries from the back, so that the list runs from the front
+        for (chunk_index, chunk) in self.chunks.iter_mut().enumerate().rev() {
+            for (slot, entry) in chunk.iter_mut().enumerate().rev() {
+                if let Entry::Vacant(next) = entry {
+                    *next = self.next;
+                    self.next = chunk_index * self.chunk_size + slot;
+                }
+            }
+        }
+    }
+}
+
+type ChunkedIter<'a, T> =
+    std::iter::Enumerate<std::iter::Flatten<std::slice::Iter<'a, Vec<Entry<T>>>>>;
+
+type ChunkedIterMut<'a, T> =
+    std::iter::Enumerate<std::iter::Flatten<std::slice::IterMut<'a, Vec<Entry<T>>>>>;
+
+pub(crate) enum Iter<'a, T> {
+    Slab(slab::Iter<'a, Option<T>>),
+    Chunked(ChunkedIter<'a, T>),
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Slab(iter) => iter.find_map(|(key, value)| Some((key, value.as_ref()?))),
+            Iter::Chunked(iter) => iter.find_map(|(key, entry)| match entry {
+                Entry::Occupied(value) => Some((key, value)),
+                _ => None,
+            }),
+        }
+    }
+}
+
+pub(crate) enum IterMut<'a, T> {
+    Slab(slab::IterMut<'a, Option<T>>),
+    Chunked(ChunkedIterMut<'a, T>),
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = (usize, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IterMut::Slab(iter) => iter.find_map(|(key, value)| Some((key, value.as_mut()?))),
+            IterMut::Chunked(iter) => iter.find_map(|(key, entry)| match entry {
+                Entry::Occupied(value) => Some((key, value)),
+                _ => None,
+            }),
+        }
+    }
+}
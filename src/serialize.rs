@@ -0,0 +1,705 @@
+use serde::de::{DeserializeOwned, DeserializeSeed, Error as _, IgnoredAny, MapAccess, SeqAccess};
+use serde::ser::{Error as _, SerializeMap, SerializeStruct};
+use serde::{Deserializer, Serialize, Serializer};
+
+use crate::{CompRow, EntityKey, Error, PartitionId, Registered, Rows, WorldHasher, WorldId, ECS};
+
+/// How [`ECS::serialize`] and [`ECS::deserialize_into`] treat the components outside the serialized types,
+/// along with the taken components and, when loading, the component type names no serialized type goes by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unserializable {
+    /// Leave the components out.
+    Skip,
+    /// Fail with an error of the format.
+    Fail,
+}
+
+/// The component types written by [`ECS::serialize`] and read by [`ECS::deserialize_into`],
+/// as a tuple of types registered by [`ECS::register_serde`].
+///
+/// The columns are type-erased, so the world cannot find the serializer of a column by itself,
+/// and the types are named once more where the world is saved or loaded.
+pub trait SerdeComps {
+    #[doc(hidden)]
+    fn check<W: WorldHasher>(ecs: &ECS<W>) -> Result<(), String>;
+
+    #[doc(hidden)]
+    fn contains(type_key: std::any::TypeId) -> bool;
+
+    #[doc(hidden)]
+    fn serialize_comps<M, W: WorldHasher>(ecs: &ECS<W>, map: &mut M) -> Result<(), M::Error>
+    where
+        M: SerializeMap;
+
+    /// Read the value of the entry if the name is the one of a type, and return whether it is.
+    #[doc(hidden)]
+    fn deserialize_comps<'de, M, W: WorldHasher>(
+        ecs: &mut ECS<W>,
+        name: &str,
+        map: &mut M,
+    ) -> Result<bool, M::Error>
+    where
+        M: MapAccess<'de>;
+}
+
+macro_rules! impl_serde_comps {
+    ($($comp:ident),*) => {
+        impl<$($comp),*> SerdeComps for ($($comp,)*)
+        where
+            $($comp: Serialize + DeserializeOwned + std::any::Any),*
+        {
+            fn check<W: WorldHasher>(ecs: &ECS<W>) -> Result<(), String> {
+                $(serde_name::<$comp, W>(ecs)?;)*
+                Ok(())
+            }
+
+            fn contains(type_key: std::any::TypeId) -> bool {
+                $(type_key == std::any::TypeId::of::<$comp>())||*
+            }
+
+            fn serialize_comps<M, W: WorldHasher>(ecs: &ECS<W>, map: &mut M) -> Result<(), M::Error>
+            where
+                M: SerializeMap,
+            {
+                $(map.serialize_entry(
+                    serde_name::<$comp, W>(ecs).map_err(M::Error::custom)?,
+                    &ColumnSer::<$comp, W>::new(ecs),
+                )?;)*
+                Ok(())
+            }
+
+            fn deserialize_comps<'de, M, W: WorldHasher>(ecs: &mut ECS<W>, name: &str, map: &mut M) -> Result<bool, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                $(if serde_name::<$comp, W>(ecs).map_err(M::Error::custom)? == name {
+                    map.next_value_seed(ColumnSeed::<$comp, W>::new(ecs))?;
+                    return Ok(true);
+                })*
+                Ok(false)
+            }
+        }
+    };
+}
+
+impl_serde_comps!(A);
+impl_serde_comps!(A, B);
+impl_serde_comps!(A, B, C);
+impl_serde_comps!(A, B, C, D);
+impl_serde_comps!(A, B, C, D, E);
+impl_serde_comps!(A, B, C, D, E, F);
+impl_serde_comps!(A, B, C, D, E, F, G);
+impl_serde_comps!(A, B, C, D, E, F, G, H);
+
+/// Return the name of the component type given by `register_serde`, or the message of the error.
+fn serde_name<T, W: WorldHasher>(ecs: &ECS<W>) -> Result<&'static str, String>
+where
+    T: std::any::Any,
+{
+    let comp_col = ecs
+        .comp_cols
+        .get(&std::any::TypeId::of::<T>())
+        .ok_or_else(|| {
+            format!(
+                "component type not registered: {}",
+                std::any::type_name::<T>()
+            )
+        })?;
+
+    comp_col.serde_name.ok_or_else(|| {
+        format!(
+            "component type not registered for serde: {}",
+            comp_col.type_name
+        )
+    })
+}
+
+const WORLD_FIELDS: &[&str] = &["world", "generations", "entities", "comps"];
+const COLUMN_FIELDS: &[&str] = &["generations", "rows"];
+
+/// The components of the serialized types, by name.
+struct CompsSer<'a, C, W> {
+    ecs: &'a ECS<W>,
+    marker: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<C, W: WorldHasher> Serialize for CompsSer<'_, C, W>
+where
+    C: SerdeComps,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        C::serialize_comps(self.ecs, &mut map)?;
+        map.end()
+    }
+}
+
+/// The generations of the rows of a column and its components,
+/// each with the row key and the slot index of its entity.
+struct ColumnSer<'a, T, W> {
+    ecs: &'a ECS<W>,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<'a, T, W: WorldHasher> ColumnSer<'a, T, W> {
+    fn new(ecs: &'a ECS<W>) -> Self {
+        Self {
+            ecs,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, W: WorldHasher> Serialize for ColumnSer<'_, T, W>
+where
+    T: Serialize + std::any::Any,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+        let comp_rows = self.ecs.comp_cols[&type_key]
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        // the rows are written in the order of each entity, which the insertion on load keeps
+        let rows = saved_entities(self.ecs).flat_map(|entity_key| {
+            self.ecs
+                .ref_1_cols
+                .get(&(entity_key, type_key))
+                .into_iter()
+                .flatten()
+                .filter_map(move |row_key| {
+                    let comp_row = comp_rows.get(*row_key as usize)?;
+                    Some((*row_key, entity_key.index, &comp_row.comp))
+                })
+        });
+
+        let mut state = serializer.serialize_struct("Column", COLUMN_FIELDS.len())?;
+        state.serialize_field("generations", self.ecs.comp_generations.of(type_key))?;
+        state.serialize_field("rows", &Seq(std::cell::Cell::new(Some(rows))))?;
+        state.end()
+    }
+}
+
+/// An iterator serialized as a sequence, at most once.
+struct Seq<I>(std::cell::Cell<Option<I>>);
+
+impl<I> Serialize for Seq<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.take().unwrap())
+    }
+}
+
+/// Return an iterator over the entity keys of the entities written by `serialize`, leaving out the reserved ones.
+fn saved_entities<W: WorldHasher>(ecs: &ECS<W>) -> impl Iterator<Item = EntityKey> + '_ {
+    ecs.iter_entity_slots()
+        .filter(|(_, slot)| !slot.is_reserved())
+        .map(|(entity_key, _)| entity_key)
+}
+
+/// Return the message of the error if the world holds components left out in `Unserializable::Fail`.
+fn check_unserializable<C, W: WorldHasher>(ecs: &ECS<W>) -> Result<(), String>
+where
+    C: SerdeComps,
+{
+    if let Some(comp_col) = ecs
+        .comp_cols
+        .iter()
+        .find(|(type_key, comp_col)| !C::contains(**type_key) && (comp_col.len_fn)(comp_col) > 0)
+        .map(|(_, comp_col)| comp_col)
+    {
+        return Err(format!(
+            "components of a type not serialized: {}",
+            comp_col.type_name
+        ));
+    }
+
+    if ecs.taken.values().any(|taken_row| taken_row.is_live()) {
+        return Err("components taken".to_string());
+    }
+
+    Ok(())
+}
+
+/// The world state shared by the seeds of `deserialize_into`.
+struct WorldSeed<'a, C, W> {
+    ecs: &'a mut ECS<W>,
+    mode: Unserializable,
+    marker: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<'de, C, W: WorldHasher> DeserializeSeed<'de> for WorldSeed<'_, C, W>
+where
+    C: SerdeComps,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("World", WORLD_FIELDS, self)
+    }
+}
+
+impl<'de, C, W: WorldHasher> serde::de::Visitor<'de> for WorldSeed<'_, C, W>
+where
+    C: SerdeComps,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a serialized world")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let world = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let generations = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+        let entities = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(2, &self))?;
+        load_entities(self.ecs, world, generations, entities).map_err(A::Error::custom)?;
+
+        let comps = CompsSeed::<C, W> {
+            ecs: self.ecs,
+            mode: self.mode,
+            marker: std::marker::PhantomData,
+        };
+        seq.next_element_seed(comps)?
+            .ok_or_else(|| A::Error::invalid_length(3, &"a serialized world"))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut world = None;
+        let mut generations = None;
+        let mut entities = None;
+
+        // the entities are needed by the components, so the fields are read in the order `serialize` writes them
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "world" => world = Some(map.next_value()?),
+                "generations" => generations = Some(map.next_value()?),
+                "entities" => entities = Some(map.next_value()?),
+                "comps" => {
+                    let world = world.ok_or_else(|| A::Error::missing_field("world"))?;
+                    let generations =
+                        generations.ok_or_else(|| A::Error::missing_field("generations"))?;
+                    let entities = entities.ok_or_else(|| A::Error::missing_field("entities"))?;
+                    load_entities(self.ecs, world, generations, entities)
+                        .map_err(A::Error::custom)?;
+
+                    map.next_value_seed(CompsSeed::<C, W> {
+                        ecs: self.ecs,
+                        mode: self.mode,
+                        marker: std::marker::PhantomData,
+                    })?;
+                    while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+                    return Ok(());
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Err(A::Error::missing_field("comps"))
+    }
+}
+
+/// Adopt the world identifier and the generations of the entity slots, and insert the entities at their slots.
+fn load_entities<W: WorldHasher>(
+    ecs: &mut ECS<W>,
+    world: u32,
+    generations: Vec<u32>,
+    entities: Vec<u32>,
+) -> Result<(), String> {
+    ecs.world = WorldId(world);
+    ecs.generations = generations;
+
+    for index in entities {
+        let generation = *ecs
+            .generations
+            .get(index as usize)
+            .ok_or_else(|| format!("entity slot without generation: {}", index))?;
+
+        let entity_key = EntityKey {
+            index,
+            generation,
+            world: ecs.world,
+        };
+        ecs.insert_entity_slot(entity_key, PartitionId::DEFAULT)
+            .ok_or_else(|| format!("entity slot occupied: {}", index))?;
+    }
+
+    Ok(())
+}
+
+/// The components of the serialized types, by name, read into the world.
+struct CompsSeed<'a, C, W> {
+    ecs: &'a mut ECS<W>,
+    mode: Unserializable,
+    marker: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<'de, C, W: WorldHasher> DeserializeSeed<'de> for CompsSeed<'_, C, W>
+where
+    C: SerdeComps,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, C, W: WorldHasher> serde::de::Visitor<'de> for CompsSeed<'_, C, W>
+where
+    C: SerdeComps,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a map of components by type name")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(name) = map.next_key::<String>()? {
+            if C::deserialize_comps(self.ecs, &name, &mut map)? {
+                continue;
+            }
+
+            match self.mode {
+                Unserializable::Skip => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+                Unserializable::Fail => {
+                    return Err(A::Error::custom(format!(
+                        "component type name not serialized: {}",
+                        name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The generations and the components of a column, read into the world.
+struct ColumnSeed<'a, T, W> {
+    ecs: &'a mut ECS<W>,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<'a, T, W: WorldHasher> ColumnSeed<'a, T, W> {
+    fn new(ecs: &'a mut ECS<W>) -> Self {
+        Self {
+            ecs,
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    fn load_generations(&mut self, generations: Vec<u32>)
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+        for (row_key, generation) in generations.into_iter().enumerate() {
+            self.ecs
+                .comp_generations
+                .set(type_key, row_key as u32, generation);
+        }
+    }
+}
+
+impl<'de, T, W: WorldHasher> DeserializeSeed<'de> for ColumnSeed<'_, T, W>
+where
+    T: DeserializeOwned + std::any::Any,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Column", COLUMN_FIELDS, self)
+    }
+}
+
+impl<'de, T, W: WorldHasher> serde::de::Visitor<'de> for ColumnSeed<'_, T, W>
+where
+    T: DeserializeOwned + std::any::Any,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a serialized column")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let generations = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        self.load_generations(generations);
+
+        seq.next_element_seed(RowsSeed::<T, W> {
+            ecs: self.ecs,
+            marker: std::marker::PhantomData,
+        })?
+        .ok_or_else(|| A::Error::invalid_length(1, &"a serialized column"))
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut rows = false;
+
+        // the generations come first, so that the components are inserted at their keys
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "generations" if !rows => {
+                    let generations = map.next_value()?;
+                    self.load_generations(generations);
+                }
+                "rows" if !rows => {
+                    map.next_value_seed(RowsSeed::<T, W> {
+                        ecs: self.ecs,
+                        marker: std::marker::PhantomData,
+                    })?;
+                    rows = true;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        if !rows {
+            return Err(A::Error::missing_field("rows"));
+        }
+
+        Ok(())
+    }
+}
+
+/// The components of a column, each with the row key and the slot index of its entity, read into the world.
+struct RowsSeed<'a, T, W> {
+    ecs: &'a mut ECS<W>,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<'de, T, W: WorldHasher> DeserializeSeed<'de> for RowsSeed<'_, T, W>
+where
+    T: DeserializeOwned + std::any::Any,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T, W: WorldHasher> serde::de::Visitor<'de> for RowsSeed<'_, T, W>
+where
+    T: DeserializeOwned + std::any::Any,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a sequence of components")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while let Some((row_key, index, comp)) = seq.next_element::<(u32, u32, T)>()? {
+            let entity_key = EntityKey {
+                index,
+                generation: self
+                    .ecs
+                    .generations
+                    .get(index as usize)
+                    .copied()
+                    .unwrap_or(0),
+                world: self.ecs.world,
+            };
+            if self.ecs.check_entity(entity_key).is_err() {
+                return Err(A::Error::custom(format!("entity not found: {}", index)));
+            }
+
+            self.ecs
+                .insert_comp_row_at(entity_key, row_key, None, None, comp)
+                .ok_or_else(|| A::Error::custom(format!("component row occupied: {}", row_key)))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: WorldHasher> ECS<W> {
+    /// Register the component type under the name, as in `register_named`,
+    /// which it goes by in serialized worlds, as in `serialize` and `deserialize_into`.
+    /// If the component type is already registered, return an `Err(Error::TypeAlreadyRegistered)`.
+    /// If another component type goes by the name, return an `Err(Error::DuplicateTypeName)`.
+    /// Otherwise, return an `Ok(Registered<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.register_serde::<i32>("hp").unwrap();
+    ///
+    /// assert_eq!(
+    ///     ecs.register_serde::<u32>("hp").err(),
+    ///     Some(ecs_tiny::Error::DuplicateTypeName("hp")),
+    /// );
+    /// ```
+    pub fn register_serde<T>(&mut self, name: &'static str) -> Result<Registered<T>, Error>
+    where
+        T: Serialize + DeserializeOwned + std::any::Any + Send + Sync,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        if self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeAlreadyRegistered);
+        }
+
+        if self
+            .comp_cols
+            .values()
+            .any(|comp_col| comp_col.serde_name == Some(name))
+        {
+            return Err(Error::DuplicateTypeName(name));
+        }
+
+        self.insert_comp_col_named::<T>(name)?.serde_name = Some(name);
+        self.record_register::<T>(Self::register_op::<T>);
+
+        Ok(self.registered::<T>().unwrap())
+    }
+
+    /// Serialize the world: the world identifier, the generations of the entity slots, the alive entities,
+    /// and the components of the types, registered by `register_serde`, each with its row key, generation and entity,
+    /// so that the entity keys and component keys handed out by the world resolve in the world loaded by `deserialize_into`.
+    /// The flags and partitions of the entities, the reserved entities and the configurations of the types are not written.
+    /// In `Unserializable::Skip`, the components of other types and the taken components are left out.
+    /// In `Unserializable::Fail`, if the world holds any of them, return an error of the serializer.
+    /// If a component type is not registered by `register_serde`, return an error of the serializer.
+    /// Otherwise, return the output of the serializer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ecs_tiny::Unserializable;
+    ///
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_serde::<i32>("hp").unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let mut json = Vec::new();
+    /// let mut serializer = serde_json::Serializer::new(&mut json);
+    /// ecs.serialize::<(i32,), _>(&mut serializer, Unserializable::Fail).unwrap();
+    ///
+    /// let mut loaded = ecs_tiny::ECS::new();
+    /// loaded.register_serde::<i32>("hp").unwrap();
+    /// let mut deserializer = serde_json::Deserializer::from_slice(&json);
+    /// loaded.deserialize_into::<(i32,), _>(&mut deserializer, Unserializable::Fail).unwrap();
+    ///
+    /// assert_eq!(loaded.get_comp::<i32>(comp_key), Ok(&42));
+    /// assert_eq!(loaded.get_entity_by_comp(comp_key), Ok(entity_key));
+    /// ```
+    pub fn serialize<C, S>(&self, serializer: S, mode: Unserializable) -> Result<S::Ok, S::Error>
+    where
+        C: SerdeComps,
+        S: Serializer,
+    {
+        C::check(self).map_err(S::Error::custom)?;
+        if mode == Unserializable::Fail {
+            check_unserializable::<C, W>(self).map_err(S::Error::custom)?;
+        }
+
+        let generations = &self.generations;
+        let entities = saved_entities(self).map(|entity_key| entity_key.index);
+        let comps = CompsSer::<C, W> {
+            ecs: self,
+            marker: std::marker::PhantomData,
+        };
+
+        let mut state = serializer.serialize_struct("World", WORLD_FIELDS.len())?;
+        state.serialize_field("world", &self.world.0)?;
+        state.serialize_field("generations", generations)?;
+        state.serialize_field("entities", &Seq(std::cell::Cell::new(Some(entities))))?;
+        state.serialize_field("comps", &comps)?;
+        state.end()
+    }
+
+    /// Deserialize a world written by `serialize` into the world, which must hold no entity,
+    /// adopting the world identifier, so that the saved entity keys and component keys resolve, as in `replay`.
+    /// The component types must be registered by `register_serde` under the names they were saved with.
+    /// The components are inserted without checks, so neither the insert validators nor the insert hooks run.
+    /// In `Unserializable::Skip`, the components of names no component type goes by are left out.
+    /// In `Unserializable::Fail`, if any are found, return an error of the deserializer.
+    /// If the world holds any entity, or a component type is not registered by `register_serde`,
+    /// return an error of the deserializer.
+    /// Otherwise, return an `Ok(())`.
+    /// On error, the world may be left partly loaded.
+    pub fn deserialize_into<'de, C, D>(
+        &mut self,
+        deserializer: D,
+        mode: Unserializable,
+    ) -> Result<(), D::Error>
+    where
+        C: SerdeComps,
+        D: Deserializer<'de>,
+    {
+        C::check(self).map_err(D::Error::custom)?;
+        if !self.entities.is_empty() {
+            return Err(D::Error::custom("world not empty"));
+        }
+
+        WorldSeed::<C, W> {
+            ecs: self,
+            mode,
+            marker: std::marker::PhantomData,
+        }
+        .deserialize(deserializer)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::generations::CompGenerations;
+use crate::take::{missing_comp, TakenRow, TakenRows};
+use crate::{Column, CompColumn, CompKey, CompRow, EntityKey, Error, Rows, WorldHasher, WorldId};
+
+/// Read access to the columns left over by [`ECS::shard`](crate::ECS::shard),
+/// held alongside the mutable handles of the sharded columns.
+///
+/// The reference tables are shared, so the rows of the sharded component types can still be looked up by entity.
+pub struct ShardRest<'a, S = crate::DefaultHashBuilder> {
+    comp_cols: crate::hash::HashMap<std::any::TypeId, &'a CompColumn<S>, S>,
+    ref_1_cols: &'a crate::hash::HashMap<(EntityKey, std::any::TypeId), crate::refs::RefList, S>,
+    taken: &'a TakenRows<S>,
+    comp_generations: &'a CompGenerations<S>,
+    world: WorldId,
+}
+
+impl<'a, S: WorldHasher> ShardRest<'a, S> {
+    pub(crate) fn new(
+        comp_cols: crate::hash::HashMap<std::any::TypeId, &'a CompColumn<S>, S>,
+        ref_1_cols: &'a crate::hash::HashMap<
+            (EntityKey, std::any::TypeId),
+            crate::refs::RefList,
+            S,
+        >,
+        taken: &'a TakenRows<S>,
+        comp_generations: &'a CompGenerations<S>,
+        world: WorldId,
+    ) -> Self {
+        Self {
+            comp_cols,
+            ref_1_cols,
+            taken,
+            comp_generations,
+            world,
+        }
+    }
+
+    /// Return a read-only handle of the column of the component type.
+    /// If the component type is not registered or is sharded out, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(Column<T>)`.
+    pub fn column<T>(&self) -> Result<Column<'a, T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_rows = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap();
+
+        Ok(Column::new(
+            comp_rows,
+            self.comp_generations.of(type_key),
+            self.world,
+        ))
+    }
+
+    /// Return a component with the corresponding component key and type.
+    /// If the component key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the component key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// If the component type is not registered or is sharded out, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component is taken, as in `ECS::take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(&T)`.
+    pub fn get_comp<T>(&self, comp_key: CompKey) -> Result<&'a T, Error>
+    where
+        T: std::any::Any,
+    {
+        if comp_key.world != self.world {
+            return Err(Error::WrongWorld);
+        }
+
+        if comp_key.type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let column = self.column::<T>()?;
+
+        if comp_key.generation
+            != self
+                .comp_generations
+                .get(comp_key.type_key, comp_key.row_key)
+        {
+            return Err(Error::CompNotFound);
+        }
+
+        column
+            .get(comp_key.row_key)
+            .ok_or_else(|| missing_comp(self.taken, comp_key.type_key, comp_key.row_key))
+    }
+
+    /// Return an iterator over the row keys of all components with the corresponding entity key and type,
+    /// including the sharded component types, as in `ECS::rows_by_entity`.
+    /// The iterator is empty if the entity is not found or has no components of the type.
+    pub fn rows_by_entity<T>(&self, entity_key: EntityKey) -> impl Iterator<Item = u32> + 'a
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+        let taken = self.taken;
+
+        self.ref_1_cols
+            .get(&(entity_key, type_key))
+            .into_iter()
+            .flat_map(|ref_1_col| ref_1_col.iter().copied())
+            .filter(move |row_key| {
+                taken
+                    .get(&(type_key, *row_key))
+                    .is_none_or(TakenRow::is_live)
+            })
+    }
+}
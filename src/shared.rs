@@ -0,0 +1,150 @@
+/// A handle of a shared immutable component interned in the per-type pool.
+/// Handles of equal values point at the same allocation,
+/// and the pool entry is dropped together with the last handle.
+///
+/// # Examples
+///
+/// ```
+/// let mut ecs = ecs_tiny::ECS::new();
+/// ecs.register_shared::<String>().unwrap();
+/// let handle0 = ecs.intern_shared("material".to_string()).unwrap();
+/// let handle1 = ecs.intern_shared("material".to_string()).unwrap();
+///
+/// assert!(ecs_tiny::SharedComp::ptr_eq(&handle0, &handle1));
+/// assert_eq!(*handle0, "material");
+/// ```
+pub struct SharedComp<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    value: std::sync::Arc<T>,
+    pool: std::sync::Weak<std::sync::Mutex<SharedPool<T>>>,
+}
+
+impl<T> SharedComp<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    /// Return whether two handles point at the same interned value.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&this.value, &other.value)
+    }
+}
+
+impl<T> Clone for SharedComp<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<T> std::ops::Deref for SharedComp<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> std::fmt::Debug for SharedComp<T>
+where
+    T: std::hash::Hash + Eq + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SharedComp").field(&self.value).finish()
+    }
+}
+
+impl<T> Drop for SharedComp<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    fn drop(&mut self) {
+        if std::sync::Arc::strong_count(&self.value) > 1 {
+            return;
+        }
+
+        if let Some(pool) = self.pool.upgrade() {
+            pool.lock().unwrap().remove(&self.value);
+        }
+    }
+}
+
+/// A per-type pool of interned values, grouped by hash.
+pub(crate) struct SharedPool<T> {
+    hasher: crate::hash::DefaultHashBuilder,
+    entries: crate::hash::HashMap<u64, Vec<std::sync::Weak<T>>>,
+}
+
+impl<T> SharedPool<T>
+where
+    T: std::hash::Hash + Eq,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            hasher: Default::default(),
+            entries: Default::default(),
+        }
+    }
+
+    pub(crate) fn intern(pool: &std::sync::Arc<std::sync::Mutex<Self>>, value: T) -> SharedComp<T> {
+        let mut guard = pool.lock().unwrap();
+        let hash = std::hash::BuildHasher::hash_one(&guard.hasher, &value);
+
+        let entries = guard.entries.entry(hash).or_default();
+        entries.retain(|entry| entry.strong_count() > 0);
+
+        let found = entries
+            .iter()
+            .filter_map(|entry| entry.upgrade())
+            .find(|entry| **entry == value);
+
+        let value = match found {
+            Some(value) => value,
+            None => {
+                let value = std::sync::Arc::new(value);
+                entries.push(std::sync::Arc::downgrade(&value));
+                value
+            }
+        };
+
+        SharedComp {
+            value,
+            pool: std::sync::Arc::downgrade(pool),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries
+            .values()
+            .flatten()
+            .filter(|entry| entry.strong_count() > 0)
+            .count()
+    }
+
+    /// Remove the entry of the value if the given handle is the last one.
+    /// The reference count is checked under the lock so that a concurrent intern cannot revive the entry.
+    fn remove(&mut self, value: &std::sync::Arc<T>) {
+        if std::sync::Arc::strong_count(value) > 1 {
+            return;
+        }
+
+        let hash = std::hash::BuildHasher::hash_one(&self.hasher, &**value);
+
+        if let Some(entries) = self.entries.get_mut(&hash) {
+            entries.retain(|entry| !std::ptr::eq(entry.as_ptr(), std::sync::Arc::as_ptr(value)));
+
+            if entries.is_empty() {
+                self.entries.remove(&hash);
+            }
+        }
+    }
+}
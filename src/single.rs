@@ -0,0 +1,133 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, ECS};
+
+impl<S: WorldHasher> ECS<S> {
+    /// Return the only component with the corresponding entity key and type,
+    /// for component types of which each entity holds at most one.
+    /// If the entity corresponding to the entity key is not found, the component type is not registered,
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the entity holds several components of the type, return an `Err(Error::AmbiguousComp)`.
+    /// If the entity holds no component of the type, return an `Err(Error::CompNotFound)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// Otherwise, return an `Ok(&T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp_by_entity::<i32>(entity_key), Ok(&42));
+    ///
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp_by_entity::<i32>(entity_key), Err(ecs_tiny::Error::AmbiguousComp));
+    /// ```
+    pub fn get_comp_by_entity<T>(&self, entity_key: EntityKey) -> Result<&T, Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_key = self
+            .only_comp_key::<T>(entity_key)?
+            .ok_or(Error::CompNotFound)?;
+        self.get_comp::<T>(comp_key)
+    }
+
+    /// Set the only component with the corresponding entity key and type,
+    /// replacing the value of the component in place, so that its component key is kept,
+    /// or inserting the component, as in `insert_comp`, if the entity holds none.
+    /// If the entity corresponding to the entity key is not found, the component type is not registered,
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the entity holds several components of the type, nothing is changed and return an `Err(Error::AmbiguousComp)`.
+    /// If the component cannot be replaced or inserted, return the error of `replace_comp` or `insert_comp`.
+    /// If the component is inserted, return an `Ok(None)`.
+    /// Otherwise, return an `Ok(Some(T))` with the replaced value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    ///
+    /// assert_eq!(ecs.set_comp(entity_key, 42), Ok(None));
+    /// assert_eq!(ecs.set_comp(entity_key, 63), Ok(Some(42)));
+    /// assert_eq!(ecs.get_comp_by_entity::<i32>(entity_key), Ok(&63));
+    /// ```
+    pub fn set_comp<T>(&mut self, entity_key: EntityKey, comp: T) -> Result<Option<T>, Error>
+    where
+        T: std::any::Any,
+    {
+        match self.only_comp_key::<T>(entity_key)? {
+            Some(comp_key) => Ok(Some(self.replace_comp(comp_key, comp)?)),
+            None => {
+                self.insert_comp(entity_key, comp)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Remove the only component with the corresponding entity key and type, and return the component.
+    /// If the entity corresponding to the entity key is not found, the component type is not registered,
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the entity holds several components of the type, nothing is removed and return an `Err(Error::AmbiguousComp)`.
+    /// If the entity holds no component of the type, return an `Err(Error::CompNotFound)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// Otherwise, return an `Ok(T)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.set_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.remove_comp_by_entity::<i32>(entity_key), Ok(42));
+    /// assert_eq!(ecs.remove_comp_by_entity::<i32>(entity_key), Err(ecs_tiny::Error::CompNotFound));
+    /// ```
+    pub fn remove_comp_by_entity<T>(&mut self, entity_key: EntityKey) -> Result<T, Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_key = self
+            .only_comp_key::<T>(entity_key)?
+            .ok_or(Error::CompNotFound)?;
+        self.remove_comp::<T>(comp_key)
+    }
+
+    /// Return the key of the only component of the type held by the entity, counting taken components.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the entity is not found, return the error of `check_entity`.
+    /// If the entity holds several components, return an `Err(Error::AmbiguousComp)`.
+    /// Otherwise, return an `Ok(Option<CompKey>)`, which is `None` if the entity holds no component of the type.
+    fn only_comp_key<T>(&self, entity_key: EntityKey) -> Result<Option<CompKey>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
+        }
+        self.check_entity(entity_key)?;
+
+        let mut row_keys = self
+            .ref_1_cols
+            .get(&(entity_key, type_key))
+            .into_iter()
+            .flatten();
+        match (row_keys.next(), row_keys.next()) {
+            (None, _) => Ok(None),
+            (Some(row_key), None) => Ok(Some(self.comp_key(type_key, *row_key))),
+            (Some(_), Some(_)) => Err(Error::AmbiguousComp),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{CloneError, Error, WorldHasher, ECS};
+
+/// A copy of the state of a world, taken by [`ECS::snapshot`] and put back by [`ECS::restore`].
+///
+/// The copy is a clone of the world, as in `clone_filtered`, so every entity key and component key
+/// resolves to the same value after restoring as when the snapshot was taken.
+pub struct Snapshot<S = crate::DefaultHashBuilder> {
+    ecs: ECS<S>,
+}
+
+impl<S> Snapshot<S> {
+    /// Return the frame of the world when the snapshot was taken, as in `ECS::frame`.
+    pub fn frame(&self) -> u64 {
+        self.ecs.frame
+    }
+}
+
+/// A buffer of the last snapshots of a world, dropping the oldest one once full, as for rollback over a few frames.
+///
+/// # Examples
+///
+/// ```
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let mut ring = ecs_tiny::SnapshotRing::new(2);
+/// for _ in 0..3 {
+///     ring.push(ecs.snapshot().unwrap());
+///     ecs.end_frame();
+/// }
+///
+/// assert_eq!(ring.len(), 2);
+/// assert!(ring.get(0).is_none());
+/// assert_eq!(ring.latest().map(|snapshot| snapshot.frame()), Some(2));
+///
+/// ecs.restore(ring.get(1).unwrap()).unwrap();
+/// assert_eq!(ecs.frame(), 1);
+/// ```
+pub struct SnapshotRing<S = crate::DefaultHashBuilder> {
+    snapshots: std::collections::VecDeque<Snapshot<S>>,
+    capacity: usize,
+}
+
+impl<S> SnapshotRing<S> {
+    /// Create a new empty buffer keeping at most the number of snapshots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "snapshot ring capacity must be non-zero");
+
+        Self {
+            snapshots: std::collections::VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Push the snapshot as the latest one, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, snapshot: Snapshot<S>) {
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Return the latest snapshot.
+    /// If the buffer is empty, return an `None`.
+    /// Otherwise, return an `Some(&Snapshot)`.
+    pub fn latest(&self) -> Option<&Snapshot<S>> {
+        self.snapshots.back()
+    }
+
+    /// Return the latest snapshot taken in the frame, as in `Snapshot::frame`.
+    /// If no snapshot of the frame is kept, return an `None`.
+    /// Otherwise, return an `Some(&Snapshot)`.
+    pub fn get(&self, frame: u64) -> Option<&Snapshot<S>> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.frame() == frame)
+    }
+
+    /// Return the number of snapshots kept.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Return whether no snapshot is kept.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Take a snapshot of the world, cloning every entity and component, as in `clone_filtered`,
+    /// to be put back by `restore`.
+    /// Taken components, as in `take_comp`, and reserved entities, as in `reserve_entity`, are left out.
+    /// If the world holds a component of a type registered without clone support, as in `register_cloneable`,
+    /// return an `Err(CloneError::NotCloneable)`.
+    /// Otherwise, return an `Ok(Snapshot)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// let snapshot = ecs.snapshot().unwrap();
+    /// *ecs.get_comp_mut::<i32>(comp_key).unwrap() += 1;
+    /// ecs.remove_entity(entity_key).unwrap();
+    ///
+    /// ecs.restore(&snapshot).unwrap();
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+    /// assert_eq!(ecs.get_entity_by_comp(comp_key), Ok(entity_key));
+    /// ```
+    pub fn snapshot(&self) -> Result<Snapshot<S>, CloneError> {
+        Ok(Snapshot {
+            ecs: self.clone_filtered(|_| true)?,
+        })
+    }
+
+    /// Replace the whole state of the world with the snapshot taken by `snapshot`,
+    /// including the registrations of the component types, the partitions and the frame counter,
+    /// so that the snapshot can be restored again later.
+    /// The keys handed out after the snapshot was taken are handed out again,
+    /// and the queued hook commands and taken components of the world in the meantime are dropped.
+    /// The journal and the lifecycle events keep being collected, but the restore is not recorded by them,
+    /// and the undo history is discarded.
+    /// If the snapshot belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// Otherwise, return an `Ok(())`.
+    pub fn restore(&mut self, snapshot: &Snapshot<S>) -> Result<(), Error> {
+        if snapshot.ecs.world != self.world {
+            return Err(Error::WrongWorld);
+        }
+
+        // the snapshot holds components of cloneable types only, so cloning it cannot fail
+        let mut ecs = snapshot.ecs.clone_filtered(|_| true).unwrap();
+        ecs.journal = self.journal.take();
+        ecs.undo = self.undo.take();
+        ecs.lifecycle_events = self.lifecycle_events.take();
+        ecs.key_limit = self.key_limit;
+        *self = ecs;
+
+        self.discard_undo();
+
+        Ok(())
+    }
+}
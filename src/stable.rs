@@ -0,0 +1,216 @@
+use crate::{CompKey, CompRow, Error, Rows, WorldHasher, ECS};
+
+/// The stable ids of a column, as in [`ECS::enable_stable_ids`].
+#[derive(Clone)]
+pub(crate) struct StableIds<S> {
+    next: u64,
+    /// The current row keys of the components, by their stable ids.
+    pub(crate) rows: crate::hash::HashMap<u64, u32, S>,
+}
+
+impl<S: WorldHasher> StableIds<S> {
+    pub(crate) fn with_hasher(hasher: S) -> Self {
+        Self {
+            next: 0,
+            rows: crate::hash::HashMap::with_hasher(hasher),
+        }
+    }
+
+    /// Hand out the next stable id to the row.
+    pub(crate) fn assign(&mut self, row_key: u32) -> u64 {
+        let stable_id = self.next;
+        self.next += 1;
+        self.rows.insert(stable_id, row_key);
+        stable_id
+    }
+}
+
+/// Drop the stable id of a removed component from the stable ids of its column, if any.
+pub(crate) fn forget<S: WorldHasher>(
+    stable_ids: &mut Option<StableIds<S>>,
+    stable_id: Option<u64>,
+) {
+    if let (Some(stable_ids), Some(stable_id)) = (stable_ids, stable_id) {
+        stable_ids.rows.remove(&stable_id);
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Assign each component of the component type a stable id, a `u64` handed out in increasing order,
+    /// which identifies the component for as long as it lives, unlike the component key,
+    /// which changes when the component is relocated by `compact_step`.
+    /// The existing components get their ids in the order of the column, and every inserted component gets the next one.
+    /// Ids are never handed out again, even after the removal of their components,
+    /// so a component restored by `undo` or journal replay gets a new id.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.enable_stable_ids::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.stable_id(comp_key), Ok(0));
+    /// ```
+    pub fn enable_stable_ids<T>(&mut self) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let hasher = self.hasher().clone();
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        if comp_col.stable_ids.is_some() {
+            return Ok(());
+        }
+
+        let mut stable_ids = StableIds::with_hasher(hasher);
+        let comp_rows = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap();
+        for (row_key, comp_row) in comp_rows.iter_mut() {
+            comp_row.stable_id = Some(stable_ids.assign(row_key as u32));
+        }
+
+        // taken components are out of the column, so their ids are kept until they are put back
+        for ((_, row_key), taken_row) in self
+            .taken
+            .iter_mut()
+            .filter(|((taken_type_key, _), _)| *taken_type_key == type_key)
+        {
+            taken_row.stable_id = Some(stable_ids.assign(*row_key));
+        }
+
+        comp_col.stable_ids = Some(stable_ids);
+
+        Ok(())
+    }
+
+    /// Return the stable id of a component with the corresponding component key, as in `enable_stable_ids`.
+    /// If the key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// If the component type has no stable ids, return an `Err(Error::StableIdsNotEnabled)`.
+    /// Otherwise, return an `Ok(u64)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.enable_stable_ids::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// assert_eq!(ecs.stable_id(comp_key), Ok(1));
+    /// ```
+    pub fn stable_id(&self, comp_key: CompKey) -> Result<u64, Error> {
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+        if !self.comp_cols.contains_key(&type_key) {
+            return Err(Error::TypeNotRegistered);
+        }
+
+        self.get_row_or_taken(type_key, row_key)
+            .ok_or(Error::CompNotFound)?
+            .stable_id
+            .ok_or(Error::StableIdsNotEnabled)
+    }
+
+    /// Return the component key and the component with the corresponding stable id and type, as in `enable_stable_ids`.
+    /// If the component is not found or is taken, as in `take_comp`,
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type has no stable ids, return an `Err(Error::StableIdsNotEnabled)`.
+    /// If the component corresponding to the stable id is not found, return an `Err(Error::CompNotFound)`.
+    /// If the component is taken, as in `take_comp`, return an `Err(Error::TemporarilyTaken)`.
+    /// Otherwise, return an `Ok((CompKey, &T))`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.enable_stable_ids::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert_eq!(ecs.comp_by_stable_id::<i32>(0), Ok((comp_key, &42)));
+    /// ```
+    pub fn comp_by_stable_id<T>(&self, stable_id: u64) -> Result<(CompKey, &T), Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        let row_key = *comp_col
+            .stable_ids
+            .as_ref()
+            .ok_or(Error::StableIdsNotEnabled)?
+            .rows
+            .get(&stable_id)
+            .ok_or(Error::CompNotFound)?;
+        let comp_row = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .get(row_key as usize)
+            .ok_or_else(|| crate::take::missing_comp(&self.taken, type_key, row_key))?;
+
+        Ok((self.comp_key(type_key, row_key), &comp_row.comp))
+    }
+
+    /// Return an iterator over the stable ids and the components of the component type, as in `enable_stable_ids`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type has no stable ids, return an `Err(Error::StableIdsNotEnabled)`.
+    /// Otherwise, return an `Ok(impl Iterator<Item = (u64, &T)>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.enable_stable_ids::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.insert_comp(entity_key, 63).unwrap();
+    ///
+    /// let comps = ecs.iter_comp_with_stable_id::<i32>().unwrap().collect::<Vec<_>>();
+    /// assert_eq!(comps, vec![(0, &42), (1, &63)]);
+    /// ```
+    pub fn iter_comp_with_stable_id<T>(&self) -> Result<impl Iterator<Item = (u64, &T)>, Error>
+    where
+        T: std::any::Any,
+    {
+        let type_key = std::any::TypeId::of::<T>();
+
+        let comp_col = self
+            .comp_cols
+            .get(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+
+        if comp_col.stable_ids.is_none() {
+            return Err(Error::StableIdsNotEnabled);
+        }
+
+        let iter = comp_col
+            .comp_rows
+            .downcast_ref::<Rows<CompRow<T>>>()
+            .unwrap()
+            .iter()
+            .map(|(_, comp_row)| (comp_row.stable_id.unwrap(), &comp_row.comp));
+
+        Ok(iter)
+    }
+}
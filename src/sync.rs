@@ -0,0 +1,83 @@
+use crate::{WorldHasher, ECS};
+
+/// A world behind a read-write lock, to be shared between threads, as in an `Arc<SharedECS>`.
+///
+/// The closures given to `read` and `write` run while the lock is held,
+/// so calling `read` or `write` of the same world from within them deadlocks.
+/// A panic in a closure does not poison the world, which is left as the closure left it.
+///
+/// # Examples
+///
+/// ```
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let entity_key = ecs.insert_entity();
+/// ecs.register::<i32>().unwrap();
+/// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+///
+/// let shared = std::sync::Arc::new(ecs_tiny::SharedECS::new(ecs));
+/// let handle = std::thread::spawn({
+///     let shared = shared.clone();
+///     move || shared.write(|ecs| *ecs.get_comp_mut::<i32>(comp_key).unwrap() += 1)
+/// });
+/// handle.join().unwrap();
+///
+/// assert_eq!(shared.read(|ecs| *ecs.get_comp::<i32>(comp_key).unwrap()), 43);
+/// ```
+pub struct SharedECS<S = crate::DefaultHashBuilder> {
+    ecs: std::sync::RwLock<ECS<S>>,
+}
+
+impl<S: WorldHasher> SharedECS<S> {
+    /// Create a new shared world holding the world.
+    pub fn new(ecs: ECS<S>) -> Self {
+        Self {
+            ecs: std::sync::RwLock::new(ecs),
+        }
+    }
+
+    /// Call the function with a shared reference to the world, blocking while it is written,
+    /// and return the result of the function.
+    pub fn read<R>(&self, f: impl FnOnce(&ECS<S>) -> R) -> R {
+        let ecs = self
+            .ecs
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        f(&ecs)
+    }
+
+    /// Call the function with a mutable reference to the world, blocking while it is read or written,
+    /// and return the result of the function.
+    pub fn write<R>(&self, f: impl FnOnce(&mut ECS<S>) -> R) -> R {
+        let mut ecs = self
+            .ecs
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        f(&mut ecs)
+    }
+
+    /// Return a mutable reference to the world, without locking, since the shared world is borrowed mutably.
+    pub fn get_mut(&mut self) -> &mut ECS<S> {
+        self.ecs
+            .get_mut()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Return the world, consuming the shared world.
+    pub fn into_inner(self) -> ECS<S> {
+        self.ecs
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl<S: WorldHasher> Default for SharedECS<S> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<S: WorldHasher> From<ECS<S>> for SharedECS<S> {
+    fn from(ecs: ECS<S>) -> Self {
+        Self::new(ecs)
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{stable, CompKey, CompRow, EntityKey, Error, Rows, WorldHasher, WorldOp, ECS};
+
+/// A component taken out of the world by value, as in [`ECS::take_comp`].
+///
+/// The row of the component stays reserved, so the component key and the association with the entity remain,
+/// until the component is put back by [`ECS::put_back`] or discarded.
+/// Dropping the handle discards the component, and the world releases the row on its next structural change.
+pub struct TakenComp<T> {
+    comp: T,
+    comp_key: CompKey,
+    token: std::sync::Arc<()>,
+}
+
+impl<T> TakenComp<T> {
+    /// Return the component key of the taken component.
+    pub fn comp_key(&self) -> CompKey {
+        self.comp_key
+    }
+
+    /// Remove the taken component from the world for good, and return the component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let taken = ecs.take_comp::<i32>(comp_key).unwrap();
+    ///
+    /// assert_eq!(taken.discard(&mut ecs), 42);
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Err(ecs_tiny::Error::CompNotFound));
+    /// ```
+    pub fn discard<S: WorldHasher>(self, ecs: &mut ECS<S>) -> T {
+        let TakenComp {
+            comp,
+            comp_key,
+            token,
+        } = self;
+
+        let key = (comp_key.type_key, comp_key.row_key);
+        if comp_key.world == ecs.world && ecs.taken.get(&key).is_some_and(|row| row.is(&token)) {
+            ecs.release_taken(key);
+        }
+
+        comp
+    }
+}
+
+impl<T> std::ops::Deref for TakenComp<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.comp
+    }
+}
+
+impl<T> std::ops::DerefMut for TakenComp<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.comp
+    }
+}
+
+impl<T> std::fmt::Debug for TakenComp<T>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TakenComp")
+            .field("comp", &self.comp)
+            .field("comp_key", &self.comp_key)
+            .finish()
+    }
+}
+
+/// The reserved rows of the taken components, by type and row key.
+pub(crate) type TakenRows<S> = crate::hash::HashMap<(std::any::TypeId, u32), TakenRow, S>;
+
+/// The bookkeeping of a reserved row, kept by the world while its component is taken.
+pub(crate) struct TakenRow {
+    pub(crate) entity_key: EntityKey,
+    pub(crate) ref_0_row_key: u32,
+    pub(crate) ref_1_row_key: u32,
+    pub(crate) stable_id: Option<u64>,
+    pub(crate) ticks: crate::change::Ticks,
+    /// Dangles once the handle is dropped.
+    token: std::sync::Weak<()>,
+}
+
+impl TakenRow {
+    /// Return whether the handle of the taken component is still alive.
+    pub(crate) fn is_live(&self) -> bool {
+        self.token.strong_count() > 0
+    }
+
+    fn is(&self, token: &std::sync::Arc<()>) -> bool {
+        std::ptr::eq(self.token.as_ptr(), std::sync::Arc::as_ptr(token))
+    }
+}
+
+/// Return the error of accessing a row without a component:
+/// an `Error::TemporarilyTaken` if the component is taken, or an `Error::CompNotFound` otherwise.
+/// The taken rows are passed alone, so that the columns can stay borrowed.
+pub(crate) fn missing_comp<S: WorldHasher>(
+    taken: &TakenRows<S>,
+    type_key: std::any::TypeId,
+    row_key: u32,
+) -> Error {
+    match taken.get(&(type_key, row_key)) {
+        Some(taken_row) if taken_row.is_live() => Error::TemporarilyTaken,
+        _ => Error::CompNotFound,
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Take a component with the corresponding component key and type out of the world by value,
+    /// leaving its row reserved, so that the component key and the association with the entity remain.
+    /// While taken, the component is skipped by iteration and queries,
+    /// and accessing it returns an `Err(Error::TemporarilyTaken)`.
+    /// Taking a component discards the undo history, since the history cannot restore the value.
+    /// If the component key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component type is immutable, return an `Err(Error::ImmutableType)`.
+    /// If the component is already taken, return an `Err(Error::TemporarilyTaken)`.
+    /// If the component corresponding to the component key is not found, return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(TakenComp<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<Vec<i32>>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, vec![1, 2]).unwrap();
+    ///
+    /// let mut taken = ecs.take_comp::<Vec<i32>>(comp_key).unwrap();
+    /// assert_eq!(ecs.get_comp::<Vec<i32>>(comp_key), Err(ecs_tiny::Error::TemporarilyTaken));
+    ///
+    /// taken.push(3);
+    /// ecs.put_back(taken).unwrap();
+    /// assert_eq!(ecs.get_comp::<Vec<i32>>(comp_key), Ok(&vec![1, 2, 3]));
+    /// ```
+    pub fn take_comp<T>(&mut self, comp_key: CompKey) -> Result<TakenComp<T>, Error>
+    where
+        T: std::any::Any,
+    {
+        self.release_dropped_taken();
+
+        let (type_key, row_key) = self.check_comp_key(comp_key)?;
+
+        if type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let comp_col = self
+            .comp_cols
+            .get_mut(&type_key)
+            .ok_or(Error::TypeNotRegistered)?;
+        comp_col.count_access(true);
+
+        if comp_col.immutable {
+            return Err(Error::ImmutableType);
+        }
+
+        let Some(comp_row) = comp_col
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .take(row_key as usize)
+        else {
+            return Err(missing_comp(&self.taken, type_key, row_key));
+        };
+
+        let token = std::sync::Arc::new(());
+        let taken_row = TakenRow {
+            entity_key: comp_row.entity_key,
+            ref_0_row_key: comp_row.ref_0_row_key,
+            ref_1_row_key: comp_row.ref_1_row_key,
+            stable_id: comp_row.stable_id,
+            ticks: comp_row.ticks,
+            token: std::sync::Arc::downgrade(&token),
+        };
+        self.taken.insert((type_key, row_key), taken_row);
+
+        self.discard_undo();
+
+        Ok(TakenComp {
+            comp: comp_row.comp,
+            comp_key,
+            token,
+        })
+    }
+
+    /// Put a taken component back into its reserved row, as in `take_comp`.
+    /// If the component key belongs to another world, return an `Err(Error::WrongWorld)`.
+    /// If the component was removed in the meantime, as by removing its entity,
+    /// the component is dropped and return an `Err(Error::CompNotFound)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// let taken = ecs.take_comp::<i32>(comp_key).unwrap();
+    /// ecs.put_back(taken).unwrap();
+    ///
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+    /// ```
+    pub fn put_back<T>(&mut self, taken: TakenComp<T>) -> Result<(), Error>
+    where
+        T: std::any::Any,
+    {
+        let TakenComp {
+            comp,
+            comp_key,
+            token,
+        } = taken;
+
+        if comp_key.world != self.world {
+            return Err(Error::WrongWorld);
+        }
+
+        let key = (comp_key.type_key, comp_key.row_key);
+        if !self.taken.get(&key).is_some_and(|row| row.is(&token)) {
+            return Err(Error::CompNotFound);
+        }
+        let taken_row = self.taken.remove(&key).unwrap();
+
+        let comp_row = CompRow {
+            comp,
+            entity_key: taken_row.entity_key,
+            ref_0_row_key: taken_row.ref_0_row_key,
+            ref_1_row_key: taken_row.ref_1_row_key,
+            stable_id: taken_row.stable_id,
+            // the value may have changed while taken
+            ticks: crate::change::Ticks {
+                changed: self.tick,
+                ..taken_row.ticks
+            },
+        };
+        self.comp_cols
+            .get_mut(&comp_key.type_key)
+            .unwrap()
+            .comp_rows
+            .downcast_mut::<Rows<CompRow<T>>>()
+            .unwrap()
+            .restore(comp_key.row_key as usize, comp_row)
+            .ok()
+            .unwrap();
+
+        // the value may have changed while taken
+        self.record(None, |ecs| WorldOp::SetComp {
+            comp_key,
+            comp: ecs.recorded_comp(comp_key),
+        });
+
+        self.release_dropped_taken();
+
+        Ok(())
+    }
+
+    /// Return whether the row is reserved by a taken component, whose handle may have been dropped.
+    pub(crate) fn is_taken(&self, type_key: std::any::TypeId, row_key: u32) -> bool {
+        !self.taken.is_empty() && self.taken.contains_key(&(type_key, row_key))
+    }
+
+    /// Return the bookkeeping of the row, or of the reserved row if its component is taken.
+    pub(crate) fn get_row_or_taken(
+        &self,
+        type_key: std::any::TypeId,
+        row_key: u32,
+    ) -> Option<CompRow<()>> {
+        if let Some(taken_row) = self.taken.get(&(type_key, row_key)) {
+            return Some(CompRow {
+                comp: (),
+                entity_key: taken_row.entity_key,
+                ref_0_row_key: taken_row.ref_0_row_key,
+                ref_1_row_key: taken_row.ref_1_row_key,
+                stable_id: taken_row.stable_id,
+                ticks: taken_row.ticks,
+            });
+        }
+
+        let comp_col = self.comp_cols.get(&type_key)?;
+        (comp_col.get_row_fn)(comp_col, row_key)
+    }
+
+    /// Remove the components whose taken handles were dropped, releasing their rows,
+    /// and return the number of components removed.
+    /// Every structural change of components calls this first, so that dropped handles leave no reserved rows behind.
+    pub(crate) fn release_dropped_taken(&mut self) -> usize {
+        if self.taken.is_empty() {
+            return 0;
+        }
+
+        let dropped = self
+            .taken
+            .iter()
+            .filter(|(_, taken_row)| !taken_row.is_live())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+
+        for key in &dropped {
+            self.release_taken(*key);
+        }
+
+        dropped.len()
+    }
+
+    /// Remove a taken component from the bookkeeping, releasing its row.
+    fn release_taken(&mut self, (type_key, row_key): (std::any::TypeId, u32)) {
+        let taken_row = self.taken.remove(&(type_key, row_key)).unwrap();
+        let comp_key = self.comp_key(type_key, row_key);
+
+        let comp_col = self.comp_cols.get_mut(&type_key).unwrap();
+        comp_col.note_removed_taken(taken_row.entity_key, comp_key);
+        (comp_col.release_row_fn)(comp_col, row_key).unwrap();
+        comp_col.remaps.remove(&row_key);
+        stable::forget(&mut comp_col.stable_ids, taken_row.stable_id);
+        self.comp_generations.bump(type_key, row_key);
+
+        self.remove_ref_0(taken_row.entity_key, taken_row.ref_0_row_key);
+        self.note_comp_removed(taken_row.entity_key);
+
+        self.remove_ref_1(taken_row.entity_key, type_key, taken_row.ref_1_row_key);
+
+        // the value is gone, so the removal cannot be undone
+        self.discard_undo();
+        self.record(None, |_| WorldOp::RemoveComp { comp_key });
+    }
+}
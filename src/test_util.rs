@@ -0,0 +1,360 @@
+//! Utilities for testing the bookkeeping of [`ECS`] against a naive reference model.
+//!
+//! # Examples
+//!
+//! ```
+//! use ecs_tiny::test_util::{Op, ShadowWorld};
+//!
+//! let mut ecs = ecs_tiny::ECS::new();
+//! let mut shadow = ShadowWorld::new();
+//! shadow.register::<i32>(&mut ecs);
+//!
+//! for op in [Op::InsertEntity, Op::InsertComp(0, Box::new(42)), Op::RemoveEntity(0)] {
+//!     shadow.apply_both(&mut ecs, &op);
+//! }
+//! ```
+
+use crate::{CompKey, EntityKey, Error, ECS};
+
+impl ECS {
+    /// Panic with a readable report if `validate` finds an inconsistency.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.assert_invariants();
+    /// ```
+    pub fn assert_invariants(&self) {
+        let Err(inconsistency) = self.validate() else {
+            return;
+        };
+
+        let mut comp_cols = self
+            .comp_cols
+            .values()
+            .map(|comp_col| (comp_col.type_name, (comp_col.len_fn)(comp_col)))
+            .collect::<Vec<_>>();
+        comp_cols.sort_unstable();
+
+        let mut report = format!("ECS invariant violated: {}\n", inconsistency);
+        report += &format!("  entities: {}\n", self.entities.len());
+        report += "  columns:\n";
+        for (type_name, len) in comp_cols {
+            report += &format!("    {}: {} rows\n", type_name, len);
+        }
+        report += &format!("  ref_0_cols: {} entries\n", self.ref_0_cols.len());
+        report += &format!("  ref_1_cols: {} entries", self.ref_1_cols.len());
+
+        panic!("{}", report);
+    }
+
+    /// Lower the largest key handed out below `MAX_KEY`, so that running out of keys can be tested
+    /// without inserting billions of entities or components.
+    ///
+    /// # Examples
+    ///
+    /// ```should_panic
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.set_key_limit(0);
+    /// ecs.insert_entity();
+    /// ecs.insert_entity();
+    /// ```
+    pub fn set_key_limit(&mut self, max_key: u32) {
+        self.key_limit = Some(max_key);
+    }
+
+    /// Remove the entity while leaving its component rows and references behind,
+    /// corrupting the bookkeeping to exercise `validate` and `find_orphans`.
+    /// If the entity is not found, return an `None`.
+    /// Otherwise, return an `Some(())`.
+    pub fn corrupt_remove_entity(&mut self, entity_key: EntityKey) -> Option<()> {
+        self.check_entity(entity_key).ok()?;
+
+        self.entities.remove(entity_key.index as usize);
+        let generation = &mut self.generations[entity_key.index as usize];
+        *generation = generation.wrapping_add(1);
+
+        Some(())
+    }
+
+    /// Remove the component row while leaving its references behind,
+    /// corrupting the bookkeeping to exercise `validate` and `find_orphans`.
+    /// If the component is not found, return an `None`.
+    /// Otherwise, return an `Some(())`.
+    pub fn corrupt_remove_row(&mut self, comp_key: CompKey) -> Option<()> {
+        let (type_key, row_key) = self.check_comp_key(comp_key).ok()?;
+
+        let comp_col = self.comp_cols.get_mut(&type_key)?;
+        (comp_col.remove_row_fn)(comp_col, row_key)?;
+
+        Some(())
+    }
+
+    /// Remove the column of the component type while leaving the references to its rows behind,
+    /// corrupting the bookkeeping to exercise `validate` and `find_orphans`.
+    /// If the component type is not registered, return an `None`.
+    /// Otherwise, return an `Some(())`.
+    pub fn corrupt_remove_column<T>(&mut self) -> Option<()>
+    where
+        T: std::any::Any,
+    {
+        self.comp_cols.remove(&std::any::TypeId::of::<T>())?;
+
+        Some(())
+    }
+}
+
+/// A component value handled by the reference model,
+/// implemented for every `T: Any + Clone + PartialEq + Debug`.
+pub trait ShadowComp: std::any::Any + std::fmt::Debug {
+    /// Clone the value into a box.
+    fn clone_box(&self) -> Box<dyn ShadowComp>;
+
+    /// Return the type key of the value.
+    fn type_key(&self) -> std::any::TypeId;
+
+    /// Return whether the value is equal to the other value.
+    fn eq_box(&self, other: &dyn ShadowComp) -> bool;
+
+    /// Insert a copy of the value into the ECS as a component of the entity.
+    fn insert_into(&self, ecs: &mut ECS, entity_key: EntityKey) -> Result<CompKey, Error>;
+
+    /// Remove a component of the type of the value from the ECS.
+    fn remove_from(&self, ecs: &mut ECS, comp_key: CompKey) -> Result<Box<dyn ShadowComp>, Error>;
+
+    /// Return whether the component in the ECS is equal to the value.
+    fn eq_in(&self, ecs: &ECS, comp_key: CompKey) -> bool;
+}
+
+impl<T> ShadowComp for T
+where
+    T: std::any::Any + Clone + PartialEq + std::fmt::Debug,
+{
+    fn clone_box(&self) -> Box<dyn ShadowComp> {
+        Box::new(self.clone())
+    }
+
+    fn type_key(&self) -> std::any::TypeId {
+        std::any::TypeId::of::<T>()
+    }
+
+    fn eq_box(&self, other: &dyn ShadowComp) -> bool {
+        (other as &dyn std::any::Any).downcast_ref::<T>() == Some(self)
+    }
+
+    fn insert_into(&self, ecs: &mut ECS, entity_key: EntityKey) -> Result<CompKey, Error> {
+        ecs.insert_comp(entity_key, self.clone())
+    }
+
+    fn remove_from(&self, ecs: &mut ECS, comp_key: CompKey) -> Result<Box<dyn ShadowComp>, Error> {
+        let comp = ecs.remove_comp::<T>(comp_key)?;
+        Ok(Box::new(comp))
+    }
+
+    fn eq_in(&self, ecs: &ECS, comp_key: CompKey) -> bool {
+        ecs.get_comp::<T>(comp_key) == Ok(self)
+    }
+}
+
+impl Clone for Box<dyn ShadowComp> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// An operation applied to both the ECS and the reference model.
+///
+/// Entities and components are selected by an index into all the keys ever handed out,
+/// wrapped around the number of keys, so that operations on removed keys are exercised too.
+#[derive(Debug, Clone)]
+pub enum Op {
+    /// Insert a new entity.
+    InsertEntity,
+    /// Remove the selected entity.
+    RemoveEntity(usize),
+    /// Insert the component into the selected entity.
+    InsertComp(usize, Box<dyn ShadowComp>),
+    /// Remove the selected component.
+    RemoveComp(usize),
+    /// Clear all entities, components and registrations.
+    Clear,
+}
+
+type ShadowComps = Vec<(CompKey, Box<dyn ShadowComp>)>;
+
+/// A naive reference model of [`ECS`], storing the components of each entity in a plain map.
+#[derive(Default)]
+pub struct ShadowWorld {
+    entities: std::collections::HashMap<EntityKey, ShadowComps>,
+    registered: std::collections::HashMap<std::any::TypeId, fn(&ECS) -> usize>,
+    entity_keys: Vec<EntityKey>,
+    comp_keys: ShadowComps,
+}
+
+impl ShadowWorld {
+    /// Create a new empty reference model.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Register the component type in both the ECS and the reference model.
+    pub fn register<T>(&mut self, ecs: &mut ECS)
+    where
+        T: std::any::Any + Send + Sync,
+    {
+        let registered = ecs.register::<T>().is_ok();
+        let expected = !self.registered.contains_key(&std::any::TypeId::of::<T>());
+        assert_eq!(registered, expected, "register diverged");
+
+        self.registered.insert(std::any::TypeId::of::<T>(), |ecs| {
+            ecs.iter_comp::<T>().map_or(0, |iter| iter.count())
+        });
+    }
+
+    /// Apply the operation to both the ECS and the reference model,
+    /// and panic if the results or the observable states diverge.
+    pub fn apply_both(&mut self, ecs: &mut ECS, op: &Op) {
+        match op {
+            Op::InsertEntity => {
+                let entity_key = ecs.insert_entity();
+                assert!(
+                    !self.entity_keys.contains(&entity_key),
+                    "entity key {:?} handed out twice",
+                    entity_key
+                );
+
+                self.entities.insert(entity_key, vec![]);
+                self.entity_keys.push(entity_key);
+            }
+            Op::RemoveEntity(index) => {
+                let Some(entity_key) = select(&self.entity_keys, *index).copied() else {
+                    return;
+                };
+
+                let expected = self.entities.remove(&entity_key).is_some();
+                let removed = ecs.remove_entity(entity_key).is_ok();
+                assert_eq!(
+                    removed, expected,
+                    "remove_entity({:?}) diverged",
+                    entity_key
+                );
+            }
+            Op::InsertComp(index, comp) => {
+                let Some(entity_key) = select(&self.entity_keys, *index).copied() else {
+                    return;
+                };
+
+                let expected = self.entities.contains_key(&entity_key)
+                    && self.registered.contains_key(&comp.type_key());
+                let result = comp.insert_into(ecs, entity_key);
+                assert_eq!(
+                    result.is_ok(),
+                    expected,
+                    "insert_comp({:?}, {:?}) diverged: {:?}",
+                    entity_key,
+                    comp,
+                    result
+                );
+
+                if let Ok(comp_key) = result {
+                    assert!(
+                        self.find(comp_key).is_none(),
+                        "comp key {:?} handed out twice",
+                        comp_key
+                    );
+
+                    let comps = self.entities.get_mut(&entity_key).unwrap();
+                    comps.push((comp_key, comp.clone()));
+                    self.comp_keys.push((comp_key, comp.clone()));
+                }
+            }
+            Op::RemoveComp(index) => {
+                let Some((comp_key, comp)) = select(&self.comp_keys, *index).cloned() else {
+                    return;
+                };
+
+                let expected = self.find(comp_key).map(|(entity_key, position)| {
+                    let comps = self.entities.get_mut(&entity_key).unwrap();
+                    comps.remove(position).1
+                });
+                let result = comp.remove_from(ecs, comp_key);
+
+                match (&result, &expected) {
+                    (Ok(removed), Some(expected)) => {
+                        assert!(
+                            removed.eq_box(&**expected),
+                            "remove_comp({:?}) diverged",
+                            comp_key
+                        )
+                    }
+                    (Err(_), None) => {}
+                    _ => panic!(
+                        "remove_comp({:?}) diverged: {:?}, expected {:?}",
+                        comp_key, result, expected
+                    ),
+                }
+            }
+            Op::Clear => {
+                ecs.clear();
+                self.entities.clear();
+                self.registered.clear();
+            }
+        }
+
+        self.assert_matches(ecs);
+    }
+
+    /// Panic if the observable state of the ECS diverges from the reference model.
+    pub fn assert_matches(&self, ecs: &ECS) {
+        ecs.assert_invariants();
+
+        let mut entity_keys = ecs.iter_entity().collect::<Vec<_>>();
+        entity_keys.sort_unstable();
+        let mut expected = self.entities.keys().copied().collect::<Vec<_>>();
+        expected.sort_unstable();
+        assert_eq!(entity_keys, expected, "entities diverged");
+
+        let mut counts = std::collections::HashMap::<std::any::TypeId, usize>::new();
+        for (entity_key, comps) in &self.entities {
+            for (comp_key, comp) in comps {
+                assert_eq!(
+                    ecs.get_entity_by_comp(*comp_key),
+                    Ok(*entity_key),
+                    "owner of {:?} diverged",
+                    comp_key
+                );
+                assert!(
+                    comp.eq_in(ecs, *comp_key),
+                    "value of {:?} diverged",
+                    comp_key
+                );
+
+                *counts.entry(comp.type_key()).or_default() += 1;
+            }
+        }
+
+        for (type_key, count_fn) in &self.registered {
+            let expected = counts.get(type_key).copied().unwrap_or(0);
+            assert_eq!(count_fn(ecs), expected, "component count diverged");
+        }
+    }
+
+    fn find(&self, comp_key: CompKey) -> Option<(EntityKey, usize)> {
+        self.entities.iter().find_map(|(entity_key, comps)| {
+            let position = comps.iter().position(|(key, _)| *key == comp_key)?;
+            Some((*entity_key, position))
+        })
+    }
+}
+
+fn select<T>(items: &[T], index: usize) -> Option<&T> {
+    if items.is_empty() {
+        return None;
+    }
+
+    items.get(index % items.len())
+}
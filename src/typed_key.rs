@@ -0,0 +1,153 @@
+use crate::{CompKey, EntityKey, Error, WorldHasher, ECS};
+
+/// A key of a component of the type, returned by [`ECS::insert_comp_typed`].
+///
+/// The key carries the component type, so the methods taking a component key infer the type from it,
+/// and a key of one type cannot be passed where a component of another type is requested:
+///
+/// ```compile_fail
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let entity_key = ecs.insert_entity();
+/// ecs.register::<i32>().unwrap();
+/// let comp_key = ecs.insert_comp_typed(entity_key, 42i32).unwrap();
+/// ecs.get_comp::<u32>(comp_key).unwrap();
+/// ```
+///
+/// The untyped `CompKey` of the key, as returned by `untyped`, can be stored along keys of other types,
+/// and converted back by `CompKey::typed`.
+pub struct TypedCompKey<T> {
+    comp_key: CompKey,
+    marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> TypedCompKey<T> {
+    /// Return the untyped component key.
+    pub fn untyped(self) -> CompKey {
+        self.comp_key
+    }
+}
+
+impl<T> Clone for TypedCompKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedCompKey<T> {}
+
+impl<T> PartialEq for TypedCompKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.comp_key == other.comp_key
+    }
+}
+
+impl<T> Eq for TypedCompKey<T> {}
+
+impl<T> std::hash::Hash for TypedCompKey<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.comp_key.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for TypedCompKey<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedCompKey")
+            .field("type_name", &std::any::type_name::<T>())
+            .field("row_key", &self.comp_key.row_key)
+            .field("generation", &self.comp_key.generation)
+            .field("world", &self.comp_key.world)
+            .finish()
+    }
+}
+
+impl<T> From<TypedCompKey<T>> for CompKey {
+    fn from(comp_key: TypedCompKey<T>) -> Self {
+        comp_key.comp_key
+    }
+}
+
+/// A component key of the type, taken by [`ECS::get_comp`], [`ECS::get_comp_mut`] and [`ECS::remove_comp`]:
+/// either an untyped `CompKey`, checked against the type when used, or a `TypedCompKey` of the type.
+pub trait IntoCompKey<T> {
+    /// Return the untyped component key.
+    fn into_comp_key(self) -> CompKey;
+}
+
+impl<T> IntoCompKey<T> for CompKey {
+    fn into_comp_key(self) -> CompKey {
+        self
+    }
+}
+
+impl<T> IntoCompKey<T> for TypedCompKey<T> {
+    fn into_comp_key(self) -> CompKey {
+        self.comp_key
+    }
+}
+
+impl CompKey {
+    /// Return the key typed with the component type, as in `TypedCompKey`.
+    /// If the key does not correspond to the type, return an `Err(Error::TypeMismatch)`.
+    /// Otherwise, return an `Ok(TypedCompKey<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ///
+    /// assert!(comp_key.typed::<u32>().is_err());
+    /// assert_eq!(ecs.get_comp(comp_key.typed::<i32>().unwrap()), Ok(&42));
+    /// ```
+    pub fn typed<T>(self) -> Result<TypedCompKey<T>, Error>
+    where
+        T: std::any::Any,
+    {
+        if self.type_key != std::any::TypeId::of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        Ok(TypedCompKey {
+            comp_key: self,
+            marker: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Insert a component with the corresponding entity key, as in `insert_comp`, and return its typed key.
+    /// If the entity corresponding to the entity key is not found, return an `Err(Error::EntityNotFound)`.
+    /// If the component type is not registered, return an `Err(Error::TypeNotRegistered)`.
+    /// If the component is rejected, return the error of `insert_comp`.
+    /// Otherwise, return an `Ok(TypedCompKey<T>)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp_typed(entity_key, 42).unwrap();
+    ///
+    /// *ecs.get_comp_mut(comp_key).unwrap() += 1;
+    /// assert_eq!(ecs.get_entity_by_comp(comp_key), Ok(entity_key));
+    /// assert_eq!(ecs.remove_comp(comp_key), Ok(43));
+    /// ```
+    pub fn insert_comp_typed<T>(
+        &mut self,
+        entity_key: EntityKey,
+        comp: T,
+    ) -> Result<TypedCompKey<T>, Error>
+    where
+        T: std::any::Any,
+    {
+        let comp_key = self.insert_comp(entity_key, comp)?;
+
+        Ok(TypedCompKey {
+            comp_key,
+            marker: std::marker::PhantomData,
+        })
+    }
+}
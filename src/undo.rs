@@ -0,0 +1,271 @@
+use crate::{Error, WorldHasher, WorldOp, ECS};
+
+/// The bounded history of undoable changes, grouped as in [`ECS::begin_undo_group`].
+pub(crate) struct UndoHistory<S> {
+    max_groups: usize,
+    undo_groups: std::collections::VecDeque<UndoGroup<S>>,
+    redo_groups: Vec<UndoGroup<S>>,
+    /// Whether the last group is begun explicitly and still takes further changes.
+    open: bool,
+}
+
+struct UndoGroup<S> {
+    name: String,
+    forward: Vec<WorldOp<S>>,
+    /// The inverse operations of each change, applied in the reverse order of the changes.
+    inverse: Vec<Vec<WorldOp<S>>>,
+}
+
+impl<S> UndoHistory<S> {
+    fn close(&mut self) {
+        if self.open
+            && self
+                .undo_groups
+                .back()
+                .is_some_and(|group| group.forward.is_empty())
+        {
+            self.undo_groups.pop_back();
+        }
+
+        self.open = false;
+    }
+
+    fn push(&mut self, group: UndoGroup<S>) {
+        self.undo_groups.push_back(group);
+
+        while self.undo_groups.len() > self.max_groups {
+            self.undo_groups.pop_front();
+            self.open = self.open && !self.undo_groups.is_empty();
+        }
+    }
+}
+
+impl<S: WorldHasher> ECS<S> {
+    /// Start capturing the changes of entities and components for `undo` and `redo`,
+    /// keeping at most the number of groups given, and dropping the oldest groups beyond it.
+    /// A change made outside an undo group forms a group on its own.
+    ///
+    /// A change involving a component of a type registered without clone support cannot be undone,
+    /// so it discards the whole history, as do `unregister` and `clear`.
+    /// Keys handed out by undone changes may be handed out again by later changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_undo(100);
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.undo().unwrap();
+    ///
+    /// assert!(ecs.get_entity(entity_key).is_err());
+    /// ```
+    pub fn enable_undo(&mut self, max_groups: usize) {
+        self.undo = Some(UndoHistory {
+            max_groups,
+            undo_groups: Default::default(),
+            redo_groups: Default::default(),
+            open: false,
+        });
+    }
+
+    /// Begin a named group of changes, undone and redone together, ending the current group.
+    /// If undo is not enabled, return an `Err(Error::UndoNotEnabled)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_undo(100);
+    /// ecs.begin_undo_group("spawn").unwrap();
+    /// let entity_key0 = ecs.insert_entity();
+    /// let entity_key1 = ecs.insert_entity();
+    /// ecs.end_undo_group().unwrap();
+    ///
+    /// assert_eq!(ecs.undo(), Ok("spawn".to_string()));
+    /// assert_eq!(ecs.iter_entity().count(), 0);
+    /// ```
+    pub fn begin_undo_group(&mut self, name: impl Into<String>) -> Result<(), Error> {
+        let history = self.undo.as_mut().ok_or(Error::UndoNotEnabled)?;
+        history.close();
+
+        history.push(UndoGroup {
+            name: name.into(),
+            forward: vec![],
+            inverse: vec![],
+        });
+        history.open = true;
+
+        Ok(())
+    }
+
+    /// End the current group of changes, so that further changes form groups on their own.
+    /// If undo is not enabled, return an `Err(Error::UndoNotEnabled)`.
+    /// Otherwise, return an `Ok(())`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_undo(100);
+    /// ecs.begin_undo_group("spawn").unwrap();
+    /// ecs.insert_entity();
+    /// ecs.end_undo_group().unwrap();
+    /// ```
+    pub fn end_undo_group(&mut self) -> Result<(), Error> {
+        let history = self.undo.as_mut().ok_or(Error::UndoNotEnabled)?;
+        history.close();
+
+        Ok(())
+    }
+
+    /// Undo the last group of changes, ending the current group, and return the name of the group.
+    /// Entities and components are restored at their original keys.
+    /// If undo is not enabled, return an `Err(Error::UndoNotEnabled)`.
+    /// If there is nothing to undo, return an `Err(Error::NothingToUndo)`.
+    /// Otherwise, return an `Ok(String)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_undo(100);
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.register_cloneable::<i32>().unwrap();
+    /// let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    /// ecs.remove_entity(entity_key).unwrap();
+    ///
+    /// assert_eq!(ecs.undo(), Ok("remove_entity".to_string()));
+    /// assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+    /// ```
+    pub fn undo(&mut self) -> Result<String, Error> {
+        let mut history = self.undo.take().ok_or(Error::UndoNotEnabled)?;
+        history.close();
+
+        let Some(group) = history.undo_groups.pop_back() else {
+            self.undo = Some(history);
+            return Err(Error::NothingToUndo);
+        };
+
+        for inverse in group.inverse.iter().rev() {
+            self.apply_undo_ops(inverse);
+        }
+
+        let name = group.name.clone();
+        history.redo_groups.push(group);
+        self.undo = Some(history);
+
+        Ok(name)
+    }
+
+    /// Redo the last undone group of changes, and return the name of the group.
+    /// Any new change discards the groups to redo.
+    /// If undo is not enabled, return an `Err(Error::UndoNotEnabled)`.
+    /// If there is nothing to redo, return an `Err(Error::NothingToRedo)`.
+    /// Otherwise, return an `Ok(String)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut ecs = ecs_tiny::ECS::new();
+    /// ecs.enable_undo(100);
+    /// let entity_key = ecs.insert_entity();
+    /// ecs.undo().unwrap();
+    /// ecs.redo().unwrap();
+    ///
+    /// assert!(ecs.get_entity(entity_key).is_ok());
+    /// ```
+    pub fn redo(&mut self) -> Result<String, Error> {
+        let mut history = self.undo.take().ok_or(Error::UndoNotEnabled)?;
+        history.close();
+
+        let Some(group) = history.redo_groups.pop() else {
+            self.undo = Some(history);
+            return Err(Error::NothingToRedo);
+        };
+
+        self.apply_undo_ops(&group.forward);
+
+        let name = group.name.clone();
+        history.push(group);
+        self.undo = Some(history);
+
+        Ok(name)
+    }
+
+    fn apply_undo_ops(&mut self, ops: &[WorldOp<S>]) {
+        for op in ops {
+            // every structural change is captured, so the history cannot diverge from the world
+            self.apply(op, 0)
+                .expect("undo history diverged from the world");
+        }
+    }
+
+    /// Capture the inverse operations of a change before it is made, if undo is enabled.
+    pub(crate) fn capture_undo(
+        &self,
+        inverse: impl FnOnce(&ECS<S>) -> Vec<WorldOp<S>>,
+    ) -> Option<Vec<WorldOp<S>>> {
+        self.undo.as_ref()?;
+        Some(inverse(self))
+    }
+
+    pub(crate) fn record_undo(&mut self, forward: Vec<WorldOp<S>>, inverse: Vec<WorldOp<S>>) {
+        let missing_value = forward.iter().chain(&inverse).any(|op| {
+            matches!(
+                op,
+                WorldOp::InsertComp { comp: None, .. } | WorldOp::SetComp { comp: None, .. }
+            )
+        });
+        if missing_value {
+            self.discard_undo();
+            return;
+        }
+
+        let Some(history) = &mut self.undo else {
+            return;
+        };
+
+        history.redo_groups.clear();
+
+        if !history.open {
+            history.push(UndoGroup {
+                name: op_name(&forward[0]).to_string(),
+                forward: vec![],
+                inverse: vec![],
+            });
+        }
+
+        // the group is dropped at once if the history keeps no groups
+        let Some(group) = history.undo_groups.back_mut() else {
+            return;
+        };
+        group.forward.extend(forward);
+        group.inverse.push(inverse);
+    }
+
+    pub(crate) fn discard_undo(&mut self) {
+        if let Some(history) = &mut self.undo {
+            history.undo_groups.clear();
+            history.redo_groups.clear();
+            history.open = false;
+        }
+    }
+}
+
+fn op_name<S>(op: &WorldOp<S>) -> &'static str {
+    match op {
+        WorldOp::Register { .. } => "register",
+        WorldOp::Unregister { .. } => "unregister",
+        WorldOp::CreatePartition { .. } => "create_partition",
+        WorldOp::InsertEntity { .. } => "insert_entity",
+        WorldOp::RemoveEntity { .. } => "remove_entity",
+        WorldOp::SetEntityFlags { .. } => "set_entity_flags",
+        WorldOp::InsertComp { .. } => "insert_comp",
+        WorldOp::ReorderComp { .. } => "reorder_comp",
+        WorldOp::RemoveComp { .. } => "remove_comp",
+        WorldOp::SetComp { .. } => "set_comp",
+        WorldOp::CompactStep { .. } => "compact_step",
+        WorldOp::Clear => "clear",
+    }
+}
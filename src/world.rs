@@ -0,0 +1,229 @@
+use crate::{CompKey, EntityKey, Error, ECS};
+
+/// The read surface of a world, so that code can be written against [`ECS`] or a substitute, such as a mock.
+///
+/// The methods behave as the methods of `ECS` with the same names.
+/// The iterator types borrow the world, so an implementation names them with its own lifetime.
+///
+/// # Examples
+///
+/// ```
+/// use ecs_tiny::WorldRead;
+///
+/// fn sum<W: WorldRead>(world: &W) -> i32 {
+///     world.iter_comp::<i32>().map(|iter| iter.sum()).unwrap_or(0)
+/// }
+///
+/// let mut ecs = ecs_tiny::ECS::new();
+/// let entity_key = ecs.insert_entity();
+/// ecs.register::<i32>().unwrap();
+/// ecs.insert_comp(entity_key, 42).unwrap();
+/// ecs.insert_comp(entity_key, 63).unwrap();
+///
+/// assert_eq!(sum(&ecs), 105);
+/// ```
+pub trait WorldRead {
+    /// The iterator over entity keys, as returned by `iter_entity`.
+    type EntityIter<'a>: Iterator<Item = EntityKey>
+    where
+        Self: 'a;
+
+    /// The iterator over components, as returned by `iter_comp` and `iter_comp_by_entity`.
+    type CompIter<'a, T>: Iterator<Item = &'a T>
+    where
+        Self: 'a,
+        T: 'a;
+
+    /// Return whether the entity with the corresponding entity key is alive, as in `ECS::get_entity`.
+    fn get_entity(&self, entity_key: EntityKey) -> Result<(), Error>;
+
+    /// Return an iterator over the entity keys of all entities, as in `ECS::iter_entity`.
+    fn iter_entity(&self) -> Self::EntityIter<'_>;
+
+    /// Return the number of entities yielded by `iter_entity`.
+    fn entity_count(&self) -> usize {
+        self.iter_entity().count()
+    }
+
+    /// Return a component with the corresponding component key and type, as in `ECS::get_comp`.
+    fn get_comp<T>(&self, comp_key: CompKey) -> Result<&T, Error>
+    where
+        T: std::any::Any;
+
+    /// Return the entity key of the component with the corresponding component key, as in `ECS::get_entity_by_comp`.
+    fn get_entity_by_comp(&self, comp_key: CompKey) -> Result<EntityKey, Error>;
+
+    /// Return an iterator over all components of the type, as in `ECS::iter_comp`.
+    fn iter_comp<T>(&self) -> Result<Self::CompIter<'_, T>, Error>
+    where
+        T: std::any::Any;
+
+    /// Return the number of components of the type, as in `iter_comp`.
+    fn comp_count<T>(&self) -> Result<usize, Error>
+    where
+        T: std::any::Any,
+    {
+        Ok(self.iter_comp::<T>()?.count())
+    }
+
+    /// Return an iterator over all components of the entity and type, as in `ECS::iter_comp_by_entity`.
+    fn iter_comp_by_entity<T>(&self, entity_key: EntityKey) -> Result<Self::CompIter<'_, T>, Error>
+    where
+        T: std::any::Any;
+}
+
+/// The write surface of a world, as in [`WorldRead`].
+///
+/// # Examples
+///
+/// ```
+/// use ecs_tiny::{WorldRead, WorldWrite};
+///
+/// fn spawn<W: WorldWrite>(world: &mut W, comp: i32) -> ecs_tiny::EntityKey {
+///     let entity_key = world.insert_entity();
+///     world.insert_comp(entity_key, comp).unwrap();
+///     entity_key
+/// }
+///
+/// let mut ecs = ecs_tiny::ECS::new();
+/// ecs.register::<i32>().unwrap();
+/// let entity_key = spawn(&mut ecs, 42);
+///
+/// assert_eq!(ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().next(), Some(&42));
+/// ```
+pub trait WorldWrite: WorldRead {
+    /// The mutable iterator over components, as returned by `iter_comp_mut` and `iter_comp_mut_by_entity`.
+    type CompIterMut<'a, T>: Iterator<Item = &'a mut T>
+    where
+        Self: 'a,
+        T: 'a;
+
+    /// Insert a new entity, and return the corresponding entity key, as in `ECS::insert_entity`.
+    fn insert_entity(&mut self) -> EntityKey;
+
+    /// Remove the entity with the corresponding entity key and its components, as in `ECS::remove_entity`.
+    fn remove_entity(&mut self, entity_key: EntityKey) -> Result<(), Error>;
+
+    /// Insert a new component with the corresponding entity key, as in `ECS::insert_comp`.
+    fn insert_comp<T>(&mut self, entity_key: EntityKey, comp: T) -> Result<CompKey, Error>
+    where
+        T: std::any::Any;
+
+    /// Remove a component with the corresponding component key and type, as in `ECS::remove_comp`.
+    fn remove_comp<T>(&mut self, comp_key: CompKey) -> Result<T, Error>
+    where
+        T: std::any::Any;
+
+    /// Return a mutable component with the corresponding component key and type, as in `ECS::get_comp_mut`.
+    fn get_comp_mut<T>(&mut self, comp_key: CompKey) -> Result<&mut T, Error>
+    where
+        T: std::any::Any;
+
+    /// Return a mutable iterator over all components of the type, as in `ECS::iter_comp_mut`.
+    fn iter_comp_mut<T>(&mut self) -> Result<Self::CompIterMut<'_, T>, Error>
+    where
+        T: std::any::Any;
+
+    /// Return a mutable iterator over all components of the entity and type, as in `ECS::iter_comp_mut_by_entity`.
+    fn iter_comp_mut_by_entity<T>(
+        &mut self,
+        entity_key: EntityKey,
+    ) -> Result<Self::CompIterMut<'_, T>, Error>
+    where
+        T: std::any::Any;
+}
+
+// the iterators of `ECS` cannot be named, so they are boxed
+impl WorldRead for ECS {
+    type EntityIter<'a> = Box<dyn Iterator<Item = EntityKey> + 'a>;
+    type CompIter<'a, T>
+        = Box<dyn Iterator<Item = &'a T> + 'a>
+    where
+        T: 'a;
+
+    fn get_entity(&self, entity_key: EntityKey) -> Result<(), Error> {
+        ECS::get_entity(self, entity_key)
+    }
+
+    fn iter_entity(&self) -> Self::EntityIter<'_> {
+        Box::new(ECS::iter_entity(self))
+    }
+
+    fn get_comp<T>(&self, comp_key: CompKey) -> Result<&T, Error>
+    where
+        T: std::any::Any,
+    {
+        ECS::get_comp(self, comp_key)
+    }
+
+    fn get_entity_by_comp(&self, comp_key: CompKey) -> Result<EntityKey, Error> {
+        ECS::get_entity_by_comp(self, comp_key)
+    }
+
+    fn iter_comp<T>(&self) -> Result<Self::CompIter<'_, T>, Error>
+    where
+        T: std::any::Any,
+    {
+        Ok(Box::new(ECS::iter_comp(self)?))
+    }
+
+    fn iter_comp_by_entity<T>(&self, entity_key: EntityKey) -> Result<Self::CompIter<'_, T>, Error>
+    where
+        T: std::any::Any,
+    {
+        Ok(Box::new(ECS::iter_comp_by_entity(self, entity_key)?))
+    }
+}
+
+impl WorldWrite for ECS {
+    type CompIterMut<'a, T>
+        = Box<dyn Iterator<Item = &'a mut T> + 'a>
+    where
+        T: 'a;
+
+    fn insert_entity(&mut self) -> EntityKey {
+        ECS::insert_entity(self)
+    }
+
+    fn remove_entity(&mut self, entity_key: EntityKey) -> Result<(), Error> {
+        ECS::remove_entity(self, entity_key)
+    }
+
+    fn insert_comp<T>(&mut self, entity_key: EntityKey, comp: T) -> Result<CompKey, Error>
+    where
+        T: std::any::Any,
+    {
+        ECS::insert_comp(self, entity_key, comp)
+    }
+
+    fn remove_comp<T>(&mut self, comp_key: CompKey) -> Result<T, Error>
+    where
+        T: std::any::Any,
+    {
+        ECS::remove_comp(self, comp_key)
+    }
+
+    fn get_comp_mut<T>(&mut self, comp_key: CompKey) -> Result<&mut T, Error>
+    where
+        T: std::any::Any,
+    {
+        ECS::get_comp_mut(self, comp_key)
+    }
+
+    fn iter_comp_mut<T>(&mut self) -> Result<Self::CompIterMut<'_, T>, Error>
+    where
+        T: std::any::Any,
+    {
+        Ok(Box::new(ECS::iter_comp_mut(self)?))
+    }
+
+    fn iter_comp_mut_by_entity<T>(
+        &mut self,
+        entity_key: EntityKey,
+    ) -> Result<Self::CompIterMut<'_, T>, Error>
+    where
+        T: std::any::Any,
+    {
+        Ok(Box::new(ECS::iter_comp_mut_by_entity(self, entity_key)?))
+    }
+}
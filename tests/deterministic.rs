@@ -0,0 +1,111 @@
+fn run(ecs: &mut ecs_tiny::ECS) {
+    ecs.enable_lifecycle_events();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<u64>().unwrap();
+
+    let entity_keys = (0..64).map(|_| ecs.insert_entity()).collect::<Vec<_>>();
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        ecs.insert_comp(*entity_key, i as i32).unwrap();
+        if i % 2 == 0 {
+            ecs.insert_comp(*entity_key, i as u32).unwrap();
+        }
+        if i % 3 == 0 {
+            ecs.insert_comp(*entity_key, i as u64).unwrap();
+        }
+    }
+    for entity_key in entity_keys.iter().step_by(5) {
+        ecs.remove_entity(*entity_key).unwrap();
+    }
+    ecs.unregister::<u64>().unwrap();
+
+    // several entities leave their references behind, so the first inconsistency found depends on the order of the tables
+    for entity_key in entity_keys.iter().skip(2).step_by(5) {
+        ecs.corrupt_remove_entity(*entity_key).unwrap();
+    }
+}
+
+/// Return the slot index and the generation of the entity key, which are compared across worlds,
+/// unlike the world identifier.
+fn slot(entity_key: ecs_tiny::EntityKey) -> (u32, u32) {
+    (entity_key.index(), entity_key.generation())
+}
+
+fn lifecycle_slot(event: ecs_tiny::EntityLifecycle) -> (u8, (u32, u32)) {
+    use ecs_tiny::EntityLifecycle;
+
+    match event {
+        EntityLifecycle::FirstComponentAdded(entity_key) => (0, slot(entity_key)),
+        EntityLifecycle::LastComponentRemoved(entity_key) => (1, slot(entity_key)),
+    }
+}
+
+fn orphan_slots(orphans: ecs_tiny::OrphanReport) -> Vec<(&'static str, (u32, u32), u32)> {
+    orphans
+        .dead_owners
+        .into_iter()
+        .chain(orphans.vacant_refs)
+        .map(|row| (row.type_name, slot(row.entity_key), row.row_key))
+        .collect()
+}
+
+fn inconsistency_slot(inconsistency: ecs_tiny::Inconsistency) -> (Option<(u32, u32)>, String) {
+    use ecs_tiny::Inconsistency;
+
+    match inconsistency {
+        Inconsistency::DeadEntity { entity_key } => (Some(slot(entity_key)), "dead".to_string()),
+        Inconsistency::UnregisteredType { entity_key } => {
+            (Some(slot(entity_key)), "unregistered".to_string())
+        }
+        Inconsistency::DanglingRef {
+            type_name,
+            entity_key,
+            row_key,
+        } => (Some(slot(entity_key)), format!("{type_name} {row_key}")),
+        inconsistency => (None, inconsistency.to_string()),
+    }
+}
+
+#[test]
+fn deterministic_iteration() {
+    // the worlds are independent, with world identifiers apart
+    let mut ecs0 = ecs_tiny::ECS::new();
+    let _others = (0..10).map(|_| ecs_tiny::ECS::new()).collect::<Vec<_>>();
+    let mut ecs1 = ecs_tiny::ECS::new();
+    run(&mut ecs0);
+    run(&mut ecs1);
+
+    assert_eq!(
+        ecs0.iter_entity().map(slot).collect::<Vec<_>>(),
+        ecs1.iter_entity().map(slot).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        ecs0.query::<(&i32, &u32)>()
+            .unwrap()
+            .map(|(entity_key, (a, b))| (slot(entity_key), *a, *b))
+            .collect::<Vec<_>>(),
+        ecs1.query::<(&i32, &u32)>()
+            .unwrap()
+            .map(|(entity_key, (a, b))| (slot(entity_key), *a, *b))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        ecs0.take_entity_lifecycle_events()
+            .into_iter()
+            .map(lifecycle_slot)
+            .collect::<Vec<_>>(),
+        ecs1.take_entity_lifecycle_events()
+            .into_iter()
+            .map(lifecycle_slot)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        orphan_slots(ecs0.find_orphans()),
+        orphan_slots(ecs1.find_orphans())
+    );
+    assert!(ecs0.validate().is_err());
+    assert_eq!(
+        ecs0.validate().map_err(inconsistency_slot),
+        ecs1.validate().map_err(inconsistency_slot)
+    );
+}
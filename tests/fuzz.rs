@@ -0,0 +1,65 @@
+use arbitrary::{Arbitrary, Unstructured};
+
+/// Apply the operations as the fuzz target does, and return the world.
+fn apply_bytes(data: &[u8]) -> ecs_tiny::ECS {
+    let ops = Vec::<ecs_tiny::WorldOp>::arbitrary_take_rest(Unstructured::new(data)).unwrap();
+
+    let mut ecs = ecs_tiny::ECS::new();
+    for op in &ops {
+        ecs.apply_op(op);
+    }
+    ecs
+}
+
+#[test]
+fn corpus() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/world_ops");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let ecs = apply_bytes(&std::fs::read(entry.unwrap().path()).unwrap());
+        assert_eq!(ecs.validate(), Ok(()));
+    }
+}
+
+#[test]
+fn unregister_dangling_refs() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/world_ops");
+    let data = std::fs::read(dir.join("unregister_dangling_refs")).unwrap();
+
+    // register, insert, unregister, remove the entity, then register and insert again
+    let ops = Vec::<ecs_tiny::WorldOp>::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+    assert!(matches!(ops[3], ecs_tiny::WorldOp::Unregister { .. }));
+    assert!(matches!(ops[4], ecs_tiny::WorldOp::RemoveEntity { .. }));
+    assert_eq!(ops.len(), 8);
+
+    let ecs = apply_bytes(&data);
+    assert_eq!(ecs.iter_entity().count(), 1);
+    assert_eq!(
+        ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(),
+        vec![&63]
+    );
+}
+
+#[test]
+fn arbitrary_ops() {
+    // a fixed xorshift stream, so that failures reproduce
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..2000 {
+        let len = (next() % 1024) as usize;
+        let data = (0..len).map(|_| next() as u8).collect::<Vec<_>>();
+
+        // every byte goes to an operation, so that the worlds get deeper than with a length prefix
+        let mut u = Unstructured::new(&data);
+        let mut ecs = ecs_tiny::ECS::new();
+        while !u.is_empty() {
+            ecs.apply_op(&ecs_tiny::WorldOp::arbitrary(&mut u).unwrap());
+        }
+        assert_eq!(ecs.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,100 @@
+#[test]
+fn migration_importer() {
+    use ecs_tiny::{ImportError, MigrationImporter};
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let mut importer = MigrationImporter::new();
+    let entity_keys = importer
+        .import(&mut ecs, (0..4).map(|i| (100 + i, (i as i32, i as u32))))
+        .unwrap();
+
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        assert_eq!(importer.entity_key(&ecs, 100 + i as u64), Ok(*entity_key));
+        assert_eq!(importer.external_id(&ecs, *entity_key), Ok(100 + i as u64));
+        assert_eq!(ecs.entity_from_bits(entity_key.to_bits()), Ok(*entity_key));
+    }
+    assert_eq!(
+        importer.entity_key(&ecs, 7),
+        Err(ecs_tiny::Error::ExternalIdNotFound)
+    );
+
+    assert_eq!(
+        importer.import(&mut ecs, [(104, (4, 4u32)), (101, (5, 5u32))]),
+        Err(ImportError::DuplicateId {
+            index: 1,
+            external_id: 101
+        })
+    );
+    assert_eq!(importer.iter(&ecs).count(), 5);
+
+    // a failed bundle leaves no entity behind
+    assert_eq!(
+        importer.import(&mut ecs, [(105, (6, 6u64))]),
+        Err(ImportError::Bundle {
+            index: 0,
+            error: ecs_tiny::Error::TypeNotRegistered
+        })
+    );
+    assert_eq!(
+        importer.entity_key(&ecs, 105),
+        Err(ecs_tiny::Error::ExternalIdNotFound)
+    );
+    assert_eq!(ecs.iter_entity().count(), 5);
+
+    // the mappings follow the entities however they are removed
+    ecs.remove_entity(entity_keys[0]).unwrap();
+    ecs.despawn_deferred(entity_keys[1]).unwrap();
+    ecs.end_frame();
+    assert_eq!(
+        importer.entity_key(&ecs, 100),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(
+        importer.external_id(&ecs, entity_keys[1]),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(
+        ecs.entity_from_bits(entity_keys[0].to_bits()),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert!(matches!(
+        importer.entity_key(&ecs, 100),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    assert!(matches!(
+        ecs.entity_from_bits(entity_keys[0].to_bits()),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+
+    // the slot handed out again does not alias the removed entity
+    let entity_key = ecs.insert_entity();
+    assert_eq!(entity_key.index(), entity_keys[1].index());
+    assert_eq!(
+        importer.external_id(&ecs, entity_key),
+        Err(ecs_tiny::Error::ExternalIdNotFound)
+    );
+
+    // an external id whose entity is removed can be imported again
+    let reimported = importer.import(&mut ecs, [(100, (7, 7u32))]).unwrap();
+    assert_eq!(importer.entity_key(&ecs, 100), Ok(reimported[0]));
+    assert_eq!(importer.prune(&ecs), 1);
+
+    ecs.clear();
+    assert_eq!(importer.iter(&ecs).count(), 0);
+    assert_eq!(importer.prune(&ecs), 4);
+}
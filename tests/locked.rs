@@ -0,0 +1,150 @@
+#[test]
+fn locked_columns() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register_immutable::<u64>().unwrap();
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 42).unwrap();
+    ecs.insert_comp(entity_key, 1u32).unwrap();
+
+    {
+        let locked = ecs.lock();
+
+        // readers share a column, and writers exclude everyone
+        let ints0 = locked.read_column::<i32>().unwrap();
+        let ints1 = locked.try_read_column::<i32>().unwrap();
+        assert_eq!(ints0.column().len(), ints1.column().len());
+        assert_eq!(
+            locked.try_write_column::<i32>().err(),
+            Some(ecs_tiny::Error::ColumnLocked)
+        );
+        drop((ints0, ints1));
+
+        let uints = locked.try_write_column::<u32>().unwrap();
+        assert_eq!(
+            locked.try_read_column::<u32>().err(),
+            Some(ecs_tiny::Error::ColumnLocked)
+        );
+        drop(uints);
+
+        // the pair is acquired in lock order, whichever comes first
+        assert!(locked.lock_order::<i32>().unwrap() < locked.lock_order::<u32>().unwrap());
+        let (mut uints, ints) = locked.write_column_and::<u32, i32>().unwrap();
+        let row_key = locked.rows_by_entity::<u32>(entity_key).next().unwrap();
+        *uints.column_mut().get_mut(row_key).unwrap() += *ints.column().get(0).unwrap() as u32;
+        drop((uints, ints));
+
+        assert_eq!(
+            locked.write_column::<u64>().err(),
+            Some(ecs_tiny::Error::ImmutableType)
+        );
+        assert_eq!(
+            locked.read_column::<f32>().err(),
+            Some(ecs_tiny::Error::TypeNotRegistered)
+        );
+        assert_eq!(
+            locked.lock_order::<f32>(),
+            Err(ecs_tiny::Error::TypeNotRegistered)
+        );
+    }
+
+    // the rows are back in the world, and structural changes work again
+    assert_eq!(
+        ecs.iter_comp::<u32>().unwrap().collect::<Vec<_>>(),
+        vec![&43]
+    );
+    ecs.insert_comp(entity_key, 63).unwrap();
+    ecs.remove_entity(entity_key).unwrap();
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn locked_threads() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    for i in 0..100 {
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, i).unwrap();
+        ecs.insert_comp(entity_key, 0u32).unwrap();
+    }
+
+    {
+        let locked = ecs.lock();
+
+        // every thread adds the sum of the first column to the second one, blocking in lock order
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        let (mut uints, ints) = locked.write_column_and::<u32, i32>().unwrap();
+                        let sum = ints
+                            .column()
+                            .iter_rows()
+                            .map(|(_, _, comp)| *comp)
+                            .sum::<i32>();
+                        let mut uints = uints.column_mut();
+                        uints
+                            .iter_rows_mut()
+                            .for_each(|(_, _, comp)| *comp += sum as u32);
+                    }
+                });
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        let ints = locked.read_column::<i32>().unwrap();
+                        assert_eq!(ints.column().len(), 100);
+                    }
+                });
+            }
+        });
+    }
+
+    let sum = (0..100).sum::<u32>();
+    for comp in ecs.iter_comp::<u32>().unwrap() {
+        assert_eq!(*comp, sum * 80);
+    }
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn lock_columns() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.insert_comp(entity_key, 1).unwrap();
+    ecs.insert_comp(entity_key, 10u32).unwrap();
+
+    let locked = ecs.lock();
+
+    // reading one column while writing another goes through shared references
+    let uints = locked.try_read_column::<u32>().unwrap();
+    let mut ints = locked.try_write_column::<i32>().unwrap();
+    let delta = *uints.column().iter_rows().next().unwrap().2 as i32;
+    *ints.column_mut().get_by_entity_mut(entity_key).unwrap() += delta;
+
+    // only a conflict on the same column fails
+    assert_eq!(
+        locked.try_read_column::<i32>().err(),
+        Some(ecs_tiny::Error::ColumnLocked)
+    );
+    assert_eq!(
+        locked.try_write_column::<u32>().err(),
+        Some(ecs_tiny::Error::ColumnLocked)
+    );
+    assert!(locked.try_read_column::<u32>().is_ok());
+
+    // dropping a guard releases its column
+    drop(ints);
+    assert!(locked.try_read_column::<i32>().is_ok());
+    drop(uints);
+    assert!(locked.try_write_column::<u32>().is_ok());
+    drop(locked);
+
+    assert_eq!(
+        ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(),
+        vec![&11]
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
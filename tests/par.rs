@@ -0,0 +1,98 @@
+#[test]
+fn par_map_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    for i in 0..10_000 {
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, i).unwrap();
+    }
+
+    // vacant rows in the middle of the column keep the order
+    let column = ecs.column::<i32>().unwrap();
+    let comp_keys = column
+        .iter_rows()
+        .map(|(row_key, _, _)| column.comp_key(row_key))
+        .collect::<Vec<_>>();
+    for comp_key in comp_keys.into_iter().step_by(3) {
+        ecs.remove_comp::<i32>(comp_key).unwrap();
+    }
+
+    let mapped = ecs
+        .par_map_comp::<i32, _>(|entity_key, comp| (entity_key, *comp as i64 * 2))
+        .unwrap();
+    let sequential = ecs
+        .column::<i32>()
+        .unwrap()
+        .iter_rows()
+        .map(|(_, entity_key, comp)| (entity_key, *comp as i64 * 2))
+        .collect::<Vec<_>>();
+    assert_eq!(mapped, sequential);
+    assert!(mapped.windows(2).all(|pair| pair[0].1 < pair[1].1));
+
+    assert_eq!(ecs.par_map_comp::<u32, _>(|_, comp| *comp).unwrap(), vec![]);
+    assert_eq!(
+        ecs.par_map_comp::<f32, _>(|_, comp| *comp),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+}
+
+#[test]
+fn par_iter_comp() {
+    use rayon::prelude::*;
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i64>().unwrap();
+    ecs.register_immutable::<u32>().unwrap();
+    for i in 0..200_000 {
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, i as i64).unwrap();
+    }
+
+    let serial = ecs
+        .iter_comp::<i64>()
+        .unwrap()
+        .map(|comp| comp * 3 + 1)
+        .collect::<Vec<_>>();
+    ecs.par_iter_comp_mut::<i64>()
+        .unwrap()
+        .for_each(|comp| *comp = *comp * 3 + 1);
+    let parallel = ecs
+        .par_iter_comp::<i64>()
+        .unwrap()
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(parallel, serial);
+    assert_eq!(
+        ecs.par_iter_comp::<i64>().unwrap().sum::<i64>(),
+        serial.iter().sum::<i64>()
+    );
+
+    // the work is spread over the threads of the pool
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    let threads = std::sync::Mutex::new(std::collections::HashSet::new());
+    let iter = ecs.par_iter_comp::<i64>().unwrap().take(1_000);
+    pool.install(|| {
+        iter.for_each(|_| {
+            threads
+                .lock()
+                .unwrap()
+                .insert(rayon::current_thread_index());
+            std::thread::sleep(std::time::Duration::from_micros(500));
+        })
+    });
+    assert!(threads.into_inner().unwrap().len() > 1);
+
+    assert_eq!(ecs.par_iter_comp::<u32>().unwrap().count(), 0);
+    assert!(matches!(
+        ecs.par_iter_comp_mut::<u32>(),
+        Err(ecs_tiny::Error::ImmutableType)
+    ));
+    assert!(matches!(
+        ecs.par_iter_comp::<f32>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    ));
+}
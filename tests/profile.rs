@@ -0,0 +1,52 @@
+#[test]
+fn access_stats() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let comp_key = ecs.insert_comp(entity_key, 1).unwrap();
+    ecs.insert_comp(entity_key, 2u32).unwrap();
+    ecs.get_comp::<i32>(comp_key).unwrap();
+    ecs.iter_comp::<i32>().unwrap().for_each(drop);
+    *ecs.get_comp_mut::<i32>(comp_key).unwrap() += 1;
+    ecs.iter_comp_mut::<u32>()
+        .unwrap()
+        .for_each(|comp| *comp += 1);
+    ecs.query::<(&i32, &mut u32)>().unwrap().for_each(drop);
+    ecs.remove_comp::<i32>(comp_key).unwrap();
+
+    // failed lookups do not reach a column
+    assert!(ecs.get_comp::<u32>(comp_key).is_err());
+
+    let stats = ecs.access_stats();
+    assert_eq!(
+        stats,
+        vec![
+            (
+                "i32",
+                ecs_tiny::AccessCounts {
+                    reads: 3,
+                    writes: 3
+                }
+            ),
+            (
+                "u32",
+                ecs_tiny::AccessCounts {
+                    reads: 0,
+                    writes: 3
+                }
+            ),
+        ]
+    );
+    assert_eq!(
+        ecs.access_report(),
+        "i32: 3 reads, 3 writes\nu32: 0 reads, 3 writes\n"
+    );
+
+    ecs.reset_access_stats();
+    ecs.remove_entity(entity_key).unwrap();
+    let stats = ecs.access_stats();
+    assert_eq!(stats[0].1, Default::default());
+    assert_eq!(stats[1].1.writes, 1);
+}
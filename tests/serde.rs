@@ -0,0 +1,128 @@
+use ecs_tiny::Unserializable;
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Pos {
+    x: i32,
+    y: i32,
+}
+
+fn save<C>(ecs: &ecs_tiny::ECS, mode: Unserializable) -> Result<String, serde_json::Error>
+where
+    C: ecs_tiny::SerdeComps,
+{
+    let mut json = Vec::new();
+    ecs.serialize::<C, _>(&mut serde_json::Serializer::new(&mut json), mode)?;
+    Ok(String::from_utf8(json).unwrap())
+}
+
+fn load<C>(
+    ecs: &mut ecs_tiny::ECS,
+    json: &str,
+    mode: Unserializable,
+) -> Result<(), serde_json::Error>
+where
+    C: ecs_tiny::SerdeComps,
+{
+    ecs.deserialize_into::<C, _>(&mut serde_json::Deserializer::from_str(json), mode)
+}
+
+#[test]
+fn serde_round_trip() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_serde::<Pos>("pos").unwrap();
+    ecs.register_serde::<String>("name").unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    let removed = ecs.insert_comp(entity_key0, Pos { x: 0, y: 0 }).unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, Pos { x: 1, y: 2 }).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key2, Pos { x: 3, y: 4 }).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key0, Pos { x: 5, y: 6 }).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key2, "b".to_string()).unwrap();
+    ecs.remove_comp::<Pos>(removed).unwrap();
+    ecs.remove_entity(entity_key1).unwrap();
+
+    let json = save::<(Pos, String)>(&ecs, Unserializable::Fail).unwrap();
+
+    let mut loaded = ecs_tiny::ECS::new();
+    loaded.register_serde::<String>("name").unwrap();
+    loaded.register_serde::<Pos>("pos").unwrap();
+    load::<(Pos, String)>(&mut loaded, &json, Unserializable::Fail).unwrap();
+
+    assert_eq!(
+        loaded.iter_entity().collect::<Vec<_>>(),
+        vec![entity_key0, entity_key2]
+    );
+    assert!(loaded.get_entity(entity_key1).is_err());
+    assert_eq!(loaded.get_comp::<Pos>(comp_key0), Ok(&Pos { x: 1, y: 2 }));
+    assert_eq!(loaded.get_comp::<Pos>(comp_key1), Ok(&Pos { x: 3, y: 4 }));
+    assert_eq!(loaded.get_comp::<String>(comp_key3), Ok(&"b".to_string()));
+    assert!(loaded.get_comp::<Pos>(removed).is_err());
+    assert_eq!(loaded.get_entity_by_comp(comp_key1), Ok(entity_key2));
+
+    // the order of the components of each entity is kept
+    assert_eq!(
+        loaded
+            .iter_comp_by_entity::<Pos>(entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&Pos { x: 1, y: 2 }, &Pos { x: 5, y: 6 }]
+    );
+    assert_eq!(loaded.get_comp::<Pos>(comp_key2), Ok(&Pos { x: 5, y: 6 }));
+    assert_eq!(
+        save::<(Pos, String)>(&loaded, Unserializable::Fail).unwrap(),
+        json
+    );
+
+    // the next keys do not collide with the saved ones
+    let entity_key = loaded.insert_entity();
+    assert_ne!(entity_key, entity_key1);
+    let comp_key = loaded.insert_comp(entity_key, Pos { x: 7, y: 8 }).unwrap();
+    assert_ne!(comp_key, removed);
+}
+
+#[test]
+fn serde_unserializable() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_serde::<i32>("hp").unwrap();
+    ecs.register::<u32>().unwrap();
+    let entity_key = ecs.insert_entity();
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ecs.insert_comp(entity_key, 7u32).unwrap();
+
+    assert!(save::<(i32,)>(&ecs, Unserializable::Fail).is_err());
+    assert!(save::<(i32, u32)>(&ecs, Unserializable::Skip).is_err());
+    let json = save::<(i32,)>(&ecs, Unserializable::Skip).unwrap();
+
+    let mut loaded = ecs_tiny::ECS::new();
+    loaded.register_serde::<i32>("hp").unwrap();
+    loaded.register::<u32>().unwrap();
+    load::<(i32,)>(&mut loaded, &json, Unserializable::Fail).unwrap();
+    assert_eq!(loaded.get_comp::<i32>(comp_key), Ok(&42));
+    assert_eq!(loaded.iter_comp::<u32>().unwrap().count(), 0);
+    assert!(load::<(i32,)>(&mut loaded, &json, Unserializable::Fail).is_err());
+
+    // names no component type goes by are skipped
+    let mut loaded = ecs_tiny::ECS::new();
+    loaded.register_serde::<u8>("level").unwrap();
+    load::<(u8,)>(&mut loaded, &json, Unserializable::Skip).unwrap();
+    assert_eq!(loaded.iter_entity().count(), 1);
+
+    let mut loaded = ecs_tiny::ECS::new();
+    loaded.register_serde::<u8>("level").unwrap();
+    assert!(load::<(u8,)>(&mut loaded, &json, Unserializable::Fail).is_err());
+
+    // taken components are left out
+    ecs.unregister::<u32>().unwrap();
+    assert!(save::<(i32,)>(&ecs, Unserializable::Fail).is_ok());
+    let taken = ecs.take_comp::<i32>(comp_key).unwrap();
+    assert!(save::<(i32,)>(&ecs, Unserializable::Fail).is_err());
+    let json = save::<(i32,)>(&ecs, Unserializable::Skip).unwrap();
+    ecs.put_back(taken).unwrap();
+
+    let mut loaded = ecs_tiny::ECS::new();
+    loaded.register_serde::<i32>("hp").unwrap();
+    load::<(i32,)>(&mut loaded, &json, Unserializable::Fail).unwrap();
+    assert!(loaded.get_comp::<i32>(comp_key).is_err());
+}
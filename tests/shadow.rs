@@ -0,0 +1,261 @@
+use ecs_tiny::test_util::{Op, ShadowWorld};
+use proptest::prelude::*;
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => Just(Op::InsertEntity),
+        2 => any::<usize>().prop_map(Op::RemoveEntity),
+        4 => (any::<usize>(), any::<i32>()).prop_map(|(i, v)| Op::InsertComp(i, Box::new(v))),
+        2 => (any::<usize>(), ".{0,4}").prop_map(|(i, v)| Op::InsertComp(i, Box::new(v))),
+        1 => any::<usize>().prop_map(|i| Op::InsertComp(i, Box::new(()))),
+        1 => any::<usize>().prop_map(|i| Op::InsertComp(i, Box::new(0u64))),
+        4 => any::<usize>().prop_map(Op::RemoveComp),
+        1 => Just(Op::Clear),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn shadow_model(ops in proptest::collection::vec(op(), 1..128)) {
+        let mut ecs = ecs_tiny::ECS::new();
+        let mut shadow = ShadowWorld::new();
+        shadow.register::<i32>(&mut ecs);
+        shadow.register::<String>(&mut ecs);
+        shadow.register::<()>(&mut ecs);
+
+        for op in &ops {
+            shadow.apply_both(&mut ecs, op);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "ECS invariant violated")]
+fn assert_invariants() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.insert_comp(entity_key, 42).unwrap();
+    ecs.corrupt_remove_column::<i32>().unwrap();
+
+    ecs.assert_invariants();
+}
+
+#[test]
+fn find_orphans() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<u64>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 42u32).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key2, 42u64).unwrap();
+
+    assert!(ecs.find_orphans().is_empty());
+    assert_eq!(ecs.find_orphans().to_string(), "no orphans");
+
+    ecs.corrupt_remove_entity(entity_key0).unwrap();
+    ecs.corrupt_remove_row(comp_key1).unwrap();
+    ecs.corrupt_remove_column::<u64>().unwrap();
+
+    let report = ecs.find_orphans();
+    assert_eq!(
+        report.dead_owners,
+        vec![ecs_tiny::OrphanRow {
+            type_name: "i32",
+            entity_key: entity_key0,
+            row_key: comp_key0.row_key(),
+        }]
+    );
+    assert_eq!(
+        report.vacant_refs,
+        vec![ecs_tiny::OrphanRow {
+            type_name: "u32",
+            entity_key: entity_key1,
+            row_key: comp_key1.row_key(),
+        }]
+    );
+    assert_eq!(
+        report.unregistered_refs,
+        vec![ecs_tiny::UnregisteredRef {
+            type_key: std::any::TypeId::of::<u64>(),
+            entity_key: entity_key2,
+            row_key: comp_key2.row_key(),
+        }]
+    );
+    assert_eq!(report.to_string().lines().count(), 3);
+    assert!(ecs.validate().is_err());
+}
+
+#[test]
+fn duplicate_type_name() {
+    mod plugin_a {
+        #[derive(Debug, PartialEq)]
+        pub struct Health(pub u32);
+    }
+
+    mod plugin_b {
+        #[derive(Debug, PartialEq)]
+        pub struct Health(pub u32);
+    }
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register_named::<plugin_a::Health>("common::Health")
+        .unwrap();
+
+    // a second copy of the type under the same name is rejected, and leaves no column behind
+    assert_eq!(
+        ecs.register_named::<plugin_b::Health>("common::Health"),
+        Err(ecs_tiny::Error::DuplicateTypeName("common::Health"))
+    );
+    assert_eq!(
+        ecs.insert_comp(entity_key, plugin_b::Health(42)),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.register_named::<plugin_a::Health>("common::Health"),
+        Err(ecs_tiny::Error::TypeAlreadyRegistered)
+    );
+
+    // the opt-out allows both columns
+    ecs.allow_duplicate_names(true);
+    ecs.register_named::<plugin_b::Health>("common::Health")
+        .unwrap();
+    let comp_key = ecs.insert_comp(entity_key, plugin_b::Health(42)).unwrap();
+    assert_eq!(ecs.get_comp(comp_key), Ok(&plugin_b::Health(42)));
+
+    ecs.allow_duplicate_names(false);
+    ecs.register::<u32>().unwrap();
+    assert_eq!(
+        ecs.register_named::<u64>("u32"),
+        Err(ecs_tiny::Error::DuplicateTypeName("u32"))
+    );
+}
+
+#[derive(Debug, Clone)]
+enum CompactOp {
+    Insert(usize, i32),
+    Remove(usize),
+    RemoveEntity(usize),
+    Step(usize),
+}
+
+fn compact_op() -> impl Strategy<Value = CompactOp> {
+    prop_oneof![
+        4 => (any::<usize>(), any::<i32>()).prop_map(|(i, v)| CompactOp::Insert(i, v)),
+        3 => any::<usize>().prop_map(CompactOp::Remove),
+        1 => any::<usize>().prop_map(CompactOp::RemoveEntity),
+        3 => (0..4usize).prop_map(CompactOp::Step),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn compact_step(ops in proptest::collection::vec(compact_op(), 1..256)) {
+        let mut ecs = ecs_tiny::ECS::new();
+        ecs.register::<i32>().unwrap();
+        let mut entity_keys = (0..8).map(|_| ecs.insert_entity()).collect::<Vec<_>>();
+        let mut comps = std::collections::BTreeMap::new();
+
+        for op in &ops {
+            match op {
+                CompactOp::Insert(index, comp) => {
+                    let entity_key = entity_keys[index % entity_keys.len()];
+                    let comp_key = ecs.insert_comp(entity_key, *comp).unwrap();
+                    comps.insert(comp_key, (entity_key, *comp));
+                }
+                CompactOp::Remove(index) => {
+                    if let Some(comp_key) = comps.keys().nth(index % comps.len().max(1)).copied() {
+                        let (_, comp) = comps.remove(&comp_key).unwrap();
+                        prop_assert_eq!(ecs.remove_comp::<i32>(comp_key), Ok(comp));
+                    }
+                }
+                CompactOp::RemoveEntity(index) => {
+                    let entity_key = entity_keys.remove(index % entity_keys.len());
+                    ecs.remove_entity(entity_key).unwrap();
+                    comps.retain(|_, (owner, _)| *owner != entity_key);
+                    entity_keys.push(ecs.insert_entity());
+                }
+                CompactOp::Step(budget_rows) => {
+                    let progress = ecs.compact_step::<i32>(*budget_rows).unwrap();
+                    let remaps = ecs.take_remaps::<i32>().unwrap();
+
+                    let relocated = match progress {
+                        ecs_tiny::CompactProgress::Pending { relocated } => relocated,
+                        ecs_tiny::CompactProgress::Done { relocated } => relocated,
+                    };
+                    prop_assert!(relocated <= *budget_rows);
+                    prop_assert_eq!(remaps.len(), relocated);
+
+                    comps = comps
+                        .into_iter()
+                        .map(|(comp_key, comp)| (remaps.get(&comp_key).copied().unwrap_or(comp_key), comp))
+                        .collect();
+
+                    // a dense column holds its rows in the first row keys
+                    if matches!(progress, ecs_tiny::CompactProgress::Done { .. }) {
+                        let column = ecs.column::<i32>().unwrap();
+                        let max_row_key = column.iter_rows().map(|(row_key, _, _)| row_key).max();
+                        prop_assert_eq!(max_row_key.map_or(0, |row_key| row_key as usize + 1), column.len());
+                    }
+                }
+            }
+
+            prop_assert_eq!(ecs.validate(), Ok(()));
+            prop_assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), comps.len());
+            for (comp_key, (entity_key, comp)) in &comps {
+                prop_assert_eq!(ecs.get_comp::<i32>(*comp_key), Ok(comp));
+                prop_assert_eq!(ecs.get_entity_by_comp(*comp_key), Ok(*entity_key));
+            }
+        }
+
+        // repeated steps always finish
+        while let ecs_tiny::CompactProgress::Pending { .. } = ecs.compact_step::<i32>(1).unwrap() {}
+        let remaps = ecs.take_remaps::<i32>().unwrap();
+        let comps = comps
+            .into_iter()
+            .map(|(comp_key, comp)| (remaps.get(&comp_key).copied().unwrap_or(comp_key), comp))
+            .collect::<Vec<_>>();
+        for (comp_key, (_, comp)) in &comps {
+            prop_assert_eq!(ecs.get_comp::<i32>(*comp_key), Ok(comp));
+        }
+    }
+}
+
+#[test]
+fn key_limit() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.set_key_limit(3);
+
+    let entity_keys = (0..4).map(|_| ecs.insert_entity()).collect::<Vec<_>>();
+    assert_eq!(entity_keys[3].index(), 3);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| ecs.insert_entity()));
+    assert!(result.is_err());
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| ecs.reserve_entity()));
+    assert!(result.is_err());
+    assert_eq!(ecs.iter_entity().count(), 4);
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // the rows of a type run out in the same way, before anything is inserted
+    for i in 0..4 {
+        ecs.insert_comp(entity_keys[0], i).unwrap();
+    }
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        ecs.insert_comp(entity_keys[1], 4)
+    }));
+    assert!(result.is_err());
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 4);
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // and the keys freed by removals are handed out again
+    ecs.remove_entity(entity_keys[0]).unwrap();
+    let entity_key = ecs.insert_entity();
+    assert_eq!(entity_key.index(), entity_keys[0].index());
+    ecs.insert_comp(entity_key, 5).unwrap();
+    assert_eq!(ecs.validate(), Ok(()));
+}
@@ -3,11 +3,11 @@ fn crud_entity() {
     let mut ecs = ecs_tiny::ECS::new();
     let entity_key = ecs.insert_entity();
 
-    assert!(ecs.get_entity(entity_key).is_some());
-    assert!(ecs.remove_entity(entity_key).is_some());
+    assert!(ecs.get_entity(entity_key).is_ok());
+    assert!(ecs.remove_entity(entity_key).is_ok());
 
-    assert!(ecs.get_entity(entity_key).is_none());
-    assert!(ecs.remove_entity(entity_key).is_none());
+    assert!(ecs.get_entity(entity_key).is_err());
+    assert!(ecs.remove_entity(entity_key).is_err());
 }
 
 #[test]
@@ -17,13 +17,22 @@ fn crud_comp() {
     ecs.register::<i32>().unwrap();
     let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
 
-    assert_eq!(ecs.get_comp::<i32>(comp_key), Some(&42));
-    assert_eq!(ecs.get_comp_mut::<i32>(comp_key), Some(&mut 42));
-    assert_eq!(ecs.remove_comp::<i32>(comp_key), Some(42));
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+    assert_eq!(ecs.get_comp_mut::<i32>(comp_key), Ok(&mut 42));
+    assert_eq!(ecs.remove_comp::<i32>(comp_key), Ok(42));
 
-    assert_eq!(ecs.get_comp::<i32>(comp_key), None);
-    assert_eq!(ecs.get_comp_mut::<i32>(comp_key), None);
-    assert_eq!(ecs.remove_comp::<i32>(comp_key), None);
+    assert_eq!(
+        ecs.get_comp::<i32>(comp_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.get_comp_mut::<i32>(comp_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.remove_comp::<i32>(comp_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
 }
 
 #[test]
@@ -32,7 +41,14 @@ fn insert_comp_with_invalid_entity() {
     let entity_key = ecs.insert_entity();
     ecs.remove_entity(entity_key).unwrap();
 
-    assert!(ecs.insert_comp(entity_key, 42).is_none());
+    assert_eq!(
+        ecs.insert_comp(entity_key, 42),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
 }
 
 #[test]
@@ -42,9 +58,54 @@ fn remove_comp_with_invalid_type() {
     ecs.register::<i32>().unwrap();
     let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
 
-    assert!(ecs.get_comp::<()>(comp_key).is_none());
-    assert!(ecs.get_comp_mut::<()>(comp_key).is_none());
-    assert!(ecs.remove_comp::<()>(comp_key).is_none());
+    assert!(ecs.get_comp::<()>(comp_key).is_err());
+    assert!(ecs.get_comp_mut::<()>(comp_key).is_err());
+    assert!(ecs.remove_comp::<()>(comp_key).is_err());
+}
+
+#[test]
+fn swap_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 63).unwrap();
+
+    assert!(ecs.swap_comp::<i32>(comp_key0, comp_key1).is_ok());
+
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&63));
+    assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&42));
+    assert_eq!(ecs.get_entity_by_comp(comp_key0), Ok(entity_key0));
+    assert_eq!(ecs.get_entity_by_comp(comp_key1), Ok(entity_key1));
+}
+
+#[test]
+fn swap_comp_with_same_key() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+
+    assert!(ecs.swap_comp::<i32>(comp_key, comp_key).is_ok());
+
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+}
+
+#[test]
+fn swap_comp_with_invalid_key() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key, 63).unwrap();
+    ecs.remove_comp::<i32>(comp_key1).unwrap();
+
+    assert!(ecs.swap_comp::<i32>(comp_key0, comp_key1).is_err());
+    assert!(ecs.swap_comp::<i32>(comp_key1, comp_key1).is_err());
+    assert!(ecs.swap_comp::<()>(comp_key0, comp_key0).is_err());
+
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&42));
 }
 
 #[test]
@@ -54,11 +115,11 @@ fn remove_entity_and_associated_comp() {
     ecs.register::<i32>().unwrap();
     let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
 
-    assert!(ecs.remove_entity(entity_key).is_some());
+    assert!(ecs.remove_entity(entity_key).is_ok());
 
-    assert!(ecs.get_entity(entity_key).is_none());
-    assert!(ecs.get_comp::<i32>(comp_key).is_none());
-    assert!(ecs.get_comp_mut::<i32>(comp_key).is_none());
+    assert!(ecs.get_entity(entity_key).is_err());
+    assert!(ecs.get_comp::<i32>(comp_key).is_err());
+    assert!(ecs.get_comp_mut::<i32>(comp_key).is_err());
 }
 
 #[test]
@@ -104,8 +165,8 @@ fn iter_comp() {
 fn iter_comp_with_invalid_type() {
     let mut ecs = ecs_tiny::ECS::new();
 
-    assert!(ecs.iter_comp::<i32>().is_none());
-    assert!(ecs.iter_comp_mut::<i32>().is_none());
+    assert!(ecs.iter_comp::<i32>().is_err());
+    assert!(ecs.iter_comp_mut::<i32>().is_err());
 }
 
 #[test]
@@ -121,10 +182,13 @@ fn get_entity_by_comp() {
     let comp_key3 = ecs.insert_comp(entity_key1, ()).unwrap();
     ecs.remove_comp::<i32>(comp_key2).unwrap();
 
-    assert_eq!(ecs.get_entity_by_comp(comp_key0), Some(entity_key0));
-    assert_eq!(ecs.get_entity_by_comp(comp_key1), Some(entity_key0));
-    assert_eq!(ecs.get_entity_by_comp(comp_key2), None);
-    assert_eq!(ecs.get_entity_by_comp(comp_key3), Some(entity_key1));
+    assert_eq!(ecs.get_entity_by_comp(comp_key0), Ok(entity_key0));
+    assert_eq!(ecs.get_entity_by_comp(comp_key1), Ok(entity_key0));
+    assert_eq!(
+        ecs.get_entity_by_comp(comp_key2),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_key3), Ok(entity_key1));
 }
 
 #[test]
@@ -165,3 +229,5532 @@ fn clear() {
     ecs.insert_comp(entity_key1, ()).unwrap();
     ecs.clear();
 }
+
+#[test]
+fn update_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+
+    assert_eq!(
+        ecs.update_comp::<i32, _>(comp_key, |comp| *comp += 1),
+        Ok(())
+    );
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+
+    ecs.remove_comp::<i32>(comp_key).unwrap();
+
+    assert_eq!(
+        ecs.update_comp::<i32, _>(comp_key, |comp| *comp += 1),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+}
+
+#[test]
+fn try_update_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+
+    let result = ecs.try_update_comp::<i32, ()>(comp_key, |comp| {
+        *comp += 1;
+        Ok(())
+    });
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&43));
+}
+
+#[test]
+fn try_update_comp_with_rejection() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+
+    let result = ecs.try_update_comp::<i32, _>(comp_key, |comp| {
+        *comp = 0;
+        Err("rejected")
+    });
+
+    assert_eq!(result, Err(ecs_tiny::UpdateError::Rejected("rejected")));
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+
+    ecs.remove_comp::<i32>(comp_key).unwrap();
+    let result = ecs.try_update_comp::<i32, ()>(comp_key, |_| Ok(()));
+
+    assert_eq!(
+        result,
+        Err(ecs_tiny::UpdateError::Access(ecs_tiny::Error::CompNotFound))
+    );
+}
+
+#[test]
+fn insert_comp_with_validator() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.set_insert_validator::<i32>(|_, _, comp| {
+        if *comp > 0 {
+            Ok(())
+        } else {
+            Err("must be positive".to_string())
+        }
+    })
+    .unwrap();
+
+    assert!(ecs.insert_comp(entity_key, 42).is_ok());
+    assert_eq!(
+        ecs.insert_comp(entity_key, 0),
+        Err(ecs_tiny::Error::ValidationFailed(
+            "must be positive".to_string()
+        ))
+    );
+
+    let mut iter = ecs.iter_comp_by_entity::<i32>(entity_key).unwrap();
+
+    assert_eq!(iter.next(), Some(&42));
+    assert_eq!(iter.next(), None);
+}
+
+#[test]
+fn insert_comp_with_world_validator() {
+    struct Player;
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<Player>().unwrap();
+    ecs.set_insert_validator::<Player>(|ecs, _, _| {
+        if ecs.iter_comp::<Player>().unwrap().next().is_some() {
+            Err("player already exists".to_string())
+        } else {
+            Ok(())
+        }
+    })
+    .unwrap();
+    let comp_key = ecs.insert_comp(entity_key0, Player).unwrap();
+
+    assert!(ecs.insert_comp(entity_key1, Player).is_err());
+    assert_eq!(
+        ecs.iter_comp_by_entity::<Player>(entity_key1)
+            .unwrap()
+            .count(),
+        0
+    );
+
+    ecs.remove_comp::<Player>(comp_key).unwrap();
+
+    assert!(ecs.insert_comp(entity_key1, Player).is_ok());
+}
+
+#[test]
+fn set_insert_validator_with_invalid_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+
+    assert!(ecs.set_insert_validator::<i32>(|_, _, _| Ok(())).is_err());
+}
+
+#[test]
+fn insert_comp_with_comp_limit() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.set_comp_limit::<i32>(2).unwrap();
+    let comp_key = ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.insert_comp(entity_key0, 63).unwrap();
+
+    assert_eq!(
+        ecs.insert_comp(entity_key0, 84),
+        Err(ecs_tiny::Error::QuotaExceeded)
+    );
+    assert!(ecs.insert_comp(entity_key1, 84).is_ok());
+
+    ecs.remove_comp::<i32>(comp_key).unwrap();
+
+    assert!(ecs.insert_comp(entity_key0, 84).is_ok());
+}
+
+#[test]
+fn insert_comp_with_comp_limit_total() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.set_comp_limit_total::<i32>(2).unwrap();
+    ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.insert_comp(entity_key1, 63).unwrap();
+
+    assert_eq!(
+        ecs.insert_comp(entity_key0, 84),
+        Err(ecs_tiny::Error::QuotaExceeded)
+    );
+
+    ecs.remove_entity(entity_key1).unwrap();
+
+    assert!(ecs.insert_comp(entity_key0, 84).is_ok());
+}
+
+#[test]
+fn query() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<()>().unwrap();
+    ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.insert_comp(entity_key0, ()).unwrap();
+    ecs.insert_comp(entity_key1, 63).unwrap();
+    ecs.insert_comp(entity_key2, 84).unwrap();
+    ecs.insert_comp(entity_key2, ()).unwrap();
+
+    for (_, (comp, _)) in ecs.query::<(&mut i32, &())>().unwrap() {
+        *comp += 1;
+    }
+
+    let mut iter = ecs.query::<(&i32,)>().unwrap();
+
+    assert_eq!(iter.next(), Some((entity_key0, (&43,))));
+    assert_eq!(iter.next(), Some((entity_key1, (&63,))));
+    assert_eq!(iter.next(), Some((entity_key2, (&85,))));
+    assert_eq!(iter.next(), None);
+}
+
+#[test]
+fn query_with_invalid_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+
+    assert!(ecs.query::<(&i32, &())>().is_err());
+    assert_eq!(ecs.query_count::<(&i32, &())>(), 0);
+    assert!(!ecs.query_any::<(&i32, &())>());
+}
+
+#[test]
+#[should_panic]
+fn query_with_duplicate_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+
+    let _ = ecs.query::<(&i32, &mut i32)>();
+}
+
+#[test]
+fn query_count() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<()>().unwrap();
+    ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.insert_comp(entity_key0, 63).unwrap();
+    ecs.insert_comp(entity_key0, ()).unwrap();
+    ecs.insert_comp(entity_key1, 84).unwrap();
+
+    assert_eq!(ecs.query_count::<(&i32,)>(), 2);
+    assert_eq!(ecs.query_count::<(&i32, &())>(), 1);
+    assert!(ecs.query_any::<(&(),)>());
+
+    ecs.remove_entity(entity_key0).unwrap();
+
+    assert_eq!(ecs.query_count::<(&i32,)>(), 1);
+    assert_eq!(ecs.query_count::<(&i32, &())>(), 0);
+    assert!(!ecs.query_any::<(&(),)>());
+}
+
+#[test]
+fn query_single() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<()>().unwrap();
+    ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.insert_comp(entity_key0, ()).unwrap();
+    ecs.insert_comp(entity_key1, 63).unwrap();
+
+    assert_eq!(
+        ecs.query_single::<(&mut i32, &())>(),
+        Ok((entity_key0, (&mut 42, &())))
+    );
+}
+
+#[test]
+fn query_single_with_no_entities() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<()>().unwrap();
+    ecs.insert_comp(entity_key, 42).unwrap();
+
+    assert_eq!(
+        ecs.query_single::<(&i32, &())>(),
+        Err(ecs_tiny::SingleError::NoEntities)
+    );
+    assert_eq!(
+        ecs.query_single::<(&i32, &u32)>(),
+        Err(ecs_tiny::SingleError::Query(
+            ecs_tiny::Error::TypeNotRegistered
+        ))
+    );
+}
+
+#[test]
+fn query_single_with_multiple_entities() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.insert_comp(entity_key1, 63).unwrap();
+
+    assert_eq!(
+        ecs.query_single::<(&i32,)>(),
+        Err(ecs_tiny::SingleError::MultipleEntities)
+    );
+}
+
+#[test]
+fn remove_entity_with_many_comps() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<()>().unwrap();
+    for i in 0..10 {
+        ecs.insert_comp(entity_key0, i).unwrap();
+        ecs.insert_comp(entity_key0, i as u32).unwrap();
+        ecs.insert_comp(entity_key0, ()).unwrap();
+        ecs.insert_comp(entity_key1, i).unwrap();
+    }
+    let comp_key = ecs.insert_comp(entity_key1, 1u32).unwrap();
+
+    assert!(ecs.remove_entity(entity_key0).is_ok());
+
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 10);
+    assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 1);
+    assert_eq!(ecs.iter_comp::<()>().unwrap().count(), 0);
+    assert!(ecs.iter_comp_by_entity::<i32>(entity_key0).is_err());
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key1)
+            .unwrap()
+            .copied()
+            .collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_key), Ok(entity_key1));
+
+    let entity_key2 = ecs.insert_entity();
+    ecs.insert_comp(entity_key2, 42).unwrap();
+    ecs.insert_comp(entity_key2, ()).unwrap();
+
+    assert!(ecs.remove_entity(entity_key2).is_ok());
+    assert!(ecs.remove_entity(entity_key1).is_ok());
+
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+    assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 0);
+}
+
+#[test]
+fn collect_comp_cloned() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<String>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, "a".to_string()).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, "b".to_string()).unwrap();
+    let cloned = ecs.collect_comp_cloned::<String>().unwrap();
+
+    ecs.get_comp_mut::<String>(comp_key0).unwrap().push('!');
+    ecs.remove_entity(entity_key1).unwrap();
+
+    let handle = std::thread::spawn(move || cloned);
+
+    assert_eq!(
+        handle.join().unwrap(),
+        vec![
+            (entity_key0, comp_key0, "a".to_string()),
+            (entity_key1, comp_key1, "b".to_string()),
+        ]
+    );
+    assert!(ecs.collect_comp_cloned::<i32>().is_err());
+}
+
+#[test]
+fn query_collect_owned() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<String>().unwrap();
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key0, "a".to_string()).unwrap();
+    ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.insert_comp(entity_key1, "b".to_string()).unwrap();
+    let owned = ecs.query_collect_owned::<(&String, &i32)>().unwrap();
+
+    ecs.get_comp_mut::<String>(comp_key).unwrap().push('!');
+    ecs.remove_entity(entity_key0).unwrap();
+
+    let handle = std::thread::spawn(move || owned);
+
+    assert_eq!(
+        handle.join().unwrap(),
+        vec![(entity_key0, ("a".to_string(), 42))]
+    );
+    assert!(ecs.query_collect_owned::<(&String, &u32)>().is_err());
+}
+
+#[test]
+fn shared_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    ecs.register_shared::<String>().unwrap();
+    let handle0 = ecs.intern_shared("stone".to_string()).unwrap();
+    let handle1 = ecs.intern_shared("stone".to_string()).unwrap();
+    let handle2 = ecs.intern_shared("grass".to_string()).unwrap();
+
+    assert!(ecs_tiny::SharedComp::ptr_eq(&handle0, &handle1));
+    assert!(!ecs_tiny::SharedComp::ptr_eq(&handle0, &handle2));
+
+    ecs.insert_shared_comp(entity_key0, handle0).unwrap();
+    let comp_key = ecs.insert_shared_comp(entity_key1, handle1).unwrap();
+    ecs.insert_shared_comp(entity_key2, handle2).unwrap();
+
+    assert_eq!(ecs.shared_pool_len::<String>(), Ok(2));
+    assert_eq!(
+        ecs.iter_shared_comp::<String>()
+            .unwrap()
+            .map(String::as_str)
+            .collect::<Vec<_>>(),
+        vec!["stone", "stone", "grass"]
+    );
+
+    ecs.remove_entity(entity_key2).unwrap();
+
+    assert_eq!(ecs.shared_pool_len::<String>(), Ok(1));
+
+    ecs.remove_entity(entity_key0).unwrap();
+
+    assert_eq!(ecs.shared_pool_len::<String>(), Ok(1));
+
+    ecs.remove_comp::<ecs_tiny::SharedComp<String>>(comp_key)
+        .unwrap();
+
+    assert_eq!(ecs.shared_pool_len::<String>(), Ok(0));
+}
+
+#[test]
+fn shared_comp_with_invalid_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_shared::<String>().unwrap();
+
+    assert_eq!(
+        ecs.register_shared::<String>(),
+        Err(ecs_tiny::Error::TypeAlreadyRegistered)
+    );
+    assert!(ecs.intern_shared(42).is_err());
+    assert!(ecs.shared_pool_len::<i32>().is_err());
+    assert!(ecs.iter_shared_comp::<i32>().is_err());
+}
+
+#[test]
+fn register_immutable() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register_immutable::<i32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key, 63).unwrap();
+
+    assert_eq!(
+        ecs.register_immutable::<i32>(),
+        Err(ecs_tiny::Error::TypeAlreadyRegistered)
+    );
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&42));
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 2);
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().count(),
+        2
+    );
+    assert_eq!(ecs.query::<(&i32,)>().unwrap().count(), 1);
+
+    let immutable = ecs_tiny::Error::ImmutableType;
+    assert_eq!(ecs.get_comp_mut::<i32>(comp_key0), Err(immutable.clone()));
+    assert_eq!(
+        ecs.update_comp::<i32, _>(comp_key0, |comp| *comp += 1),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.try_update_comp::<i32, ()>(comp_key0, |_| Ok(())),
+        Err(ecs_tiny::UpdateError::Access(immutable.clone()))
+    );
+    assert_eq!(
+        ecs.swap_comp::<i32>(comp_key0, comp_key1),
+        Err(immutable.clone())
+    );
+    assert_eq!(ecs.iter_comp_mut::<i32>().err(), Some(immutable.clone()));
+    assert_eq!(
+        ecs.iter_comp_mut_by_entity::<i32>(entity_key).err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(ecs.query::<(&mut i32,)>().err(), Some(immutable.clone()));
+
+    // every other path handing out the components mutably is blocked as well
+    ecs.register::<u32>().unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key, 1u32).unwrap();
+    let token = ecs.registered::<i32>().unwrap();
+    assert_eq!(
+        ecs.replace_comp::<i32>(comp_key0, 0),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.get_many_comp_mut::<i32, 2>([comp_key0, comp_key1]),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.get_comp_mut_pair::<i32, u32>(comp_key0, comp_key2),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.get_or_insert_comp::<i32>(entity_key),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.get_comp_mut_checked(token, comp_key0),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_checked(token).err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_with_key::<i32>().err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_with_entity::<i32>().err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_with_key_by_entity::<i32>(entity_key)
+            .err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_filtered::<i32, ecs_tiny::With<u32>>()
+            .err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_and::<i32, u32>().err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.iter_comp_pair_mut::<i32, u32>().err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.for_each_comp_mut_in::<i32>(&[entity_key], |_, _| {}),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.retain_comp_mut::<i32>(|_, _| true),
+        Err(immutable.clone())
+    );
+    assert_eq!(
+        ecs.map_entities_mut::<i32, _>(&[entity_key], |_, _| ()),
+        Err(immutable.clone())
+    );
+    assert_eq!(ecs.column_mut::<i32>().err(), Some(immutable.clone()));
+    assert_eq!(
+        ecs.columns_mut::<(i32, u32)>().err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(
+        ecs.take_comp::<i32>(comp_key0).err(),
+        Some(immutable.clone())
+    );
+    assert_eq!(ecs.mark_changed(comp_key0), Err(immutable.clone()));
+    let mut entity = ecs.entity_mut(entity_key).unwrap();
+    assert_eq!(entity.get_mut::<i32>(), Err(immutable.clone()));
+    assert_eq!(entity.iter_mut::<i32>().err(), Some(immutable.clone()));
+    assert_eq!(
+        ecs.query_single::<(&mut i32,)>(),
+        Err(ecs_tiny::SingleError::Query(immutable))
+    );
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&42));
+
+    assert_eq!(ecs.remove_comp::<i32>(comp_key0), Ok(42));
+    ecs.remove_entity(entity_key).unwrap();
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+}
+
+#[test]
+fn entity_status() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    ecs.remove_entity(entity_key0).unwrap();
+    let entity_key1 = ecs.insert_entity();
+
+    assert_eq!(entity_key0.index(), entity_key1.index());
+    assert_eq!(
+        ecs.entity_status(entity_key1),
+        ecs_tiny::EntityStatus::Alive
+    );
+    assert_eq!(
+        ecs.entity_status(entity_key0),
+        ecs_tiny::EntityStatus::Removed {
+            current_generation: 1
+        }
+    );
+    assert!(ecs.get_entity(entity_key0).is_err());
+    assert!(ecs.remove_entity(entity_key0).is_err());
+    assert!(ecs.get_entity(entity_key1).is_ok());
+
+    // keys handed out by another instance never existed in this one
+    let mut other = ecs_tiny::ECS::new();
+    let other_key0 = other.insert_entity();
+    let other_key1 = other.insert_entity();
+    other.remove_entity(other_key0).unwrap();
+    let other_key2 = other.insert_entity();
+    other.remove_entity(other_key2).unwrap();
+    let other_key2 = other.insert_entity();
+
+    assert_eq!(
+        ecs.entity_status(other_key1),
+        ecs_tiny::EntityStatus::NeverExisted
+    );
+    assert_eq!(other_key2.generation(), 2);
+    assert_eq!(
+        ecs.entity_status(other_key2),
+        ecs_tiny::EntityStatus::NeverExisted
+    );
+
+    ecs.register::<i32>().unwrap();
+    assert_eq!(
+        ecs.insert_comp(other_key1, 42),
+        Err(ecs_tiny::Error::WrongWorld)
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key0).err(),
+        Some(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+}
+
+#[test]
+fn remove_bundle() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<()>().unwrap();
+    ecs.insert_comp(entity_key, 42).unwrap();
+    ecs.insert_comp(entity_key, 63).unwrap();
+    ecs.insert_comp(entity_key, ()).unwrap();
+
+    assert_eq!(
+        ecs.remove_bundle::<(i32, u32, ())>(entity_key),
+        Ok((Some(42), None, Some(())))
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&63]
+    );
+    assert!(ecs.iter_comp::<()>().unwrap().next().is_none());
+    assert_eq!(
+        ecs.remove_bundle::<(i32, u32, ())>(entity_key),
+        Ok((Some(63), None, None))
+    );
+    assert_eq!(ecs.remove_bundle::<(String,)>(entity_key), Ok((None,)));
+
+    ecs.remove_entity(entity_key).unwrap();
+
+    assert_eq!(
+        ecs.remove_bundle::<(i32,)>(entity_key),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+}
+
+#[test]
+fn remove_bundle_strict() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<()>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key, ()).unwrap();
+
+    assert_eq!(
+        ecs.remove_bundle_strict::<(i32, (), u32)>(entity_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&42));
+    assert_eq!(ecs.get_comp::<()>(comp_key1), Ok(&()));
+    assert_eq!(ecs.get_entity_by_comp(comp_key0), Ok(entity_key));
+
+    ecs.insert_comp(entity_key, 63u32).unwrap();
+
+    assert_eq!(
+        ecs.remove_bundle_strict::<(i32, (), u32)>(entity_key),
+        Ok((42, (), 63))
+    );
+    assert!(ecs.iter_comp::<i32>().unwrap().next().is_none());
+    assert!(ecs.iter_comp::<u32>().unwrap().next().is_none());
+}
+
+#[test]
+#[should_panic]
+fn remove_bundle_with_duplicate_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+
+    ecs.remove_bundle::<(i32, i32)>(entity_key).ok();
+}
+
+#[test]
+fn clone_filtered() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register_cloneable::<String>().unwrap();
+    ecs.register::<()>().unwrap();
+
+    let mut entity_keys = vec![];
+    for i in 0..8 {
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, i).unwrap();
+        ecs.insert_comp(entity_key, i.to_string()).unwrap();
+        if i % 2 == 1 {
+            ecs.insert_comp(entity_key, ()).unwrap();
+        }
+        entity_keys.push(entity_key);
+    }
+    ecs.remove_entity(entity_keys[4]).unwrap();
+
+    let kept = [entity_keys[0], entity_keys[2], entity_keys[6]];
+    let clone = ecs
+        .clone_filtered(|entity_key| kept.contains(&entity_key))
+        .unwrap();
+
+    assert_eq!(clone.validate(), Ok(()));
+    assert_eq!(clone.iter_entity().collect::<Vec<_>>(), kept);
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        if kept.contains(entity_key) {
+            assert_eq!(
+                clone
+                    .iter_comp_by_entity::<i32>(*entity_key)
+                    .unwrap()
+                    .collect::<Vec<_>>(),
+                vec![&(i as i32)]
+            );
+        } else {
+            assert!(clone.get_entity(*entity_key).is_err());
+        }
+    }
+    assert_eq!(clone.iter_comp::<String>().unwrap().count(), 3);
+    assert_eq!(clone.iter_comp::<()>().unwrap().count(), 0);
+
+    // keys of dropped entities do not resolve to entities inserted into the clone
+    let mut clone = clone;
+    let entity_key = clone.insert_entity();
+    assert!(!entity_keys.contains(&entity_key));
+    assert_eq!(clone.validate(), Ok(()));
+
+    assert_eq!(ecs.validate(), Ok(()));
+    assert_eq!(ecs.iter_entity().count(), 7);
+}
+
+#[test]
+fn clone_filtered_with_uncloneable_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<()>().unwrap();
+    ecs.insert_comp(entity_key0, ()).unwrap();
+
+    assert!(ecs
+        .clone_filtered(|entity_key| entity_key == entity_key1)
+        .is_ok());
+    assert_eq!(
+        ecs.clone_filtered(|_| true).err(),
+        Some(ecs_tiny::CloneError::NotCloneable { type_name: "()" })
+    );
+}
+
+#[test]
+fn column() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let row_key0 = ecs.insert_comp(entity_key0, 42).unwrap().row_key();
+    let row_key1 = ecs.insert_comp(entity_key1, 63).unwrap().row_key();
+    let comp_key2 = ecs.insert_comp(entity_key1, 1).unwrap();
+    ecs.remove_comp::<i32>(comp_key2).unwrap();
+
+    let column = ecs.column::<i32>().unwrap();
+
+    assert_eq!(column.len(), 2);
+    assert_eq!(column.get(row_key1), Some(&63));
+    assert_eq!(column.get(comp_key2.row_key()), None);
+    assert_eq!(column.entity_key(row_key0), Some(entity_key0));
+    assert_eq!(
+        column.iter_rows().collect::<Vec<_>>(),
+        vec![(row_key0, entity_key0, &42), (row_key1, entity_key1, &63)]
+    );
+    assert!(ecs.column::<u32>().is_err());
+}
+
+#[test]
+fn column_mut() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register_immutable::<u32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key, 63).unwrap();
+
+    let mut column = ecs.column_mut::<i32>().unwrap();
+    *column.get_mut(comp_key0.row_key()).unwrap() += 1;
+    for (_, _, comp) in column.iter_rows_mut() {
+        *comp *= 2;
+    }
+
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&86));
+    assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&126));
+    assert_eq!(
+        ecs.column_mut::<u32>().err(),
+        Some(ecs_tiny::Error::ImmutableType)
+    );
+}
+
+#[test]
+fn rows_by_entity() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let row_key0 = ecs.insert_comp(entity_key0, 42).unwrap().row_key();
+    let row_key1 = ecs.insert_comp(entity_key0, 63).unwrap().row_key();
+
+    assert_eq!(
+        ecs.rows_by_entity::<i32>(entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![row_key0, row_key1]
+    );
+    assert_eq!(ecs.rows_by_entity::<i32>(entity_key1).unwrap().count(), 0);
+    assert_eq!(
+        ecs.rows_by_entity::<u32>(entity_key0).err(),
+        Some(ecs_tiny::Error::TypeNotRegistered)
+    );
+
+    ecs.remove_entity(entity_key1).unwrap();
+
+    assert!(matches!(
+        ecs.rows_by_entity::<i32>(entity_key1),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+}
+
+#[test]
+fn set_comp_field() {
+    struct Name(String);
+
+    #[derive(Debug, PartialEq)]
+    struct Health {
+        current: i32,
+        max: i32,
+        label: String,
+    }
+
+    ecs_tiny::impl_reflect!(Health {
+        current,
+        max,
+        label
+    });
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<Name>().unwrap();
+    ecs.register::<Health>().unwrap();
+    ecs.set_reflect::<Health>().unwrap();
+    for name in ["goblin", "orc"] {
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, Name(name.to_string())).unwrap();
+        let health = Health {
+            current: 10,
+            max: 100,
+            label: "hp".to_string(),
+        };
+        ecs.insert_comp(entity_key, health).unwrap();
+    }
+
+    // `set orc Health.current 50`
+    let (_, entity_key, _) = ecs
+        .column::<Name>()
+        .unwrap()
+        .iter_rows()
+        .find(|(_, _, name)| name.0 == "orc")
+        .unwrap();
+    let row_key = ecs
+        .rows_by_entity::<Health>(entity_key)
+        .unwrap()
+        .next()
+        .unwrap();
+    let comp_key = ecs.column::<Health>().unwrap().comp_key(row_key);
+
+    ecs.set_comp_field(comp_key, "current", ecs_tiny::FieldValue::I32(50))
+        .unwrap();
+
+    assert_eq!(
+        ecs.get_comp_field(comp_key, "current"),
+        Ok(ecs_tiny::FieldValue::I32(50))
+    );
+    assert_eq!(
+        ecs.get_comp::<Health>(comp_key),
+        Ok(&Health {
+            current: 50,
+            max: 100,
+            label: "hp".to_string()
+        })
+    );
+    assert_eq!(
+        ecs.iter_comp::<Health>()
+            .unwrap()
+            .map(|health| health.current)
+            .collect::<Vec<_>>(),
+        vec![10, 50]
+    );
+
+    ecs.set_comp_field(
+        comp_key,
+        "label",
+        ecs_tiny::FieldValue::String("health".to_string()),
+    )
+    .unwrap();
+
+    assert_eq!(ecs.get_comp::<Health>(comp_key).unwrap().label, "health");
+}
+
+#[test]
+fn set_comp_field_with_invalid_field() {
+    struct Health {
+        current: i32,
+    }
+
+    ecs_tiny::impl_reflect!(Health { current });
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<Health>().unwrap();
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, Health { current: 10 }).unwrap();
+    let int_key = ecs.insert_comp(entity_key, 42).unwrap();
+
+    assert!(matches!(
+        ecs.get_comp_field(comp_key, "current"),
+        Err(ecs_tiny::FieldError::NotReflected { .. })
+    ));
+
+    ecs.set_reflect::<Health>().unwrap();
+
+    assert!(matches!(
+        ecs.set_comp_field(comp_key, "maximum", ecs_tiny::FieldValue::I32(50)),
+        Err(ecs_tiny::FieldError::UnknownField { field, .. }) if field == "maximum"
+    ));
+    assert_eq!(
+        ecs.set_comp_field(comp_key, "current", ecs_tiny::FieldValue::F32(50.0)),
+        Err(ecs_tiny::FieldError::TypeMismatch {
+            field: "current".to_string(),
+            expected: "i32",
+            found: "f32",
+        })
+    );
+    assert_eq!(
+        ecs.get_comp_field(int_key, "current"),
+        Err(ecs_tiny::FieldError::NotReflected { type_name: "i32" })
+    );
+    assert_eq!(ecs.get_comp::<Health>(comp_key).unwrap().current, 10);
+
+    ecs.remove_comp::<Health>(comp_key).unwrap();
+
+    assert_eq!(
+        ecs.get_comp_field(comp_key, "current"),
+        Err(ecs_tiny::FieldError::Access(ecs_tiny::Error::CompNotFound))
+    );
+}
+
+#[test]
+fn replay() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_journal();
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register_cloneable::<String>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 63).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, "name".to_string()).unwrap();
+    ecs.insert_comp(entity_key2, 1).unwrap();
+    ecs.remove_entity(entity_key2).unwrap();
+    ecs.remove_comp::<i32>(comp_key0).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key0, 7).unwrap();
+    ecs.update_comp::<i32, _>(comp_key1, |comp| *comp += 1)
+        .unwrap();
+    ecs.swap_comp::<i32>(comp_key1, comp_key3).unwrap();
+    let entity_key3 = ecs.insert_entity();
+
+    let mut ops = ecs.take_journal();
+    let replayed = ecs_tiny::ECS::replay(&ops).unwrap();
+
+    assert_eq!(
+        replayed.iter_entity().collect::<Vec<_>>(),
+        ecs.iter_entity().collect::<Vec<_>>()
+    );
+    assert_eq!(replayed.iter_entity().last(), Some(entity_key3));
+    assert_eq!(replayed.get_comp::<i32>(comp_key1), Ok(&7));
+    assert_eq!(replayed.get_comp::<i32>(comp_key3), Ok(&64));
+    assert_eq!(
+        replayed.get_comp::<String>(comp_key2),
+        Ok(&"name".to_string())
+    );
+    assert_eq!(
+        replayed.collect_comp_cloned::<i32>(),
+        ecs.collect_comp_cloned::<i32>()
+    );
+    assert_eq!(replayed.validate(), Ok(()));
+
+    ecs.clear();
+    ecs.register::<u32>().unwrap();
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 1u32).unwrap();
+    ops.extend(ecs.take_journal());
+
+    assert_eq!(
+        ecs_tiny::ECS::replay(&ops).err(),
+        Some(ecs_tiny::ReplayError::MissingValue {
+            index: ops.len() - 1,
+            type_name: "u32"
+        })
+    );
+    assert_eq!(
+        ecs_tiny::ECS::replay(&ops[1..]).err(),
+        Some(ecs_tiny::ReplayError::Diverged { index: 4 })
+    );
+}
+
+#[test]
+fn undo() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_undo(100);
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register_cloneable::<String>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let other_key = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key1, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 63).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, "name".to_string()).unwrap();
+
+    ecs.remove_entity(entity_key1).unwrap();
+    let entity_key2 = ecs.insert_entity();
+    ecs.insert_comp(entity_key2, 7).unwrap();
+
+    assert_eq!(ecs.undo(), Ok("insert_comp".to_string()));
+    assert_eq!(ecs.undo(), Ok("insert_entity".to_string()));
+    assert_eq!(ecs.undo(), Ok("remove_entity".to_string()));
+
+    assert_eq!(
+        ecs.iter_entity().collect::<Vec<_>>(),
+        vec![entity_key0, entity_key1]
+    );
+    assert_eq!(ecs.get_comp::<i32>(other_key), Ok(&1));
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&42));
+    assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&63));
+    assert_eq!(ecs.get_comp::<String>(comp_key2), Ok(&"name".to_string()));
+    assert_eq!(ecs.get_entity_by_comp(comp_key2), Ok(entity_key1));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key1)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&42, &63]
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+
+    assert_eq!(ecs.redo(), Ok("remove_entity".to_string()));
+    assert!(ecs.get_entity(entity_key1).is_err());
+    assert_eq!(ecs.undo(), Ok("remove_entity".to_string()));
+
+    ecs.begin_undo_group("edit").unwrap();
+    ecs.update_comp::<i32, _>(comp_key0, |comp| *comp += 1)
+        .unwrap();
+    ecs.remove_comp::<String>(comp_key2).unwrap();
+    ecs.end_undo_group().unwrap();
+
+    assert_eq!(ecs.redo(), Err(ecs_tiny::Error::NothingToRedo));
+    assert_eq!(ecs.undo(), Ok("edit".to_string()));
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&42));
+    assert_eq!(ecs.get_comp::<String>(comp_key2), Ok(&"name".to_string()));
+    assert_eq!(ecs.redo(), Ok("edit".to_string()));
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&43));
+    assert!(ecs.get_comp::<String>(comp_key2).is_err());
+    assert_eq!(ecs.validate(), Ok(()));
+
+    ecs.clear();
+
+    assert_eq!(ecs.undo(), Err(ecs_tiny::Error::NothingToUndo));
+}
+
+#[test]
+fn clear_partition() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let partition0 = ecs.create_partition("match_0");
+    let partition1 = ecs.create_partition("match_1");
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let mut entity_keys0 = vec![];
+    let mut entity_keys1 = vec![];
+    for i in 0..4 {
+        let entity_key0 = ecs.insert_entity_in(partition0).unwrap();
+        ecs.insert_comp(entity_key0, i).unwrap();
+        ecs.insert_comp(entity_key0, i as u32).unwrap();
+        entity_keys0.push(entity_key0);
+
+        let entity_key1 = ecs.insert_entity_in(partition1).unwrap();
+        ecs.insert_comp(entity_key1, -i).unwrap();
+        entity_keys1.push(entity_key1);
+    }
+    let entity_key = ecs.insert_entity();
+
+    ecs.clear_partition(partition0).unwrap();
+
+    assert_eq!(ecs.iter_entity_in(partition0).count(), 0);
+    assert_eq!(
+        ecs.iter_entity_in(partition1).collect::<Vec<_>>(),
+        entity_keys1
+    );
+    assert_eq!(
+        ecs.iter_entity_in(ecs_tiny::PartitionId::DEFAULT)
+            .collect::<Vec<_>>(),
+        vec![entity_key]
+    );
+    assert!(entity_keys0
+        .iter()
+        .all(|entity_key| ecs.get_entity(*entity_key).is_err()));
+    assert_eq!(
+        ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(),
+        vec![&0, &-1, &-2, &-3]
+    );
+    assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 0);
+    assert_eq!(ecs.validate(), Ok(()));
+
+    assert_eq!(ecs.partition_name(partition0), Ok("match_0"));
+
+    let mut other = ecs_tiny::ECS::new();
+    other.create_partition("match_0");
+    other.create_partition("match_1");
+    let unknown = other.create_partition("match_2");
+
+    assert_eq!(
+        ecs.insert_entity_in(unknown),
+        Err(ecs_tiny::Error::PartitionNotFound)
+    );
+    assert_eq!(
+        ecs.clear_partition(unknown),
+        Err(ecs_tiny::Error::PartitionNotFound)
+    );
+}
+
+#[test]
+fn wrong_world() {
+    struct Health {
+        current: i32,
+    }
+
+    ecs_tiny::impl_reflect!(Health { current });
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let mut other = ecs_tiny::ECS::new();
+    for ecs in [&mut ecs, &mut other] {
+        ecs.register::<i32>().unwrap();
+        ecs.register::<Health>().unwrap();
+        ecs.set_reflect::<Health>().unwrap();
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, 42).unwrap();
+        ecs.insert_comp(entity_key, 63).unwrap();
+        ecs.insert_comp(entity_key, Health { current: 10 }).unwrap();
+    }
+
+    let entity_key = other.iter_entity().next().unwrap();
+    let comp_keys = other
+        .collect_comp_cloned::<i32>()
+        .unwrap()
+        .into_iter()
+        .map(|(_, comp_key, _)| comp_key)
+        .collect::<Vec<_>>();
+    let health_key = other.column::<Health>().unwrap().comp_key(
+        other
+            .rows_by_entity::<Health>(entity_key)
+            .unwrap()
+            .next()
+            .unwrap(),
+    );
+
+    // the keys have the same slots in both worlds, but belong to the other one
+    assert_eq!(
+        ecs.iter_entity().next().map(|key| key.index()),
+        Some(entity_key.index())
+    );
+
+    // entities
+    assert!(ecs.get_entity(entity_key).is_err());
+    assert_eq!(
+        ecs.entity_status(entity_key),
+        ecs_tiny::EntityStatus::NeverExisted
+    );
+    assert_eq!(
+        ecs.entity_partition(entity_key),
+        Err(ecs_tiny::Error::WrongWorld)
+    );
+    assert!(ecs.remove_entity(entity_key).is_err());
+
+    // components
+    let wrong_world = Some(ecs_tiny::Error::WrongWorld);
+    assert_eq!(ecs.insert_comp(entity_key, 1).err(), wrong_world);
+    assert_eq!(ecs.get_comp::<i32>(comp_keys[0]).err(), wrong_world);
+    assert_eq!(ecs.get_comp_mut::<i32>(comp_keys[0]).err(), wrong_world);
+    assert_eq!(
+        ecs.update_comp::<i32, _>(comp_keys[0], |_| ()).err(),
+        wrong_world
+    );
+    assert_eq!(
+        ecs.swap_comp::<i32>(comp_keys[0], comp_keys[1]).err(),
+        wrong_world
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_keys[0]).err(), wrong_world);
+    assert_eq!(ecs.remove_comp::<i32>(comp_keys[0]).err(), wrong_world);
+
+    // fields
+    assert_eq!(
+        ecs.get_comp_field(health_key, "current"),
+        Err(ecs_tiny::FieldError::Access(ecs_tiny::Error::WrongWorld))
+    );
+    assert_eq!(
+        ecs.set_comp_field(health_key, "current", ecs_tiny::FieldValue::I32(0)),
+        Err(ecs_tiny::FieldError::Access(ecs_tiny::Error::WrongWorld))
+    );
+
+    // lookups by entity
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key).err(),
+        Some(ecs_tiny::Error::WrongWorld)
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_by_entity::<i32>(entity_key).err(),
+        Some(ecs_tiny::Error::WrongWorld)
+    );
+    assert_eq!(
+        ecs.rows_by_entity::<i32>(entity_key).err(),
+        Some(ecs_tiny::Error::WrongWorld)
+    );
+    assert!(ecs.remove_bundle::<(i32,)>(entity_key).is_err());
+
+    // nothing was touched in either world
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 2);
+    assert_eq!(other.iter_comp::<i32>().unwrap().count(), 2);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn shard() {
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<Position>().unwrap();
+    ecs.register::<Velocity>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register_immutable::<u64>().unwrap();
+
+    let mut entity_keys = vec![];
+    for i in 0..100 {
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, Position(i)).unwrap();
+        ecs.insert_comp(entity_key, Velocity(1)).unwrap();
+        ecs.insert_comp(entity_key, i as u32).unwrap();
+        entity_keys.push(entity_key);
+    }
+
+    {
+        let (mut pos, mut vel, rest) = ecs.shard::<Position, Velocity>().unwrap();
+
+        // both shards are mutated on their own threads while the rest is read here
+        std::thread::scope(|scope| {
+            scope.spawn(|| pos.iter_rows_mut().for_each(|(_, _, comp)| comp.0 += 1));
+            scope.spawn(|| vel.iter_rows_mut().for_each(|(_, _, comp)| comp.0 *= 2));
+
+            assert_eq!(rest.column::<u32>().unwrap().len(), 100);
+        });
+
+        assert_eq!(
+            rest.column::<Position>().err(),
+            Some(ecs_tiny::Error::TypeNotRegistered)
+        );
+        let row_key = rest.rows_by_entity::<Position>(entity_keys[0]).next();
+        assert_eq!(pos.get(row_key.unwrap()), Some(&Position(1)));
+    }
+
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        let mut pos = ecs.iter_comp_by_entity::<Position>(*entity_key).unwrap();
+        let mut vel = ecs.iter_comp_by_entity::<Velocity>(*entity_key).unwrap();
+        assert_eq!(pos.next(), Some(&Position(i as i32 + 1)));
+        assert_eq!(vel.next(), Some(&Velocity(2)));
+    }
+
+    assert_eq!(
+        ecs.shard::<Position, f32>().err(),
+        Some(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.shard::<Position, u64>().err(),
+        Some(ecs_tiny::Error::ImmutableType)
+    );
+}
+
+#[test]
+#[should_panic(expected = "each component type can appear only once")]
+fn shard_same_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let _ = ecs.shard::<i32, i32>();
+}
+
+#[test]
+fn ordered_comp() {
+    #[derive(Debug, PartialEq)]
+    struct Widget(&'static str);
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_undo(100);
+    let entity_key = ecs.insert_entity();
+    ecs.register_ordered::<Widget>().unwrap();
+    ecs.register_cloneable::<i32>().unwrap();
+
+    let widgets = |ecs: &ecs_tiny::ECS| {
+        ecs.iter_comp_by_entity::<Widget>(entity_key)
+            .unwrap()
+            .map(|widget| widget.0)
+            .collect::<Vec<_>>()
+    };
+
+    let a = ecs.insert_comp(entity_key, Widget("a")).unwrap();
+    let b = ecs.insert_comp(entity_key, Widget("b")).unwrap();
+    let c = ecs.insert_comp_at(entity_key, 0, Widget("c")).unwrap();
+    assert_eq!(widgets(&ecs), vec!["c", "a", "b"]);
+
+    // the slot of the removed component is recycled, but the order is kept
+    ecs.remove_comp::<Widget>(a).unwrap();
+    let d = ecs.insert_comp_at(entity_key, 1, Widget("d")).unwrap();
+    ecs.insert_comp_at(entity_key, 100, Widget("e")).unwrap();
+    assert_eq!(widgets(&ecs), vec!["c", "d", "b", "e"]);
+
+    ecs.reorder_comp::<Widget>(entity_key, c, 2).unwrap();
+    ecs.reorder_comp::<Widget>(entity_key, b, 0).unwrap();
+    assert_eq!(widgets(&ecs), vec!["b", "d", "c", "e"]);
+
+    ecs.remove_comp::<Widget>(d).unwrap();
+    ecs.insert_comp(entity_key, Widget("f")).unwrap();
+    assert_eq!(widgets(&ecs), vec!["b", "c", "e", "f"]);
+
+    let rows = ecs.rows_by_entity::<Widget>(entity_key).unwrap();
+    let column = ecs.column::<Widget>().unwrap();
+    let names = rows.map(|row_key| column.get(row_key).unwrap().0);
+    assert_eq!(names.collect::<Vec<_>>(), vec!["b", "c", "e", "f"]);
+
+    // undo restores the removed components at their positions
+    let comp_keys = (0..4)
+        .map(|i| ecs.insert_comp(entity_key, i).unwrap())
+        .collect::<Vec<_>>();
+    ecs.remove_comp::<i32>(comp_keys[1]).unwrap();
+    ecs.undo().unwrap();
+    let comps = ecs.iter_comp_by_entity::<i32>(entity_key).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&0, &1, &2, &3]);
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // unordered types cannot be placed explicitly
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    assert_eq!(
+        ecs.insert_comp_at(entity_key, 0, 63),
+        Err(ecs_tiny::Error::TypeNotOrdered)
+    );
+    assert_eq!(
+        ecs.reorder_comp::<i32>(entity_key, comp_key, 0),
+        Err(ecs_tiny::Error::TypeNotOrdered)
+    );
+    assert_eq!(
+        ecs.reorder_comp::<Widget>(entity_key, comp_key, 0),
+        Err(ecs_tiny::Error::TypeMismatch)
+    );
+
+    // components of other entities cannot be moved
+    let other = ecs.insert_entity();
+    assert_eq!(
+        ecs.reorder_comp::<Widget>(other, b, 0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+}
+
+#[test]
+fn comp_histogram() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    assert_eq!(ecs.comp_histogram().to_string(), "no entities");
+
+    let mut entity_keys = vec![];
+    for comp_count in [0, 0, 1, 2, 3, 5, 9] {
+        let entity_key = ecs.insert_entity();
+        for i in 0..comp_count {
+            ecs.insert_comp(entity_key, i).unwrap();
+        }
+        entity_keys.push(entity_key);
+    }
+    ecs.insert_comp(entity_keys[1], 42u32).unwrap();
+    let removed = ecs.insert_entity();
+    ecs.remove_entity(removed).unwrap();
+
+    let histogram = ecs.comp_histogram();
+    assert_eq!(histogram.buckets(), &[1, 2, 2, 1, 1]);
+    assert_eq!(histogram.entity_count(), 7);
+    assert_eq!(ecs_tiny::Histogram::bucket_range(3), 4..=7);
+    assert_eq!(
+        histogram.to_string(),
+        [
+            "     0 components: 1 entities",
+            "     1 components: 2 entities",
+            " 2..=3 components: 2 entities",
+            " 4..=7 components: 1 entities",
+            "8..=15 components: 1 entities",
+        ]
+        .join("\n")
+    );
+
+    assert_eq!(
+        ecs.top_entities_by_comp_count(3),
+        vec![
+            (entity_keys[6], 9),
+            (entity_keys[5], 5),
+            (entity_keys[4], 3)
+        ]
+    );
+    // ties are broken by the entity keys
+    assert_eq!(
+        ecs.top_entities_by_comp_count(100)[4..],
+        [
+            (entity_keys[1], 1),
+            (entity_keys[2], 1),
+            (entity_keys[0], 0)
+        ]
+    );
+    assert!(ecs.top_entities_by_comp_count(0).is_empty());
+}
+
+#[test]
+fn mirror_replication() {
+    enum Command {
+        InsertEntity,
+        RemoveEntity(usize),
+        InsertComp(usize, i32),
+        RemoveComp(usize),
+    }
+
+    let commands = [
+        Command::InsertEntity,
+        Command::InsertEntity,
+        Command::InsertEntity,
+        Command::InsertComp(0, 1),
+        Command::InsertComp(1, 2),
+        Command::InsertComp(1, 3),
+        Command::InsertComp(2, 4),
+        Command::RemoveComp(1),
+        Command::RemoveEntity(0),
+        Command::InsertEntity,
+        Command::InsertComp(3, 5),
+        Command::InsertComp(2, 6),
+        Command::RemoveComp(3),
+        Command::InsertComp(3, 7),
+    ];
+
+    let mut server = ecs_tiny::ECS::new();
+    let mut client = server.new_mirror();
+    server.register::<i32>().unwrap();
+    client.register::<i32>().unwrap();
+
+    // the server allocates the keys, and the client places everything at them
+    let mut entity_keys = vec![];
+    let mut comp_keys = vec![];
+    for command in &commands {
+        match command {
+            Command::InsertEntity => {
+                let entity_key = server.insert_entity();
+                client.insert_entity_at(entity_key).unwrap();
+                entity_keys.push(entity_key);
+            }
+            Command::RemoveEntity(index) => {
+                server.remove_entity(entity_keys[*index]).unwrap();
+                client.remove_entity(entity_keys[*index]).unwrap();
+            }
+            Command::InsertComp(index, comp) => {
+                let entity_key = entity_keys[*index];
+                let comp_key = server.insert_comp(entity_key, *comp).unwrap();
+                let mirrored = client.insert_comp_at_row(entity_key, comp_key.row_key(), *comp);
+                assert_eq!(mirrored, Ok(comp_key));
+                comp_keys.push(comp_key);
+            }
+            Command::RemoveComp(index) => {
+                let comp = server.remove_comp::<i32>(comp_keys[*index]);
+                assert_eq!(client.remove_comp::<i32>(comp_keys[*index]), comp);
+            }
+        }
+    }
+
+    let entities = |ecs: &ecs_tiny::ECS| {
+        let mut entity_keys = ecs.iter_entity().collect::<Vec<_>>();
+        entity_keys.sort_unstable();
+        entity_keys
+    };
+    assert_eq!(entities(&client), entities(&server));
+    for comp_key in &comp_keys {
+        assert_eq!(
+            client.get_comp::<i32>(*comp_key),
+            server.get_comp::<i32>(*comp_key)
+        );
+        assert_eq!(
+            client.get_entity_by_comp(*comp_key),
+            server.get_entity_by_comp(*comp_key)
+        );
+    }
+    assert_eq!(client.validate(), Ok(()));
+
+    // occupied slots and keys of other worlds are rejected
+    assert_eq!(
+        client.insert_entity_at(entity_keys[1]),
+        Err(ecs_tiny::Error::SlotOccupied)
+    );
+    assert_eq!(
+        client.insert_comp_at_row(entity_keys[1], comp_keys[2].row_key(), 0),
+        Err(ecs_tiny::Error::SlotOccupied)
+    );
+    let other = ecs_tiny::ECS::new().insert_entity();
+    assert_eq!(
+        client.insert_entity_at(other),
+        Err(ecs_tiny::Error::WrongWorld)
+    );
+}
+
+#[test]
+fn for_each_comp_mut_in() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    let entity_key3 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register_immutable::<u32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 3).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key3, 4).unwrap();
+    ecs.remove_entity(entity_key3).unwrap();
+
+    // duplicates are visited again, and entities without the type or dead are skipped
+    let mut visited = vec![];
+    let skipped = ecs
+        .for_each_comp_mut_in::<i32>(
+            &[
+                entity_key1,
+                entity_key0,
+                entity_key2,
+                entity_key3,
+                entity_key1,
+            ],
+            |entity_key, comp| {
+                visited.push((entity_key, *comp));
+                *comp *= 10;
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        visited,
+        vec![
+            (entity_key1, 3),
+            (entity_key0, 1),
+            (entity_key0, 2),
+            (entity_key1, 30)
+        ]
+    );
+    assert_eq!(skipped, vec![entity_key2, entity_key3]);
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&10));
+    assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&20));
+    assert_eq!(ecs.get_comp::<i32>(comp_key2), Ok(&300));
+    assert!(ecs.get_comp::<i32>(comp_key3).is_err());
+
+    assert_eq!(
+        ecs.for_each_comp_mut_in::<u32>(&[entity_key0], |_, _| ()),
+        Err(ecs_tiny::Error::ImmutableType)
+    );
+    assert_eq!(
+        ecs.for_each_comp_mut_in::<f32>(&[entity_key0], |_, _| ()),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+}
+
+#[test]
+fn register_chunked() {
+    #[derive(Debug, PartialEq)]
+    struct Pathfinding([u32; 512]);
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register_chunked::<Pathfinding>(4).unwrap();
+    assert_eq!(
+        ecs.register_chunked::<Pathfinding>(4),
+        Err(ecs_tiny::Error::TypeAlreadyRegistered)
+    );
+
+    let comp_keys = (0..10)
+        .map(|i| {
+            let entity_key = [entity_key0, entity_key1][i % 2];
+            ecs.insert_comp(entity_key, Pathfinding([i as u32; 512]))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let row_keys = comp_keys.iter().map(|comp_key| comp_key.row_key());
+    assert_eq!(row_keys.collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+
+    // growing the column by many chunks moves no row
+    let addresses = comp_keys
+        .iter()
+        .map(|comp_key| ecs.get_comp::<Pathfinding>(*comp_key).unwrap() as *const _)
+        .collect::<Vec<_>>();
+    for _ in 0..1000 {
+        ecs.insert_comp(entity_key0, Pathfinding([0; 512])).unwrap();
+    }
+    for (comp_key, address) in comp_keys.iter().zip(&addresses) {
+        let comp = ecs.get_comp::<Pathfinding>(*comp_key).unwrap();
+        assert_eq!(comp as *const _, *address);
+    }
+
+    // vacant rows are reused, and the column iterates in row order
+    ecs.remove_comp::<Pathfinding>(comp_keys[3]).unwrap();
+    ecs.remove_comp::<Pathfinding>(comp_keys[6]).unwrap();
+    let comp_key = ecs
+        .insert_comp(entity_key1, Pathfinding([42; 512]))
+        .unwrap();
+    assert_eq!(comp_key.row_key(), comp_keys[6].row_key());
+    let comps = ecs.iter_comp::<Pathfinding>().unwrap().take(7);
+    let firsts = comps.map(|comp| comp.0[0]).collect::<Vec<_>>();
+    assert_eq!(firsts, vec![0, 1, 2, 4, 5, 42, 7]);
+
+    let (comp0, comp1) = (comp_keys[0], comp_keys[9]);
+    ecs.swap_comp::<Pathfinding>(comp0, comp1).unwrap();
+    assert_eq!(ecs.get_comp::<Pathfinding>(comp0).unwrap().0[0], 9);
+    assert_eq!(ecs.get_comp::<Pathfinding>(comp1).unwrap().0[0], 0);
+
+    // removing an entity and compacting keep the chunked storage consistent
+    ecs.remove_entity(entity_key0).unwrap();
+    while let ecs_tiny::CompactProgress::Pending { .. } =
+        ecs.compact_step::<Pathfinding>(2).unwrap()
+    {}
+    let remaps = ecs.take_remaps::<Pathfinding>().unwrap();
+    let comp_key = remaps.get(&comp_key).copied().unwrap_or(comp_key);
+    assert_eq!(ecs.get_comp::<Pathfinding>(comp_key).unwrap().0[0], 42);
+    assert_eq!(ecs.iter_comp::<Pathfinding>().unwrap().count(), 5);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn take_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<Vec<i32>>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, vec![1]).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, vec![2]).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, vec![3]).unwrap();
+
+    let mut taken = ecs.take_comp::<Vec<i32>>(comp_key1).unwrap();
+    assert_eq!(taken.comp_key(), comp_key1);
+
+    // the taken component is skipped, but keeps its key and owner
+    let comps = ecs.iter_comp::<Vec<i32>>().unwrap().collect::<Vec<_>>();
+    assert_eq!(comps, vec![&vec![1], &vec![3]]);
+    let comps = ecs.iter_comp_by_entity::<Vec<i32>>(entity_key0).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&vec![1]]);
+    assert_eq!(
+        ecs.get_comp::<Vec<i32>>(comp_key1),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    assert_eq!(
+        ecs.get_comp_mut::<Vec<i32>>(comp_key1),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    assert_eq!(
+        ecs.remove_comp::<Vec<i32>>(comp_key1),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    assert_eq!(
+        ecs.take_comp::<Vec<i32>>(comp_key1).map(|_| ()),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_key1), Ok(entity_key0));
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // the reserved row is not handed out again
+    let comp_key3 = ecs.insert_comp(entity_key1, vec![4]).unwrap();
+    assert_ne!(comp_key3, comp_key1);
+
+    taken.push(5);
+    ecs.put_back(taken).unwrap();
+    let comps = ecs.iter_comp::<Vec<i32>>().unwrap().collect::<Vec<_>>();
+    assert_eq!(comps, vec![&vec![1], &vec![2, 5], &vec![3], &vec![4]]);
+    let comps = ecs.iter_comp_by_entity::<Vec<i32>>(entity_key0).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&vec![1], &vec![2, 5]]);
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // dropping the handle removes the component for good
+    let taken = ecs.take_comp::<Vec<i32>>(comp_key0).unwrap();
+    drop(taken);
+    assert_eq!(
+        ecs.get_comp::<Vec<i32>>(comp_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.get_entity_by_comp(comp_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    let row_keys = ecs.rows_by_entity::<Vec<i32>>(entity_key0).unwrap();
+    assert_eq!(row_keys.collect::<Vec<_>>(), vec![comp_key1.row_key()]);
+    let comp_key4 = ecs.insert_comp(entity_key1, vec![6]).unwrap();
+    assert_eq!(comp_key4.row_key(), comp_key0.row_key());
+    assert_ne!(comp_key4, comp_key0);
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // discarding returns the component, and removing the owner invalidates the handle
+    let taken = ecs.take_comp::<Vec<i32>>(comp_key2).unwrap();
+    assert_eq!(taken.discard(&mut ecs), vec![3]);
+    assert_eq!(
+        ecs.get_comp::<Vec<i32>>(comp_key2),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+
+    let taken = ecs.take_comp::<Vec<i32>>(comp_key1).unwrap();
+    ecs.remove_entity(entity_key0).unwrap();
+    assert_eq!(ecs.put_back(taken), Err(ecs_tiny::Error::CompNotFound));
+    assert_eq!(ecs.iter_comp::<Vec<i32>>().unwrap().count(), 2);
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // chunked columns reserve the rows in the same way
+    ecs.register_chunked::<i64>(2).unwrap();
+    let comp_keys = (0..3)
+        .map(|i| ecs.insert_comp(entity_key1, i as i64).unwrap())
+        .collect::<Vec<_>>();
+    let taken = ecs.take_comp::<i64>(comp_keys[1]).unwrap();
+    assert_eq!(ecs.insert_comp(entity_key1, 3i64).unwrap().row_key(), 3);
+    ecs.put_back(taken).unwrap();
+    drop(ecs.take_comp::<i64>(comp_keys[2]).unwrap());
+    let comp_key5 = ecs.insert_comp(entity_key1, 4i64).unwrap();
+    assert_eq!(comp_key5.row_key(), comp_keys[2].row_key());
+    let comps = ecs.iter_comp::<i64>().unwrap().collect::<Vec<_>>();
+    assert_eq!(comps, vec![&0, &1, &4, &3]);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn world_unique() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register_world_unique::<u64>().unwrap();
+    ecs.register::<i32>().unwrap();
+    assert_eq!(
+        ecs.world_unique::<u64>(),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.world_unique::<i32>(),
+        Err(ecs_tiny::Error::TypeNotWorldUnique)
+    );
+
+    // a second component is rejected on the holder and on any other entity
+    let comp_key0 = ecs.insert_comp(entity_key0, 1u64).unwrap();
+    assert_eq!(
+        ecs.insert_comp(entity_key0, 2u64),
+        Err(ecs_tiny::Error::AlreadyExists {
+            holder: entity_key0
+        })
+    );
+    assert_eq!(
+        ecs.insert_comp(entity_key1, 2u64),
+        Err(ecs_tiny::Error::AlreadyExists {
+            holder: entity_key0
+        })
+    );
+    assert_eq!(ecs.world_unique::<u64>(), Ok((entity_key0, comp_key0, &1)));
+
+    // removing the component frees the slot
+    ecs.remove_comp::<u64>(comp_key0).unwrap();
+    assert_eq!(
+        ecs.world_unique::<u64>(),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    let comp_key1 = ecs.insert_comp(entity_key1, 2u64).unwrap();
+    assert_eq!(ecs.world_unique::<u64>(), Ok((entity_key1, comp_key1, &2)));
+
+    // removing the holder removes its components along with it
+    ecs.remove_entity(entity_key1).unwrap();
+    assert_eq!(
+        ecs.world_unique::<u64>(),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    let comp_key2 = ecs.insert_comp(entity_key0, 3u64).unwrap();
+    assert_eq!(ecs.world_unique::<u64>(), Ok((entity_key0, comp_key2, &3)));
+
+    // a taken component still holds the slot
+    let taken = ecs.take_comp::<u64>(comp_key2).unwrap();
+    assert_eq!(
+        ecs.world_unique::<u64>(),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    assert_eq!(
+        ecs.insert_comp(entity_key0, 4u64),
+        Err(ecs_tiny::Error::AlreadyExists {
+            holder: entity_key0
+        })
+    );
+    drop(taken);
+    let comp_key3 = ecs.insert_comp(entity_key0, 4u64).unwrap();
+    assert_eq!(ecs.world_unique::<u64>(), Ok((entity_key0, comp_key3, &4)));
+
+    // the cached slot follows the component when compaction relocates it
+    ecs.remove_comp::<u64>(comp_key3).unwrap();
+    let comp_key4 = ecs.insert_comp_at_row(entity_key0, 3, 5u64).unwrap();
+    ecs.compact_step::<u64>(16).unwrap();
+    let remaps = ecs.take_remaps::<u64>().unwrap();
+    assert_eq!(
+        ecs.world_unique::<u64>(),
+        Ok((entity_key0, remaps[&comp_key4], &5))
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn stable_id() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register_cloneable::<i32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    assert_eq!(
+        ecs.iter_comp_with_stable_id::<i32>().err(),
+        Some(ecs_tiny::Error::StableIdsNotEnabled)
+    );
+    assert_eq!(
+        ecs.stable_id(comp_key0),
+        Err(ecs_tiny::Error::StableIdsNotEnabled)
+    );
+
+    // existing components get their ids on enabling, and later ones the next ids
+    ecs.enable_stable_ids::<i32>().unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 3).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key1, 4).unwrap();
+    assert_eq!(ecs.stable_id(comp_key0), Ok(0));
+    assert_eq!(ecs.stable_id(comp_key3), Ok(3));
+
+    // removed ids resolve no more and are never handed out again
+    ecs.remove_comp::<i32>(comp_key0).unwrap();
+    ecs.remove_comp::<i32>(comp_key1).unwrap();
+    assert_eq!(
+        ecs.comp_by_stable_id::<i32>(0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.comp_by_stable_id::<i32>(2), Ok((comp_key2, &3)));
+
+    // the ids follow the components relocated by compaction
+    ecs.compact_step::<i32>(16).unwrap();
+    let remaps = ecs.take_remaps::<i32>().unwrap();
+    assert_eq!(remaps.len(), 2);
+    let comp_key2 = remaps[&comp_key2];
+    let comp_key3 = remaps[&comp_key3];
+    assert_eq!(ecs.stable_id(comp_key2), Ok(2));
+    assert_eq!(ecs.comp_by_stable_id::<i32>(3), Ok((comp_key3, &4)));
+    let comps = ecs
+        .iter_comp_with_stable_id::<i32>()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(comps, vec![(3, &4), (2, &3)]);
+
+    // taken components keep their ids, and dropped ones release them
+    let comp_key4 = ecs.insert_comp(entity_key0, 5).unwrap();
+    assert_eq!(ecs.stable_id(comp_key4), Ok(4));
+    let taken = ecs.take_comp::<i32>(comp_key4).unwrap();
+    assert_eq!(ecs.stable_id(comp_key4), Ok(4));
+    assert_eq!(
+        ecs.comp_by_stable_id::<i32>(4),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    ecs.put_back(taken).unwrap();
+    assert_eq!(ecs.comp_by_stable_id::<i32>(4), Ok((comp_key4, &5)));
+    drop(ecs.take_comp::<i32>(comp_key4).unwrap());
+    ecs.insert_comp(entity_key0, 6).unwrap();
+    assert_eq!(
+        ecs.comp_by_stable_id::<i32>(4),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+
+    // removing an entity drops the ids of its components, and clones keep the ids of the kept ones
+    let clone = ecs
+        .clone_filtered(|entity_key| entity_key == entity_key0)
+        .unwrap();
+    ecs.remove_entity(entity_key1).unwrap();
+    assert_eq!(
+        ecs.comp_by_stable_id::<i32>(3),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        clone.comp_by_stable_id::<i32>(3),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        clone.comp_by_stable_id::<i32>(5),
+        ecs.comp_by_stable_id::<i32>(5)
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn spawn_batch() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let entity_keys = ecs.spawn_batch((0..4).map(|i| (i, i as u32 * 10))).unwrap();
+    assert_eq!(entity_keys.len(), 4);
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        let comps = ecs.iter_comp_by_entity::<u32>(*entity_key).unwrap();
+        assert_eq!(comps.collect::<Vec<_>>(), vec![&(i as u32 * 10)]);
+    }
+
+    // an unregistered type fails the first bundle
+    assert_eq!(
+        ecs.spawn_batch([(1i32, 2u64)]),
+        Err(ecs_tiny::SpawnError::Bundle {
+            index: 0,
+            error: ecs_tiny::Error::TypeNotRegistered
+        })
+    );
+    assert_eq!(ecs.iter_entity().count(), 4);
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 4);
+
+    // a failing bundle is removed, while the previous ones stay in place
+    ecs.set_insert_validator::<u32>(|_, _, comp| {
+        if *comp < 100 {
+            Ok(())
+        } else {
+            Err("too large".to_string())
+        }
+    })
+    .unwrap();
+    assert_eq!(
+        ecs.spawn_batch([(4, 40u32), (5, 500u32), (6, 60u32)]),
+        Err(ecs_tiny::SpawnError::Bundle {
+            index: 1,
+            error: ecs_tiny::Error::ValidationFailed("too large".to_string())
+        })
+    );
+    assert_eq!(ecs.iter_entity().count(), 5);
+    let comps = ecs.iter_comp::<i32>().unwrap().copied().collect::<Vec<_>>();
+    assert_eq!(comps, vec![0, 1, 2, 3, 4]);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn iter_comp_mut_and() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<f32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register_immutable::<i64>().unwrap();
+    ecs.insert_comp(entity_key0, 1.0f32).unwrap();
+    ecs.insert_comp(entity_key1, 2.0f32).unwrap();
+    ecs.insert_comp(entity_key0, 3u32).unwrap();
+
+    // both iterators are alive at once
+    {
+        let (velocities, mut configs) = ecs.iter_comp_mut_and::<f32, u32>().unwrap();
+        let config = configs.next().unwrap();
+        for velocity in velocities {
+            *velocity *= *config as f32;
+        }
+    }
+    let comps = ecs.iter_comp::<f32>().unwrap().collect::<Vec<_>>();
+    assert_eq!(comps, vec![&3.0, &6.0]);
+
+    assert!(matches!(
+        ecs.iter_comp_mut_and::<f32, i32>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    ));
+    assert!(matches!(
+        ecs.iter_comp_mut_and::<i64, u32>(),
+        Err(ecs_tiny::Error::ImmutableType)
+    ));
+    assert!(ecs.iter_comp_mut_and::<u32, i64>().is_ok());
+}
+
+#[test]
+#[should_panic]
+fn iter_comp_mut_and_same_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<u32>().unwrap();
+    let _ = ecs.iter_comp_mut_and::<u32, u32>();
+}
+
+#[test]
+fn entity_flags() {
+    use ecs_tiny::EntityFilter;
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_journal();
+    ecs.register_cloneable::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let filtered =
+        |ecs: &ecs_tiny::ECS, filter| ecs.iter_entity_filtered(filter).collect::<Vec<_>>();
+
+    // reserved entities are not alive until flushed
+    let entity_key1 = ecs.reserve_entity();
+    assert_eq!(ecs.entity_flags(entity_key1), Ok(EntityFilter::RESERVED));
+    assert_eq!(
+        ecs.insert_comp(entity_key1, 1),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Reserved
+        ))
+    );
+    assert!(ecs.disable_entity(entity_key1).is_err());
+    assert_eq!(filtered(&ecs, EntityFilter::LIVE), vec![entity_key0]);
+    assert_eq!(filtered(&ecs, EntityFilter::RESERVED), vec![entity_key1]);
+    assert_eq!(filtered(&ecs, EntityFilter::ALL).len(), 2);
+    assert_ne!(ecs.insert_entity(), entity_key1);
+    assert_eq!(ecs.flush_reserved(), 1);
+    assert_eq!(ecs.entity_flags(entity_key1), Ok(EntityFilter::LIVE));
+    ecs.insert_comp(entity_key1, 1).unwrap();
+
+    // disabled and pending entities stay alive, and are selected by their flags
+    ecs.disable_entity(entity_key1).unwrap();
+    assert_eq!(filtered(&ecs, EntityFilter::DISABLED), vec![entity_key1]);
+    assert!(!ecs
+        .iter_entity()
+        .any(|entity_key| entity_key == entity_key1));
+    ecs.despawn_deferred(entity_key1).unwrap();
+    assert_eq!(
+        ecs.entity_flags(entity_key1),
+        Ok(EntityFilter::DISABLED | EntityFilter::PENDING_DESPAWN)
+    );
+    assert!(filtered(&ecs, EntityFilter::DISABLED).is_empty());
+    assert_eq!(
+        filtered(&ecs, EntityFilter::DISABLED | EntityFilter::PENDING_DESPAWN),
+        vec![entity_key1]
+    );
+    ecs.enable_entity(entity_key1).unwrap();
+    assert_eq!(
+        filtered(&ecs, EntityFilter::PENDING_DESPAWN),
+        vec![entity_key1]
+    );
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 1);
+    assert_eq!(ecs.comp_histogram().buckets(), &[2, 1]);
+
+    // the flags are journaled
+    let replayed = ecs_tiny::ECS::replay(&ecs.take_journal()).unwrap();
+    assert_eq!(
+        replayed.entity_flags(entity_key1),
+        Ok(EntityFilter::PENDING_DESPAWN)
+    );
+
+    // flushing removes the pending entities with their components
+    assert_eq!(ecs.flush_despawns(), 1);
+    assert!(ecs.entity_flags(entity_key1).is_err());
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+    assert_eq!(ecs.flush_despawns(), 0);
+    assert_eq!(ecs.iter_entity_filtered(EntityFilter::ALL).count(), 2);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn entity_flags_undo() {
+    use ecs_tiny::EntityFilter;
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_undo(100);
+    let entity_key = ecs.insert_entity();
+    ecs.disable_entity(entity_key).unwrap();
+    ecs.remove_entity(entity_key).unwrap();
+
+    // undoing the removal restores the flags, and undoing the flags restores the entity as live
+    ecs.undo().unwrap();
+    assert_eq!(ecs.entity_flags(entity_key), Ok(EntityFilter::DISABLED));
+    ecs.undo().unwrap();
+    assert_eq!(ecs.entity_flags(entity_key), Ok(EntityFilter::LIVE));
+
+    // the entities inserted by a flush are undone together with it
+    let reserved = ecs.reserve_entity();
+    ecs.flush_reserved();
+    ecs.undo().unwrap();
+    assert!(ecs.get_entity(reserved).is_err());
+    let clone = ecs.clone_filtered(|_| true).unwrap();
+    assert_eq!(clone.iter_entity().collect::<Vec<_>>(), vec![entity_key]);
+}
+
+#[test]
+fn into_buffers() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    for i in 0..8 {
+        ecs.insert_comp(entity_key0, i).unwrap();
+        ecs.insert_comp(entity_key1, i + 8).unwrap();
+    }
+
+    // the buffer holds the same components as the allocating variant
+    let mut cloned = vec![];
+    ecs.collect_comp_cloned_into::<i32>(&mut cloned).unwrap();
+    assert_eq!(cloned, ecs.collect_comp_cloned::<i32>().unwrap());
+
+    // the buffer is cleared, and its allocation reused
+    let capacity = cloned.capacity();
+    let ptr = cloned.as_ptr();
+    ecs.collect_comp_cloned_into::<i32>(&mut cloned).unwrap();
+    assert_eq!(cloned.len(), 16);
+    assert_eq!((cloned.capacity(), cloned.as_ptr()), (capacity, ptr));
+
+    let mut comps = Vec::with_capacity(16);
+    let ptr = comps.as_ptr();
+    ecs.remove_comps_by_entity_into::<i32>(entity_key0, &mut comps)
+        .unwrap();
+    assert_eq!(comps, (0..8).collect::<Vec<_>>());
+    assert_eq!(
+        ecs.remove_comps_by_entity::<i32>(entity_key1),
+        Ok((8..16).collect())
+    );
+
+    ecs.remove_comps_by_entity_into::<i32>(entity_key0, &mut comps)
+        .unwrap();
+    assert!(comps.is_empty());
+    assert_eq!((comps.capacity(), comps.as_ptr()), (16, ptr));
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+
+    // the failures leave the buffer empty
+    comps.push(42);
+    ecs.remove_entity(entity_key0).unwrap();
+    assert!(matches!(
+        ecs.remove_comps_by_entity_into::<i32>(entity_key0, &mut comps),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    assert!(comps.is_empty());
+    assert_eq!(
+        ecs.remove_comps_by_entity::<u32>(entity_key1),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn end_frame() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let comp_key = ecs.insert_comp(entity_key0, 42).unwrap();
+
+    // frame 0: a reserved entity and a dropped taken component
+    let entity_key1 = ecs.reserve_entity();
+    drop(ecs.take_comp::<i32>(comp_key).unwrap());
+    let report = ecs.end_frame();
+    assert_eq!(
+        report,
+        ecs_tiny::FrameReport {
+            frame: 0,
+            reserved: 1,
+            despawned: 0,
+            released: 1,
+        }
+    );
+    assert_eq!(
+        ecs.get_comp::<i32>(comp_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+
+    // frame 1: the inserted entity can be given components and despawned
+    ecs.insert_comp(entity_key1, 63).unwrap();
+    ecs.despawn_deferred(entity_key1).unwrap();
+    let report = ecs.end_frame();
+    assert_eq!((report.frame, report.reserved), (1, 0));
+    assert_eq!((report.despawned, report.released), (1, 0));
+    assert!(ecs.get_entity(entity_key1).is_err());
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 0);
+
+    // frame 2: nothing to do
+    let report = ecs.end_frame();
+    assert_eq!(
+        report,
+        ecs_tiny::FrameReport {
+            frame: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(ecs.frame(), 3);
+    assert_eq!(ecs.iter_entity().collect::<Vec<_>>(), vec![entity_key0]);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn insert_hook() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.set_insert_hook::<i32>(|ctx, entity_key, comp_key| {
+        let comp = *ctx.ecs().get_comp::<i32>(comp_key).unwrap();
+        ctx.register::<u32>();
+        ctx.insert_comp(entity_key, comp as u32);
+    })
+    .unwrap();
+
+    // the hook inserts onto the same entity afterwards, visible to the next operation
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 42).unwrap();
+    let comps = ecs.iter_comp_by_entity::<u32>(entity_key).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&42]);
+    assert!(ecs.take_hook_errors().is_empty());
+
+    // the failures of queued operations are recorded
+    ecs.insert_comp(entity_key, 63).unwrap();
+    assert_eq!(
+        ecs.take_hook_errors(),
+        vec![ecs_tiny::Error::TypeAlreadyRegistered]
+    );
+    assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 2);
+
+    // a hook triggering itself is cut off
+    ecs.set_insert_hook::<i32>(|ctx, entity_key, _| ctx.insert_comp(entity_key, 0))
+        .unwrap();
+    ecs.insert_comp(entity_key, 0).unwrap();
+    assert_eq!(
+        ecs.take_hook_errors(),
+        vec![ecs_tiny::Error::ReentrantMutation]
+    );
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().count(), 2 + 1 + 1024);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn insert_hook_paths() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_ordered::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let hook = |ctx: &mut ecs_tiny::HookContext<'_>, entity_key, comp_key| {
+        let comp = *ctx.ecs().get_comp::<i32>(comp_key).unwrap();
+        ctx.insert_comp(entity_key, comp as u32);
+    };
+    ecs.set_insert_hook::<i32>(hook).unwrap();
+
+    // the hook runs for a positional insertion
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp_at(entity_key, 0, 42).unwrap();
+    assert_eq!(
+        ecs.iter_comp_by_entity::<u32>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&42]
+    );
+
+    // the hook runs for an insertion at a row key handed out by a mirror
+    let mut mirror = ecs.new_mirror();
+    mirror.register_ordered::<i32>().unwrap();
+    mirror.register::<u32>().unwrap();
+    mirror.set_insert_hook::<i32>(hook).unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 63).unwrap();
+    mirror.insert_entity_at(entity_key).unwrap();
+    assert_eq!(
+        mirror.insert_comp_at_row(entity_key, comp_key.row_key(), 63),
+        Ok(comp_key)
+    );
+    assert_eq!(
+        mirror
+            .iter_comp_by_entity::<u32>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&63]
+    );
+    assert!(ecs.take_hook_errors().is_empty());
+    assert!(mirror.take_hook_errors().is_empty());
+    assert_eq!(mirror.validate(), Ok(()));
+}
+
+#[test]
+fn map_entities() {
+    use ecs_tiny::{EntityLookup, EntityStatus};
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    ecs.insert_comp(entity_key0, 1).unwrap();
+    ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key2, 3).unwrap();
+    ecs.remove_entity(entity_key2).unwrap();
+
+    // the first component wins, and the misses tell dead entities from entities without the component
+    let entity_keys = [entity_key2, entity_key1, entity_key0, entity_key0];
+    let lookups = ecs
+        .map_entities::<i32, _>(&entity_keys, |entity_key, comp| {
+            (entity_key, comp.map(|comp| *comp))
+        })
+        .unwrap();
+    assert_eq!(
+        lookups,
+        vec![
+            (
+                entity_key2,
+                EntityLookup::EntityNotFound(EntityStatus::Removed {
+                    current_generation: 1
+                })
+            ),
+            (entity_key1, EntityLookup::NoComp),
+            (entity_key0, EntityLookup::Found(1)),
+            (entity_key0, EntityLookup::Found(1)),
+        ]
+    );
+
+    // the mutable variant visits a repeated entity once per occurrence
+    let found = ecs
+        .map_entities_mut::<i32, _>(&entity_keys, |_, comp| match comp {
+            EntityLookup::Found(comp) => {
+                *comp *= 10;
+                true
+            }
+            _ => false,
+        })
+        .unwrap();
+    assert_eq!(found, vec![false, false, true, true]);
+    let comps = ecs.iter_comp_by_entity::<i32>(entity_key0).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&100, &2]);
+
+    assert_eq!(
+        ecs.map_entities::<u32, _>(&entity_keys, |_, _| ()),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+}
+
+#[test]
+fn world_traits() {
+    use ecs_tiny::{CompKey, EntityKey, Error, WorldRead, WorldWrite};
+
+    // a world recording the writes, substituted for the concrete world
+    #[derive(Default)]
+    struct Recording {
+        ecs: ecs_tiny::ECS,
+        writes: Vec<&'static str>,
+    }
+
+    impl WorldRead for Recording {
+        type EntityIter<'a> = <ecs_tiny::ECS as WorldRead>::EntityIter<'a>;
+        type CompIter<'a, T>
+            = <ecs_tiny::ECS as WorldRead>::CompIter<'a, T>
+        where
+            T: 'a;
+
+        fn get_entity(&self, entity_key: EntityKey) -> Result<(), ecs_tiny::Error> {
+            self.ecs.get_entity(entity_key)
+        }
+
+        fn iter_entity(&self) -> Self::EntityIter<'_> {
+            WorldRead::iter_entity(&self.ecs)
+        }
+
+        fn get_comp<T: std::any::Any>(&self, comp_key: CompKey) -> Result<&T, Error> {
+            self.ecs.get_comp(comp_key)
+        }
+
+        fn get_entity_by_comp(&self, comp_key: CompKey) -> Result<EntityKey, Error> {
+            self.ecs.get_entity_by_comp(comp_key)
+        }
+
+        fn iter_comp<T: std::any::Any>(&self) -> Result<Self::CompIter<'_, T>, Error> {
+            WorldRead::iter_comp(&self.ecs)
+        }
+
+        fn iter_comp_by_entity<T: std::any::Any>(
+            &self,
+            entity_key: EntityKey,
+        ) -> Result<Self::CompIter<'_, T>, Error> {
+            WorldRead::iter_comp_by_entity(&self.ecs, entity_key)
+        }
+    }
+
+    impl WorldWrite for Recording {
+        type CompIterMut<'a, T>
+            = <ecs_tiny::ECS as WorldWrite>::CompIterMut<'a, T>
+        where
+            T: 'a;
+
+        fn insert_entity(&mut self) -> EntityKey {
+            self.writes.push("insert_entity");
+            self.ecs.insert_entity()
+        }
+
+        fn remove_entity(&mut self, entity_key: EntityKey) -> Result<(), ecs_tiny::Error> {
+            self.writes.push("remove_entity");
+            self.ecs.remove_entity(entity_key)
+        }
+
+        fn insert_comp<T: std::any::Any>(
+            &mut self,
+            entity_key: EntityKey,
+            comp: T,
+        ) -> Result<CompKey, Error> {
+            self.writes.push("insert_comp");
+            self.ecs.insert_comp(entity_key, comp)
+        }
+
+        fn remove_comp<T: std::any::Any>(&mut self, comp_key: CompKey) -> Result<T, Error> {
+            self.writes.push("remove_comp");
+            self.ecs.remove_comp(comp_key)
+        }
+
+        fn get_comp_mut<T: std::any::Any>(&mut self, comp_key: CompKey) -> Result<&mut T, Error> {
+            self.writes.push("get_comp_mut");
+            self.ecs.get_comp_mut(comp_key)
+        }
+
+        fn iter_comp_mut<T: std::any::Any>(&mut self) -> Result<Self::CompIterMut<'_, T>, Error> {
+            self.writes.push("iter_comp_mut");
+            WorldWrite::iter_comp_mut(&mut self.ecs)
+        }
+
+        fn iter_comp_mut_by_entity<T: std::any::Any>(
+            &mut self,
+            entity_key: EntityKey,
+        ) -> Result<Self::CompIterMut<'_, T>, Error> {
+            self.writes.push("iter_comp_mut_by_entity");
+            WorldWrite::iter_comp_mut_by_entity(&mut self.ecs, entity_key)
+        }
+    }
+
+    fn step<W: WorldWrite>(world: &mut W) -> usize {
+        let entity_key = world.insert_entity();
+        world.insert_comp(entity_key, 42).unwrap();
+        for comp in world.iter_comp_mut::<i32>().unwrap() {
+            *comp += 1;
+        }
+        world.comp_count::<i32>().unwrap()
+    }
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    assert_eq!(step(&mut ecs), 1);
+    assert_eq!(step(&mut ecs), 2);
+    assert_eq!(
+        ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(),
+        vec![&44, &43]
+    );
+    assert_eq!(WorldRead::entity_count(&ecs), 2);
+
+    let mut recording = Recording::default();
+    recording.ecs.register::<i32>().unwrap();
+    assert_eq!(step(&mut recording), 1);
+    assert_eq!(
+        recording.writes,
+        vec!["insert_entity", "insert_comp", "iter_comp_mut"]
+    );
+    assert_eq!(recording.comp_count::<u32>(), Err(Error::TypeNotRegistered));
+}
+
+#[test]
+fn lifecycle_events() {
+    use ecs_tiny::EntityLifecycle::{FirstComponentAdded, LastComponentRemoved};
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.insert_comp(entity_key0, 1).unwrap();
+    assert!(ecs.take_entity_lifecycle_events().is_empty());
+
+    ecs.enable_lifecycle_events();
+
+    // the entity removal emits once, however many components the cascade removes
+    ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key1, 3).unwrap();
+    ecs.insert_comp(entity_key1, 4).unwrap();
+    ecs.insert_comp(entity_key1, 5u32).unwrap();
+    ecs.remove_entity(entity_key1).unwrap();
+    assert_eq!(
+        ecs.take_entity_lifecycle_events(),
+        vec![
+            FirstComponentAdded(entity_key1),
+            LastComponentRemoved(entity_key1)
+        ]
+    );
+
+    // the individual removals emit on the last one only
+    let comp_key = ecs.insert_comp(entity_key0, 6u32).unwrap();
+    ecs.remove_comps_by_entity::<i32>(entity_key0).unwrap();
+    assert!(ecs.take_entity_lifecycle_events().is_empty());
+    ecs.remove_comp::<u32>(comp_key).unwrap();
+    assert_eq!(
+        ecs.take_entity_lifecycle_events(),
+        vec![LastComponentRemoved(entity_key0)]
+    );
+
+    // an entity without components emits nothing on removal
+    ecs.remove_entity(entity_key0).unwrap();
+    assert!(ecs.take_entity_lifecycle_events().is_empty());
+
+    // a dropped taken component counts until its row is released
+    let entity_key2 = ecs.insert_entity();
+    let comp_key = ecs.insert_comp(entity_key2, 7).unwrap();
+    drop(ecs.take_comp::<i32>(comp_key).unwrap());
+    assert_eq!(
+        ecs.take_entity_lifecycle_events(),
+        vec![FirstComponentAdded(entity_key2)]
+    );
+    ecs.end_frame();
+    assert_eq!(
+        ecs.take_entity_lifecycle_events(),
+        vec![LastComponentRemoved(entity_key2)]
+    );
+
+    // unregistering and clearing emit for the entities left without components
+    let entity_key3 = ecs.insert_entity();
+    let entity_key4 = ecs.insert_entity();
+    ecs.insert_comp(entity_key3, 8).unwrap();
+    ecs.insert_comp(entity_key4, 9).unwrap();
+    ecs.insert_comp(entity_key4, 10u32).unwrap();
+    ecs.take_entity_lifecycle_events();
+    ecs.unregister::<i32>().unwrap();
+    assert_eq!(
+        ecs.take_entity_lifecycle_events(),
+        vec![LastComponentRemoved(entity_key3)]
+    );
+    ecs.clear();
+    assert_eq!(
+        ecs.take_entity_lifecycle_events(),
+        vec![LastComponentRemoved(entity_key4)]
+    );
+}
+
+#[test]
+fn registered_token() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    let token = ecs.register::<i32>().unwrap();
+    assert_eq!(ecs.registered::<i32>(), Ok(token));
+
+    let comp_key = ecs.insert_comp_checked(token, entity_key, 42).unwrap();
+    *ecs.get_comp_mut_checked(token, comp_key).unwrap() += 1;
+    assert_eq!(
+        ecs.iter_comp_by_entity_checked(token, entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&43]
+    );
+    assert_eq!(ecs.remove_comp_checked(token, comp_key), Ok(43));
+    assert_eq!(ecs.iter_comp_checked(token).count(), 0);
+
+    // only the failures of the entities and components remain
+    ecs.remove_entity(entity_key).unwrap();
+    assert!(matches!(
+        ecs.insert_comp_checked(token, entity_key, 42),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+
+    // a registration again hands out a new token
+    ecs.unregister::<i32>().unwrap();
+    assert_eq!(
+        ecs.registered::<i32>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    let new_token = ecs.register::<i32>().unwrap();
+    assert_ne!(new_token, token);
+
+    let stale = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        ecs.iter_comp_checked(token).count();
+    }));
+    assert!(stale.is_err());
+
+    let mut other = ecs_tiny::ECS::new();
+    other.register::<i32>().unwrap();
+    let foreign = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        other.iter_comp_checked(new_token).count();
+    }));
+    assert!(foreign.is_err());
+}
+
+#[test]
+fn clear_incremental() {
+    use ecs_tiny::ClearProgress;
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let mut entity_keys = vec![];
+    let mut comp_keys = vec![];
+    for i in 0..10 {
+        let entity_key = ecs.insert_entity();
+        for j in 0..i {
+            ecs.insert_comp(entity_key, j).unwrap();
+        }
+        comp_keys.push(ecs.insert_comp(entity_key, i as u32).unwrap());
+        entity_keys.push(entity_key);
+    }
+    // a taken component goes with its entity
+    let taken = ecs.take_comp::<u32>(comp_keys[9]).unwrap();
+
+    let total = 10 + 45 + 10;
+    let mut removed_total = 0;
+    let mut steps = 0;
+    loop {
+        let progress = ecs.clear_incremental(3);
+        ecs.validate().unwrap();
+        steps += 1;
+
+        // the world is used as usual between the steps
+        if steps == 2 {
+            let entity_key = ecs.insert_entity();
+            ecs.insert_comp(entity_key, -1).unwrap();
+            entity_keys.push(entity_key);
+            ecs.remove_entity(entity_keys[8]).unwrap();
+            removed_total += 10;
+        }
+
+        match progress {
+            ClearProgress::Pending { removed } => {
+                assert!(removed <= 3);
+                removed_total += removed;
+            }
+            ClearProgress::Done { removed } => {
+                removed_total += removed;
+                break;
+            }
+        }
+    }
+
+    assert_eq!(removed_total, total);
+    assert!(steps * 3 >= total - 10);
+    assert_eq!(ecs.iter_entity().collect::<Vec<_>>(), vec![entity_keys[10]]);
+    assert_eq!(
+        ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(),
+        vec![&-1]
+    );
+    assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 0);
+    assert!(ecs.put_back(taken).is_err());
+
+    // a whole entity over the budget is cleared component by component
+    assert_eq!(
+        ecs.clear_incremental(1),
+        ClearProgress::Pending { removed: 1 }
+    );
+    assert_eq!(ecs.clear_incremental(1), ClearProgress::Done { removed: 1 });
+    assert_eq!(ecs.clear_incremental(1), ClearProgress::Done { removed: 0 });
+
+    // an entity holding taken components goes over a tiny budget, ending the step
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key1, 3).unwrap();
+    let taken0 = ecs.take_comp::<i32>(comp_key0).unwrap();
+    let taken1 = ecs.take_comp::<i32>(comp_key1).unwrap();
+    assert_eq!(
+        ecs.clear_incremental(1),
+        ClearProgress::Pending { removed: 3 }
+    );
+    ecs.validate().unwrap();
+    assert!(ecs.get_entity(entity_key0).is_err());
+    assert!(ecs.get_entity(entity_key1).is_ok());
+    assert_eq!(
+        ecs.clear_incremental(1),
+        ClearProgress::Pending { removed: 1 }
+    );
+    assert_eq!(ecs.clear_incremental(1), ClearProgress::Done { removed: 1 });
+    ecs.validate().unwrap();
+    assert!(ecs.put_back(taken0).is_err());
+    assert!(ecs.put_back(taken1).is_err());
+}
+
+#[test]
+fn stale_entity_key() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let stale_key = ecs.insert_entity();
+    ecs.insert_comp(stale_key, 42).unwrap();
+    ecs.remove_entity(stale_key).unwrap();
+
+    // the slot is handed out again, and the stale key does not resolve to the new entity
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 63).unwrap();
+    assert_eq!(entity_key.index(), stale_key.index());
+    assert_ne!(entity_key, stale_key);
+
+    assert!(ecs.get_entity(stale_key).is_err());
+    assert!(matches!(
+        ecs.insert_comp(stale_key, 0),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    assert!(ecs.iter_comp_by_entity::<i32>(stale_key).is_err());
+    assert!(ecs.remove_entity(stale_key).is_err());
+
+    assert!(ecs.get_entity(entity_key).is_ok());
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&63]
+    );
+}
+
+#[test]
+fn stale_comp_key() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+
+    // the row is handed out again after a direct removal
+    let stale_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
+    ecs.remove_comp::<i32>(stale_key0).unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key1, 63).unwrap();
+    assert_eq!(comp_key0.row_key(), stale_key0.row_key());
+    assert_ne!(comp_key0, stale_key0);
+
+    assert_eq!(
+        ecs.get_comp::<i32>(stale_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.get_comp_mut::<i32>(stale_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.get_entity_by_comp(stale_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.remove_comp::<i32>(stale_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&63));
+
+    // and after a removal through the entity
+    let stale_key1 = ecs.insert_comp(entity_key0, 84).unwrap();
+    ecs.remove_entity(entity_key0).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 105).unwrap();
+    assert_eq!(comp_key1.row_key(), stale_key1.row_key());
+    assert_eq!(
+        ecs.get_comp::<i32>(stale_key1),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.get_entity_by_comp(stale_key1),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_key1), Ok(entity_key1));
+
+    // undoing a removal restores the component under its original key
+    ecs.register_cloneable::<u64>().unwrap();
+    ecs.enable_undo(100);
+    let comp_key2 = ecs.insert_comp(entity_key1, 126u64).unwrap();
+    ecs.remove_comp::<u64>(comp_key2).unwrap();
+    ecs.undo().unwrap();
+    assert_eq!(ecs.get_comp::<u64>(comp_key2), Ok(&126));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn ref_stats() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    // short-lived components of short-lived entities leave no reference tables behind
+    for i in 0..10000 {
+        let entity_key = ecs.insert_entity();
+        let comp_key = ecs.insert_comp(entity_key, i).unwrap();
+        ecs.insert_comp(entity_key, i as u32).unwrap();
+        ecs.remove_comp::<i32>(comp_key).unwrap();
+        ecs.remove_entity(entity_key).unwrap();
+    }
+    assert_eq!(ecs.ref_stats(), ecs_tiny::RefStats::default());
+
+    // and neither do components removed from long-lived entities
+    let entity_keys = (0..100).map(|_| ecs.insert_entity()).collect::<Vec<_>>();
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        let comp_key = ecs.insert_comp(*entity_key, i as i32).unwrap();
+        ecs.remove_comp::<i32>(comp_key).unwrap();
+    }
+    assert_eq!(ecs.ref_stats(), ecs_tiny::RefStats::default());
+
+    ecs.insert_comp(entity_keys[0], 42).unwrap();
+    ecs.insert_comp(entity_keys[0], 42u32).unwrap();
+    ecs.insert_comp(entity_keys[1], 42u32).unwrap();
+    let stats = ecs.ref_stats();
+    assert_eq!((stats.ref_0_entries, stats.ref_1_entries), (2, 3));
+
+    ecs.unregister::<u32>().unwrap();
+    let stats = ecs.ref_stats();
+    assert_eq!((stats.ref_0_entries, stats.ref_1_entries), (1, 1));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn unregister_then_remove_entity() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 42).unwrap();
+    ecs.insert_comp(entity_key, 42u32).unwrap();
+
+    ecs.unregister::<i32>().unwrap();
+    assert_eq!(ecs.validate(), Ok(()));
+    assert!(ecs.find_orphans().is_empty());
+
+    ecs.remove_entity(entity_key).unwrap();
+    assert!(ecs.get_entity(entity_key).is_err());
+    assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 0);
+    assert_eq!(ecs.ref_stats(), ecs_tiny::RefStats::default());
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn unregister_then_insert_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key = ecs.insert_entity();
+    let stale_key = ecs.insert_comp(entity_key, 42).unwrap();
+
+    ecs.unregister::<i32>().unwrap();
+    assert_eq!(
+        ecs.insert_comp(entity_key, 63),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+
+    // the type starts afresh once registered again
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 63).unwrap();
+    assert_eq!(
+        ecs.get_comp::<i32>(stale_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&63));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&63]
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn iter_comp_mut_by_entity_order() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_ordered::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+
+    // the rows of the two entities interleave, and the order of the entity differs from the order of the rows
+    let comp_keys = (0..6)
+        .map(|i| {
+            let entity_key = [entity_key0, entity_key1][i % 2];
+            ecs.insert_comp(entity_key, i as i32).unwrap()
+        })
+        .collect::<Vec<_>>();
+    ecs.reorder_comp::<i32>(entity_key0, comp_keys[4], 0)
+        .unwrap();
+    ecs.remove_comp::<i32>(comp_keys[1]).unwrap();
+
+    for comp in ecs.iter_comp_mut_by_entity::<i32>(entity_key0).unwrap() {
+        *comp += 10;
+    }
+    let comps = ecs.iter_comp_mut_by_entity::<i32>(entity_key0).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&mut 14, &mut 10, &mut 12]);
+    let comps = ecs.iter_comp_mut_by_entity::<i32>(entity_key1).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&mut 3, &mut 5]);
+
+    // taken components are skipped
+    let taken = ecs.take_comp::<i32>(comp_keys[2]).unwrap();
+    let comps = ecs.iter_comp_mut_by_entity::<i32>(entity_key0).unwrap();
+    assert_eq!(comps.collect::<Vec<_>>(), vec![&mut 14, &mut 10]);
+    ecs.put_back(taken).unwrap();
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn iter_comp_by_entity_empty() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key = ecs.insert_entity();
+
+    // a live entity without components of the type iterates over nothing
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().count(),
+        0
+    );
+    assert_eq!(
+        ecs.iter_comp_mut_by_entity::<i32>(entity_key)
+            .unwrap()
+            .count(),
+        0
+    );
+
+    // and so does one whose components were removed
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    ecs.remove_comp::<i32>(comp_key).unwrap();
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key).unwrap().count(),
+        0
+    );
+
+    // while a removed entity and an unregistered type are errors
+    ecs.remove_entity(entity_key).unwrap();
+    assert!(matches!(
+        ecs.iter_comp_by_entity::<i32>(entity_key),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    assert!(matches!(
+        ecs.iter_comp_mut_by_entity::<i32>(entity_key),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    assert!(matches!(
+        ecs.iter_comp_by_entity::<u32>(entity_key),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    ));
+}
+
+#[test]
+fn error_variants() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    let removed_key = ecs.insert_entity();
+    ecs.remove_entity(removed_key).unwrap();
+    let removed = ecs_tiny::Error::EntityNotFound(ecs_tiny::EntityStatus::Removed {
+        current_generation: 1,
+    });
+
+    // inserting on a removed entity and inserting an unregistered type fail apart
+    assert_eq!(
+        ecs.insert_comp(entity_key, 42),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    ecs.register::<i32>().unwrap();
+    assert_eq!(ecs.insert_comp(removed_key, 42), Err(removed.clone()));
+    assert_eq!(
+        ecs.register::<i32>().err(),
+        Some(ecs_tiny::Error::TypeAlreadyRegistered)
+    );
+
+    let comp_key = ecs.insert_comp(entity_key, 42).unwrap();
+    assert_eq!(
+        ecs.get_comp::<u32>(comp_key),
+        Err(ecs_tiny::Error::TypeMismatch)
+    );
+    ecs.remove_comp::<i32>(comp_key).unwrap();
+    assert_eq!(
+        ecs.remove_comp::<i32>(comp_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+
+    // the entity and type lookups have fallible variants too
+    assert_eq!(ecs.get_entity(entity_key), Ok(()));
+    assert_eq!(ecs.get_entity(removed_key), Err(removed.clone()));
+    assert_eq!(ecs.remove_entity(removed_key), Err(removed));
+    let other_key = ecs_tiny::ECS::new().insert_entity();
+    assert_eq!(
+        ecs.remove_entity(other_key),
+        Err(ecs_tiny::Error::WrongWorld)
+    );
+    assert_eq!(
+        ecs.unregister::<u32>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(ecs.unregister::<i32>(), Ok(()));
+
+    // and every error describes itself
+    let error: Box<dyn std::error::Error> = Box::new(ecs_tiny::Error::TypeNotRegistered);
+    assert!(!error.to_string().is_empty());
+}
+
+#[test]
+fn typed_comp_key() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let entity_key = ecs.insert_entity();
+
+    // the type is inferred from the typed key
+    let comp_key0 = ecs.insert_comp_typed(entity_key, 42).unwrap();
+    let comp_key1 = ecs.insert_comp_typed(entity_key, 63u32).unwrap();
+    assert_eq!(ecs.get_comp(comp_key0), Ok(&42));
+    *ecs.get_comp_mut(comp_key1).unwrap() += 1;
+    assert_eq!(ecs.get_comp(comp_key1), Ok(&64));
+    assert_eq!(ecs.get_entity_by_comp(comp_key0), Ok(entity_key));
+
+    // the untyped keys are stored together, and typed again by their type
+    let comp_keys: Vec<ecs_tiny::CompKey> = vec![comp_key0.into(), comp_key1.untyped()];
+    assert_eq!(comp_keys[0].typed::<i32>(), Ok(comp_key0));
+    assert_eq!(
+        comp_keys[1].typed::<i32>(),
+        Err(ecs_tiny::Error::TypeMismatch)
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_keys[1]), Ok(entity_key));
+    assert_eq!(ecs.get_comp::<i32>(comp_keys[0]), Ok(&42));
+
+    // a typed key is invalidated by the removal like an untyped one
+    assert_eq!(ecs.remove_comp(comp_key0), Ok(42));
+    assert_eq!(ecs.get_comp(comp_key0), Err(ecs_tiny::Error::CompNotFound));
+    assert_eq!(
+        ecs.insert_comp_typed(entity_key, ()),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+}
+
+#[test]
+fn iter_comp_with_key() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_keys = (0..10)
+        .map(|i| {
+            let entity_key = [entity_key0, entity_key1][i % 2];
+            ecs.insert_comp(entity_key, i as i32).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    // the keys are the ones handed out by the insertions
+    let keys = ecs
+        .iter_comp_with_key::<i32>()
+        .unwrap()
+        .map(|(comp_key, _)| comp_key)
+        .collect::<Vec<_>>();
+    assert_eq!(keys, comp_keys);
+
+    // collect the keys of the odd components, and remove them after the iteration
+    let odd_keys = ecs
+        .iter_comp_with_key::<i32>()
+        .unwrap()
+        .filter(|(_, comp)| **comp % 2 == 1)
+        .map(|(comp_key, _)| comp_key)
+        .collect::<Vec<_>>();
+    for comp_key in odd_keys {
+        ecs.remove_comp::<i32>(comp_key).unwrap();
+    }
+    let comps = ecs.iter_comp::<i32>().unwrap().copied().collect::<Vec<_>>();
+    assert_eq!(comps, vec![0, 2, 4, 6, 8]);
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key1).unwrap().count(),
+        0
+    );
+
+    // the mutable and per-entity variants yield the same keys
+    for (comp_key, comp) in ecs.iter_comp_mut_with_key::<i32>().unwrap() {
+        assert_eq!(comp_key, comp_keys[*comp as usize]);
+        *comp += 100;
+    }
+    let comps = ecs
+        .iter_comp_with_key_by_entity::<i32>(entity_key0)
+        .unwrap()
+        .map(|(comp_key, comp)| (comp_key, *comp))
+        .collect::<Vec<_>>();
+    assert_eq!(comps[1], (comp_keys[2], 102));
+    let comps = ecs
+        .iter_comp_mut_with_key_by_entity::<i32>(entity_key0)
+        .unwrap()
+        .map(|(comp_key, comp)| (comp_key, *comp))
+        .collect::<Vec<_>>();
+    assert_eq!(comps.len(), 5);
+    assert_eq!(comps[4], (comp_keys[8], 108));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn iter_comp_with_entity() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let entity_keys = (0..6).map(|_| ecs.insert_entity()).collect::<Vec<_>>();
+    let mut comp_keys = vec![];
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        comp_keys.push(ecs.insert_comp(*entity_key, i as i32).unwrap());
+        ecs.insert_comp(*entity_key, i as u32 * 10).unwrap();
+    }
+
+    // the owners stay correct after removals, and after the rows are reused and compacted
+    ecs.remove_entity(entity_keys[1]).unwrap();
+    ecs.remove_comp::<i32>(comp_keys[3]).unwrap();
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 6).unwrap();
+    ecs.compact_step::<i32>(usize::MAX).unwrap();
+
+    let mut comps = ecs
+        .iter_comp_with_entity::<i32>()
+        .unwrap()
+        .map(|(owner, comp)| (owner, *comp))
+        .collect::<Vec<_>>();
+    comps.sort_unstable_by_key(|(_, comp)| *comp);
+    let expected = [0, 2, 4, 5]
+        .into_iter()
+        .map(|i| (entity_keys[i], i as i32))
+        .chain([(entity_key, 6)])
+        .collect::<Vec<_>>();
+    assert_eq!(comps, expected);
+
+    // look up another component of the owner while iterating
+    for (owner, comp) in ecs.iter_comp_with_entity::<i32>().unwrap() {
+        let other = ecs.iter_comp_by_entity::<u32>(owner).unwrap().next();
+        if owner != entity_key {
+            assert_eq!(other, Some(&(*comp as u32 * 10)));
+        }
+    }
+
+    for (owner, comp) in ecs.iter_comp_mut_with_entity::<i32>().unwrap() {
+        if owner == entity_keys[0] {
+            *comp = 42;
+        }
+    }
+    assert_eq!(ecs.get_comp::<i32>(comp_keys[0]), Ok(&42));
+}
+
+#[test]
+fn iter_comp_pair() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+
+    // the entity with several components of a type is yielded once, with the first of each type
+    ecs.insert_comp(entity_key0, 1).unwrap();
+    ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key0, 10u32).unwrap();
+    ecs.insert_comp(entity_key0, 20u32).unwrap();
+    // entities missing either type are skipped, whichever column is smaller
+    ecs.insert_comp(entity_key1, 3).unwrap();
+    ecs.insert_comp(entity_key2, 4).unwrap();
+    ecs.insert_comp(entity_key2, 40u32).unwrap();
+    for _ in 0..4 {
+        let entity_key = ecs.insert_entity();
+        ecs.insert_comp(entity_key, 0u32).unwrap();
+    }
+
+    let pairs = ecs
+        .iter_comp_pair::<i32, u32>()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        pairs,
+        vec![(entity_key0, (&1, &10)), (entity_key2, (&4, &40))]
+    );
+    let pairs = ecs.iter_comp_pair::<u32, i32>().unwrap().count();
+    assert_eq!(pairs, 2);
+
+    // the first component of an entity is the first left, after a removal or while taken
+    let first_key = ecs
+        .iter_comp_with_key_by_entity::<i32>(entity_key0)
+        .unwrap()
+        .next()
+        .unwrap()
+        .0;
+    let taken = ecs.take_comp::<i32>(first_key).unwrap();
+    let pairs = ecs
+        .iter_comp_pair::<i32, u32>()
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(pairs[0], (entity_key0, (&2, &10)));
+    ecs.put_back(taken).unwrap();
+
+    for (_, (comp_a, comp_b)) in ecs.iter_comp_pair_mut::<i32, u32>().unwrap() {
+        *comp_a += *comp_b as i32;
+    }
+    let comps = ecs.iter_comp::<i32>().unwrap().copied().collect::<Vec<_>>();
+    assert_eq!(comps, vec![11, 2, 3, 44]);
+
+    assert!(matches!(
+        ecs.iter_comp_pair::<i32, u64>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    ));
+}
+
+#[test]
+fn query_four() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<i64>().unwrap();
+    ecs.register::<u64>().unwrap();
+
+    let entity_keys = (0..4).map(|_| ecs.insert_entity()).collect::<Vec<_>>();
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        ecs.insert_comp(*entity_key, i as i32).unwrap();
+        ecs.insert_comp(*entity_key, i as u32).unwrap();
+        // the last entity misses the third type
+        if i < 3 {
+            ecs.insert_comp(*entity_key, i as i64).unwrap();
+        }
+        ecs.insert_comp(*entity_key, i as u64).unwrap();
+    }
+    // a second component of a type is not yielded
+    ecs.insert_comp(entity_keys[1], 100u32).unwrap();
+
+    for (_, (a, b, c)) in ecs.query::<(&i32, &mut u32, &i64)>().unwrap() {
+        *b += (*a as u32) + (*c as u32);
+    }
+    let items = ecs
+        .query::<(&i32, &u32, &i64, &mut u64)>()
+        .unwrap()
+        .map(|(entity_key, (a, b, c, d))| (entity_key, (*a, *b, *c, *d)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        vec![
+            (entity_keys[0], (0, 0, 0, 0)),
+            (entity_keys[1], (1, 3, 1, 1)),
+            (entity_keys[2], (2, 6, 2, 2)),
+        ]
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<u32>(entity_keys[1])
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&3, &100]
+    );
+}
+
+#[test]
+#[should_panic(expected = "each component type can appear only once")]
+fn query_with_duplicate_type_among_four() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<i64>().unwrap();
+
+    let _ = ecs.query::<(&i32, &u32, &i64, &mut u32)>();
+}
+
+#[test]
+fn query_optional() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<u64>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    ecs.insert_comp(entity_key0, 1).unwrap();
+    ecs.insert_comp(entity_key0, 10u32).unwrap();
+    ecs.insert_comp(entity_key1, 2).unwrap();
+    ecs.insert_comp(entity_key1, 20u64).unwrap();
+    ecs.insert_comp(entity_key2, 30u32).unwrap();
+
+    for (_, (a, b, c)) in ecs
+        .query::<(&i32, Option<&mut u32>, Option<&u64>)>()
+        .unwrap()
+    {
+        if let Some(b) = b {
+            *b += *a as u32 + c.map_or(0, |c| *c as u32);
+        }
+    }
+    let items = ecs
+        .query::<(&i32, Option<&u32>, Option<&u64>)>()
+        .unwrap()
+        .map(|(entity_key, (a, b, c))| (entity_key, (*a, b.copied(), c.copied())))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        vec![
+            (entity_key0, (1, Some(11), None)),
+            (entity_key1, (2, None, Some(20))),
+        ]
+    );
+    assert_eq!(
+        ecs.query_collect_owned::<(Option<&i32>, &u32)>().unwrap(),
+        vec![(entity_key0, (Some(1), 11)), (entity_key2, (None, 30))]
+    );
+    assert_eq!(ecs.query_count::<(Option<&u64>, &i32)>(), 2);
+
+    assert!(matches!(
+        ecs.query::<(Option<&i32>, Option<&u32>)>(),
+        Err(ecs_tiny::Error::NoRequiredElement)
+    ));
+    assert_eq!(ecs.query_count::<(Option<&i32>,)>(), 0);
+}
+
+#[test]
+fn iter_comp_filtered() {
+    use ecs_tiny::{With, Without};
+
+    struct Visible;
+    struct Frozen;
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<Visible>().unwrap();
+    ecs.register::<Frozen>().unwrap();
+
+    let entity_keys = (0..4).map(|_| ecs.insert_entity()).collect::<Vec<_>>();
+    for (i, entity_key) in entity_keys.iter().enumerate() {
+        ecs.insert_comp(*entity_key, i as i32).unwrap();
+        if i % 2 == 0 {
+            ecs.insert_comp(*entity_key, Visible).unwrap();
+        }
+        if i >= 2 {
+            ecs.insert_comp(*entity_key, Frozen).unwrap();
+        }
+    }
+    let frozen_key = ecs
+        .iter_comp_with_key::<Frozen>()
+        .unwrap()
+        .map(|(comp_key, _)| comp_key)
+        .next()
+        .unwrap();
+    // the entity holds no frozen marker once it is removed
+    ecs.remove_comp::<Frozen>(frozen_key).unwrap();
+
+    let comps = |ecs: &ecs_tiny::ECS| {
+        (
+            ecs.iter_comp_filtered::<i32, With<Visible>>()
+                .unwrap()
+                .copied()
+                .collect::<Vec<_>>(),
+            ecs.iter_comp_filtered::<i32, Without<Frozen>>()
+                .unwrap()
+                .copied()
+                .collect::<Vec<_>>(),
+            ecs.iter_comp_filtered::<i32, (With<Visible>, Without<Frozen>)>()
+                .unwrap()
+                .copied()
+                .collect::<Vec<_>>(),
+        )
+    };
+    assert_eq!(comps(&ecs), (vec![0, 2], vec![0, 1, 2], vec![0, 2]));
+
+    for comp in ecs
+        .iter_comp_mut_filtered::<i32, (With<Frozen>,)>()
+        .unwrap()
+    {
+        *comp += 10;
+    }
+    assert_eq!(
+        ecs.iter_comp::<i32>().unwrap().copied().collect::<Vec<_>>(),
+        vec![0, 1, 2, 13]
+    );
+
+    struct Unregistered;
+    assert!(matches!(
+        ecs.iter_comp_filtered::<i32, With<Unregistered>>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    ));
+    assert!(matches!(
+        ecs.iter_comp_mut_filtered::<i32, Without<Unregistered>>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    ));
+}
+
+#[test]
+fn count_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key1, 3).unwrap();
+    ecs.insert_comp(entity_key1, 4u32).unwrap();
+    let reserved_key = ecs.reserve_entity();
+
+    assert_eq!(ecs.entity_count(), 2);
+    assert_eq!(ecs.count_comp::<i32>(), Ok(3));
+    assert_eq!(ecs.count_comp::<u32>(), Ok(1));
+    assert_eq!(
+        ecs.count_comp::<u64>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key0), Ok(2));
+    assert_eq!(ecs.count_comp_by_entity::<u32>(entity_key0), Ok(0));
+    assert_eq!(
+        ecs.count_comp_by_entity::<u64>(entity_key0),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.count_comp_by_entity::<i32>(reserved_key),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Reserved
+        ))
+    );
+
+    ecs.remove_comp::<i32>(comp_key0).unwrap();
+    assert_eq!(ecs.count_comp::<i32>(), Ok(2));
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key0), Ok(1));
+
+    let taken = ecs.take_comp::<i32>(comp_key1).unwrap();
+    assert_eq!(ecs.count_comp::<i32>(), Ok(1));
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key0), Ok(0));
+    ecs.put_back(taken).unwrap();
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key0), Ok(1));
+
+    // removing the entity removes its components
+    ecs.remove_entity(entity_key1).unwrap();
+    assert_eq!(ecs.entity_count(), 1);
+    assert_eq!(ecs.count_comp::<i32>(), Ok(1));
+    assert_eq!(ecs.count_comp::<u32>(), Ok(0));
+    assert_eq!(
+        ecs.count_comp_by_entity::<i32>(entity_key1),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+
+    assert_eq!(ecs.flush_reserved(), 1);
+    assert_eq!(ecs.entity_count(), 2);
+    ecs.clear();
+    assert_eq!(ecs.entity_count(), 0);
+}
+
+#[test]
+fn contains() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 42).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 63).unwrap();
+    let reserved_key = ecs.reserve_entity();
+
+    assert!(ecs.contains_entity(entity_key0));
+    assert!(!ecs.contains_entity(reserved_key));
+    assert!(ecs.contains_comp(comp_key0));
+    assert!(ecs.has_comp::<i32>(entity_key0));
+    assert!(!ecs.has_comp::<u32>(entity_key0));
+
+    // a taken component keeps its key and its association
+    let taken = ecs.take_comp::<i32>(comp_key0).unwrap();
+    assert!(ecs.contains_comp(comp_key0));
+    assert!(ecs.has_comp::<i32>(entity_key0));
+    taken.discard(&mut ecs);
+    assert!(!ecs.contains_comp(comp_key0));
+    assert!(!ecs.has_comp::<i32>(entity_key0));
+
+    ecs.remove_entity(entity_key1).unwrap();
+    assert!(!ecs.contains_entity(entity_key1));
+    assert!(!ecs.contains_comp(comp_key1));
+    assert!(!ecs.has_comp::<i32>(entity_key1));
+
+    let entity_key2 = ecs.insert_entity();
+    let comp_key2 = ecs.insert_comp(entity_key2, 1).unwrap();
+    ecs.unregister::<i32>().unwrap();
+    assert!(!ecs.contains_comp(comp_key2));
+    assert!(!ecs.has_comp::<i32>(entity_key2));
+
+    let other = ecs_tiny::ECS::new();
+    assert!(!other.contains_entity(entity_key2));
+    assert!(!other.contains_comp(comp_key2));
+}
+
+#[test]
+fn remove_comps_by_entity_refs() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key = ecs.insert_comp(entity_key0, 1u32).unwrap();
+    ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key1, 3).unwrap();
+
+    assert_eq!(
+        ecs.remove_comps_by_entity::<i32>(entity_key0),
+        Ok(vec![1, 2])
+    );
+    assert!(!ecs.has_comp::<i32>(entity_key0));
+    assert_eq!(ecs.get_comp::<u32>(comp_key), Ok(&1));
+    assert_eq!(
+        ecs.iter_comp::<i32>().unwrap().collect::<Vec<_>>(),
+        vec![&3]
+    );
+    let stats = ecs.ref_stats();
+    assert_eq!((stats.ref_0_entries, stats.ref_1_entries), (2, 2));
+
+    // a live entity with no components of the type yields none
+    assert_eq!(ecs.remove_comps_by_entity::<i32>(entity_key0), Ok(vec![]));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn clear_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key1, 3).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 1u32).unwrap();
+    let taken = ecs.take_comp::<i32>(comp_key1).unwrap();
+
+    // the taken component is left in place
+    assert_eq!(ecs.clear_comp::<i32>(), Ok(2));
+    assert_eq!(
+        ecs.clear_comp::<u64>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.get_comp::<i32>(comp_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_comp::<u32>(comp_key2), Ok(&1));
+    assert!(!ecs.has_comp::<i32>(entity_key1));
+    assert_eq!(ecs.validate(), Ok(()));
+
+    ecs.put_back(taken).unwrap();
+    assert_eq!(ecs.clear_comp::<i32>(), Ok(1));
+    assert_eq!(ecs.clear_comp::<i32>(), Ok(0));
+    let stats = ecs.ref_stats();
+    assert_eq!((stats.ref_0_entries, stats.ref_1_entries), (1, 1));
+
+    // the type stays registered, and the entities stay usable
+    ecs.insert_comp(entity_key0, 4).unwrap();
+    ecs.remove_entity(entity_key0).unwrap();
+    ecs.remove_entity(entity_key1).unwrap();
+    assert_eq!(ecs.count_comp::<i32>(), Ok(0));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn remove_entity_verbose() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 1u32).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key0, 2).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key1, 3).unwrap();
+    let _taken = ecs.take_comp::<i32>(comp_key2).unwrap();
+
+    let removed = ecs.remove_entity_verbose(entity_key0).unwrap();
+    assert_eq!(removed, vec![comp_key0, comp_key1, comp_key2]);
+    let i32_count = removed
+        .iter()
+        .filter(|comp_key| comp_key.type_key() == std::any::TypeId::of::<i32>())
+        .count();
+    assert_eq!(i32_count, 2);
+    for comp_key in removed {
+        assert!(!ecs.contains_comp(comp_key));
+    }
+    assert_eq!(ecs.get_comp::<i32>(comp_key3), Ok(&3));
+
+    assert!(matches!(
+        ecs.remove_entity_verbose(entity_key0),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    let entity_key2 = ecs.insert_entity();
+    assert_eq!(ecs.remove_entity_verbose(entity_key2), Ok(vec![]));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn take_entity() {
+    let mut ecs0 = ecs_tiny::ECS::new();
+    ecs0.register::<i32>().unwrap();
+    ecs0.register::<u32>().unwrap();
+    ecs0.register_ordered::<String>().unwrap();
+
+    let entity_key = ecs0.insert_entity();
+    ecs0.insert_comp(entity_key, 1).unwrap();
+    ecs0.insert_comp(entity_key, 1u32).unwrap();
+    let comp_key = ecs0.insert_comp(entity_key, 2).unwrap();
+    ecs0.insert_comp(entity_key, "a".to_string()).unwrap();
+    let string_key = ecs0.insert_comp(entity_key, "b".to_string()).unwrap();
+    ecs0.reorder_comp::<String>(entity_key, string_key, 0)
+        .unwrap();
+    let taken = ecs0.take_comp::<i32>(comp_key).unwrap();
+
+    let parts = ecs0.take_entity(entity_key).unwrap();
+    assert_eq!(parts.len(), 4);
+    assert!(ecs0.get_entity(entity_key).is_err());
+    assert!(ecs0.take_entity(entity_key).is_err());
+    assert_eq!(ecs0.put_back(taken), Err(ecs_tiny::Error::CompNotFound));
+    assert_eq!(ecs0.validate(), Ok(()));
+
+    // the failures insert nothing
+    let mut ecs1 = ecs_tiny::ECS::new();
+    ecs1.register::<i32>().unwrap();
+    ecs1.register::<String>().unwrap();
+    let mismatched = vec![(
+        std::any::TypeId::of::<i32>(),
+        Box::new(1u32) as Box<dyn std::any::Any>,
+    )];
+    assert_eq!(
+        ecs1.insert_entity_from_parts(mismatched),
+        Err(ecs_tiny::Error::TypeMismatch)
+    );
+    let unregistered = vec![(
+        std::any::TypeId::of::<u32>(),
+        Box::new(1u32) as Box<dyn std::any::Any>,
+    )];
+    assert_eq!(
+        ecs1.insert_entity_from_parts(unregistered),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(ecs1.entity_count(), 0);
+
+    ecs1.register::<u32>().unwrap();
+    let entity_key = ecs1.insert_entity_from_parts(parts).unwrap();
+    assert_eq!(
+        ecs1.iter_comp_by_entity::<i32>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&1]
+    );
+    assert_eq!(
+        ecs1.iter_comp_by_entity::<String>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec!["b", "a"]
+    );
+    assert_eq!(ecs1.entity_count(), 1);
+}
+
+#[test]
+fn move_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_journal();
+    ecs.enable_undo(100);
+    ecs.register_cloneable::<i32>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 3).unwrap();
+
+    assert_eq!(ecs.move_comp(comp_key0, entity_key1), Ok(comp_key0));
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&1));
+    assert_eq!(ecs.get_entity_by_comp(comp_key0), Ok(entity_key1));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&2]
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key1)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&3, &1]
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // the journal replays the move under the same key
+    let replayed = ecs_tiny::ECS::replay(&ecs.take_journal()).unwrap();
+    assert_eq!(replayed.get_entity_by_comp(comp_key0), Ok(entity_key1));
+    assert_eq!(replayed.get_comp::<i32>(comp_key0), Ok(&1));
+
+    ecs.undo().unwrap();
+    assert_eq!(ecs.get_entity_by_comp(comp_key0), Ok(entity_key0));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&1, &2]
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+
+    // a dead target leaves everything untouched
+    ecs.remove_entity(entity_key1).unwrap();
+    assert_eq!(
+        ecs.move_comp(comp_key1, entity_key1),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(
+        ecs.move_comp(comp_key2, entity_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_key1), Ok(entity_key0));
+
+    // and so does a target at the component limit
+    let entity_key2 = ecs.insert_entity();
+    ecs.insert_comp(entity_key2, 4).unwrap();
+    ecs.set_comp_limit::<i32>(1).unwrap();
+    assert_eq!(
+        ecs.move_comp(comp_key1, entity_key2),
+        Err(ecs_tiny::Error::QuotaExceeded)
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_key1), Ok(entity_key0));
+
+    // moving the last components leaves no references behind
+    ecs.remove_entity(entity_key2).unwrap();
+    let entity_key3 = ecs.insert_entity();
+    ecs.set_comp_limit::<i32>(2).unwrap();
+    ecs.move_comp(comp_key0, entity_key3).unwrap();
+    ecs.move_comp(comp_key1, entity_key3).unwrap();
+    let stats = ecs.ref_stats();
+    assert_eq!((stats.ref_0_entries, stats.ref_1_entries), (1, 1));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn move_all_comps() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register_ordered::<u32>().unwrap();
+
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.insert_comp(entity_key0, 1).unwrap();
+    ecs.insert_comp(entity_key0, 1u32).unwrap();
+    let comp_key = ecs.insert_comp(entity_key0, 2u32).unwrap();
+    ecs.reorder_comp::<u32>(entity_key0, comp_key, 0).unwrap();
+    let taken_key = ecs.insert_comp(entity_key0, 3u32).unwrap();
+    ecs.insert_comp(entity_key1, 4).unwrap();
+    let taken = ecs.take_comp::<u32>(taken_key).unwrap();
+
+    // a dead target moves nothing
+    let entity_key2 = ecs.insert_entity();
+    ecs.remove_entity(entity_key2).unwrap();
+    assert_eq!(
+        ecs.move_all_comps(entity_key0, entity_key2),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(
+        ecs.move_all_comps(entity_key2, entity_key0),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+
+    // neither does a target at a component limit
+    ecs.set_comp_limit::<i32>(1).unwrap();
+    assert_eq!(
+        ecs.move_all_comps(entity_key0, entity_key1),
+        Err(ecs_tiny::Error::QuotaExceeded)
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<u32>(entity_key0).unwrap().count(),
+        2
+    );
+
+    ecs.set_comp_limit::<i32>(2).unwrap();
+    assert_eq!(ecs.move_all_comps(entity_key0, entity_key0), Ok(0));
+    assert_eq!(ecs.move_all_comps(entity_key0, entity_key1), Ok(3));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key1)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&4, &1]
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<u32>(entity_key1)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&2, &1]
+    );
+
+    // the taken component stays with its entity
+    ecs.put_back(taken).unwrap();
+    assert_eq!(ecs.get_entity_by_comp(taken_key), Ok(entity_key0));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn clone_entity() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register_cloneable::<String>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let partition = ecs.create_partition("prefabs");
+    let entity_key = ecs.insert_entity_in(partition).unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key, 1).unwrap();
+    ecs.insert_comp(entity_key, 2).unwrap();
+    ecs.insert_comp(entity_key, "a".to_string()).unwrap();
+
+    let clone_key = ecs.clone_entity(entity_key).unwrap();
+    assert_ne!(clone_key, entity_key);
+    assert_eq!(ecs.entity_partition(clone_key), Ok(partition));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(clone_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&1, &2]
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<String>(clone_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec!["a"]
+    );
+
+    // the clone has fresh keys, and the source is untouched
+    let clone_comp_key = ecs
+        .iter_comp_with_key_by_entity::<i32>(clone_key)
+        .unwrap()
+        .next()
+        .unwrap()
+        .0;
+    assert_ne!(clone_comp_key, comp_key0);
+    *ecs.get_comp_mut::<i32>(clone_comp_key).unwrap() += 10;
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&1));
+
+    // a component without clone support fails before inserting anything
+    ecs.insert_comp(entity_key, 3u32).unwrap();
+    let entity_count = ecs.entity_count();
+    assert_eq!(
+        ecs.clone_entity(entity_key),
+        Err(ecs_tiny::CloneError::NotCloneable { type_name: "u32" })
+    );
+    assert_eq!(ecs.entity_count(), entity_count);
+
+    // a rejected clone removes the new entity
+    ecs.set_comp_limit_total::<String>(1).unwrap();
+    ecs.remove_comps_by_entity::<u32>(entity_key).unwrap();
+    assert!(matches!(
+        ecs.clone_entity(entity_key),
+        Err(ecs_tiny::CloneError::Rejected(_))
+    ));
+    assert_eq!(ecs.entity_count(), entity_count);
+
+    ecs.remove_entity(entity_key).unwrap();
+    assert_eq!(
+        ecs.clone_entity(entity_key),
+        Err(ecs_tiny::CloneError::EntityNotFound)
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn insert_bundle() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register::<String>().unwrap();
+
+    let entity_key = ecs.insert_entity();
+    let (comp_key0, comp_key1, comp_key2) = ecs
+        .insert_bundle(entity_key, (1, 2u32, "a".to_string()))
+        .unwrap();
+    assert_eq!(ecs.get_comp(comp_key0), Ok(&1));
+    assert_eq!(ecs.get_comp(comp_key1), Ok(&2));
+    assert_eq!(ecs.get_comp(comp_key2).map(String::as_str), Ok("a"));
+
+    // a rejected component fails the whole bundle
+    ecs.set_comp_limit::<String>(1).unwrap();
+    assert_eq!(
+        ecs.insert_bundle(entity_key, (3, 4u32, "b".to_string())),
+        Err(ecs_tiny::Error::QuotaExceeded)
+    );
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key), Ok(1));
+    assert_eq!(ecs.count_comp_by_entity::<u32>(entity_key), Ok(1));
+
+    assert_eq!(
+        ecs.remove_bundle_strict::<(i32, u32, String)>(entity_key),
+        Ok((1, 2, "a".to_string()))
+    );
+
+    ecs.remove_entity(entity_key).unwrap();
+    assert!(matches!(
+        ecs.insert_bundle(entity_key, (5,)),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+}
+
+#[test]
+fn insert_bundle_with_hook() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.set_comp_limit::<u32>(1).unwrap();
+    ecs.set_insert_hook::<i32>(|ctx, entity_key, _| ctx.insert_comp(entity_key, 0u32))
+        .unwrap();
+    let entity_key = ecs.insert_entity();
+
+    // the hook of the first component runs after the second one is in, so the bundle is not torn
+    let (comp_key0, comp_key1) = ecs.insert_bundle(entity_key, (1, 2u32)).unwrap();
+    assert_eq!(ecs.get_comp(comp_key0), Ok(&1));
+    assert_eq!(ecs.get_comp(comp_key1), Ok(&2));
+    assert_eq!(ecs.count_comp_by_entity::<u32>(entity_key), Ok(1));
+    assert_eq!(ecs.take_hook_errors(), vec![ecs_tiny::Error::QuotaExceeded]);
+
+    // the hooks still run for each component of a bundle
+    let entity_key = ecs.insert_entity();
+    ecs.insert_bundle(entity_key, (3,)).unwrap();
+    assert_eq!(
+        ecs.iter_comp_by_entity::<u32>(entity_key)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&0]
+    );
+    ecs.validate().unwrap();
+}
+
+#[test]
+#[should_panic(expected = "each component type can appear only once in a bundle")]
+fn insert_bundle_with_duplicate_type() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let entity_key = ecs.insert_entity();
+
+    let _ = ecs.insert_bundle(entity_key, (1, 2));
+}
+
+#[test]
+fn spawn() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_journal();
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register_cloneable::<u32>().unwrap();
+
+    let entity_key = ecs.spawn((1, 2u32)).unwrap();
+    assert_eq!(
+        ecs.query_collect_owned::<(&i32, &u32)>().unwrap(),
+        vec![(entity_key, (1, 2))]
+    );
+
+    // a failed spawn leaks no entity
+    assert_eq!(
+        ecs.spawn((3, 4u32, 5u64)),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    ecs.set_comp_limit_total::<u32>(1).unwrap();
+    assert_eq!(ecs.spawn((3, 4u32)), Err(ecs_tiny::Error::QuotaExceeded));
+    assert_eq!(ecs.entity_count(), 1);
+    assert_eq!(ecs.count_comp::<i32>(), Ok(1));
+
+    let replayed = ecs_tiny::ECS::replay(&ecs.take_journal()).unwrap();
+    assert_eq!(replayed.entity_count(), 1);
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn build_entity() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let builder = ecs.build_entity().with(1).with(2).with(3u32);
+    assert!(builder.error().is_none());
+    let entity_key = builder.entity_key();
+    let comp_keys = builder.comp_keys().to_vec();
+    assert_eq!(builder.finish(), Ok(entity_key));
+    assert_eq!(comp_keys.len(), 3);
+    assert_eq!(ecs.get_comp::<i32>(comp_keys[1]), Ok(&2));
+    assert_eq!(ecs.get_entity_by_comp(comp_keys[2]), Ok(entity_key));
+
+    // the first failure is reported, and the components after it are dropped
+    ecs.set_comp_limit::<i32>(1).unwrap();
+    let builder = ecs.build_entity().with(4).with(5).with(6u32).with(7u64);
+    assert_eq!(builder.error(), Some(&ecs_tiny::Error::QuotaExceeded));
+    assert_eq!(builder.comp_keys().len(), 1);
+    let failed_key = builder.entity_key();
+    assert_eq!(builder.finish(), Err(ecs_tiny::Error::QuotaExceeded));
+    assert!(ecs.get_entity(failed_key).is_err());
+    assert_eq!(ecs.count_comp::<i32>(), Ok(2));
+    assert_eq!(ecs.count_comp::<u32>(), Ok(1));
+
+    // a dropped builder keeps what was inserted
+    let builder = ecs.build_entity().with(8).with(9u64);
+    let dropped_key = builder.entity_key();
+    drop(builder);
+    assert_eq!(ecs.count_comp_by_entity::<i32>(dropped_key), Ok(1));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn entity_handles() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+
+    let mut entity = ecs.entity_mut(entity_key0).unwrap();
+    assert_eq!(entity.entity_key(), entity_key0);
+    let comp_key = entity.insert(1).unwrap();
+    entity.insert(2).unwrap();
+    entity.insert(3u32).unwrap();
+    assert_eq!(entity.insert(4u64), Err(ecs_tiny::Error::TypeNotRegistered));
+    for comp in entity.iter_mut::<i32>().unwrap() {
+        *comp *= 10;
+    }
+    assert_eq!(
+        entity.iter::<i32>().unwrap().collect::<Vec<_>>(),
+        vec![&10, &20]
+    );
+    assert_eq!(entity.as_ref().get::<u32>(), Ok(&3));
+    assert_eq!(entity.remove::<i32>(), Ok(10));
+    assert_eq!(entity.remove::<u64>(), Err(ecs_tiny::Error::CompNotFound));
+    assert!(entity.has::<i32>());
+    assert_eq!(
+        ecs.get_comp::<i32>(comp_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+
+    let entity = ecs.entity(entity_key0).unwrap();
+    assert_eq!(entity.get::<i32>(), Ok(&20));
+    assert!(entity.iter::<u64>().is_err());
+    let other = ecs.entity(entity_key1).unwrap();
+    assert!(!other.has::<i32>());
+    assert_eq!(other.get::<i32>(), Err(ecs_tiny::Error::CompNotFound));
+
+    ecs.entity_mut(entity_key0).unwrap().despawn();
+    assert!(ecs.entity(entity_key0).is_err());
+    assert!(ecs.entity_mut(entity_key0).is_err());
+    assert_eq!(ecs.count_comp::<i32>(), Ok(0));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn get_or_insert_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register_immutable::<u32>().unwrap();
+
+    assert_eq!(ecs.get_or_insert_comp::<i32>(entity_key), Ok(&mut 0));
+    assert_eq!(ecs.get_or_insert_comp_with(entity_key, || 42), Ok(&mut 0));
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key), Ok(1));
+
+    // the closure runs only on insertion
+    let mut called = false;
+    ecs.get_or_insert_comp_with(entity_key, || {
+        called = true;
+        1
+    })
+    .unwrap();
+    assert!(!called);
+
+    // a taken component is not replaced
+    let comp_key = ecs
+        .iter_comp_with_key_by_entity::<i32>(entity_key)
+        .unwrap()
+        .next()
+        .unwrap()
+        .0;
+    let taken = ecs.take_comp::<i32>(comp_key).unwrap();
+    assert_eq!(
+        ecs.get_or_insert_comp::<i32>(entity_key),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    ecs.put_back(taken).unwrap();
+
+    assert_eq!(
+        ecs.get_or_insert_comp::<u32>(entity_key),
+        Err(ecs_tiny::Error::ImmutableType)
+    );
+    assert_eq!(ecs.count_comp::<u32>(), Ok(0));
+    assert_eq!(
+        ecs.get_or_insert_comp::<u64>(entity_key),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+
+    ecs.remove_entity(entity_key).unwrap();
+    assert_eq!(
+        ecs.get_or_insert_comp::<i32>(entity_key),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(ecs.count_comp::<i32>(), Ok(0));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn set_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+
+    // the replace path keeps the component key
+    assert_eq!(ecs.set_comp(entity_key0, 1), Ok(None));
+    let comp_key = ecs
+        .iter_comp_with_key_by_entity::<i32>(entity_key0)
+        .unwrap()
+        .next()
+        .unwrap()
+        .0;
+    assert_eq!(ecs.set_comp(entity_key0, 2), Ok(Some(1)));
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&2));
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key0), Ok(1));
+    assert_eq!(
+        ecs.get_comp_by_entity::<i32>(entity_key1),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.set_comp(entity_key1, 3), Ok(None));
+    assert_eq!(ecs.get_comp_by_entity::<i32>(entity_key1), Ok(&3));
+
+    // several components break the uniqueness, so nothing is changed
+    ecs.insert_comp(entity_key1, 4).unwrap();
+    assert_eq!(
+        ecs.set_comp(entity_key1, 5),
+        Err(ecs_tiny::Error::AmbiguousComp)
+    );
+    assert_eq!(
+        ecs.get_comp_by_entity::<i32>(entity_key1),
+        Err(ecs_tiny::Error::AmbiguousComp)
+    );
+    assert_eq!(
+        ecs.remove_comp_by_entity::<i32>(entity_key1),
+        Err(ecs_tiny::Error::AmbiguousComp)
+    );
+    assert_eq!(ecs.count_comp_by_entity::<i32>(entity_key1), Ok(2));
+
+    assert_eq!(
+        ecs.set_comp(entity_key0, 1u64),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.get_comp_by_entity::<u64>(entity_key0),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+
+    // removing the entity removes its component
+    ecs.remove_entity(entity_key0).unwrap();
+    assert_eq!(
+        ecs.get_comp::<i32>(comp_key),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(
+        ecs.get_comp_by_entity::<i32>(entity_key0),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(
+        ecs.set_comp(entity_key0, 6),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(
+        ecs.remove_comp_by_entity::<i32>(entity_key0),
+        Err(ecs_tiny::Error::EntityNotFound(
+            ecs_tiny::EntityStatus::Removed {
+                current_generation: 1
+            }
+        ))
+    );
+    assert_eq!(ecs.count_comp::<i32>(), Ok(2));
+
+    let entity_key2 = ecs.insert_entity();
+    assert_eq!(ecs.set_comp(entity_key2, 7), Ok(None));
+    assert_eq!(ecs.remove_comp_by_entity::<i32>(entity_key2), Ok(7));
+    assert_eq!(
+        ecs.get_comp_by_entity::<i32>(entity_key2),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn replace_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register_immutable::<u32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key, 3u32).unwrap();
+
+    // the key and the order within the entity are kept
+    assert_eq!(ecs.replace_comp::<i32>(comp_key0, 10), Ok(1));
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&10));
+    assert_eq!(ecs.get_entity_by_comp(comp_key0), Ok(entity_key));
+    let comps = ecs
+        .iter_comp_by_entity::<i32>(entity_key)
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(comps, vec![&10, &2]);
+
+    assert_eq!(
+        ecs.replace_comp::<u32>(comp_key1, 20),
+        Err(ecs_tiny::Error::TypeMismatch)
+    );
+    assert_eq!(
+        ecs.replace_comp::<u32>(comp_key2, 30),
+        Err(ecs_tiny::Error::ImmutableType)
+    );
+    assert_eq!(ecs.get_comp::<u32>(comp_key2), Ok(&3));
+
+    ecs.remove_comp::<i32>(comp_key1).unwrap();
+    assert_eq!(
+        ecs.replace_comp::<i32>(comp_key1, 20),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn get_many_comp_mut() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register_chunked::<u32>(2).unwrap();
+    ecs.register_immutable::<u64>().unwrap();
+
+    let comp_keys = (0..5)
+        .map(|i| ecs.insert_comp(entity_key, i).unwrap())
+        .collect::<Vec<_>>();
+    let [comp0, comp1, comp2] = ecs
+        .get_many_comp_mut::<i32, 3>([comp_keys[4], comp_keys[0], comp_keys[2]])
+        .unwrap();
+    assert_eq!((*comp0, *comp1, *comp2), (4, 0, 2));
+    *comp0 += 10;
+    assert_eq!(ecs.get_comp::<i32>(comp_keys[4]), Ok(&14));
+    assert!(ecs
+        .get_many_comp_mut::<i32, 3>([comp_keys[1], comp_keys[3], comp_keys[1]])
+        .is_err());
+
+    // the chunked rows span several chunks
+    let comp_keys = (0..5u32)
+        .map(|i| ecs.insert_comp(entity_key, i).unwrap())
+        .collect::<Vec<_>>();
+    let [comp0, comp1, comp2] = ecs
+        .get_many_comp_mut::<u32, 3>([comp_keys[3], comp_keys[2], comp_keys[4]])
+        .unwrap();
+    assert_eq!((*comp0, *comp1, *comp2), (3, 2, 4));
+    assert!(ecs
+        .get_many_comp_mut::<u32, 2>([comp_keys[0], comp_keys[0]])
+        .is_err());
+    ecs.remove_comp::<u32>(comp_keys[1]).unwrap();
+    assert!(ecs
+        .get_many_comp_mut::<u32, 2>([comp_keys[0], comp_keys[1]])
+        .is_err());
+    assert!(ecs
+        .get_many_comp_mut::<i32, 2>([comp_keys[0], comp_keys[2]])
+        .is_err());
+
+    let comp_key = ecs.insert_comp(entity_key, 1u64).unwrap();
+    assert!(ecs.get_many_comp_mut::<u64, 1>([comp_key]).is_err());
+    let i32_key = ecs
+        .iter_comp_with_key_by_entity::<i32>(entity_key)
+        .unwrap()
+        .next()
+        .unwrap()
+        .0;
+    assert!(ecs
+        .get_comp_mut_pair::<i32, u64>(i32_key, comp_key)
+        .is_err());
+
+    let (comp_a, comp_b) = ecs
+        .get_comp_mut_pair::<i32, u32>(i32_key, comp_keys[0])
+        .unwrap();
+    *comp_a = 100;
+    *comp_b = 200;
+    assert_eq!(ecs.get_comp::<i32>(i32_key), Ok(&100));
+    assert_eq!(ecs.get_comp::<u32>(comp_keys[0]), Ok(&200));
+    assert!(ecs
+        .get_comp_mut_pair::<u32, i32>(i32_key, comp_keys[0])
+        .is_err());
+
+    // the same type needs distinct keys
+    assert!(ecs
+        .get_comp_mut_pair::<u32, u32>(comp_keys[0], comp_keys[0])
+        .is_err());
+    let (comp_a, comp_b) = ecs
+        .get_comp_mut_pair::<u32, u32>(comp_keys[0], comp_keys[4])
+        .unwrap();
+    std::mem::swap(comp_a, comp_b);
+    assert_eq!(ecs.get_comp::<u32>(comp_keys[0]), Ok(&4));
+    assert_eq!(ecs.validate(), Ok(()));
+}
+
+#[test]
+fn columns_mut() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.register_chunked::<u64>(2).unwrap();
+    ecs.register_immutable::<i64>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 2).unwrap();
+    ecs.insert_comp(entity_key1, 10u32).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 20u32).unwrap();
+    ecs.insert_comp(entity_key0, 100u64).unwrap();
+
+    let (mut ints, mut uints, mut longs) = ecs.columns_mut::<(i32, u32, u64)>().unwrap();
+    assert_eq!(ints.get_comp(comp_key0), Ok(&1));
+    assert_eq!(ints.get_comp(comp_key2), Err(ecs_tiny::Error::TypeMismatch));
+    *uints.get_comp_mut(comp_key2).unwrap() += 1;
+    assert_eq!(uints.get_by_entity(entity_key1), Ok(&10));
+    assert_eq!(
+        uints.get_by_entity(entity_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    for comp in ints.iter_mut() {
+        *comp += *longs.get_by_entity(entity_key0).unwrap() as i32;
+    }
+    *longs.get_by_entity_mut(entity_key0).unwrap() = 0;
+    assert_eq!(uints.iter().collect::<Vec<_>>(), vec![&10, &21]);
+
+    assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&102));
+    assert_eq!(
+        ecs.iter_comp::<u64>().unwrap().collect::<Vec<_>>(),
+        vec![&0]
+    );
+
+    // a removed component is not found through its stale key
+    ecs.remove_comp::<i32>(comp_key0).unwrap();
+    ecs.insert_comp(entity_key0, 3).unwrap();
+    let (ints, _) = ecs.columns_mut::<(i32, u32)>().unwrap();
+    assert_eq!(ints.get_comp(comp_key0), Err(ecs_tiny::Error::CompNotFound));
+    assert_eq!(ints.get_by_entity(entity_key0), Ok(&3));
+
+    assert_eq!(
+        ecs.columns_mut::<(i32, u32, i32)>().err(),
+        Some(ecs_tiny::Error::DuplicateType)
+    );
+    assert_eq!(
+        ecs.columns_mut::<(i32, f32)>().err(),
+        Some(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.columns_mut::<(i32, i64)>().err(),
+        Some(ecs_tiny::Error::ImmutableType)
+    );
+}
+
+#[test]
+fn for_each_comp_mut_chunked() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i64>().unwrap();
+    ecs.register_immutable::<u32>().unwrap();
+    let entity_keys = (0..10_000)
+        .map(|i| {
+            let entity_key = ecs.insert_entity();
+            ecs.insert_comp(entity_key, i as i64).unwrap();
+            entity_key
+        })
+        .collect::<Vec<_>>();
+
+    // vacant rows in the middle of the column are skipped
+    for entity_key in entity_keys.iter().step_by(7) {
+        ecs.remove_entity(*entity_key).unwrap();
+    }
+
+    let serial = ecs
+        .iter_comp::<i64>()
+        .unwrap()
+        .map(|comp| comp * 3 + 1)
+        .collect::<Vec<_>>();
+    let visits = std::sync::atomic::AtomicUsize::new(0);
+    ecs.for_each_comp_mut_chunked::<i64>(256, |entity_key, comp| {
+        assert!(entity_keys.contains(&entity_key));
+        visits.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        *comp = *comp * 3 + 1;
+    })
+    .unwrap();
+    assert_eq!(visits.into_inner(), serial.len());
+    assert_eq!(
+        ecs.iter_comp::<i64>().unwrap().copied().collect::<Vec<_>>(),
+        serial
+    );
+
+    // a column within one chunk runs on the calling thread
+    let caller = std::thread::current().id();
+    ecs.for_each_comp_mut_chunked::<i64>(serial.len(), |_, _| {
+        assert_eq!(std::thread::current().id(), caller);
+    })
+    .unwrap();
+
+    assert_eq!(
+        ecs.for_each_comp_mut_chunked::<u32>(1, |_, _| {}),
+        Err(ecs_tiny::Error::ImmutableType)
+    );
+    assert_eq!(
+        ecs.for_each_comp_mut_chunked::<f32>(1, |_, _| {}),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+}
+
+#[test]
+fn registered_type_names() {
+    struct Position;
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<u32>().unwrap();
+    ecs.register_named::<Position>("my_game::Position").unwrap();
+    ecs.insert_comp(entity_key, Position).unwrap();
+    ecs.insert_comp(entity_key, Position).unwrap();
+
+    let type_key = std::any::TypeId::of::<Position>();
+    assert_eq!(ecs.type_name_of(type_key), Ok("my_game::Position"));
+    assert_eq!(ecs.type_id_of_name("my_game::Position"), Ok(type_key));
+    assert_eq!(ecs.type_name_of(std::any::TypeId::of::<u32>()), Ok("u32"));
+    assert_eq!(
+        ecs.type_name_of(std::any::TypeId::of::<i32>()),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.type_id_of_name(std::any::type_name::<Position>()),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.register_named::<i32>("my_game::Position").err(),
+        Some(ecs_tiny::Error::DuplicateTypeName("my_game::Position"))
+    );
+
+    // the first registration under a shared name wins
+    ecs.allow_duplicate_names(true);
+    ecs.register_named::<i32>("my_game::Position").unwrap();
+    assert_eq!(ecs.type_id_of_name("my_game::Position"), Ok(type_key));
+
+    ecs.unregister::<u32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    assert_eq!(
+        ecs.iter_registered_types().collect::<Vec<_>>(),
+        vec![
+            (type_key, "my_game::Position", 2),
+            (std::any::TypeId::of::<i32>(), "my_game::Position", 0),
+            (std::any::TypeId::of::<u32>(), "u32", 0),
+        ]
+    );
+}
+
+#[test]
+fn snapshot_restore() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 2).unwrap();
+    ecs.insert_comp(entity_key0, 3).unwrap();
+
+    let snapshot = ecs.snapshot().unwrap();
+
+    *ecs.get_comp_mut::<i32>(comp_key0).unwrap() = 10;
+    ecs.remove_entity(entity_key1).unwrap();
+    let entity_key2 = ecs.insert_entity();
+    let comp_key3 = ecs.insert_comp(entity_key2, 4).unwrap();
+    ecs.insert_comp(entity_key0, 5u32).unwrap();
+    ecs.end_frame();
+
+    ecs.restore(&snapshot).unwrap();
+    assert_eq!(ecs.frame(), 0);
+    assert_eq!(
+        ecs.iter_entity().collect::<Vec<_>>(),
+        vec![entity_key0, entity_key1]
+    );
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&1));
+    assert_eq!(ecs.get_comp::<i32>(comp_key1), Ok(&2));
+    assert_eq!(ecs.get_entity_by_comp(comp_key1), Ok(entity_key1));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&1, &3]
+    );
+    assert!(ecs.get_comp::<i32>(comp_key3).is_err());
+    assert_eq!(ecs.iter_comp::<u32>().unwrap().count(), 0);
+    ecs.validate().unwrap();
+
+    // replaying the same operations hands out the same keys
+    ecs.remove_entity(entity_key1).unwrap();
+    assert_eq!(ecs.insert_entity(), entity_key2);
+    assert_eq!(ecs.insert_comp(entity_key2, 4), Ok(comp_key3));
+
+    // a world holding components without clone support cannot be snapshot
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 7u32).unwrap();
+    assert!(matches!(
+        ecs.snapshot(),
+        Err(ecs_tiny::CloneError::NotCloneable { .. })
+    ));
+
+    let mut other = ecs_tiny::ECS::new();
+    assert_eq!(other.restore(&snapshot), Err(ecs_tiny::Error::WrongWorld));
+}
+
+#[test]
+fn merge() {
+    let mut chunk = ecs_tiny::ECS::new();
+    chunk.register::<i32>().unwrap();
+    chunk.register::<u32>().unwrap();
+    let entity_key0 = chunk.insert_entity();
+    let entity_key1 = chunk.insert_entity();
+    let comp_key0 = chunk.insert_comp(entity_key0, 1u32).unwrap();
+    let comp_key1 = chunk.insert_comp(entity_key0, 2).unwrap();
+    let comp_key2 = chunk.insert_comp(entity_key0, 3).unwrap();
+    let comp_key3 = chunk.insert_comp(entity_key1, 4).unwrap();
+    let taken = chunk.take_comp::<i32>(comp_key3).unwrap();
+
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register::<i32>().unwrap();
+    let old = ecs.insert_entity();
+    ecs.insert_comp(old, 0).unwrap();
+
+    let mut unregistered = ecs_tiny::ECS::new();
+    unregistered.register::<i32>().unwrap();
+    unregistered.register::<u64>().unwrap();
+    unregistered.register::<u32>().unwrap();
+    let entity_key = unregistered.insert_entity();
+    unregistered.insert_comp(entity_key, 5u64).unwrap();
+    unregistered.insert_comp(entity_key, 6u32).unwrap();
+    let Err(ecs_tiny::MergeError::TypesNotRegistered { types }) = ecs.merge(unregistered) else {
+        panic!("merged");
+    };
+    assert_eq!(
+        types,
+        vec![
+            (std::any::TypeId::of::<u32>(), "u32"),
+            (std::any::TypeId::of::<u64>(), "u64"),
+        ]
+    );
+    assert_eq!(ecs.iter_entity().count(), 1);
+
+    ecs.register::<u32>().unwrap();
+    let report = ecs.merge(chunk).unwrap();
+    drop(taken);
+
+    assert_eq!(report.entities.len(), 2);
+    assert_eq!(report.comps.len(), 3);
+    let new_entity_key0 = report.entities[&entity_key0];
+    assert_ne!(new_entity_key0, old);
+    assert_eq!(ecs.get_comp::<u32>(report.comps[&comp_key0]), Ok(&1));
+    assert_eq!(
+        ecs.get_entity_by_comp(report.comps[&comp_key2]),
+        Ok(new_entity_key0)
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(new_entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&2, &3]
+    );
+    assert_eq!(ecs.get_comp::<i32>(report.comps[&comp_key1]), Ok(&2));
+    assert!(!report.comps.contains_key(&comp_key3));
+    assert_eq!(
+        ecs.count_comp_by_entity::<i32>(report.entities[&entity_key1]),
+        Ok(0)
+    );
+    ecs.validate().unwrap();
+
+    // a rejected component stops the merge at its entity
+    let mut chunk = ecs_tiny::ECS::new();
+    chunk.register::<i32>().unwrap();
+    let entity_key0 = chunk.insert_entity();
+    let entity_key1 = chunk.insert_entity();
+    chunk.insert_comp(entity_key0, 7).unwrap();
+    chunk.insert_comp(entity_key1, -7).unwrap();
+    ecs.set_insert_validator::<i32>(|_, _, comp| {
+        if *comp > 0 {
+            Ok(())
+        } else {
+            Err("must be positive".to_string())
+        }
+    })
+    .unwrap();
+    let Err(ecs_tiny::MergeError::Rejected {
+        entity_key, report, ..
+    }) = ecs.merge(chunk)
+    else {
+        panic!("merged");
+    };
+    assert_eq!(entity_key, entity_key1);
+    assert_eq!(report.entities.len(), 1);
+    assert_eq!(ecs.iter_entity().count(), 4);
+}
+
+#[test]
+fn merge_with_hasher() {
+    let hasher = std::collections::hash_map::RandomState::new();
+
+    // worlds of the same custom hasher merge as the default ones do
+    let mut chunk = ecs_tiny::ECS::with_hasher(hasher.clone());
+    chunk.register::<i32>().unwrap();
+    let entity_key = chunk.insert_entity();
+    let comp_key = chunk.insert_comp(entity_key, 42).unwrap();
+
+    let mut ecs = ecs_tiny::ECS::with_hasher(hasher);
+    ecs.register::<i32>().unwrap();
+    ecs.insert_entity();
+    let report = ecs.merge(chunk).unwrap();
+
+    let comp_key = report.comps[&comp_key];
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&42));
+    assert_eq!(
+        ecs.get_entity_by_comp(comp_key),
+        Ok(report.entities[&entity_key])
+    );
+    assert_eq!(ecs.iter_entity().count(), 2);
+    ecs.validate().unwrap();
+}
+
+#[test]
+fn diff_worlds() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register_cloneable::<u32>().unwrap();
+    ecs.set_eq::<i32>().unwrap();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 3).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key1, 4u32).unwrap();
+
+    let mut peer = ecs.clone_filtered(|_| true).unwrap();
+    let diff = ecs.diff(&peer);
+    assert!(diff.is_empty());
+    assert_eq!(diff.unchecked_types, vec!["u32"]);
+
+    // the peer diverges
+    *peer.get_comp_mut::<i32>(comp_key1).unwrap() = 20;
+    peer.remove_comp::<i32>(comp_key0).unwrap();
+    peer.move_comp(comp_key2, entity_key0).unwrap();
+    let entity_key2 = peer.insert_entity();
+    *peer.get_comp_mut::<u32>(comp_key3).unwrap() = 40;
+
+    let diff = ecs.diff(&peer);
+    assert_eq!(diff.only_in_self, vec![]);
+    assert_eq!(diff.only_in_other, vec![entity_key2]);
+    assert_eq!(
+        diff.comps
+            .iter()
+            .map(|comp_diff| (comp_diff.entity_key, comp_diff.comp_key, comp_diff.kind))
+            .collect::<Vec<_>>(),
+        vec![
+            (entity_key0, comp_key0, ecs_tiny::CompDiffKind::OnlyInSelf),
+            (entity_key0, comp_key1, ecs_tiny::CompDiffKind::ValueDiffers),
+            (entity_key1, comp_key2, ecs_tiny::CompDiffKind::OwnerDiffers),
+        ]
+    );
+    assert!(diff
+        .comps
+        .iter()
+        .all(|comp_diff| comp_diff.type_name == "i32"));
+    assert_eq!(peer.diff(&ecs).only_in_self, vec![entity_key2]);
+}
+
+#[test]
+fn send_sync() {
+    fn assert_send_sync<T>()
+    where
+        T: Send + Sync,
+    {
+    }
+    assert_send_sync::<ecs_tiny::ECS>();
+    assert_send_sync::<ecs_tiny::SharedECS>();
+    assert_send_sync::<ecs_tiny::Snapshot>();
+    assert_send_sync::<ecs_tiny::WorldOp>();
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<String>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 0).unwrap();
+
+    let shared = ecs_tiny::SharedECS::new(ecs);
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    shared.write(|ecs| *ecs.get_comp_mut::<i32>(comp_key).unwrap() += 1);
+                }
+            });
+        }
+        scope.spawn(|| {
+            let comp = shared.read(|ecs| *ecs.get_comp::<i32>(comp_key).unwrap());
+            assert!((0..=400).contains(&comp));
+        });
+    });
+
+    let ecs = shared.into_inner();
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&400));
+
+    // the world moves to another thread with its components
+    let ecs = std::thread::spawn(move || {
+        let mut ecs = ecs;
+        ecs.insert_comp(entity_key, "moved".to_string()).unwrap();
+        ecs
+    })
+    .join()
+    .unwrap();
+    assert_eq!(ecs.iter_comp::<String>().unwrap().count(), 1);
+}
+
+#[test]
+fn change_ticks() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 3u32).unwrap();
+
+    let added = |ecs: &ecs_tiny::ECS, tick| {
+        ecs.iter_comp_added::<i32>(tick)
+            .unwrap()
+            .map(|(comp_key, _)| comp_key)
+            .collect::<Vec<_>>()
+    };
+    let changed = |ecs: &ecs_tiny::ECS, tick| {
+        ecs.iter_comp_changed::<i32>(tick)
+            .unwrap()
+            .map(|(comp_key, _)| comp_key)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(changed(&ecs, 0), vec![comp_key0, comp_key1]);
+
+    // read-only access does not mark changes
+    let tick1 = ecs.advance_tick();
+    assert_eq!(ecs.iter_comp::<i32>().unwrap().sum::<i32>(), 3);
+    assert_eq!(ecs.get_comp::<i32>(comp_key0), Ok(&1));
+    assert_eq!(ecs.iter_comp_with_key::<i32>().unwrap().count(), 2);
+    assert_eq!(changed(&ecs, tick1), vec![]);
+
+    // mutable access marks the yielded components only
+    *ecs.get_comp_mut::<i32>(comp_key1).unwrap() += 1;
+    let tick2 = ecs.advance_tick();
+    ecs.iter_comp_mut::<i32>()
+        .unwrap()
+        .take(1)
+        .for_each(|comp| *comp += 1);
+    let comp_key3 = ecs.insert_comp(entity_key0, 4).unwrap();
+    assert_eq!(changed(&ecs, tick1), vec![comp_key0, comp_key1, comp_key3]);
+    assert_eq!(changed(&ecs, tick2), vec![comp_key0, comp_key3]);
+    assert_eq!(added(&ecs, tick1), vec![comp_key3]);
+    assert_eq!(added(&ecs, 0), vec![comp_key0, comp_key1, comp_key3]);
+
+    // the ticks survive removals of unrelated components and entities
+    let tick3 = ecs.advance_tick();
+    ecs.remove_comp::<u32>(comp_key2).unwrap();
+    ecs.remove_comp::<i32>(comp_key0).unwrap();
+    ecs.remove_entity(entity_key0).unwrap();
+    assert_eq!(changed(&ecs, tick1), vec![comp_key1]);
+    assert_eq!(changed(&ecs, tick2), vec![]);
+    assert_eq!(added(&ecs, 0), vec![comp_key1]);
+
+    // swapping, putting back after taking, and marking by hand count as changes
+    let comp_key4 = ecs.insert_comp(entity_key1, 5).unwrap();
+    let tick4 = ecs.advance_tick();
+    ecs.swap_comp::<i32>(comp_key1, comp_key4).unwrap();
+    assert_eq!(changed(&ecs, tick4), vec![comp_key1, comp_key4]);
+    let tick5 = ecs.advance_tick();
+    let taken = ecs.take_comp::<i32>(comp_key4).unwrap();
+    ecs.put_back(taken).unwrap();
+    assert_eq!(changed(&ecs, tick5), vec![comp_key4]);
+    assert_eq!(added(&ecs, tick4), vec![]);
+    assert_eq!(added(&ecs, tick3), vec![comp_key4]);
+    let tick6 = ecs.advance_tick();
+    ecs.mark_changed(comp_key1).unwrap();
+    assert_eq!(changed(&ecs, tick6), vec![comp_key1]);
+    assert_eq!(
+        ecs.mark_changed(comp_key0),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert!(ecs.iter_comp_changed::<u8>(0).is_err());
+}
+
+#[test]
+fn rejected_update_ticks() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    let comp_key = ecs.insert_comp(entity_key, 1).unwrap();
+
+    // a rejected update leaves the ticks untouched as well as the value
+    let tick = ecs.advance_tick();
+    assert_eq!(
+        ecs.try_update_comp::<i32, _>(comp_key, |comp| {
+            *comp = 2;
+            Err("no")
+        }),
+        Err(ecs_tiny::UpdateError::Rejected("no"))
+    );
+    assert_eq!(ecs.iter_comp_changed::<i32>(tick).unwrap().count(), 0);
+    assert_eq!(ecs.get_comp::<i32>(comp_key), Ok(&1));
+
+    // while an accepted one marks the component changed
+    ecs.try_update_comp::<i32, ()>(comp_key, |comp| {
+        *comp = 3;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(ecs.iter_comp_changed::<i32>(tick).unwrap().count(), 1);
+}
+
+#[test]
+fn view_ticks() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.insert_comp(entity_key, 1).unwrap();
+    ecs.insert_comp(entity_key, 1u32).unwrap();
+
+    // a write through a query marks the component changed
+    let tick = ecs.advance_tick();
+    for (_, (comp,)) in ecs.query::<(&mut i32,)>().unwrap() {
+        *comp += 1;
+    }
+    assert_eq!(ecs.iter_comp_changed::<i32>(tick).unwrap().count(), 1);
+    assert_eq!(ecs.iter_comp_changed::<u32>(tick).unwrap().count(), 0);
+
+    // as do the writes through a column
+    let tick = ecs.advance_tick();
+    for comp in ecs.column_mut::<i32>().unwrap().iter_mut() {
+        *comp += 1;
+    }
+    assert_eq!(ecs.iter_comp_changed::<i32>(tick).unwrap().count(), 1);
+
+    // a shard
+    let tick = ecs.advance_tick();
+    let (mut comps0, mut comps1, _) = ecs.shard::<i32, u32>().unwrap();
+    *comps0.iter_mut().next().unwrap() += 1;
+    *comps1.iter_mut().next().unwrap() += 1;
+    assert_eq!(ecs.iter_comp_changed::<i32>(tick).unwrap().count(), 1);
+    assert_eq!(ecs.iter_comp_changed::<u32>(tick).unwrap().count(), 1);
+
+    // and a locked column
+    let tick = ecs.advance_tick();
+    let locked = ecs.lock();
+    for comp in locked
+        .write_column::<u32>()
+        .unwrap()
+        .column_mut()
+        .iter_mut()
+    {
+        *comp += 1;
+    }
+    drop(locked);
+    assert_eq!(ecs.iter_comp_changed::<i32>(tick).unwrap().count(), 0);
+    assert_eq!(ecs.iter_comp_changed::<u32>(tick).unwrap().count(), 1);
+}
+
+#[test]
+fn removal_tracking() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register_cloneable::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    assert_eq!(
+        ecs.enable_removal_tracking::<u8>(8),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    ecs.enable_removal_tracking::<i32>(8).unwrap();
+    ecs.enable_removal_tracking::<u32>(2).unwrap();
+
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key1, 3).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key1, 4).unwrap();
+    let comp_key4 = ecs.insert_comp(entity_key1, 5u32).unwrap();
+
+    let removed = |ecs: &mut ecs_tiny::ECS| {
+        ecs.drain_removed::<i32>()
+            .into_iter()
+            .map(|removed| (removed.entity_key, removed.comp_key, removed.comp))
+            .collect::<Vec<_>>()
+    };
+
+    // removals one by one and by the cascade of the entity
+    ecs.remove_comp::<i32>(comp_key1).unwrap();
+    ecs.remove_entity(entity_key0).unwrap();
+    assert_eq!(
+        removed(&mut ecs),
+        vec![
+            (entity_key0, comp_key1, Some(2)),
+            (entity_key0, comp_key0, Some(1)),
+        ]
+    );
+    assert_eq!(removed(&mut ecs), vec![]);
+
+    // taken components are left in place by clear_comp, and tracked without value once released
+    let taken = ecs.take_comp::<i32>(comp_key3).unwrap();
+    assert_eq!(ecs.clear_comp::<i32>(), Ok(1));
+    assert_eq!(removed(&mut ecs), vec![(entity_key1, comp_key2, Some(3))]);
+    drop(taken);
+    ecs.end_frame();
+    assert_eq!(removed(&mut ecs), vec![(entity_key1, comp_key3, None)]);
+
+    // the values of types registered without clone support are not kept, and the oldest removals are dropped
+    let comp_key5 = ecs.insert_comp(entity_key1, 6u32).unwrap();
+    let comp_key6 = ecs.insert_comp(entity_key1, 7u32).unwrap();
+    ecs.remove_comp::<u32>(comp_key4).unwrap();
+    ecs.remove_comp::<u32>(comp_key5).unwrap();
+    ecs.remove_comp::<u32>(comp_key6).unwrap();
+    let removed = ecs.drain_removed::<u32>();
+    assert_eq!(
+        removed
+            .iter()
+            .map(|removed| (removed.comp_key, removed.comp))
+            .collect::<Vec<_>>(),
+        vec![(comp_key5, None), (comp_key6, None)]
+    );
+
+    // untracked removals are not kept
+    ecs.insert_comp(entity_key1, 8u32).unwrap();
+    ecs.disable_removal_tracking::<u32>().unwrap();
+    ecs.remove_entity(entity_key1).unwrap();
+    assert!(ecs.drain_removed::<u32>().is_empty());
+    assert!(ecs.drain_removed::<u8>().is_empty());
+}
+
+#[test]
+fn observers() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let observe = |tag: &'static str| {
+        let log = log.clone();
+        move |entity_key, comp_key, comp: &i32| {
+            log.lock().unwrap().push((tag, entity_key, comp_key, *comp))
+        }
+    };
+    let take_log = || std::mem::take(&mut *log.lock().unwrap());
+
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register::<u32>().unwrap();
+    assert!(ecs.on_insert::<u8>(|_, _, _| {}).is_err());
+
+    let insert0 = ecs.on_insert::<i32>(observe("insert0")).unwrap();
+    ecs.on_insert::<i32>(observe("insert1")).unwrap();
+    let remove = ecs.on_remove::<i32>(observe("remove")).unwrap();
+
+    let comp_key0 = ecs.insert_comp(entity_key0, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key0, 2).unwrap();
+    ecs.insert_comp(entity_key0, 3u32).unwrap();
+    assert_eq!(
+        take_log(),
+        vec![
+            ("insert0", entity_key0, comp_key0, 1),
+            ("insert1", entity_key0, comp_key0, 1),
+            ("insert0", entity_key0, comp_key1, 2),
+            ("insert1", entity_key0, comp_key1, 2),
+        ]
+    );
+
+    // removed handles are called no more
+    ecs.remove_observer(insert0).unwrap();
+    assert_eq!(
+        ecs.remove_observer(insert0),
+        Err(ecs_tiny::Error::ObserverNotFound)
+    );
+    let comp_key2 = ecs.insert_comp(entity_key1, 4).unwrap();
+    assert_eq!(take_log(), vec![("insert1", entity_key1, comp_key2, 4)]);
+
+    // removals one by one and by the cascade
+    ecs.remove_comp::<i32>(comp_key1).unwrap();
+    ecs.remove_entity(entity_key0).unwrap();
+    assert_eq!(
+        take_log(),
+        vec![
+            ("remove", entity_key0, comp_key1, 2),
+            ("remove", entity_key0, comp_key0, 1),
+        ]
+    );
+
+    // moving is not a removal, and taken components are removed unobserved
+    let entity_key2 = ecs.insert_entity();
+    let comp_key3 = ecs.insert_comp(entity_key2, 5).unwrap();
+    ecs.move_comp(comp_key3, entity_key1).unwrap();
+    let taken = ecs.take_comp::<i32>(comp_key2).unwrap();
+    ecs.clear_comp::<i32>().unwrap();
+    drop(taken);
+    ecs.end_frame();
+    assert_eq!(
+        take_log(),
+        vec![
+            ("insert1", entity_key2, comp_key3, 5),
+            ("remove", entity_key1, comp_key3, 5),
+        ]
+    );
+
+    // the components dropped with the column are removed too
+    let comp_key4 = ecs.insert_comp(entity_key1, 6).unwrap();
+    ecs.remove_observer(remove).unwrap();
+    ecs.on_remove::<i32>(observe("remove")).unwrap();
+    ecs.clear();
+    assert_eq!(
+        take_log(),
+        vec![
+            ("insert1", entity_key1, comp_key4, 6),
+            ("remove", entity_key1, comp_key4, 6),
+        ]
+    );
+}
+
+#[test]
+fn retain_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<u32>().unwrap();
+    ecs.register_immutable::<i32>().unwrap();
+    let comp_key0 = ecs.insert_comp(entity_key0, 1u32).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, 2u32).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key0, 3u32).unwrap();
+    let comp_key3 = ecs.insert_comp(entity_key0, 4u32).unwrap();
+    let comp_key4 = ecs.insert_comp(entity_key1, 5u32).unwrap();
+    ecs.insert_comp(entity_key0, 6).unwrap();
+
+    let mut seen = vec![];
+    let removed = ecs.retain_comp::<u32>(|entity_key, comp| {
+        seen.push((entity_key, *comp));
+        *comp != 3 && *comp != 5
+    });
+    assert_eq!(removed, Ok(2));
+    assert_eq!(
+        seen,
+        vec![
+            (entity_key0, 1),
+            (entity_key1, 2),
+            (entity_key0, 3),
+            (entity_key0, 4),
+            (entity_key1, 5),
+        ]
+    );
+
+    // the remaining components keep their order and owners
+    assert_eq!(
+        ecs.iter_comp::<u32>().unwrap().collect::<Vec<_>>(),
+        vec![&1, &2, &4]
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<u32>(entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&1, &4]
+    );
+    assert_eq!(ecs.get_entity_by_comp(comp_key3), Ok(entity_key0));
+    assert!(ecs.get_comp::<u32>(comp_key2).is_err());
+    assert!(ecs.get_comp::<u32>(comp_key4).is_err());
+    ecs.validate().unwrap();
+
+    // the mutable variant updates and removes in one pass, leaving taken components in place
+    let taken = ecs.take_comp::<u32>(comp_key0).unwrap();
+    let tick = ecs.advance_tick();
+    let removed = ecs.retain_comp_mut::<u32>(|_, comp| {
+        *comp -= 1;
+        *comp > 1
+    });
+    assert_eq!(removed, Ok(1));
+    assert_eq!(
+        ecs.iter_comp_changed::<u32>(tick)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![(comp_key3, &3)]
+    );
+    assert!(ecs.get_comp::<u32>(comp_key1).is_err());
+    ecs.put_back(taken).unwrap();
+    assert_eq!(ecs.get_comp::<u32>(comp_key0), Ok(&1));
+
+    assert_eq!(
+        ecs.retain_comp_mut::<i32>(|_, _| true),
+        Err(ecs_tiny::Error::ImmutableType)
+    );
+    assert_eq!(ecs.retain_comp::<i32>(|_, _| false), Ok(1));
+    assert_eq!(
+        ecs.retain_comp::<u8>(|_, _| true),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+}
+
+#[test]
+fn drain_comp() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<String>().unwrap();
+    ecs.register::<u32>().unwrap();
+    ecs.insert_comp(entity_key0, "a".to_string()).unwrap();
+    ecs.insert_comp(entity_key1, "b".to_string()).unwrap();
+    ecs.insert_comp(entity_key0, "c".to_string()).unwrap();
+    let comp_key = ecs.insert_comp(entity_key1, 1u32).unwrap();
+    assert!(ecs.drain_comp::<u8>().is_err());
+
+    let drained = ecs.drain_comp::<String>().unwrap().collect::<Vec<_>>();
+    assert_eq!(
+        drained,
+        vec![
+            (entity_key0, "a".to_string()),
+            (entity_key1, "b".to_string()),
+            (entity_key0, "c".to_string()),
+        ]
+    );
+    assert_eq!(ecs.iter_comp::<String>().unwrap().count(), 0);
+    assert!(!ecs.has_comp::<String>(entity_key0));
+    assert_eq!(ecs.get_comp::<u32>(comp_key), Ok(&1));
+
+    // a partially consumed drain removes the rest on drop, leaving taken components in place
+    let comp_key0 = ecs.insert_comp(entity_key0, "d".to_string()).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key1, "e".to_string()).unwrap();
+    ecs.insert_comp(entity_key1, "f".to_string()).unwrap();
+    let taken = ecs.take_comp::<String>(comp_key1).unwrap();
+    let mut drain = ecs.drain_comp::<String>().unwrap();
+    assert_eq!(drain.size_hint(), (2, Some(2)));
+    let (_, comp) = drain.next().unwrap();
+    assert!(comp == "d" || comp == "f");
+    drop(drain);
+    assert!(ecs.get_comp::<String>(comp_key0).is_err());
+    assert_eq!(ecs.count_comp::<String>(), Ok(0));
+    ecs.put_back(taken).unwrap();
+    assert_eq!(
+        ecs.iter_comp_by_entity::<String>(entity_key1)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&"e".to_string()]
+    );
+    ecs.validate().unwrap();
+}
+
+#[test]
+fn insert_comps() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.enable_lifecycle_events();
+    ecs.enable_journal();
+
+    let comp_keys = ecs.insert_comps(entity_key0, [1, 2, 3]).unwrap();
+    assert_eq!(
+        comp_keys
+            .iter()
+            .map(|comp_key| *ecs.get_comp::<i32>(*comp_key).unwrap())
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key0)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&1, &2, &3]
+    );
+    assert_eq!(
+        ecs.take_entity_lifecycle_events(),
+        vec![ecs_tiny::EntityLifecycle::FirstComponentAdded(entity_key0)]
+    );
+    assert_eq!(ecs.take_journal().len(), 3);
+    assert_eq!(ecs.insert_comps::<i32>(entity_key1, []), Ok(vec![]));
+    assert!(!ecs.has_comp::<i32>(entity_key1));
+    ecs.validate().unwrap();
+
+    assert_eq!(
+        ecs.insert_comps(entity_key0, [0u32]),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    ecs.remove_entity(entity_key1).unwrap();
+    assert!(matches!(
+        ecs.insert_comps(entity_key1, [0]),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+
+    // a checked type inserts one by one, removing the batch on failure
+    ecs.set_comp_limit::<i32>(4).unwrap();
+    assert_eq!(
+        ecs.insert_comps(entity_key0, [4, 5]),
+        Err(ecs_tiny::Error::QuotaExceeded)
+    );
+    assert_eq!(ecs.count_comp::<i32>(), Ok(3));
+    assert_eq!(ecs.insert_comps(entity_key0, [4]).unwrap().len(), 1);
+    ecs.validate().unwrap();
+}
+
+#[test]
+fn insert_comp_batch() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    let entity_key2 = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+
+    let comp_keys = ecs
+        .insert_comp_batch([(entity_key1, 0), (entity_key0, 1), (entity_key1, 2)])
+        .unwrap();
+    assert_eq!(ecs.get_comp::<i32>(comp_keys[1]), Ok(&1));
+    assert_eq!(ecs.get_entity_by_comp(comp_keys[2]), Ok(entity_key1));
+    assert_eq!(
+        ecs.iter_comp_by_entity::<i32>(entity_key1)
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![&0, &2]
+    );
+
+    // a failure removes the components of every entity inserted before it
+    ecs.remove_entity(entity_key2).unwrap();
+    assert!(matches!(
+        ecs.insert_comp_batch([(entity_key0, 3), (entity_key2, 4)]),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    assert_eq!(ecs.count_comp::<i32>(), Ok(3));
+    ecs.validate().unwrap();
+}
+
+#[test]
+fn insert_entities() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.reserve_entities(4);
+    let entity_keys = ecs.insert_entities(4);
+    assert_eq!(ecs.iter_entity().collect::<Vec<_>>(), entity_keys);
+    assert!(ecs.insert_entities(0).is_empty());
+
+    // freed slots are reused with new generations
+    ecs.remove_entity(entity_keys[1]).unwrap();
+    let reused = ecs.insert_entities(2);
+    assert_eq!(reused[0].index(), entity_keys[1].index());
+    assert_ne!(reused[0], entity_keys[1]);
+    assert!(ecs.get_entity(entity_keys[1]).is_err());
+    assert_eq!(ecs.iter_entity().count(), 5);
+    ecs.validate().unwrap();
+}
+
+#[test]
+fn shrink_to_fit() {
+    let mut ecs = ecs_tiny::ECS::with_capacity(8);
+    ecs.register_with_capacity::<i32>(8).unwrap();
+    ecs.reserve_comp::<i32>(8).unwrap();
+    assert!(ecs.reserve_comp::<u32>(8).is_err());
+
+    let entity_keys = ecs.insert_entities(8);
+    let comp_keys = entity_keys
+        .iter()
+        .map(|entity_key| {
+            ecs.insert_comp(*entity_key, entity_key.index() as i32)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    for entity_key in &entity_keys[2..] {
+        ecs.remove_entity(*entity_key).unwrap();
+    }
+    ecs.remove_entity(entity_keys[0]).unwrap();
+
+    // only the vacant slots after the last occupied one are dropped, so the keys stay put
+    ecs.shrink_to_fit();
+    assert_eq!(ecs.get_comp::<i32>(comp_keys[1]), Ok(&1));
+    assert_eq!(ecs.get_entity_by_comp(comp_keys[1]), Ok(entity_keys[1]));
+    ecs.validate().unwrap();
+
+    // the generations of the dropped slots are kept
+    let entity_keys_new = ecs.insert_entities(8);
+    for entity_key in &entity_keys_new {
+        ecs.insert_comp(*entity_key, -1).unwrap();
+    }
+    for (entity_key, comp_key) in entity_keys.iter().zip(&comp_keys) {
+        if *entity_key != entity_keys[1] {
+            assert!(ecs.get_entity(*entity_key).is_err());
+            assert!(ecs.get_comp::<i32>(*comp_key).is_err());
+        }
+    }
+    assert_eq!(ecs.iter_entity().count(), 9);
+    ecs.validate().unwrap();
+}
+
+#[test]
+fn ref_table_order() {
+    let mut ecs = ecs_tiny::ECS::new();
+    ecs.enable_undo(100);
+    let entity_key0 = ecs.insert_entity();
+    let entity_key1 = ecs.insert_entity();
+    ecs.register_cloneable::<u32>().unwrap();
+    ecs.register_ordered::<i32>().unwrap();
+
+    let comps = |ecs: &ecs_tiny::ECS, entity_key| {
+        ecs.iter_comp_by_entity::<i32>(entity_key)
+            .unwrap()
+            .copied()
+            .collect::<Vec<_>>()
+    };
+
+    // the unordered components keep their insertion order across removals in any order
+    let comp_keys = (0..6)
+        .map(|i| ecs.insert_comp(entity_key0, i as u32).unwrap())
+        .collect::<Vec<_>>();
+    ecs.remove_comp::<u32>(comp_keys[3]).unwrap();
+    ecs.remove_comp::<u32>(comp_keys[0]).unwrap();
+    ecs.remove_comp::<u32>(comp_keys[5]).unwrap();
+    ecs.insert_comp(entity_key0, 6u32).unwrap();
+    let unordered = ecs
+        .iter_comp_by_entity::<u32>(entity_key0)
+        .unwrap()
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(unordered, vec![1, 2, 4, 6]);
+    ecs.validate().unwrap();
+
+    // the ordered components keep their explicit order across removals and reorders
+    let comp_keys = (0..6)
+        .map(|i| ecs.insert_comp(entity_key0, i).unwrap())
+        .collect::<Vec<_>>();
+    ecs.reorder_comp::<i32>(entity_key0, comp_keys[5], 0)
+        .unwrap();
+    ecs.remove_comp::<i32>(comp_keys[2]).unwrap();
+    ecs.reorder_comp::<i32>(entity_key0, comp_keys[0], 100)
+        .unwrap();
+    ecs.insert_comp_at(entity_key0, 1, 6).unwrap();
+    ecs.remove_comp::<i32>(comp_keys[4]).unwrap();
+    assert_eq!(comps(&ecs, entity_key0), vec![5, 6, 1, 3, 0]);
+    ecs.validate().unwrap();
+
+    // the entries follow the rows moved by the compaction and by another owner
+    let remaining = ecs.compact_step::<i32>(16).unwrap();
+    assert!(matches!(remaining, ecs_tiny::CompactProgress::Done { .. }));
+    let remaps = ecs.take_remaps::<i32>().unwrap();
+    let comp_key3 = remaps.get(&comp_keys[3]).copied().unwrap_or(comp_keys[3]);
+    ecs.move_comp(comp_key3, entity_key1).unwrap();
+    assert_eq!(comps(&ecs, entity_key0), vec![5, 6, 1, 0]);
+    assert_eq!(comps(&ecs, entity_key1), vec![3]);
+    ecs.validate().unwrap();
+
+    // removing the entity is undone with the components in their order
+    let entity_key2 = ecs.insert_entity();
+    let comp_keys = (0..4)
+        .map(|i| ecs.insert_comp(entity_key2, i as u32).unwrap())
+        .collect::<Vec<_>>();
+    ecs.remove_comp::<u32>(comp_keys[1]).unwrap();
+    ecs.insert_comp(entity_key2, 4u32).unwrap();
+    ecs.remove_entity(entity_key2).unwrap();
+    ecs.undo().unwrap();
+    let restored = ecs
+        .iter_comp_by_entity::<u32>(entity_key2)
+        .unwrap()
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(restored, vec![0, 2, 3, 4]);
+    ecs.validate().unwrap();
+}
+
+#[test]
+fn with_hasher() {
+    type StdHasher = std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+    // the world works the same with the hasher of the standard library
+    let mut ecs = ecs_tiny::ECS::with_hasher(std::collections::hash_map::RandomState::new());
+    ecs.register_cloneable::<u32>().unwrap();
+    ecs.enable_undo(16);
+    let entity_key = ecs.insert_entity();
+    let comp_key = ecs.insert_comp(entity_key, 1u32).unwrap();
+    ecs.insert_comp(entity_key, 2u32).unwrap();
+    *ecs.get_comp_mut::<u32>(comp_key).unwrap() += 10;
+    let comps = ecs.iter_comp::<u32>().unwrap().copied().collect::<Vec<_>>();
+    assert_eq!(comps, vec![11, 2]);
+    ecs.remove_entity(entity_key).unwrap();
+    ecs.undo().unwrap();
+    let comps = ecs
+        .iter_comp_by_entity::<u32>(entity_key)
+        .unwrap()
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(comps, vec![11, 2]);
+    ecs.validate().unwrap();
+
+    // as does a hasher created by default, and its clones keep it
+    let mut ecs = ecs_tiny::ECS::<StdHasher>::default();
+    ecs.register_cloneable::<u32>().unwrap();
+    let entity_key = ecs.insert_entity();
+    ecs.insert_comp(entity_key, 1u32).unwrap();
+    let clone = ecs.clone_filtered(|_| true).unwrap();
+    let comps = clone
+        .iter_comp::<u32>()
+        .unwrap()
+        .copied()
+        .collect::<Vec<_>>();
+    assert_eq!(comps, vec![1]);
+
+    // the default world needs no type annotation
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.insert_comp(entity_key, 1i32).unwrap();
+    assert_eq!(ecs.count_comp::<i32>(), Ok(1));
+}
+
+#[test]
+fn world_method_errors() {
+    let mut ecs = ecs_tiny::ECS::new();
+    let entity_key = ecs.insert_entity();
+    ecs.register::<i32>().unwrap();
+    ecs.register_immutable::<u32>().unwrap();
+    ecs.register_world_unique::<u64>().unwrap();
+
+    // every lookup of a type names why it failed
+    assert_eq!(
+        ecs.count_comp::<i8>(),
+        Err(ecs_tiny::Error::TypeNotRegistered)
+    );
+    assert_eq!(
+        ecs.world_unique::<i32>(),
+        Err(ecs_tiny::Error::TypeNotWorldUnique)
+    );
+    assert_eq!(
+        ecs.world_unique::<u64>(),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+
+    // the keys of the many components must be distinct and mutable
+    let comp_key0 = ecs.insert_comp(entity_key, 1).unwrap();
+    let comp_key1 = ecs.insert_comp(entity_key, 2).unwrap();
+    let comp_key2 = ecs.insert_comp(entity_key, 3u32).unwrap();
+    assert_eq!(
+        ecs.get_many_comp_mut::<i32, 2>([comp_key0, comp_key0]),
+        Err(ecs_tiny::Error::DuplicateKey)
+    );
+    assert_eq!(
+        ecs.get_many_comp_mut::<i32, 2>([comp_key0, comp_key2]),
+        Err(ecs_tiny::Error::TypeMismatch)
+    );
+    assert_eq!(
+        ecs.get_comp_mut_pair::<i32, u32>(comp_key0, comp_key2),
+        Err(ecs_tiny::Error::ImmutableType)
+    );
+    assert_eq!(
+        ecs.replace_comp::<u32>(comp_key2, 4),
+        Err(ecs_tiny::Error::ImmutableType)
+    );
+
+    // the only component of an entity is neither several nor taken
+    assert_eq!(
+        ecs.get_comp_by_entity::<i32>(entity_key),
+        Err(ecs_tiny::Error::AmbiguousComp)
+    );
+    assert_eq!(
+        ecs.remove_comp_by_entity::<i32>(entity_key),
+        Err(ecs_tiny::Error::AmbiguousComp)
+    );
+    let taken = ecs.take_comp::<i32>(comp_key1).unwrap();
+    assert_eq!(
+        ecs.get_many_comp_mut::<i32, 2>([comp_key0, comp_key1]),
+        Err(ecs_tiny::Error::TemporarilyTaken)
+    );
+    assert_eq!(
+        ecs.get_comp_by_entity::<i32>(entity_key),
+        Err(ecs_tiny::Error::AmbiguousComp)
+    );
+    ecs.put_back(taken).unwrap();
+    ecs.remove_comp::<i32>(comp_key0).unwrap();
+    assert_eq!(
+        ecs.get_many_comp_mut::<i32, 2>([comp_key0, comp_key1]),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+    assert_eq!(ecs.get_comp_by_entity::<i32>(entity_key), Ok(&2));
+
+    // stable ids are looked up only once enabled
+    assert_eq!(
+        ecs.stable_id(comp_key1),
+        Err(ecs_tiny::Error::StableIdsNotEnabled)
+    );
+    ecs.enable_stable_ids::<i32>().unwrap();
+    assert_eq!(
+        ecs.comp_by_stable_id::<i32>(7),
+        Err(ecs_tiny::Error::CompNotFound)
+    );
+
+    // observers and partitions are found by their handles
+    let handle = ecs.on_insert::<i32>(|_, _, _| {}).unwrap();
+    ecs.remove_observer(handle).unwrap();
+    assert_eq!(
+        ecs.remove_observer(handle),
+        Err(ecs_tiny::Error::ObserverNotFound)
+    );
+    let partition = ecs_tiny::ECS::new().create_partition("other");
+    assert_eq!(
+        ecs.partition_name(partition),
+        Err(ecs_tiny::Error::PartitionNotFound)
+    );
+
+    // and the history tells apart being disabled from being exhausted
+    assert_eq!(ecs.undo(), Err(ecs_tiny::Error::UndoNotEnabled));
+    assert_eq!(
+        ecs.begin_undo_group("group"),
+        Err(ecs_tiny::Error::UndoNotEnabled)
+    );
+    ecs.enable_undo(16);
+    assert_eq!(ecs.undo(), Err(ecs_tiny::Error::NothingToUndo));
+    assert_eq!(ecs.redo(), Err(ecs_tiny::Error::NothingToRedo));
+
+    // entities keep failing as removed entities
+    ecs.remove_entity(entity_key).unwrap();
+    assert!(matches!(
+        ecs.take_entity(entity_key),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+    assert!(matches!(
+        ecs.count_comp_by_entity::<i32>(entity_key),
+        Err(ecs_tiny::Error::EntityNotFound(_))
+    ));
+}